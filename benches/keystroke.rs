@@ -0,0 +1,76 @@
+//! Keystroke latency in the contact list with a large address book.
+//!
+//! Seeds a temporary database (100,000 contacts unless `ROLODEX_BENCH_CONTACTS`
+//! says otherwise), then types queries one character at a time and reports
+//! how long each keystroke takes to filter and to draw.
+//!
+//!     cargo bench --bench keystroke
+
+use std::time::{Duration, Instant};
+
+use ratatui::{Terminal, backend::TestBackend};
+use rolodex_tui::{
+    Db,
+    components::{
+        browse::{Browse, BrowseMsg},
+        input::InputMsg,
+    },
+};
+
+const QUERIES: &[&str] = &["john smith", "acme", "example.com"];
+
+fn main() {
+    let count = std::env::var("ROLODEX_BENCH_CONTACTS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(100_000);
+
+    let dir = tempfile::tempdir().expect("temp dir");
+    let mut db = Db::open(dir.path().join("bench.db")).expect("open database");
+    let start = Instant::now();
+    db.seed(count).expect("seed contacts");
+    let contacts = db.load_customers().expect("load contacts");
+    println!("seeded and loaded {} contacts in {:?}", contacts.len(), start.elapsed());
+
+    let mut browse = Browse::new(contacts);
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).expect("terminal");
+
+    for query in QUERIES {
+        println!("\nquery {query:?}");
+        println!("{:<14} {:>8} {:>12} {:>12}", "typed", "matches", "filter", "draw");
+        let mut filter_total = Duration::ZERO;
+        let mut draw_total = Duration::ZERO;
+
+        for (end, c) in query.char_indices() {
+            let start = Instant::now();
+            browse.update(BrowseMsg::Input(InputMsg::TypeChar(c)), |output| output);
+            let filtered = start.elapsed();
+
+            let start = Instant::now();
+            terminal
+                .draw(|f| browse.draw(f, f.area(), true))
+                .expect("draw");
+            let drawn = start.elapsed();
+
+            filter_total += filtered;
+            draw_total += drawn;
+            println!(
+                "{:<14} {:>8} {:>12?} {:>12?}",
+                &query[..end + c.len_utf8()],
+                browse.contact_list.len(),
+                filtered,
+                drawn
+            );
+        }
+
+        let keys = query.chars().count() as u32;
+        println!(
+            "{:<14} {:>8} {:>12?} {:>12?}",
+            "mean",
+            "",
+            filter_total / keys,
+            draw_total / keys
+        );
+        browse.update(BrowseMsg::Input(InputMsg::Clear), |output| output);
+    }
+}
@@ -1,19 +1,119 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
+use crate::{
+    model::{SortKey, SortOrder},
+    sync::Conflict,
+    tui::Height,
+};
+
 #[derive(Parser)]
 #[command(name = "rolodex")]
 #[command(author, version, about)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Seed fake users into the database
     #[arg(long)]
     pub seed: Option<u32>,
 
-    /// Backup contacts to a JSON file
+    /// Backup contacts to a JSON file, sealed if the address book is encrypted
     #[arg(long)]
     pub backup: Option<PathBuf>,
 
-    /// Restore contacts from a JSON backup file
+    /// Restore contacts from a JSON backup file, sealed or not
     #[arg(long)]
     pub restore: Option<PathBuf>,
+
+    /// Start with this text in the search box
+    #[arg(short, long)]
+    pub query: Option<String>,
+
+    /// Print the contact straight away if exactly one matches the query
+    #[arg(short = '1', long)]
+    pub select_one: bool,
+
+    /// Exit with status 1 without showing the UI if nothing matches the query
+    #[arg(short = '0', long = "exit-0")]
+    pub exit_0: bool,
+
+    /// Draw inline below the prompt using this many rows (20) or this share
+    /// of the terminal (40%), instead of the full screen
+    #[arg(long)]
+    pub height: Option<Height>,
+
+    /// Read a mail message on stdin and offer to add its sender
+    #[arg(long)]
+    pub add_from_mail: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print all contacts as JSON
+    List {
+        /// Column to sort by (defaults to the order last used in the TUI)
+        #[arg(long, value_enum)]
+        sort: Option<SortKey>,
+
+        /// Sort direction (defaults to the order last used in the TUI)
+        #[arg(long, value_enum)]
+        order: Option<SortOrder>,
+    },
+    /// Print matching contacts for a mail client's query_command
+    Query {
+        /// Text to look for in names, companies, emails and phone numbers
+        query: String,
+    },
+    /// Serve the contacts over an HTTP/JSON API
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:7878", conflicts_with = "socket")]
+        listen: String,
+
+        /// Listen on this Unix socket instead of a TCP port
+        #[arg(long)]
+        socket: Option<PathBuf>,
+
+        /// Require `Authorization: Bearer <token>` on every request
+        /// (defaults to $ROLODEX_TOKEN)
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Serve the address book over CardDAV for phones and mail clients
+    Carddav {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:5232")]
+        listen: String,
+
+        /// Require this password with HTTP Basic auth, any user name
+        /// (defaults to $ROLODEX_TOKEN)
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Serve the address book as a read-only LDAP directory for desk phones
+    Ldap {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:10389")]
+        listen: String,
+
+        /// DN the contacts are listed under
+        #[arg(long, default_value = "ou=contacts,dc=rolodex")]
+        base: String,
+    },
+    /// Answer line-delimited JSON-RPC 2.0 on stdin/stdout for editor plugins
+    Rpc,
+    /// Two-way sync with the CardDAV collection in the [sync] config table
+    Sync {
+        /// How to settle contacts changed on both sides since the last sync
+        /// (defaults to `conflict` in [sync])
+        #[arg(long, value_enum)]
+        conflict: Option<Conflict>,
+
+        /// Forget earlier syncs and match every contact up afresh
+        #[arg(long)]
+        reset: bool,
+    },
+    /// Change the passphrase of the encrypted address book
+    Rekey,
 }
@@ -0,0 +1,190 @@
+//! Copying text to the system clipboard, either through the terminal with
+//! the OSC 52 escape sequence or by piping it to a command such as
+//! `wl-copy` or `xclip`.
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult as Result};
+
+/// Many terminals ignore OSC 52 payloads beyond about 100 kB.
+pub const OSC52_LIMIT: usize = 100_000;
+
+/// The `[clipboard]` table from `config.toml`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClipboardConfig {
+    /// Copy through the terminal with OSC 52. Turn off for terminals that
+    /// don't support it.
+    #[serde(default = "default_osc52")]
+    pub osc52: bool,
+    /// Command that reads the text to copy on stdin, used when OSC 52 is off
+    /// or the text is too long for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+fn default_osc52() -> bool {
+    true
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            osc52: true,
+            command: None,
+        }
+    }
+}
+
+impl ClipboardConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Copies text, queueing OSC 52 sequences for the UI loop to write to the
+/// terminal between draws.
+#[derive(Debug, Default)]
+pub struct Clipboard {
+    config: ClipboardConfig,
+    tmux: bool,
+    pending: Vec<String>,
+}
+
+impl Clipboard {
+    pub fn new(config: ClipboardConfig) -> Self {
+        Self {
+            config,
+            tmux: std::env::var_os("TMUX").is_some(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn copy(&mut self, text: &str) -> Result<()> {
+        if self.config.osc52 && encoded_len(text) <= OSC52_LIMIT {
+            self.pending.push(osc52(text, self.tmux));
+            return Ok(());
+        }
+        match &self.config.command {
+            Some(command) => pipe_to(command, text),
+            None if self.config.osc52 => Err(AppError::Command(
+                "too long for OSC 52, set a clipboard command in config.toml".to_string(),
+            )),
+            None => Err(AppError::Command(
+                "no clipboard command set in config.toml".to_string(),
+            )),
+        }
+    }
+
+    /// Escape sequences waiting to be written to the terminal.
+    pub fn take_pending(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// The OSC 52 sequence that sets the clipboard to `text`. Inside tmux it is
+/// wrapped in a passthrough sequence so it reaches the outer terminal.
+pub fn osc52(text: &str, tmux: bool) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    if tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+fn encoded_len(text: &str) -> usize {
+    text.len().div_ceil(3) * 4
+}
+
+/// Run `command` with `sh -c` and write `text` to its stdin.
+fn pipe_to(command: &str, text: &str) -> Result<()> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| AppError::Command(format!("run {command}: {e}")))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| AppError::Command(format!("write to {command}: {e}")))?;
+    }
+    let status = child
+        .wait()
+        .map_err(|e| AppError::Command(format!("wait for {command}: {e}")))?;
+    if !status.success() {
+        return Err(AppError::Command(format!("{command} failed with {status}")));
+    }
+    Ok(())
+}
+
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64("ada@example.com".as_bytes()), "YWRhQGV4YW1wbGUuY29t");
+    }
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52("hi", true), "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+    }
+
+    #[test]
+    fn test_copy_queues_osc52_or_runs_command() {
+        let mut clipboard = Clipboard::new(ClipboardConfig::default());
+        clipboard.copy("hi").unwrap();
+        assert_eq!(clipboard.take_pending().len(), 1);
+        assert!(clipboard.take_pending().is_empty());
+
+        let mut clipboard = Clipboard::new(ClipboardConfig {
+            osc52: false,
+            command: Some("cat > /dev/null".to_string()),
+        });
+        clipboard.copy("hi").unwrap();
+        assert!(clipboard.take_pending().is_empty());
+
+        let mut clipboard = Clipboard::new(ClipboardConfig {
+            osc52: false,
+            command: Some("exit 1".to_string()),
+        });
+        assert!(clipboard.copy("hi").is_err());
+
+        // Too long for OSC 52 and nothing to fall back on
+        let mut clipboard = Clipboard::new(ClipboardConfig::default());
+        assert!(clipboard.copy(&"x".repeat(OSC52_LIMIT)).is_err());
+    }
+}
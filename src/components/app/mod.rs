@@ -1,9 +1,13 @@
-use std::time::{Duration, Instant};
+use std::{
+    io::Write,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
 
 use crossterm::{
     ExecutableCommand,
     cursor::SetCursorStyle,
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    event::{self, Event, KeyCode, KeyEvent, MouseEvent},
 };
 use ratatui::{
     prelude::*,
@@ -12,21 +16,40 @@ use ratatui::{
 use tracing::{debug, info};
 
 use crate::{
-    Db,
+    clipboard::{Clipboard, ClipboardConfig},
     components::{
         Component,
         browse::{Browse, BrowseMsg, BrowseOutput},
+        contact_list::ContactListMsg,
         delete_confirmation::{DeleteConfirmation, DeleteMsg, DeleteOutput},
+        duplicates::{Duplicates, DuplicatesMsg, DuplicatesOutput},
         error_dialog::{ErrorDialog, ErrorMsg, ErrorOutput},
         form::{Form, FormMsg, FormOutput},
+        help::{Help, HelpMsg, HelpOutput, HelpSection},
+        input::InputMsg,
+        message_log::{MessageLog, MessageLogMsg, MessageLogOutput},
+        notifications::{Level, Notifications, NotificationsMsg},
+        palette::{Palette, PaletteCommand, PaletteMsg, PaletteOutput},
         status_bar::{StatusBar, StatusBarMsg},
     },
-    error::AppResult as Result,
+    error::{AppError, AppResult as Result},
+    hooks::{ActionHooks, HookReply, HookRunner},
+    keymap::{Action, KeyScope, Keymap},
     layout::fixed_centered_rect,
-    model::Contact,
+    mail,
+    model::{Contact, Sort, contact::format_timestamp},
     mode::AppMode,
+    store::ContactStore,
+    theme::Theme,
+    vcard,
+    vi::{self, Vi, ViCommand, ViMode, ViMsg},
+    worker::{DbReply, DbRequest, DbResponse, DbWorker},
 };
 
+/// How often the contact list is checked for changes made by other
+/// processes sharing the database.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
 pub enum AppMsg {
     // UI events from components
     Browse(BrowseMsg),
@@ -34,20 +57,64 @@ pub enum AppMsg {
     DeleteDialog(DeleteMsg),
     ErrorDialog(ErrorMsg),
     StatusBar(StatusBarMsg),
+    Help(HelpMsg),
+    Palette(PaletteMsg),
+    MessageLog(MessageLogMsg),
+    Duplicates(DuplicatesMsg),
+    Vi(ViMsg),
+    /// A finished request from the database worker.
+    Db(Box<DbReply>),
 
     //High-level app messages
     AddContact,
     EditContact(Contact),
     ConfirmDelete(Contact),
+    /// Star the contact if it isn't already, otherwise unstar it.
+    ToggleFavorite(Contact),
+    /// Run the configured call, mail or open command for the contact.
+    RunHook(Action, Contact),
+    /// A hook command finished.
+    Hook(Box<HookReply>),
+    /// Copy a field of the contact, picked by one of the copy actions.
+    Copy(Action, Contact),
+    CycleSort,
+    ToggleSortOrder,
+    ShowHelp,
+    ShowLog,
+    OpenPalette,
+    /// Write every contact to a timestamped file in `export_dir`.
+    Export,
+    /// Look for possible duplicates and list them.
+    ShowDuplicates,
+    /// Non-blocking toast, also recorded in the message log.
+    Notify(Level, String),
+    /// Advance time-based state such as toast expiry.
+    Tick(Instant),
+    /// The terminal was resized to the given columns and rows.
+    Resize(u16, u16),
+    /// The terminal window gained (`true`) or lost (`false`) focus.
+    Focus(bool),
+    /// Modal error dialog, reserved for failures the app cannot carry on from.
     ShowError(String),
     Quit,
 }
 pub type AppOutput = AppMsg;
 
-
+/// What `--select-one` and `--exit-0` decide before the UI is shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preselect {
+    /// Nothing matches the query.
+    NoMatches,
+    /// The only match, already recorded as used.
+    Selected(Box<Contact>),
+}
 
 pub struct App {
-    pub db: Db,
+    /// All database access goes through this worker thread.
+    pub worker: DbWorker,
+    /// Runs the `[actions]` commands from the config in the background.
+    pub hooks: HookRunner,
+    pub clipboard: Clipboard,
     pub selected_contact: Option<Contact>,
     pub mode: AppMode,
     pub should_quit: bool,
@@ -56,12 +123,36 @@ pub struct App {
     pub error_dialog: ErrorDialog,
     pub delete_confirmation: DeleteConfirmation,
     pub status_bar: StatusBar,
+    pub help: Help,
+    pub palette: Palette,
+    pub notifications: Notifications,
+    pub message_log: MessageLog,
+    pub duplicates: Duplicates,
+    /// Mode to return to when the help overlay or command palette closes.
+    pub return_mode: AppMode,
+    pub keymap: Keymap,
+    /// Vi-style modal navigation state, present only when enabled in config.
+    pub vi: Option<Vi>,
+    /// Set whenever state changes; `run` only draws when it is set.
+    pub needs_redraw: bool,
+    /// Whether the terminal window has focus. Toasts don't expire while it
+    /// doesn't.
+    pub focused: bool,
+    /// Quit as soon as the contact form closes, for `--add-from-mail`.
+    pub form_only: bool,
+    /// When the list was last checked for changes made elsewhere.
+    pub last_refresh: Instant,
+    /// Where the export command writes, the working directory by default.
+    pub export_dir: PathBuf,
 }
 
 impl App {
-    pub fn new(db: Db) -> Result<Self> {
-        let all_contacts = db.load_customers()?;
-        let browse = Browse::new(&all_contacts);
+    /// Load the initial contacts synchronously, then hand the store to the
+    /// worker thread.
+    pub fn new(db: impl ContactStore + 'static) -> Result<Self> {
+        let mut browse = Browse::new(db.load_customers()?);
+        browse.set_sort(db.load_sort()?);
+        let worker = DbWorker::spawn(db)?;
         let mut error_dialog = ErrorDialog::new();
         error_dialog.set_error("Error loading contacts");
 
@@ -72,13 +163,15 @@ impl App {
         }
         let mut status_bar = StatusBar::new();
         status_bar.update(StatusBarMsg::UpdateCounts {
-            total: all_contacts.len(),
-            filtered: all_contacts.len(),
+            total: browse.all_contacts.len(),
+            filtered: browse.contact_list.len(),
         });
         status_bar.update(StatusBarMsg::UpdateMode(AppMode::Browse));
 
-        Ok(Self {
-            db,
+        let mut app = Self {
+            worker,
+            hooks: HookRunner::default(),
+            clipboard: Clipboard::default(),
             selected_contact: browse.contact_list.get_selected_contact(),
             mode: AppMode::Browse,
             should_quit: false,
@@ -87,33 +180,130 @@ impl App {
             error_dialog,
             delete_confirmation,
             status_bar,
-        })
+            help: Help::new(),
+            palette: Palette::new(),
+            notifications: Notifications::new(),
+            message_log: MessageLog::new(),
+            duplicates: Duplicates::new(),
+            return_mode: AppMode::Browse,
+            keymap: Keymap::default(),
+            vi: None,
+            needs_redraw: true,
+            focused: true,
+            form_only: false,
+            last_refresh: Instant::now(),
+            export_dir: PathBuf::from("."),
+        };
+        app.set_keymap(Keymap::default());
+        Ok(app)
+    }
+    /// Replace the active key bindings and refresh every hint derived from them.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.contact_form.set_hint(
+            &keymap.describe(KeyScope::Form, &[Action::Submit, Action::Cancel]),
+        );
+        self.delete_confirmation.set_hints(
+            &keymap.describe(KeyScope::Delete, &[Action::Confirm]),
+            &keymap.describe(KeyScope::Delete, &[Action::Cancel]),
+        );
+        self.error_dialog
+            .set_hint(&keymap.describe(KeyScope::Error, &[Action::Dismiss]));
+        self.keymap = keymap;
+        self.update_status_bar_mode();
+    }
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.browse.set_theme(theme);
+        self.contact_form.set_theme(theme);
+        self.delete_confirmation.set_theme(theme);
+        self.error_dialog.set_theme(theme);
+        self.help.set_theme(theme);
+        self.palette.set_theme(theme);
+        self.notifications.set_theme(theme);
+        self.message_log.set_theme(theme);
+        self.duplicates.set_theme(theme);
+        self.status_bar.set_theme(theme);
+    }
+    /// Fill the search box as if `query` had been typed.
+    pub fn set_query(&mut self, query: &str) {
+        let input = BrowseMsg::Input(InputMsg::Paste(query.to_string()));
+        self.update(AppMsg::Browse(BrowseMsg::Input(InputMsg::Clear)), |msg| msg);
+        self.update(AppMsg::Browse(input), |msg| msg);
+        self.selected_contact = self.browse.contact_list.get_selected_contact();
+    }
+    /// Settle the pick without the UI when the current matches allow it:
+    /// `exit_0` with no matches, or `select_one` with exactly one.
+    pub fn preselect(&mut self, select_one: bool, exit_0: bool) -> Option<Preselect> {
+        match self.browse.contact_list.len() {
+            0 if exit_0 => Some(Preselect::NoMatches),
+            1 if select_one => {
+                let activate = BrowseMsg::List(ContactListMsg::Activate);
+                self.update(AppMsg::Browse(activate), |msg| msg);
+                self.selected_contact.clone().map(|c| Preselect::Selected(Box::new(c)))
+            }
+            _ => None,
+        }
+    }
+    /// Open the form prefilled with `contact` and quit once it is saved or
+    /// cancelled. The saved contact becomes the selection.
+    pub fn add_only(&mut self, contact: Contact) {
+        self.update(AppMsg::AddContact, |msg| msg);
+        self.contact_form.set_contact(contact);
+        self.selected_contact = None;
+        self.form_only = true;
+    }
+    pub fn set_hooks(&mut self, hooks: ActionHooks) {
+        self.hooks = HookRunner::new(hooks);
+    }
+    pub fn set_clipboard(&mut self, config: ClipboardConfig) {
+        self.clipboard = Clipboard::new(config);
     }
-    pub fn run<B: Backend>(terminal: &mut Terminal<B>, db: Db) -> Result<Option<Contact>> {
+    pub fn set_vi_mode(&mut self, enabled: bool) {
+        self.vi = enabled.then(Vi::new);
+        self.update_status_bar_mode();
+    }
+    pub fn run<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<Option<Contact>> {
         std::io::stderr()
             .execute(SetCursorStyle::BlinkingBar)
             .expect("Failed to set cursor style");
-        let mut app = App::new(db)?;
         let tick_rate = Duration::from_millis(250);
         let mut last_tick = Instant::now();
 
         loop {
-            terminal.draw(|f| app.draw(f, f.area(), false))?;
+            if app.needs_redraw {
+                terminal.draw(|f| app.draw(f, f.area(), false))?;
+                app.needs_redraw = false;
+            }
+            for sequence in app.clipboard.take_pending() {
+                let mut stderr = std::io::stderr();
+                stderr.write_all(sequence.as_bytes())?;
+                stderr.flush()?;
+            }
 
-            let timeout = tick_rate
+            let mut timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or(Duration::from_secs(0));
+            // Check back often for replies while the worker is busy
+            if app.worker.is_busy() || app.hooks.is_busy() {
+                timeout = timeout.min(Duration::from_millis(20));
+            }
 
-            if event::poll(timeout)?
-                && let Event::Key(key_event) = event::read()?
-                && let Some(mut msg) = app.handle_key(key_event)
-            {
+            let msg = if event::poll(timeout)? {
+                app.handle_event(event::read()?)
+            } else {
+                None
+            };
+            if let Some(mut msg) = msg {
                 while let Some(next) = app.update(msg, |msg| msg) {
                     msg = next;
                 }
             }
+            app.drain_db(false);
+            app.drain_hooks(false);
 
-            last_tick = Instant::now();
+            if last_tick.elapsed() >= tick_rate {
+                last_tick = Instant::now();
+                app.update(AppMsg::Tick(last_tick), |msg| msg);
+            }
 
             if app.should_quit {
                 debug!("Quitting");
@@ -123,28 +313,370 @@ impl App {
         Ok(app.selected_contact)
     }
     fn dismiss_error(&mut self) {
-        self.mode = AppMode::Browse;
+        self.set_mode(AppMode::Browse);
         self.error_dialog.set_error("");
-        self.update_status_bar_mode();
     }
-    fn refresh_contacts(&mut self) -> Option<AppMsg> {
-        let result = self.db.load_customers();
-        if let Err(err) = result {
-            return Some(AppMsg::ShowError(err.to_string()));
+    /// Apply replies from the database worker. With `block`, keep waiting
+    /// until every outstanding request has been answered.
+    pub fn drain_db(&mut self, block: bool) {
+        loop {
+            let reply = if block && self.worker.is_busy() {
+                self.worker.recv_timeout(Duration::from_secs(5))
+            } else {
+                self.worker.try_recv()
+            };
+            let Some(reply) = reply else {
+                break;
+            };
+            let mut msg = AppMsg::Db(Box::new(reply));
+            while let Some(next) = self.update(msg, |msg| msg) {
+                msg = next;
+            }
+        }
+        self.update_status_bar_activity();
+    }
+
+    /// Apply results of finished hook commands. With `block`, wait for every
+    /// running command.
+    pub fn drain_hooks(&mut self, block: bool) {
+        loop {
+            let reply = if block && self.hooks.is_busy() {
+                self.hooks.recv_timeout(Duration::from_secs(5))
+            } else {
+                self.hooks.try_recv()
+            };
+            let Some(reply) = reply else {
+                break;
+            };
+            let mut msg = AppMsg::Hook(Box::new(reply));
+            while let Some(next) = self.update(msg, |msg| msg) {
+                msg = next;
+            }
+        }
+        self.update_status_bar_activity();
+    }
+
+    fn run_hook(&mut self, action: Action, contact: Contact) -> Option<AppMsg> {
+        let name = contact.name.clone();
+        let started = self.hooks.spawn(action, contact);
+        self.update_status_bar_activity();
+        match started {
+            Ok(()) => {
+                self.status_bar.update(StatusBarMsg::UpdateMessage(None));
+                None
+            }
+            // Nothing configured, or the contact lacks the field
+            Err(AppError::Command(message)) => Some(AppMsg::Notify(Level::Warning, message)),
+            Err(err) => {
+                let message = format!("Could not {} {name}: {err}", action.name());
+                Some(AppMsg::Notify(Level::Error, message))
+            }
+        }
+    }
+
+    fn copy(&mut self, action: Action, contact: &Contact) -> AppMsg {
+        let email = contact.email.as_deref().filter(|email| !email.trim().is_empty());
+        let phone = contact.phone.as_deref().filter(|phone| !phone.trim().is_empty());
+        let (what, text) = match action {
+            Action::CopyEmail => ("email", email.map(str::to_string)),
+            Action::CopyPhone => ("phone number", phone.map(str::to_string)),
+            Action::CopyNameEmail => ("email", email.map(|e| mail::mailbox(&contact.name, e))),
+            _ => ("vCard", Some(vcard::to_vcard(contact))),
+        };
+        let Some(text) = text else {
+            return AppMsg::Notify(Level::Warning, format!("{} has no {what}", contact.name));
+        };
+        match self.clipboard.copy(&text) {
+            Ok(()) if action == Action::CopyVcard => {
+                AppMsg::Notify(Level::Success, format!("Copied vCard for {}", contact.name))
+            }
+            Ok(()) => AppMsg::Notify(Level::Success, format!("Copied {text}")),
+            Err(AppError::Command(reason)) => {
+                AppMsg::Notify(Level::Error, format!("Could not copy {what}: {reason}"))
+            }
+            Err(err) => AppMsg::Notify(Level::Error, format!("Could not copy {what}: {err}")),
+        }
+    }
+
+    fn handle_hook_reply(&mut self, reply: HookReply) -> Option<AppMsg> {
+        let HookReply { action, contact, result } = reply;
+        match result {
+            Ok(outcome) => {
+                let summary = outcome.summary(action);
+                self.status_bar
+                    .update(StatusBarMsg::UpdateMessage(Some(summary.clone())));
+                // Failures also go to the message log
+                (!outcome.success())
+                    .then(|| AppMsg::Notify(Level::Error, format!("{summary} ({})", contact.name)))
+            }
+            Err(err) => {
+                let message = format!("Could not {} {}: {err}", action.name(), contact.name);
+                Some(AppMsg::Notify(Level::Error, message))
+            }
+        }
+    }
+
+    fn send_db(&mut self, request: DbRequest) {
+        self.worker.send(request);
+        self.update_status_bar_activity();
+    }
+
+    fn update_status_bar_activity(&mut self) {
+        let activity = self.worker.activity().or(self.hooks.activity());
+        self.status_bar.update(StatusBarMsg::UpdateActivity(activity));
+    }
+
+    fn handle_db_reply(&mut self, mut reply: DbReply) -> Option<AppMsg> {
+        if self.worker.is_stale(&reply) {
+            debug!("Dropping reply to superseded request {}", reply.id);
+            return None;
+        }
+        if let Some(err) = reply.hook_error.take() {
+            let message = match err {
+                AppError::Command(reason) => reason,
+                err => err.to_string(),
+            };
+            self.update(AppMsg::Notify(Level::Warning, message), |msg| msg);
+        }
+        let response = match reply.result {
+            Ok(response) => response,
+            Err(AppError::Cancelled) => return None,
+            Err(err) => {
+                return Some(match reply.request {
+                    // Without a contact list there is nothing to carry on with
+                    DbRequest::LoadContacts => AppMsg::ShowError(err.to_string()),
+                    // Another refresh follows shortly
+                    DbRequest::Refresh => {
+                        debug!("Refresh failed: {err}");
+                        return None;
+                    }
+                    // The form stays open so the edit is not lost
+                    DbRequest::AddContact(contact) | DbRequest::UpdateContact(contact) => {
+                        let message = format!("Could not save {}: {err}", contact.name);
+                        AppMsg::Notify(Level::Error, message)
+                    }
+                    DbRequest::DeleteContact(contact) => {
+                        let message = format!("Could not delete {}: {err}", contact.name);
+                        AppMsg::Notify(Level::Error, message)
+                    }
+                    DbRequest::SaveSort(_) => {
+                        AppMsg::Notify(Level::Warning, format!("Sort order not saved: {err}"))
+                    }
+                    DbRequest::SetFavorite(contact, _) => {
+                        let message = format!("Could not star {}: {err}", contact.name);
+                        AppMsg::Notify(Level::Error, message)
+                    }
+                    DbRequest::TouchContact(_) => AppMsg::Notify(Level::Error, err.to_string()),
+                    DbRequest::Export(path) => {
+                        let message = format!("Could not export to {}: {err}", path.display());
+                        AppMsg::Notify(Level::Error, message)
+                    }
+                    DbRequest::FindDuplicates => {
+                        let message = format!("Could not look for duplicates: {err}");
+                        AppMsg::Notify(Level::Error, message)
+                    }
+                });
+            }
+        };
+
+        match response {
+            DbResponse::Contacts(contacts) => {
+                self.browse.set_contacts(contacts);
+                self.selected_contact = self.browse.contact_list.get_selected_contact();
+                self.update_status_bar_counts();
+                None
+            }
+            DbResponse::Added(contact) | DbResponse::Updated(contact) => {
+                let done = match reply.request {
+                    DbRequest::AddContact(_) => "Added",
+                    _ => "Saved",
+                };
+                if self.mode == AppMode::ContactForm {
+                    self.set_mode(AppMode::Browse);
+                }
+                if self.form_only {
+                    self.selected_contact = Some(contact);
+                    self.should_quit = true;
+                    return None;
+                }
+                self.send_db(DbRequest::LoadContacts);
+                Some(AppMsg::Notify(Level::Success, format!("{done} {}", contact.name)))
+            }
+            DbResponse::Conflict(current) => {
+                let message = format!("{} was modified elsewhere", current.name);
+                self.contact_form.update(FormMsg::Conflict(Box::new(current)), |_| ());
+                Some(AppMsg::Notify(Level::Warning, message))
+            }
+            DbResponse::Deleted(contact) => {
+                self.send_db(DbRequest::LoadContacts);
+                Some(AppMsg::Notify(Level::Success, format!("Deleted {}", contact.name)))
+            }
+            DbResponse::FavoriteSet(contact, favorite) => {
+                self.send_db(DbRequest::LoadContacts);
+                let done = if favorite { "Starred" } else { "Unstarred" };
+                Some(AppMsg::Notify(Level::Info, format!("{done} {}", contact.name)))
+            }
+            DbResponse::Exported(path) => {
+                let message = format!("Exported contacts to {}", path.display());
+                Some(AppMsg::Notify(Level::Success, message))
+            }
+            DbResponse::Duplicates(groups) if groups.is_empty() => {
+                Some(AppMsg::Notify(Level::Info, "No possible duplicates".to_string()))
+            }
+            DbResponse::Duplicates(groups) => {
+                self.duplicates.set_groups(groups);
+                self.enter_overlay(AppMode::Duplicates);
+                None
+            }
+            // A failed hook was reported above
+            DbResponse::Touched
+            | DbResponse::SortSaved
+            | DbResponse::Unchanged
+            | DbResponse::HookFinished => None,
         }
-        self.browse.set_contacts(&result.unwrap());
-        self.update_status_bar_counts();
-        None
+    }
+
+    /// Select contact `id` in the list, clearing the search if it hides it.
+    fn show_contact(&mut self, id: i64) {
+        if !self.browse.contact_list.select_id(id) {
+            self.set_query("");
+            self.browse.contact_list.select_id(id);
+        }
+        self.selected_contact = self.browse.contact_list.get_selected_contact();
+    }
+
+    fn set_sort(&mut self, sort: Sort) {
+        self.browse.set_sort(sort);
+        self.selected_contact = self.browse.contact_list.get_selected_contact();
+        self.send_db(DbRequest::SaveSort(sort));
     }
 
     fn update_status_bar_counts(&mut self) {
         let total = self.browse.all_contacts.len();
-        let filtered = self.browse.contact_list.filtered_contacts.len();
+        let filtered = self.browse.contact_list.len();
         self.status_bar.update(StatusBarMsg::UpdateCounts { total, filtered });
     }
 
+    fn set_mode(&mut self, mode: AppMode) {
+        // Forms and the palette open ready for typing; everything else starts
+        // in normal mode
+        let vi_mode = match mode {
+            AppMode::ContactForm | AppMode::Palette => ViMode::Insert,
+            _ => ViMode::Normal,
+        };
+        if let Some(vi) = self.vi.as_mut() {
+            vi.update(ViMsg::SetMode(vi_mode));
+        }
+        self.mode = mode;
+        self.update_status_bar_mode();
+    }
+
     fn update_status_bar_mode(&mut self) {
         self.status_bar.update(StatusBarMsg::UpdateMode(self.mode.clone()));
+        self.status_bar
+            .update(StatusBarMsg::UpdateHelp(self.keymap.help_text(&self.mode)));
+        self.status_bar
+            .update(StatusBarMsg::UpdateVi(self.vi.as_ref().map(Vi::status)));
+    }
+
+    /// Remember the mode an overlay was opened from, unless switching between
+    /// overlays, so closing it always returns to the underlying mode.
+    fn enter_overlay(&mut self, mode: AppMode) {
+        if !self.mode.is_overlay() {
+            self.return_mode = self.mode.clone();
+        }
+        self.set_mode(mode);
+    }
+
+    fn close_overlay(&mut self) {
+        self.set_mode(self.return_mode.clone());
+    }
+
+    /// Every binding for the mode the help overlay covers, followed by the
+    /// global bindings and, when enabled, the vi keys.
+    fn help_sections(&self) -> Vec<HelpSection> {
+        let section = |scope: KeyScope| -> HelpSection {
+            let rows = self
+                .keymap
+                .bindings(scope)
+                .into_iter()
+                .map(|(action, chords)| {
+                    let keys: Vec<String> = chords.iter().map(ToString::to_string).collect();
+                    (keys.join(" / "), action.description().to_string())
+                })
+                .collect();
+            (scope.title().to_string(), rows)
+        };
+
+        let mut sections = vec![
+            section(KeyScope::for_mode(&self.return_mode)),
+            section(KeyScope::Global),
+        ];
+        if self.vi.is_some()
+            && matches!(self.return_mode, AppMode::Browse | AppMode::ContactForm)
+        {
+            let rows = vi::HELP
+                .iter()
+                .map(|(keys, description)| (keys.to_string(), description.to_string()))
+                .collect();
+            sections.push(("Vi normal mode".to_string(), rows));
+        }
+        sections.retain(|(_, rows)| !rows.is_empty());
+        sections
+    }
+
+    fn palette_commands(&self) -> Vec<PaletteCommand> {
+        Action::COMMANDS
+            .iter()
+            .map(|&action| {
+                let keys: Vec<String> = [KeyScope::Global, KeyScope::Browse]
+                    .into_iter()
+                    .flat_map(|scope| self.keymap.chords(scope, action))
+                    .map(|chord| chord.to_string())
+                    .collect();
+                PaletteCommand {
+                    action,
+                    keys: keys.join("/"),
+                }
+            })
+            .collect()
+    }
+
+    fn is_vi_normal(&self) -> bool {
+        self.vi.as_ref().is_some_and(|vi| vi.mode == ViMode::Normal)
+    }
+
+    /// Translate a completed vi command into the message for the current mode.
+    fn vi_command(&self, command: ViCommand) -> Option<AppMsg> {
+        let list = |msg| Some(AppMsg::Browse(BrowseMsg::List(msg)));
+        match (&self.mode, command) {
+            (AppMode::Browse, ViCommand::Down(n)) => list(ContactListMsg::Move(n as isize)),
+            (AppMode::Browse, ViCommand::Up(n)) => list(ContactListMsg::Move(-(n as isize))),
+            (AppMode::Browse, ViCommand::HalfPageDown(n)) => list(ContactListMsg::HalfPageDown(n)),
+            (AppMode::Browse, ViCommand::HalfPageUp(n)) => list(ContactListMsg::HalfPageUp(n)),
+            (AppMode::Browse, ViCommand::Top) => list(ContactListMsg::First),
+            (AppMode::Browse, ViCommand::Bottom) => list(ContactListMsg::Last),
+            (AppMode::Browse, ViCommand::GoTo(line)) => {
+                list(ContactListMsg::Jump(line.saturating_sub(1)))
+            }
+            (AppMode::Browse, ViCommand::Search) => {
+                Some(AppMsg::Browse(BrowseMsg::Input(InputMsg::Clear)))
+            }
+            (AppMode::Browse, ViCommand::Delete) => {
+                self.selected_contact.clone().map(AppMsg::ConfirmDelete)
+            }
+            (AppMode::Browse, ViCommand::Add) => Some(AppMsg::AddContact),
+            (AppMode::Browse, ViCommand::ToggleFavorite) => {
+                self.selected_contact.clone().map(AppMsg::ToggleFavorite)
+            }
+            (AppMode::Browse, ViCommand::Edit) => {
+                self.selected_contact.clone().map(AppMsg::EditContact)
+            }
+            (AppMode::ContactForm, ViCommand::Down(_)) => Some(AppMsg::Form(FormMsg::Next)),
+            (AppMode::ContactForm, ViCommand::Up(_)) => Some(AppMsg::Form(FormMsg::Previous)),
+            (_, ViCommand::Help) => Some(AppMsg::ShowHelp),
+            _ => None,
+        }
     }
     pub fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
         // This is where we split our frame into multiple areas and delegate to our components to
@@ -159,15 +691,26 @@ impl App {
         let main_area = chunks[0];
         let status_area = chunks[1];
 
-        // Draw the main UI
-        self.browse.draw(f, main_area, focused);
+        // Draw the main UI; the search box only has focus when it accepts typing
+        let search_focused = self.mode == AppMode::Browse && !self.is_vi_normal();
+        self.browse.draw(f, main_area, focused || search_focused);
 
         // Draw the status bar
         self.status_bar.draw(f, status_area, false);
 
-        // Draw any mode related UI overlaying the main UI
-        match self.mode {
-            AppMode::Browse => {}
+        // Draw any mode related UI overlaying the main UI, keeping the dialog
+        // underneath the help overlay or command palette visible
+        let base_mode = if self.mode.is_overlay() {
+            &self.return_mode
+        } else {
+            &self.mode
+        };
+        match base_mode {
+            AppMode::Browse
+            | AppMode::Help
+            | AppMode::Palette
+            | AppMode::Log
+            | AppMode::Duplicates => {}
             AppMode::ContactForm => {
                 let overlay = fixed_centered_rect(50, 10, main_area);
                 self.contact_form.draw(f, overlay, true);
@@ -181,30 +724,102 @@ impl App {
                 self.error_dialog.draw(f, overlay, true);
             }
         }
+        match self.mode {
+            AppMode::Help => {
+                let overlay = fixed_centered_rect(72, 24, main_area);
+                self.help.draw(f, overlay, true);
+            }
+            AppMode::Palette => {
+                let overlay = fixed_centered_rect(60, 14, main_area);
+                self.palette.draw(f, overlay, true);
+            }
+            AppMode::Log => {
+                let overlay = fixed_centered_rect(80, 20, main_area);
+                self.message_log.draw(f, overlay, true);
+            }
+            AppMode::Duplicates => {
+                let overlay = fixed_centered_rect(80, 20, main_area);
+                self.duplicates.draw(f, overlay, true);
+            }
+            _ => {}
+        }
+
+        // Toasts float above everything except the log, which already lists them
+        if self.mode != AppMode::Log {
+            self.notifications.draw(f, main_area, false);
+        }
+    }
+    /// Route a mouse event to the component shown for the current mode.
+    pub fn handle_mouse(&self, event: MouseEvent) -> Option<AppMsg> {
+        match self.mode {
+            AppMode::Browse => self.browse.handle_mouse(event).map(AppMsg::Browse),
+            AppMode::ContactForm => self.contact_form.handle_mouse(event).map(AppMsg::Form),
+            AppMode::Delete => self
+                .delete_confirmation
+                .handle_mouse(event)
+                .map(AppMsg::DeleteDialog),
+            AppMode::Error(_) => None,
+            AppMode::Help => self.help.handle_mouse(event).map(AppMsg::Help),
+            AppMode::Palette => self.palette.handle_mouse(event).map(AppMsg::Palette),
+            AppMode::Log => self.message_log.handle_mouse(event).map(AppMsg::MessageLog),
+            AppMode::Duplicates => self.duplicates.handle_mouse(event).map(AppMsg::Duplicates),
+        }
+    }
+    /// Translate any terminal event into a message.
+    pub fn handle_event(&self, event: Event) -> Option<AppMsg> {
+        match event {
+            Event::Key(key_event) => self.handle_key(key_event),
+            Event::Mouse(mouse_event) => self.handle_mouse(mouse_event),
+            Event::Paste(text) => self.handle_paste(text),
+            Event::Resize(columns, rows) => Some(AppMsg::Resize(columns, rows)),
+            Event::FocusGained => Some(AppMsg::Focus(true)),
+            Event::FocusLost => Some(AppMsg::Focus(false)),
+        }
+    }
+    /// Bracketed paste goes to the focused text field, if there is one.
+    pub fn handle_paste(&self, text: String) -> Option<AppMsg> {
+        if self.is_vi_normal() {
+            return None;
+        }
+        let msg = InputMsg::Paste(text);
+        match self.mode {
+            AppMode::Browse => Some(AppMsg::Browse(BrowseMsg::Input(msg))),
+            AppMode::ContactForm => Some(AppMsg::Form(FormMsg::Input(msg))),
+            AppMode::Palette => Some(AppMsg::Palette(PaletteMsg::Input(msg))),
+            _ => None,
+        }
     }
     pub fn handle_key(&self, event: KeyEvent) -> Option<AppMsg> {
-        // Handle global app keys
-        match event.code {
-            KeyCode::Char('q') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                info!("Ctrl+Q pressed - Quitting");
-                return Some(AppMsg::Quit);
-            }
-            KeyCode::Char('a') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                info!("Ctrl+A pressed - Opening Add Contact Modal");
-                return Some(AppMsg::AddContact);
-            }
-            KeyCode::Char('e') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                info!("Ctrl+E pressed - Opening Edit Contact Modal");
-                return self.selected_contact.clone().map(AppMsg::EditContact);
-            }
-            KeyCode::Char('d') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                info!("Ctrl+D pressed - Opening Delete Modal");
-                return self.selected_contact.clone().map(AppMsg::ConfirmDelete);
+        if let Some(vi) = &self.vi
+            && matches!(self.mode, AppMode::Browse | AppMode::ContactForm)
+        {
+            if vi.handles(event) {
+                return Some(AppMsg::Vi(ViMsg::Key(event)));
+            }
+            // Enter finishes a search and returns to the list, as in vim
+            if vi.mode == ViMode::Insert
+                && self.mode == AppMode::Browse
+                && event.code == KeyCode::Enter
+            {
+                return Some(AppMsg::Vi(ViMsg::SetMode(ViMode::Normal)));
             }
-            _ => {}
         }
 
-        // Handle mode-specific keys
+        // Global bindings win over the mode's own bindings
+        let action = self
+            .keymap
+            .action(KeyScope::Global, event)
+            .or_else(|| self.keymap.action(KeyScope::for_mode(&self.mode), event));
+        if let Some(action) = action {
+            info!("{:?} pressed - {}", event.code, action.name());
+            return self.handle_action(action);
+        }
+
+        // Unbound keys go to the focused component as raw input, except in vi
+        // normal mode where there is no text field to type into
+        if self.is_vi_normal() {
+            return None;
+        }
         match self.mode {
             AppMode::Browse => self.browse.handle_key(event).map(AppMsg::Browse),
             AppMode::ContactForm => self.contact_form.handle_key(event).map(AppMsg::Form),
@@ -213,6 +828,68 @@ impl App {
                 .handle_key(event)
                 .map(AppMsg::DeleteDialog),
             AppMode::Error(_) => self.error_dialog.handle_key(event).map(AppMsg::ErrorDialog),
+            AppMode::Help => self.help.handle_key(event).map(AppMsg::Help),
+            AppMode::Palette => self.palette.handle_key(event).map(AppMsg::Palette),
+            AppMode::Log => self.message_log.handle_key(event).map(AppMsg::MessageLog),
+            AppMode::Duplicates => self.duplicates.handle_key(event).map(AppMsg::Duplicates),
+        }
+    }
+    pub fn handle_action(&self, action: Action) -> Option<AppMsg> {
+        match action {
+            Action::Quit => return Some(AppMsg::Quit),
+            Action::AddContact => return Some(AppMsg::AddContact),
+            Action::EditContact => return self.selected_contact.clone().map(AppMsg::EditContact),
+            Action::DeleteContact => {
+                return self.selected_contact.clone().map(AppMsg::ConfirmDelete);
+            }
+            Action::ToggleFavorite => {
+                return self.selected_contact.clone().map(AppMsg::ToggleFavorite);
+            }
+            Action::Call | Action::Mail | Action::Open => {
+                let contact = self.selected_contact.clone();
+                return contact.map(|contact| AppMsg::RunHook(action, contact));
+            }
+            Action::CopyEmail | Action::CopyPhone | Action::CopyNameEmail | Action::CopyVcard => {
+                let contact = self.selected_contact.clone();
+                return contact.map(|contact| AppMsg::Copy(action, contact));
+            }
+            Action::CycleSort if self.mode == AppMode::Browse => return Some(AppMsg::CycleSort),
+            Action::ReverseSort if self.mode == AppMode::Browse => {
+                return Some(AppMsg::ToggleSortOrder);
+            }
+            // Pressing an overlay's own key again closes it
+            Action::ShowHelp if self.mode == AppMode::Help => {
+                return Some(AppMsg::Help(HelpMsg::Close));
+            }
+            Action::CommandPalette if self.mode == AppMode::Palette => {
+                return Some(AppMsg::Palette(PaletteMsg::Cancel));
+            }
+            Action::ShowLog if self.mode == AppMode::Log => {
+                return Some(AppMsg::MessageLog(MessageLogMsg::Close));
+            }
+            Action::ShowHelp => return Some(AppMsg::ShowHelp),
+            Action::ShowLog => return Some(AppMsg::ShowLog),
+            Action::Export => return Some(AppMsg::Export),
+            Action::ShowDuplicates => return Some(AppMsg::ShowDuplicates),
+            Action::CommandPalette => return Some(AppMsg::OpenPalette),
+            _ => {}
+        }
+
+        match self.mode {
+            AppMode::Browse => self.browse.handle_action(action).map(AppMsg::Browse),
+            AppMode::ContactForm => self.contact_form.handle_action(action).map(AppMsg::Form),
+            AppMode::Delete => self
+                .delete_confirmation
+                .handle_action(action)
+                .map(AppMsg::DeleteDialog),
+            AppMode::Error(_) => self
+                .error_dialog
+                .handle_action(action)
+                .map(AppMsg::ErrorDialog),
+            AppMode::Help => self.help.handle_action(action).map(AppMsg::Help),
+            AppMode::Palette => self.palette.handle_action(action).map(AppMsg::Palette),
+            AppMode::Log => self.message_log.handle_action(action).map(AppMsg::MessageLog),
+            AppMode::Duplicates => self.duplicates.handle_action(action).map(AppMsg::Duplicates),
         }
     }
     pub fn update<ParentMsg>(
@@ -220,6 +897,10 @@ impl App {
         msg: AppMsg,
         map: impl Fn(AppOutput) -> ParentMsg,
     ) -> Option<ParentMsg> {
+        // Ticks decide for themselves whether anything visible changed
+        if !matches!(msg, AppMsg::Tick(_)) {
+            self.needs_redraw = true;
+        }
         match msg {
             AppMsg::Quit => {
                 self.should_quit = true;
@@ -227,12 +908,15 @@ impl App {
                 None
             }
             AppMsg::Browse(browse_msg) => {
+                // A hook's result stays up until the user carries on
+                self.status_bar.update(StatusBarMsg::UpdateMessage(None));
                 match self.browse.update(browse_msg, |output| output) {
                     Some(BrowseOutput::ContactSelected(contact)) => {
                         info!("Contact selected: {:?}", contact);
                         self.selected_contact = Some(contact);
                     }
                     Some(BrowseOutput::ContactActivated(contact)) => {
+                        self.send_db(DbRequest::TouchContact(contact.id));
                         self.selected_contact = Some(contact);
                         self.should_quit = true;
                     }
@@ -247,24 +931,16 @@ impl App {
                 match form_output {
                     Some(FormOutput::Submitted(contact)) => {
                         info!("Contact submitted: {:?}", contact);
+                        // The form closes once the worker confirms the save
                         if contact.id == 0 {
-                            let result = self.db.add_contact(contact);
-                            if let Err(err) = result {
-                                return Some(map(AppMsg::ShowError(err.to_string())));
-                            }
+                            self.send_db(DbRequest::AddContact(*contact));
                         } else {
-                            let result = self.db.update_contact(contact.id, contact);
-                            if let Err(err) = result {
-                                return Some(map(AppMsg::ShowError(err.to_string())));
-                            }
+                            self.send_db(DbRequest::UpdateContact(*contact));
                         }
-                        self.refresh_contacts();
-                        self.mode = AppMode::Browse;
-                        self.update_status_bar_mode();
                     }
                     Some(FormOutput::Cancelled) => {
-                        self.mode = AppMode::Browse;
-                        self.update_status_bar_mode();
+                        self.set_mode(AppMode::Browse);
+                        self.should_quit = self.form_only;
                     }
                     None => {}
                 }
@@ -274,18 +950,12 @@ impl App {
             AppMsg::DeleteDialog(delete_msg) => {
                 match self.delete_confirmation.update(delete_msg, |output| output) {
                     Some(DeleteOutput::Confirmed(contact)) => {
-                        self.mode = AppMode::Browse;
-                        let result = self.db.delete_contact(contact.id);
-                        self.refresh_contacts();
-                        self.update_status_bar_mode();
-                        if let Err(err) = result {
-                            return Some(map(AppMsg::ShowError(err.to_string())));
-                        }
+                        self.set_mode(AppMode::Browse);
+                        self.send_db(DbRequest::DeleteContact(*contact));
                         None
                     }
                     Some(DeleteOutput::Cancelled) => {
-                        self.mode = AppMode::Browse;
-                        self.update_status_bar_mode();
+                        self.set_mode(AppMode::Browse);
                         None
                     }
                     None => None,
@@ -300,31 +970,156 @@ impl App {
                 None
             }
             AppMsg::AddContact => {
-                self.mode = AppMode::ContactForm;
+                self.set_mode(AppMode::ContactForm);
                 self.contact_form.set_contact(Contact::default());
-                self.update_status_bar_mode();
                 None
             }
             AppMsg::EditContact(_contact) => {
-                self.mode = AppMode::ContactForm;
+                self.set_mode(AppMode::ContactForm);
                 if let Some(contact) = &self.selected_contact {
                     self.contact_form.set_contact(contact.clone());
                 }
-                self.update_status_bar_mode();
                 None
             }
             AppMsg::ConfirmDelete(contact) => {
                 self.delete_confirmation.set_contact(contact);
-                self.mode = AppMode::Delete;
-                self.update_status_bar_mode();
+                self.set_mode(AppMode::Delete);
+                None
+            }
+            AppMsg::RunHook(action, contact) => self.run_hook(action, contact).map(map),
+            AppMsg::Hook(reply) => self.handle_hook_reply(*reply).map(map),
+            AppMsg::Copy(action, contact) => Some(map(self.copy(action, &contact))),
+            AppMsg::ToggleFavorite(contact) => {
+                let favorite = !contact.favorite;
+                self.send_db(DbRequest::SetFavorite(contact, favorite));
+                None
+            }
+            AppMsg::CycleSort => {
+                let sort = self.browse.sort();
+                self.set_sort(Sort::new(sort.key.next(), sort.order));
                 None
             }
+            AppMsg::ToggleSortOrder => {
+                let sort = self.browse.sort();
+                self.set_sort(Sort::new(sort.key, sort.order.toggle()));
+                None
+            }
+            AppMsg::ShowHelp => {
+                self.enter_overlay(AppMode::Help);
+                self.help.set_sections(self.help_sections());
+                None
+            }
+            AppMsg::ShowLog => {
+                self.message_log.set_entries(self.notifications.log());
+                self.message_log.update(MessageLogMsg::Bottom, |output| output);
+                self.enter_overlay(AppMode::Log);
+                None
+            }
+            AppMsg::Export => {
+                let stamp = format_timestamp(SystemTime::now()).replace([' ', ':'], "-");
+                let path = self.export_dir.join(format!("rolodex-{stamp}.json"));
+                self.send_db(DbRequest::Export(path));
+                None
+            }
+            AppMsg::ShowDuplicates => {
+                self.send_db(DbRequest::FindDuplicates);
+                None
+            }
+            AppMsg::Duplicates(duplicates_msg) => {
+                match self.duplicates.update(duplicates_msg, |output| output) {
+                    Some(DuplicatesOutput::Selected(contact)) => {
+                        self.close_overlay();
+                        self.show_contact(contact.id);
+                    }
+                    Some(DuplicatesOutput::Closed) => self.close_overlay(),
+                    None => {}
+                }
+                None
+            }
+            AppMsg::MessageLog(log_msg) => {
+                if let Some(MessageLogOutput::Closed) =
+                    self.message_log.update(log_msg, |output| output)
+                {
+                    self.close_overlay();
+                }
+                None
+            }
+            AppMsg::Notify(level, message) => {
+                info!("Notify {}: {message}", level.label());
+                self.notifications
+                    .update(NotificationsMsg::Push(level, message));
+                if self.mode == AppMode::Log {
+                    self.message_log.set_entries(self.notifications.log());
+                }
+                None
+            }
+            AppMsg::Tick(now) => {
+                if self.focused {
+                    let toasts = self.notifications.toasts().count();
+                    self.notifications.update(NotificationsMsg::Tick(now));
+                    self.needs_redraw |= self.notifications.toasts().count() != toasts;
+                }
+                if self.worker.activity().is_some() || self.hooks.is_busy() {
+                    self.status_bar.update(StatusBarMsg::Tick);
+                    self.needs_redraw = true;
+                }
+                // Pick up edits from other processes, unless something of
+                // ours is still on its way to the database
+                let due = now.saturating_duration_since(self.last_refresh) >= REFRESH_INTERVAL;
+                if due && self.mode == AppMode::Browse && !self.worker.is_busy() {
+                    self.last_refresh = now;
+                    self.send_db(DbRequest::Refresh);
+                }
+                None
+            }
+            AppMsg::Resize(columns, rows) => {
+                // The terminal picks up the new size on the next draw
+                debug!("Resized to {columns}x{rows}");
+                None
+            }
+            AppMsg::Focus(focused) => {
+                debug!("Focus {}", if focused { "gained" } else { "lost" });
+                if focused && !self.focused {
+                    self.notifications
+                        .update(NotificationsMsg::Restart(Instant::now()));
+                }
+                self.focused = focused;
+                None
+            }
+            AppMsg::Db(reply) => self.handle_db_reply(*reply).map(map),
+            AppMsg::OpenPalette => {
+                self.palette.open(self.palette_commands());
+                self.enter_overlay(AppMode::Palette);
+                None
+            }
+            AppMsg::Help(help_msg) => {
+                if let Some(HelpOutput::Closed) = self.help.update(help_msg, |output| output) {
+                    self.close_overlay();
+                }
+                None
+            }
+            AppMsg::Palette(palette_msg) => {
+                match self.palette.update(palette_msg, |output| output)? {
+                    PaletteOutput::Run(action) => {
+                        self.close_overlay();
+                        self.handle_action(action).map(map)
+                    }
+                    PaletteOutput::Cancelled => {
+                        self.close_overlay();
+                        None
+                    }
+                }
+            }
             AppMsg::ShowError(error) => {
                 self.error_dialog.set_error(&error);
-                self.mode = AppMode::Error(error);
-                self.update_status_bar_mode();
+                self.set_mode(AppMode::Error(error));
                 None
             }
+            AppMsg::Vi(vi_msg) => {
+                let command = self.vi.as_mut().and_then(|vi| vi.update(vi_msg));
+                self.update_status_bar_mode();
+                command.and_then(|command| self.vi_command(command)).map(map)
+            }
             AppMsg::StatusBar(_) => {
                 // Status bar messages don't need handling at app level
                 None
@@ -349,6 +1144,10 @@ impl Component for App {
         self.handle_key(key)
     }
 
+    fn handle_mouse(&self, event: MouseEvent) -> Option<Self::Msg> {
+        self.handle_mouse(event)
+    }
+
     fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
         self.draw(f, area, focused)
     }
@@ -1,5 +1,6 @@
-use crossterm::event::{KeyCode, KeyEvent};
-use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use std::rc::Rc;
+
+use crossterm::event::{KeyEvent, MouseEvent};
 use ratatui::prelude::*;
 
 use crate::{
@@ -8,7 +9,9 @@ use crate::{
         contact_list::{ContactList, ContactListMsg, ContactListOutput},
         input::{Input, InputMode, InputMsg, InputOutput},
     },
-    model::Contact,
+    keymap::Action,
+    model::{Contact, ContactFilter, Sort},
+    theme::Theme,
 };
 
 pub enum BrowseMsg {
@@ -24,41 +27,66 @@ pub enum BrowseOutput {
 pub struct Browse {
     pub search: Input,
     pub contact_list: ContactList,
-    pub all_contacts: Vec<Contact>,
+    /// Shared with the list, which shows rows as indices into it.
+    pub all_contacts: Rc<[Contact]>,
+    filter: ContactFilter,
 }
 
 impl Browse {
-    pub fn new(contacts: &[Contact]) -> Self {
+    pub fn new(contacts: Vec<Contact>) -> Self {
+        let all_contacts: Rc<[Contact]> = contacts.into();
+        let sort = Sort::default();
         Self {
             search: Input::new("Search", "", 10, InputMode::Regular, 40),
-            contact_list: ContactList::new(contacts),
-            all_contacts: contacts.to_vec(),
+            contact_list: ContactList::new(Rc::clone(&all_contacts)),
+            filter: ContactFilter::new(&all_contacts, sort),
+            all_contacts,
         }
     }
-    pub fn set_contacts(&mut self, contacts: &[Contact]) {
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.search.set_theme(theme);
+        self.contact_list.set_theme(theme);
+    }
+    pub fn sort(&self) -> Sort {
+        self.contact_list.sort
+    }
+    pub fn set_sort(&mut self, sort: Sort) {
         let query = self.search.value.clone();
-        self.all_contacts = contacts.to_vec();
+        self.contact_list.sort = sort;
+        self.filter.set_sort(&self.all_contacts, sort);
         self.filter_contacts(&query);
     }
+    /// Replace the contact list, keeping the same contact selected if it is
+    /// still shown, e.g. after starring it moves it to the top.
+    pub fn set_contacts(&mut self, contacts: Vec<Contact>) {
+        let query = self.search.value.clone();
+        let selected = self.contact_list.get_selected_contact();
+        self.all_contacts = contacts.into();
+        self.filter.set_contacts(&self.all_contacts);
+        self.filter_contacts(&query);
+        if let Some(contact) = selected {
+            self.contact_list.select_id(contact.id);
+        }
+    }
     pub fn handle_key(&self, event: KeyEvent) -> Option<BrowseMsg> {
-        match event.code {
-            KeyCode::Home
-            | KeyCode::End
-            | KeyCode::PageUp
-            | KeyCode::PageDown
-            | KeyCode::Up
-            | KeyCode::Down
-            | KeyCode::Enter => self.contact_list.handle_key(event).map(BrowseMsg::List),
-            _ => self.search.handle_key(event).map(BrowseMsg::Input),
+        self.search.handle_key(event).map(BrowseMsg::Input)
+    }
+    pub fn handle_mouse(&self, event: MouseEvent) -> Option<BrowseMsg> {
+        self.contact_list.handle_mouse(event).map(BrowseMsg::List)
+    }
+    pub fn handle_action(&self, action: Action) -> Option<BrowseMsg> {
+        match action {
+            Action::ClearInput => self.search.handle_action(action).map(BrowseMsg::Input),
+            _ => self.contact_list.handle_action(action).map(BrowseMsg::List),
         }
     }
-    pub fn draw(&self, f: &mut Frame, area: Rect, _focused: bool) {
+    pub fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Length(3), Constraint::Min(0)])
             .split(area);
 
-        self.search.draw(f, chunks[0], true);
+        self.search.draw(f, chunks[0], focused);
         self.contact_list.draw(f, chunks[1], false);
     }
     pub fn update<ParentMsg>(
@@ -88,31 +116,8 @@ impl Browse {
         }
     }
     fn filter_contacts(&mut self, query: &str) {
-        let matcher = SkimMatcherV2::default();
-
-        if query.trim().is_empty() {
-            self.contact_list.filtered_contacts = self.all_contacts.clone();
-        }
-
-        let mut matches: Vec<(i64, &Contact)> = self
-            .all_contacts
-            .iter()
-            .filter_map(|c| {
-                let haystack = format!(
-                    "{} {} {} {}",
-                    c.name,
-                    c.company.as_deref().unwrap_or(""),
-                    c.email.as_deref().unwrap_or(""),
-                    c.phone.as_deref().unwrap_or("")
-                );
-                matcher
-                    .fuzzy_match(&haystack, query)
-                    .map(|score| (score, c))
-            })
-            .collect();
-        matches.sort_by(|a, b| b.0.cmp(&a.0)); // descending score
-
-        self.contact_list.filtered_contacts = matches.into_iter().map(|(_, c)| c.clone()).collect();
+        let rows = self.filter.apply(query);
+        self.contact_list.set_rows(Rc::clone(&self.all_contacts), rows);
     }
 }
 
@@ -132,6 +137,14 @@ impl Component for Browse {
         self.handle_key(key)
     }
 
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
+    }
+
+    fn handle_mouse(&self, event: MouseEvent) -> Option<Self::Msg> {
+        self.handle_mouse(event)
+    }
+
     fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
         self.draw(f, area, focused);
     }
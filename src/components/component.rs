@@ -1,6 +1,8 @@
-use crossterm::event::KeyEvent;
+use crossterm::event::{KeyEvent, MouseEvent};
 use ratatui::{Frame, layout::Rect};
 
+use crate::keymap::Action;
+
 pub trait Component {
     type Msg;
     type Output;
@@ -15,6 +17,17 @@ pub trait Component {
     /// Map a key event to an internal message.
     fn handle_key(&self, key: KeyEvent) -> Option<Self::Msg>;
 
+    /// Map a keymap action to an internal message.
+    fn handle_action(&self, _action: Action) -> Option<Self::Msg> {
+        None
+    }
+
+    /// Map a mouse event to an internal message, hit-testing against the
+    /// areas recorded during the last `draw`.
+    fn handle_mouse(&self, _event: MouseEvent) -> Option<Self::Msg> {
+        None
+    }
+
     /// Draw the component to the given area.
     fn draw(&self, f: &mut Frame, area: Rect, focused: bool);
 }
@@ -1,7 +1,18 @@
-use crossterm::event::{KeyCode, KeyEvent};
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{prelude::*, widgets::*};
 
-use crate::{components::Component, model::Contact};
+use crate::{
+    components::Component,
+    keymap::Action,
+    model::{Contact, Sort, SortKey},
+    theme::Theme,
+};
 
 pub enum ContactListMsg {
     Activate,
@@ -11,6 +22,16 @@ pub enum ContactListMsg {
     Last,
     PgUp,
     PgDown,
+    /// Move up by a number of half pages.
+    HalfPageUp(usize),
+    /// Move down by a number of half pages.
+    HalfPageDown(usize),
+    /// Move the selection by a signed number of rows, clamped to the list.
+    Move(isize),
+    /// Select an absolute row, clamped to the list.
+    Jump(usize),
+    /// Mouse click on a row; a second click on the same row activates it.
+    Click(usize),
 }
 
 pub enum ContactListOutput {
@@ -19,24 +40,110 @@ pub enum ContactListOutput {
 }
 
 pub struct ContactList {
-    pub filtered_contacts: Vec<Contact>,
+    contacts: Rc<[Contact]>,
+    /// Indices into `contacts` of the rows shown, in display order.
+    rows: Vec<usize>,
     pub selected_index: usize,
+    pub sort: Sort,
+    /// Number of rows visible in the last draw, used for half-page motions.
+    visible_rows: Cell<usize>,
+    /// Row area from the last draw, for mouse hit-testing.
+    rows_area: Cell<Rect>,
+    /// First row drawn. Only the visible window of rows is rendered.
+    offset: Cell<usize>,
+    last_click: Option<(usize, Instant)>,
+    theme: Theme,
 }
 
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
+const SCROLL_ROWS: isize = 3;
+/// Marker drawn before starred contacts.
+const FAVORITE: &str = "★";
+
 impl ContactList {
-    pub fn new(contacts: &[Contact]) -> Self {
+    pub fn new(contacts: Rc<[Contact]>) -> Self {
         Self {
-            filtered_contacts: contacts.to_vec(),
+            rows: (0..contacts.len()).collect(),
+            contacts,
             selected_index: 0,
+            sort: Sort::default(),
+            visible_rows: Cell::new(0),
+            rows_area: Cell::new(Rect::default()),
+            offset: Cell::new(0),
+            last_click: None,
+            theme: Theme::default(),
         }
     }
-    pub fn get_selected_contact(&self) -> Option<Contact> {
-        if self.selected_index < self.filtered_contacts.len() {
-            Some(self.filtered_contacts[self.selected_index].clone())
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+    }
+    /// Show `rows`, given as indices into `contacts`, keeping the selection
+    /// within the new list.
+    pub fn set_rows(&mut self, contacts: Rc<[Contact]>, rows: Vec<usize>) {
+        self.contacts = contacts;
+        self.rows = rows;
+        self.selected_index = self.selected_index.min(self.rows.len().saturating_sub(1));
+    }
+    /// Number of rows shown.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+    /// Move the selection to the row showing the contact with `id`, if any.
+    pub fn select_id(&mut self, id: i64) -> bool {
+        match self.rows.iter().position(|&index| self.contacts[index].id == id) {
+            Some(row) => {
+                self.selected_index = row;
+                true
+            }
+            None => false,
+        }
+    }
+    /// The contact shown at a row.
+    pub fn contact(&self, row: usize) -> Option<&Contact> {
+        self.rows.get(row).map(|&index| &self.contacts[index])
+    }
+    /// Index of the contact drawn at a terminal position, if any.
+    pub fn row_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.rows_area.get();
+        if !area.contains(Position::new(column, row)) {
+            return None;
+        }
+        let index = self.offset.get() + (row - area.y) as usize;
+        (index < self.rows.len()).then_some(index)
+    }
+    fn half_page(&self) -> usize {
+        match self.visible_rows.get() {
+            0 => 5,
+            rows => (rows / 2).max(1),
+        }
+    }
+    fn select_index(&mut self, index: usize) -> Option<Contact> {
+        self.selected_index = index.min(self.rows.len().saturating_sub(1));
+        self.get_selected_contact()
+    }
+    fn heading(&self, label: &str, key: SortKey) -> String {
+        if self.sort.key == key {
+            format!("{label} {}", self.sort.order.arrow())
         } else {
-            None
+            label.to_string()
         }
     }
+    fn title(&self) -> String {
+        match self.sort.key {
+            SortKey::Created | SortKey::Modified | SortKey::LastUsed => format!(
+                "Contacts (by {} {})",
+                self.sort.key.label(),
+                self.sort.order.arrow()
+            ),
+            _ => "Contacts".to_string(),
+        }
+    }
+    pub fn get_selected_contact(&self) -> Option<Contact> {
+        self.contact(self.selected_index).cloned()
+    }
     pub fn update<ParentMsg>(
         &mut self,
         msg: ContactListMsg,
@@ -44,7 +151,7 @@ impl ContactList {
     ) -> Option<ParentMsg> {
         match msg {
             ContactListMsg::Next => {
-                if self.selected_index < self.filtered_contacts.len().saturating_sub(1) {
+                if self.selected_index < self.rows.len().saturating_sub(1) {
                     self.selected_index += 1;
                     return Some(map(ContactListOutput::ContactSelected(
                         self.get_selected_contact().unwrap(),
@@ -61,37 +168,41 @@ impl ContactList {
                 }
                 None
             }
-            ContactListMsg::First => {
-                self.selected_index = 0;
-                Some(map(ContactListOutput::ContactSelected(
-                    self.get_selected_contact().unwrap(),
-                )))
+            ContactListMsg::First => self
+                .select_index(0)
+                .map(|contact| map(ContactListOutput::ContactSelected(contact))),
+            ContactListMsg::Last => self
+                .select_index(usize::MAX)
+                .map(|contact| map(ContactListOutput::ContactSelected(contact))),
+            ContactListMsg::PgUp => self.update(ContactListMsg::Move(-10), map),
+            ContactListMsg::PgDown => self.update(ContactListMsg::Move(10), map),
+            ContactListMsg::HalfPageUp(count) => {
+                let delta = -((self.half_page() * count) as isize);
+                self.update(ContactListMsg::Move(delta), map)
             }
-            ContactListMsg::Last => {
-                self.selected_index = self.filtered_contacts.len().saturating_sub(1);
-                Some(map(ContactListOutput::ContactSelected(
-                    self.get_selected_contact().unwrap(),
-                )))
+            ContactListMsg::HalfPageDown(count) => {
+                let delta = (self.half_page() * count) as isize;
+                self.update(ContactListMsg::Move(delta), map)
             }
-            ContactListMsg::PgUp => {
-                if self.selected_index < 10 {
-                    self.selected_index = 0;
-                } else {
-                    self.selected_index -= 10;
-                }
-                Some(map(ContactListOutput::ContactSelected(
-                    self.get_selected_contact().unwrap(),
-                )))
+            ContactListMsg::Move(delta) => {
+                let index = self.selected_index.saturating_add_signed(delta);
+                self.select_index(index)
+                    .map(|contact| map(ContactListOutput::ContactSelected(contact)))
             }
-            ContactListMsg::PgDown => {
-                if self.selected_index > self.filtered_contacts.len().saturating_sub(10) {
-                    self.selected_index = self.filtered_contacts.len().saturating_sub(1);
-                } else {
-                    self.selected_index += 10;
+            ContactListMsg::Jump(index) => self
+                .select_index(index)
+                .map(|contact| map(ContactListOutput::ContactSelected(contact))),
+            ContactListMsg::Click(index) => {
+                let now = Instant::now();
+                let double = self.last_click.is_some_and(|(last, at)| {
+                    last == index && now.duration_since(at) <= DOUBLE_CLICK
+                });
+                if double {
+                    self.last_click = None;
+                    return self.update(ContactListMsg::Activate, map);
                 }
-                Some(map(ContactListOutput::ContactSelected(
-                    self.get_selected_contact().unwrap(),
-                )))
+                self.last_click = Some((index, now));
+                self.update(ContactListMsg::Jump(index), map)
             }
             ContactListMsg::Activate => self
                 .get_selected_contact()
@@ -99,7 +210,7 @@ impl ContactList {
         }
     }
     fn draw(&self, f: &mut Frame, area: Rect, _focused: bool) {
-        let block = Block::default().borders(Borders::ALL).title("Contacts");
+        let block = Block::default().borders(Borders::ALL).title(self.title());
         f.render_widget(block, area);
 
         let inner = Layout::default()
@@ -110,19 +221,35 @@ impl ContactList {
 
         // Render column headings
         let header = Paragraph::new(format!(
-            "   {:<20} {:<20} {:<35} {:<15}",
-            "Name", "Company", "Email", "Phone"
+            "     {:<20} {:<20} {:<35} {:<15}",
+            self.heading("Name", SortKey::Name),
+            self.heading("Company", SortKey::Company),
+            self.heading("Email", SortKey::Email),
+            "Phone"
         ))
-        .style(Style::default().add_modifier(Modifier::UNDERLINED | Modifier::BOLD));
+        .style(self.theme.header);
 
         f.render_widget(header, inner[0]);
 
-        let items: Vec<ListItem> = self
-            .filtered_contacts
+        // Keep the selection in view, then build items for the visible window only
+        let height = inner[1].height as usize;
+        let len = self.rows.len();
+        let mut offset = self.offset.get().min(len.saturating_sub(height));
+        if self.selected_index < offset {
+            offset = self.selected_index;
+        } else if height > 0 && self.selected_index >= offset + height {
+            offset = self.selected_index + 1 - height;
+        }
+        self.offset.set(offset);
+        let window = &self.rows[offset.min(len)..(offset + height).min(len)];
+
+        let items: Vec<ListItem> = window
             .iter()
-            .map(|c| {
+            .map(|&index| {
+                let c = &self.contacts[index];
                 ListItem::new(format!(
-                    "{:<20} {:<20} {:<35} {:<15}",
+                    "{} {:<20} {:<20} {:<35} {:<15}",
+                    if c.favorite { FAVORITE } else { " " },
                     c.name,
                     c.company.as_deref().unwrap_or("-"),
                     c.email.as_deref().unwrap_or("-"),
@@ -132,37 +259,51 @@ impl ContactList {
             .collect();
 
         let list = List::new(items)
-            .highlight_style(
-                Style::default()
-                    .fg(Color::Cyan)
-                    .add_modifier(Modifier::BOLD),
-            )
+            .highlight_style(self.theme.highlight)
             .highlight_symbol(">> ");
 
+        self.visible_rows.set(height);
+
         let mut state = ListState::default();
-        state.select(Some(self.selected_index));
+        if !window.is_empty() {
+            state.select(Some(self.selected_index - offset));
+        }
 
         f.render_stateful_widget(list, inner[1], &mut state);
+        self.rows_area.set(inner[1]);
 
         // Draw the scrollbar on the right
-        let mut scroll_state = ScrollbarState::new(self.filtered_contacts.len())
+        let mut scroll_state = ScrollbarState::new(len)
             .position(self.selected_index)
-            .content_length(self.filtered_contacts.len());
+            .content_length(len);
 
         let scrollbar = Scrollbar::default()
             .orientation(ScrollbarOrientation::VerticalRight)
-            .thumb_style(Style::default().bg(Color::Cyan));
+            .thumb_style(self.theme.scrollbar);
 
         f.render_stateful_widget(scrollbar, inner[1], &mut scroll_state);
     }
 
-    fn handle_key(&self, key: KeyEvent) -> Option<ContactListMsg> {
-        match key.code {
-            KeyCode::Down => Some(ContactListMsg::Next),
-            KeyCode::Up => Some(ContactListMsg::Prev),
-            KeyCode::Home => Some(ContactListMsg::First),
-            KeyCode::End => Some(ContactListMsg::Last),
-            KeyCode::Enter => Some(ContactListMsg::Activate),
+    fn handle_mouse(&self, event: MouseEvent) -> Option<ContactListMsg> {
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => self
+                .row_at(event.column, event.row)
+                .map(ContactListMsg::Click),
+            MouseEventKind::ScrollDown => Some(ContactListMsg::Move(SCROLL_ROWS)),
+            MouseEventKind::ScrollUp => Some(ContactListMsg::Move(-SCROLL_ROWS)),
+            _ => None,
+        }
+    }
+
+    fn handle_action(&self, action: Action) -> Option<ContactListMsg> {
+        match action {
+            Action::Down => Some(ContactListMsg::Next),
+            Action::Up => Some(ContactListMsg::Prev),
+            Action::First => Some(ContactListMsg::First),
+            Action::Last => Some(ContactListMsg::Last),
+            Action::PageUp => Some(ContactListMsg::PgUp),
+            Action::PageDown => Some(ContactListMsg::PgDown),
+            Action::Select => Some(ContactListMsg::Activate),
             _ => None,
         }
     }
@@ -184,7 +325,16 @@ impl Component for ContactList {
         self.draw(f, area, focused)
     }
 
-    fn handle_key(&self, key: KeyEvent) -> Option<Self::Msg> {
-        self.handle_key(key)
+    fn handle_key(&self, _key: KeyEvent) -> Option<Self::Msg> {
+        // Navigation keys come from the keymap via `handle_action`
+        None
+    }
+
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
+    }
+
+    fn handle_mouse(&self, event: MouseEvent) -> Option<Self::Msg> {
+        self.handle_mouse(event)
     }
 }
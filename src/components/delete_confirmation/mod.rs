@@ -1,36 +1,64 @@
-use crossterm::event::{KeyCode, KeyEvent};
+use std::cell::Cell;
+
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{prelude::*, widgets::*};
 
-use crate::{components::Component, model::Contact};
+use crate::{components::Component, keymap::Action, model::Contact, theme::Theme};
 
 pub enum DeleteMsg {
     Yes,
     No,
 }
 pub enum DeleteOutput {
-    Confirmed(Contact),
+    Confirmed(Box<Contact>),
     Cancelled,
 }
 
 #[derive(Debug, Default)]
 pub struct DeleteConfirmation {
     contact: Contact,
+    confirm_hint: String,
+    cancel_hint: String,
+    theme: Theme,
+    /// Button areas from the last draw, for mouse hit-testing.
+    confirm_area: Cell<Rect>,
+    cancel_area: Cell<Rect>,
 }
 
 impl DeleteConfirmation {
     pub fn new() -> Self {
         Self::default()
     }
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+    }
+    pub fn set_hints(&mut self, confirm: &str, cancel: &str) {
+        self.confirm_hint = confirm.to_string();
+        self.cancel_hint = cancel.to_string();
+    }
     pub fn set_contact(&mut self, contact: Contact) {
         self.contact = contact;
     }
-    pub fn handle_key(&self, event: KeyEvent) -> Option<DeleteMsg> {
-        match event.code {
-            KeyCode::Char('y') => Some(DeleteMsg::Yes),
-            KeyCode::Char('n') | KeyCode::Esc => Some(DeleteMsg::No),
+    pub fn handle_action(&self, action: Action) -> Option<DeleteMsg> {
+        match action {
+            Action::Confirm => Some(DeleteMsg::Yes),
+            Action::Cancel => Some(DeleteMsg::No),
             _ => None,
         }
     }
+    pub fn handle_mouse(&self, event: MouseEvent) -> Option<DeleteMsg> {
+        if event.kind != MouseEventKind::Down(MouseButton::Left) {
+            return None;
+        }
+        let position = Position::new(event.column, event.row);
+        if self.confirm_area.get().contains(position) {
+            Some(DeleteMsg::Yes)
+        } else if self.cancel_area.get().contains(position) {
+            Some(DeleteMsg::No)
+        } else {
+            None
+        }
+    }
     pub fn draw(&self, f: &mut Frame, area: Rect, _focused: bool) {
         f.render_widget(Clear, area);
 
@@ -38,7 +66,7 @@ impl DeleteConfirmation {
         let outer_block = Block::default()
             .title(" Confirm Delete ")
             .borders(Borders::ALL)
-            .style(Style::default().fg(Color::White).bg(Color::Black));
+            .style(self.theme.dialog);
         let inner_area = outer_block.inner(area);
         f.render_widget(outer_block, area);
 
@@ -55,7 +83,7 @@ impl DeleteConfirmation {
         let contact_area = chunks[1];
         let prompt_area = chunks[2];
         let msg = Paragraph::new("\n  Are you sure you want to delete this contact?")
-            .style(Style::default().fg(Color::Yellow))
+            .style(self.theme.dialog_message)
             .alignment(Alignment::Left);
         f.render_widget(msg, msg_area);
 
@@ -81,15 +109,26 @@ impl DeleteConfirmation {
         );
 
         let contact_paragraph = Paragraph::new(contact_text)
-            .style(Style::default().fg(Color::Gray))
+            .style(self.theme.dialog_text)
             .alignment(Alignment::Left);
 
         f.render_widget(contact_paragraph, inset_area);
 
-        let hint = Paragraph::new("\n[Y] Confirm  |  [N] Cancel")
-            .style(Style::default().fg(Color::White))
-            .alignment(Alignment::Center);
-        f.render_widget(hint, prompt_area);
+        // Clickable buttons, labelled with their key bindings
+        let confirm = format!("[ {} ]", self.confirm_hint);
+        let cancel = format!("[ {} ]", self.cancel_hint);
+        let [_, buttons_area] = Layout::vertical([Constraint::Length(1); 2]).areas(prompt_area);
+        let [confirm_area, _, cancel_area] = Layout::horizontal([
+            Constraint::Length(confirm.chars().count() as u16),
+            Constraint::Length(3),
+            Constraint::Length(cancel.chars().count() as u16),
+        ])
+        .flex(layout::Flex::Center)
+        .areas(buttons_area);
+        f.render_widget(Paragraph::new(confirm).style(self.theme.dialog), confirm_area);
+        f.render_widget(Paragraph::new(cancel).style(self.theme.dialog), cancel_area);
+        self.confirm_area.set(confirm_area);
+        self.cancel_area.set(cancel_area);
     }
     pub fn update<ParentMsg>(
         &mut self,
@@ -97,7 +136,7 @@ impl DeleteConfirmation {
         map: impl Fn(DeleteOutput) -> ParentMsg,
     ) -> Option<ParentMsg> {
         match msg {
-            DeleteMsg::Yes => Some(map(DeleteOutput::Confirmed(self.contact.clone()))),
+            DeleteMsg::Yes => Some(map(DeleteOutput::Confirmed(Box::new(self.contact.clone())))),
             DeleteMsg::No => Some(map(DeleteOutput::Cancelled)),
         }
     }
@@ -117,7 +156,13 @@ impl Component for DeleteConfirmation {
     fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
         self.draw(f, area, focused)
     }
-    fn handle_key(&self, event: KeyEvent) -> Option<Self::Msg> {
-        self.handle_key(event)
+    fn handle_key(&self, _event: KeyEvent) -> Option<Self::Msg> {
+        None
+    }
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
+    }
+    fn handle_mouse(&self, event: MouseEvent) -> Option<Self::Msg> {
+        self.handle_mouse(event)
     }
 }
@@ -0,0 +1,201 @@
+use std::cell::Cell;
+
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::{components::Component, keymap::Action, model::Contact, theme::Theme};
+
+#[derive(Debug, Clone)]
+pub enum DuplicatesMsg {
+    Move(isize),
+    Top,
+    Bottom,
+    Select,
+    Close,
+}
+
+pub enum DuplicatesOutput {
+    /// Jump to this contact in the list.
+    Selected(Box<Contact>),
+    Closed,
+}
+
+/// Groups of contacts that look like the same person, one group under
+/// another, to pick one from and edit or delete.
+#[derive(Debug, Default)]
+pub struct Duplicates {
+    groups: Vec<Vec<Contact>>,
+    /// Index into every contact of every group, in order.
+    selected: usize,
+    scroll: Cell<usize>,
+    visible_rows: Cell<usize>,
+    theme: Theme,
+}
+
+impl Duplicates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+    }
+    pub fn set_groups(&mut self, groups: Vec<Vec<Contact>>) {
+        self.groups = groups;
+        self.selected = 0;
+        self.scroll.set(0);
+    }
+    pub fn groups(&self) -> &[Vec<Contact>] {
+        &self.groups
+    }
+    pub fn selected_contact(&self) -> Option<&Contact> {
+        self.groups.iter().flatten().nth(self.selected)
+    }
+
+    fn len(&self) -> usize {
+        self.groups.iter().map(Vec::len).sum()
+    }
+
+    /// Line of the selected contact, counting a heading above each group.
+    fn selected_line(&self) -> usize {
+        let mut before = 0;
+        for (group, contacts) in self.groups.iter().enumerate() {
+            before += contacts.len();
+            if self.selected < before {
+                return self.selected + group + 1;
+            }
+        }
+        0
+    }
+
+    pub fn handle_action(&self, action: Action) -> Option<DuplicatesMsg> {
+        let page = self.visible_rows.get().max(1) as isize;
+        match action {
+            Action::Up => Some(DuplicatesMsg::Move(-1)),
+            Action::Down => Some(DuplicatesMsg::Move(1)),
+            Action::PageUp => Some(DuplicatesMsg::Move(-page)),
+            Action::PageDown => Some(DuplicatesMsg::Move(page)),
+            Action::First => Some(DuplicatesMsg::Top),
+            Action::Last => Some(DuplicatesMsg::Bottom),
+            Action::Select => Some(DuplicatesMsg::Select),
+            Action::Dismiss => Some(DuplicatesMsg::Close),
+            _ => None,
+        }
+    }
+    pub fn handle_mouse(&self, event: MouseEvent) -> Option<DuplicatesMsg> {
+        match event.kind {
+            MouseEventKind::ScrollDown => Some(DuplicatesMsg::Move(1)),
+            MouseEventKind::ScrollUp => Some(DuplicatesMsg::Move(-1)),
+            _ => None,
+        }
+    }
+    pub fn update<ParentMsg>(
+        &mut self,
+        msg: DuplicatesMsg,
+        map: impl Fn(DuplicatesOutput) -> ParentMsg,
+    ) -> Option<ParentMsg> {
+        let last = self.len().saturating_sub(1);
+        match msg {
+            DuplicatesMsg::Move(delta) => {
+                self.selected = self.selected.saturating_add_signed(delta).min(last);
+                None
+            }
+            DuplicatesMsg::Top => {
+                self.selected = 0;
+                None
+            }
+            DuplicatesMsg::Bottom => {
+                self.selected = last;
+                None
+            }
+            DuplicatesMsg::Select => {
+                let contact = self.selected_contact()?.clone();
+                Some(map(DuplicatesOutput::Selected(Box::new(contact))))
+            }
+            DuplicatesMsg::Close => Some(map(DuplicatesOutput::Closed)),
+        }
+    }
+    pub fn draw(&self, f: &mut Frame, area: Rect, _focused: bool) {
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .title(" Possible duplicates ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(self.theme.dialog)
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        let rows = inner.height as usize;
+        self.visible_rows.set(rows);
+
+        let mut lines = Vec::new();
+        let mut index = 0;
+        for (group, contacts) in self.groups.iter().enumerate() {
+            lines.push(Line::styled(format!("Group {}", group + 1), self.theme.header));
+            for contact in contacts {
+                let details: Vec<&str> = [&contact.email, &contact.phone, &contact.company]
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect();
+                let line = Line::from(vec![
+                    Span::raw(format!("  {:<24} ", contact.name)),
+                    Span::styled(details.join("  "), self.theme.hint),
+                ]);
+                lines.push(if index == self.selected {
+                    line.style(self.theme.highlight)
+                } else {
+                    line
+                });
+                index += 1;
+            }
+        }
+        if lines.is_empty() {
+            lines.push(Line::styled("No possible duplicates", self.theme.hint));
+        }
+
+        // Keep the selected contact, and its group's heading when it fits, in view
+        let line = self.selected_line();
+        let mut scroll = self.scroll.get().min(line.saturating_sub(1));
+        if line >= scroll + rows {
+            scroll = line + 1 - rows.max(1);
+        }
+        self.scroll.set(scroll);
+
+        let total = lines.len();
+        let paragraph = Paragraph::new(lines).block(block).scroll((scroll as u16, 0));
+        f.render_widget(paragraph, area);
+
+        if total > rows {
+            let mut state = ScrollbarState::new(total - rows).position(scroll);
+            let scrollbar = Scrollbar::default()
+                .orientation(ScrollbarOrientation::VerticalRight)
+                .thumb_style(self.theme.scrollbar);
+            f.render_stateful_widget(scrollbar, area.inner(Margin::new(0, 1)), &mut state);
+        }
+    }
+}
+
+impl Component for Duplicates {
+    type Msg = DuplicatesMsg;
+    type Output = DuplicatesOutput;
+
+    fn update<ParentMsg>(
+        &mut self,
+        msg: Self::Msg,
+        map: impl Fn(Self::Output) -> ParentMsg,
+    ) -> Option<ParentMsg> {
+        self.update(msg, map)
+    }
+    fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
+        self.draw(f, area, focused)
+    }
+    fn handle_key(&self, _event: KeyEvent) -> Option<Self::Msg> {
+        None
+    }
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
+    }
+    fn handle_mouse(&self, event: MouseEvent) -> Option<Self::Msg> {
+        self.handle_mouse(event)
+    }
+}
@@ -1,7 +1,7 @@
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::KeyEvent;
 use ratatui::{prelude::*, widgets::*};
 
-use crate::components::Component;
+use crate::{components::Component, keymap::Action, theme::Theme};
 
 pub enum ErrorMsg {
     Dismiss,
@@ -12,17 +12,25 @@ pub enum ErrorOutput {
 #[derive(Debug, Default)]
 pub struct ErrorDialog {
     message: String,
+    hint: String,
+    theme: Theme,
 }
 impl ErrorDialog {
     pub fn new() -> Self {
         Self::default()
     }
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+    }
+    pub fn set_hint(&mut self, hint: &str) {
+        self.hint = hint.to_string();
+    }
     pub fn set_error(&mut self, message: &str) {
         self.message = message.to_string();
     }
-    pub fn handle_key(&self, event: KeyEvent) -> Option<ErrorMsg> {
-        match event.code {
-            KeyCode::Esc => Some(ErrorMsg::Dismiss),
+    pub fn handle_action(&self, action: Action) -> Option<ErrorMsg> {
+        match action {
+            Action::Dismiss => Some(ErrorMsg::Dismiss),
             _ => None,
         }
     }
@@ -30,15 +38,15 @@ impl ErrorDialog {
         f.render_widget(Clear, area);
 
         // Main error message
-        let text = format!("\n{}\n\n[Press Esc to dismiss]", self.message);
+        let text = format!("\n{}\n\n[{}]", self.message, self.hint);
         let paragraph = Paragraph::new(text)
-            .style(Style::default().fg(Color::Red))
+            .style(self.theme.error)
             .alignment(Alignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
                     .title(" Error ")
-                    .style(Style::default().fg(Color::White).bg(Color::Black)),
+                    .style(self.theme.dialog),
             );
         f.render_widget(paragraph, area);
     }
@@ -65,8 +73,12 @@ impl Component for ErrorDialog {
         self.update(msg, map)
     }
 
-    fn handle_key(&self, key: KeyEvent) -> Option<Self::Msg> {
-        self.handle_key(key)
+    fn handle_key(&self, _key: KeyEvent) -> Option<Self::Msg> {
+        None
+    }
+
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
     }
 
     fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
@@ -1,6 +1,6 @@
-use std::iter::repeat_n;
+use std::{cell::RefCell, iter::repeat_n};
 
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{prelude::*, widgets::*};
 use tracing::info;
 
@@ -9,7 +9,9 @@ use crate::{
         component::opt,
         input::{Input, InputMode, InputMsg, InputOutput},
     },
+    keymap::Action,
     model::Contact,
+    theme::Theme,
 };
 
 #[derive(Debug, Clone)]
@@ -17,12 +19,17 @@ pub enum FormMsg {
     Input(InputMsg),
     Next,
     Previous,
+    /// Focus a field by index, e.g. when it is clicked.
+    Focus(usize),
     Submit,
     Cancel,
+    /// Saving was refused because the contact was saved elsewhere since the
+    /// form opened; this is the stored one. Submitting again overwrites it.
+    Conflict(Box<Contact>),
 }
 
 pub enum FormOutput {
-    Submitted(Contact),
+    Submitted(Box<Contact>),
     Cancelled,
 }
 
@@ -47,6 +54,12 @@ pub struct Form {
     contact: Contact,
     focused: usize,
     editing_id: Option<i64>,
+    hint: String,
+    /// Set while the last save was refused as stale.
+    conflict: bool,
+    theme: Theme,
+    /// Field areas from the last draw, for mouse hit-testing.
+    field_areas: RefCell<Vec<Rect>>,
 }
 
 impl Form {
@@ -82,8 +95,19 @@ impl Form {
             contact: contact_clone,
             focused: 0,
             editing_id: None,
+            hint: String::new(),
+            conflict: false,
+            theme: Theme::default(),
+            field_areas: RefCell::default(),
         }
     }
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.fields.iter_mut().for_each(|field| field.set_theme(theme));
+        self.theme = theme.clone();
+    }
+    pub fn set_hint(&mut self, hint: &str) {
+        self.hint = hint.to_string();
+    }
     pub fn set_contact(&mut self, contact: Contact) {
         self.editing_id = Some(contact.id);
         self.contact = contact.clone();
@@ -93,6 +117,10 @@ impl Form {
         self.fields[3].value = contact.phone.unwrap_or_default();
         self.focused = 0;
         self.fields[0].set_focused(true);
+        self.conflict = false;
+    }
+    pub fn has_conflict(&self) -> bool {
+        self.conflict
     }
 
     pub fn update<ParentMsg>(
@@ -138,17 +166,36 @@ impl Form {
                 }
                 None
             }
-            FormMsg::Submit => Some(map(FormOutput::Submitted(self.contact.clone()))),
+            FormMsg::Focus(index) => {
+                if index < self.fields.len() {
+                    self.fields[self.focused].set_focused(false);
+                    self.focused = index;
+                    self.fields[self.focused].set_focused(true);
+                }
+                None
+            }
+            FormMsg::Submit => Some(map(FormOutput::Submitted(Box::new(self.contact.clone())))),
             FormMsg::Cancel => Some(map(FormOutput::Cancelled)),
+            FormMsg::Conflict(current) => {
+                // The edit is kept; the next save is made over the stored version
+                self.contact.version = current.version;
+                self.conflict = true;
+                None
+            }
         }
     }
 
     pub fn draw(&self, f: &mut Frame, area: Rect, _focused: bool) {
         f.render_widget(Clear, area);
 
+        let title = if self.contact.id == 0 {
+            " Add Contact "
+        } else {
+            " Edit Contact "
+        };
         let block = Block::default()
             .borders(Borders::ALL)
-            .title(" Add Contact ")
+            .title(title)
             .border_type(BorderType::Rounded)
             .padding(Padding {
                 left: 2,
@@ -175,23 +222,45 @@ impl Form {
             let is_focused = self.focused == i;
             field.draw(f, chunks[i], is_focused);
         }
+        *self.field_areas.borrow_mut() = chunks[..num_fields].to_vec();
+
+        if self.conflict {
+            let text = Span::styled(
+                "Modified elsewhere since opened – save again to overwrite",
+                self.theme.warning,
+            );
+            f.render_widget(Paragraph::new(text).alignment(Alignment::Center), chunks[num_fields]);
+        }
 
         let button_area = chunks[num_fields + 1];
-        let text = Span::styled(
-            "[Enter] = Save / [Esc] = Cancel",
-            Style::default().fg(Color::DarkGray),
-        );
+        let text = Span::styled(self.hint.as_str(), self.theme.hint);
         let paragraph = Paragraph::new(text).alignment(Alignment::Center);
         f.render_widget(paragraph, button_area);
     }
     pub fn handle_key(&self, event: KeyEvent) -> Option<FormMsg> {
-        match event.code {
-            KeyCode::Tab => Some(FormMsg::Next),
-            KeyCode::BackTab => Some(FormMsg::Previous),
-            KeyCode::Enter => Some(FormMsg::Submit),
-            KeyCode::Esc => Some(FormMsg::Cancel),
+        self.fields[self.focused]
+            .handle_key(event)
+            .map(FormMsg::Input)
+    }
+    pub fn handle_mouse(&self, event: MouseEvent) -> Option<FormMsg> {
+        if event.kind != MouseEventKind::Down(MouseButton::Left) {
+            return None;
+        }
+        let position = Position::new(event.column, event.row);
+        self.field_areas
+            .borrow()
+            .iter()
+            .position(|area| area.contains(position))
+            .map(FormMsg::Focus)
+    }
+    pub fn handle_action(&self, action: Action) -> Option<FormMsg> {
+        match action {
+            Action::NextField => Some(FormMsg::Next),
+            Action::PrevField => Some(FormMsg::Previous),
+            Action::Submit => Some(FormMsg::Submit),
+            Action::Cancel => Some(FormMsg::Cancel),
             _ => self.fields[self.focused]
-                .handle_key(event)
+                .handle_action(action)
                 .map(FormMsg::Input),
         }
     }
@@ -207,6 +276,12 @@ impl crate::components::Component for Form {
     fn handle_key(&self, event: KeyEvent) -> Option<Self::Msg> {
         self.handle_key(event)
     }
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
+    }
+    fn handle_mouse(&self, event: MouseEvent) -> Option<Self::Msg> {
+        self.handle_mouse(event)
+    }
 
     fn update<ParentMsg>(
         &mut self,
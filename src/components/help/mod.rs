@@ -0,0 +1,172 @@
+use std::cell::Cell;
+
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::{components::Component, keymap::Action, theme::Theme};
+
+#[derive(Debug, Clone)]
+pub enum HelpMsg {
+    Scroll(isize),
+    Top,
+    Bottom,
+    Close,
+}
+
+pub enum HelpOutput {
+    Closed,
+}
+
+/// A titled group of `(keys, description)` rows.
+pub type HelpSection = (String, Vec<(String, String)>);
+
+/// Full-screen overlay listing every key binding for the mode it was opened from.
+#[derive(Debug, Default)]
+pub struct Help {
+    sections: Vec<HelpSection>,
+    scroll: usize,
+    visible_rows: Cell<usize>,
+    theme: Theme,
+}
+
+impl Help {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+    }
+    pub fn set_sections(&mut self, sections: Vec<HelpSection>) {
+        self.sections = sections;
+        self.scroll = 0;
+    }
+    pub fn sections(&self) -> &[HelpSection] {
+        &self.sections
+    }
+
+    fn lines(&self) -> Vec<Line<'_>> {
+        let key_width = self
+            .sections
+            .iter()
+            .flat_map(|(_, rows)| rows.iter().map(|(keys, _)| keys.chars().count()))
+            .max()
+            .unwrap_or_default();
+
+        let mut lines = Vec::new();
+        for (title, rows) in &self.sections {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            lines.push(Line::styled(title.as_str(), self.theme.header));
+            for (keys, description) in rows {
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {keys:<key_width$}  "), self.theme.label),
+                    Span::raw(description.as_str()),
+                ]));
+            }
+        }
+        lines
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines().len().saturating_sub(self.visible_rows.get())
+    }
+
+    pub fn handle_action(&self, action: Action) -> Option<HelpMsg> {
+        let page = self.visible_rows.get().max(1) as isize;
+        match action {
+            Action::Up => Some(HelpMsg::Scroll(-1)),
+            Action::Down => Some(HelpMsg::Scroll(1)),
+            Action::PageUp => Some(HelpMsg::Scroll(-page)),
+            Action::PageDown => Some(HelpMsg::Scroll(page)),
+            Action::First => Some(HelpMsg::Top),
+            Action::Last => Some(HelpMsg::Bottom),
+            Action::Dismiss => Some(HelpMsg::Close),
+            _ => None,
+        }
+    }
+    pub fn handle_mouse(&self, event: MouseEvent) -> Option<HelpMsg> {
+        match event.kind {
+            MouseEventKind::ScrollDown => Some(HelpMsg::Scroll(3)),
+            MouseEventKind::ScrollUp => Some(HelpMsg::Scroll(-3)),
+            _ => None,
+        }
+    }
+    pub fn update<ParentMsg>(
+        &mut self,
+        msg: HelpMsg,
+        map: impl Fn(HelpOutput) -> ParentMsg,
+    ) -> Option<ParentMsg> {
+        match msg {
+            HelpMsg::Scroll(delta) => {
+                self.scroll = self
+                    .scroll
+                    .saturating_add_signed(delta)
+                    .min(self.max_scroll());
+                None
+            }
+            HelpMsg::Top => {
+                self.scroll = 0;
+                None
+            }
+            HelpMsg::Bottom => {
+                self.scroll = self.max_scroll();
+                None
+            }
+            HelpMsg::Close => Some(map(HelpOutput::Closed)),
+        }
+    }
+    pub fn draw(&self, f: &mut Frame, area: Rect, _focused: bool) {
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .title(" Key bindings ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(self.theme.dialog)
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        self.visible_rows.set(inner.height as usize);
+
+        let lines = self.lines();
+        let total = lines.len();
+        let paragraph = Paragraph::new(lines)
+            .block(block)
+            .scroll((self.scroll as u16, 0));
+        f.render_widget(paragraph, area);
+
+        if total > inner.height as usize {
+            let mut state = ScrollbarState::new(total.saturating_sub(inner.height as usize))
+                .position(self.scroll);
+            let scrollbar = Scrollbar::default()
+                .orientation(ScrollbarOrientation::VerticalRight)
+                .thumb_style(self.theme.scrollbar);
+            f.render_stateful_widget(scrollbar, area.inner(Margin::new(0, 1)), &mut state);
+        }
+    }
+}
+
+impl Component for Help {
+    type Msg = HelpMsg;
+    type Output = HelpOutput;
+
+    fn update<ParentMsg>(
+        &mut self,
+        msg: Self::Msg,
+        map: impl Fn(Self::Output) -> ParentMsg,
+    ) -> Option<ParentMsg> {
+        self.update(msg, map)
+    }
+    fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
+        self.draw(f, area, focused)
+    }
+    fn handle_key(&self, _event: KeyEvent) -> Option<Self::Msg> {
+        None
+    }
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
+    }
+    fn handle_mouse(&self, event: MouseEvent) -> Option<Self::Msg> {
+        self.handle_mouse(event)
+    }
+}
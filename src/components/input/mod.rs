@@ -1,6 +1,7 @@
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{prelude::*, widgets::*};
-use tracing::info;
+
+use crate::{keymap::Action, theme::Theme};
 
 #[derive(Debug, Default)]
 pub enum InputMode {
@@ -19,6 +20,9 @@ pub enum InputMsg {
     Backspace,
     Delete,
     TypeChar(char),
+    /// Insert pasted text at the cursor. Line breaks are dropped since inputs
+    /// are single-line, and text past the length limit is cut off.
+    Paste(String),
 }
 
 #[derive(Debug, Clone)]
@@ -31,10 +35,12 @@ pub struct Input {
     label: String,
     label_width: u16,
     pub value: String,
+    /// Position in characters, not bytes, so it stays on a char boundary.
     cursor: usize,
     focused: bool,
     mode: InputMode,
     max_len: usize,
+    theme: Theme,
 }
 
 impl Input {
@@ -50,21 +56,38 @@ impl Input {
             label_width,
             value: value.to_string(),
             focused: false,
-            cursor: value.len(),
+            cursor: value.chars().count(),
             mode,
             max_len,
+            theme: Theme::default(),
         }
     }
 
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+    }
+
     pub fn set_focused(&mut self, focused: bool) {
         self.focused = focused;
-        self.cursor = self.value.len();
+        self.cursor = self.char_count();
     }
 
     pub fn set_label(&mut self, label: &str) {
         self.label = label.to_string();
     }
 
+    fn char_count(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    /// Byte offset of the cursor in `value`.
+    fn byte_index(&self) -> usize {
+        self.value
+            .char_indices()
+            .nth(self.cursor)
+            .map_or(self.value.len(), |(i, _)| i)
+    }
+
     pub fn update<ParentMsg>(
         &mut self,
         msg: InputMsg,
@@ -83,7 +106,7 @@ impl Input {
                 None
             }
             InputMsg::CursorRight => {
-                if self.cursor < self.value.len() {
+                if self.cursor < self.char_count() {
                     self.cursor += 1;
                 }
                 None
@@ -93,21 +116,21 @@ impl Input {
                 None
             }
             InputMsg::CursorEnd => {
-                self.cursor = self.value.len();
+                self.cursor = self.char_count();
                 None
             }
             InputMsg::Backspace => {
                 if self.cursor > 0 {
                     self.cursor -= 1;
-                    self.value.remove(self.cursor);
+                    self.value.remove(self.byte_index());
                     Some(map(InputOutput::Changed(self.value.clone())))
                 } else {
                     None
                 }
             }
             InputMsg::Delete => {
-                if self.cursor < self.value.len() {
-                    self.value.remove(self.cursor);
+                if self.cursor < self.char_count() {
+                    self.value.remove(self.byte_index());
                     Some(map(InputOutput::Changed(self.value.clone())))
                 } else {
                     None
@@ -115,13 +138,25 @@ impl Input {
             }
             InputMsg::TypeChar(c) => {
                 if self.value.len() < self.max_len {
-                    self.value.insert(self.cursor, c);
+                    self.value.insert(self.byte_index(), c);
                     self.cursor += 1;
                     Some(map(InputOutput::Changed(self.value.clone())))
                 } else {
                     None
                 }
             }
+            InputMsg::Paste(text) => {
+                let mut changed = false;
+                for c in text.chars().filter(|c| !matches!(c, '\n' | '\r')) {
+                    if self.value.len() >= self.max_len {
+                        break;
+                    }
+                    self.value.insert(self.byte_index(), c);
+                    self.cursor += 1;
+                    changed = true;
+                }
+                changed.then(|| map(InputOutput::Changed(self.value.clone())))
+            }
         }
     }
 
@@ -130,14 +165,14 @@ impl Input {
             .title(self.label.clone())
             .borders(Borders::ALL)
             .border_style(if focused {
-                Style::default().fg(Color::Cyan)
+                self.theme.border_focused
             } else {
                 Style::default()
             });
         f.render_widget(&block, area);
 
         let text_style = if focused {
-            Style::default().fg(Color::Yellow)
+            self.theme.input
         } else {
             Style::default()
         };
@@ -150,7 +185,7 @@ impl Input {
     }
     fn set_cursor_position(&self, f: &mut Frame, area: Rect, focused: bool) {
         if focused {
-            let clamped_cursor = self.cursor.min(self.value.len());
+            let clamped_cursor = self.cursor.min(self.char_count());
             let cursor_x = area.x + clamped_cursor as u16;
             let cursor_y = area.y;
 
@@ -162,7 +197,7 @@ impl Input {
     }
     fn draw_inline(&self, f: &mut Frame, area: Rect, focused: bool) {
         let text_style = if focused {
-            Style::default().fg(Color::Yellow)
+            self.theme.input
         } else {
             Style::default()
         };
@@ -172,7 +207,7 @@ impl Input {
             width = self.label_width as usize
         );
 
-        let label = Paragraph::new(label_text).style(Style::default().fg(Color::Cyan));
+        let label = Paragraph::new(label_text).style(self.theme.label);
         let input = Paragraph::new(self.value.clone()).style(text_style);
         let layout = Layout::default()
             .direction(Direction::Horizontal)
@@ -191,12 +226,22 @@ impl Input {
         }
     }
 
+    pub fn handle_action(&self, action: Action) -> Option<InputMsg> {
+        match action {
+            Action::ClearInput => Some(InputMsg::Clear),
+            _ => None,
+        }
+    }
+
     pub fn handle_key(&self, event: KeyEvent) -> Option<InputMsg> {
+        // Unbound control/alt chords are ignored rather than typed
+        if event
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return None;
+        }
         match event.code {
-            KeyCode::Char('l') if event.modifiers.contains(KeyModifiers::CONTROL) => {
-                info!("Ctrl+L pressed - Clearing input");
-                Some(InputMsg::Clear)
-            }
             KeyCode::Left => Some(InputMsg::CursorLeft),
             KeyCode::Right => Some(InputMsg::CursorRight),
             KeyCode::Home => Some(InputMsg::CursorStart),
@@ -219,6 +264,9 @@ impl crate::components::Component for Input {
     fn handle_key(&self, event: KeyEvent) -> Option<Self::Msg> {
         self.handle_key(event)
     }
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
+    }
 
     fn update<ParentMsg>(
         &mut self,
@@ -0,0 +1,166 @@
+use std::cell::Cell;
+
+use crossterm::event::{KeyEvent, MouseEvent, MouseEventKind};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::{
+    components::{Component, notifications::Notification},
+    keymap::Action,
+    theme::Theme,
+};
+
+#[derive(Debug, Clone)]
+pub enum MessageLogMsg {
+    Scroll(isize),
+    Top,
+    Bottom,
+    Close,
+}
+
+pub enum MessageLogOutput {
+    Closed,
+}
+
+/// Scrollable view of every notification raised this session.
+#[derive(Debug, Default)]
+pub struct MessageLog {
+    entries: Vec<Notification>,
+    scroll: usize,
+    visible_rows: Cell<usize>,
+    theme: Theme,
+}
+
+impl MessageLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+    }
+    /// Replace the entries, keeping the view pinned to the newest one if it was.
+    pub fn set_entries(&mut self, entries: &[Notification]) {
+        let at_bottom = self.scroll >= self.max_scroll();
+        self.entries = entries.to_vec();
+        if at_bottom {
+            self.scroll = self.max_scroll();
+        }
+    }
+    pub fn entries(&self) -> &[Notification] {
+        &self.entries
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.entries.len().saturating_sub(self.visible_rows.get())
+    }
+
+    pub fn handle_action(&self, action: Action) -> Option<MessageLogMsg> {
+        let page = self.visible_rows.get().max(1) as isize;
+        match action {
+            Action::Up => Some(MessageLogMsg::Scroll(-1)),
+            Action::Down => Some(MessageLogMsg::Scroll(1)),
+            Action::PageUp => Some(MessageLogMsg::Scroll(-page)),
+            Action::PageDown => Some(MessageLogMsg::Scroll(page)),
+            Action::First => Some(MessageLogMsg::Top),
+            Action::Last => Some(MessageLogMsg::Bottom),
+            Action::Dismiss => Some(MessageLogMsg::Close),
+            _ => None,
+        }
+    }
+    pub fn handle_mouse(&self, event: MouseEvent) -> Option<MessageLogMsg> {
+        match event.kind {
+            MouseEventKind::ScrollDown => Some(MessageLogMsg::Scroll(3)),
+            MouseEventKind::ScrollUp => Some(MessageLogMsg::Scroll(-3)),
+            _ => None,
+        }
+    }
+    pub fn update<ParentMsg>(
+        &mut self,
+        msg: MessageLogMsg,
+        map: impl Fn(MessageLogOutput) -> ParentMsg,
+    ) -> Option<ParentMsg> {
+        match msg {
+            MessageLogMsg::Scroll(delta) => {
+                // `scroll` may be past the end until the first draw sizes the view
+                self.scroll = self
+                    .scroll
+                    .min(self.max_scroll())
+                    .saturating_add_signed(delta)
+                    .min(self.max_scroll());
+                None
+            }
+            MessageLogMsg::Top => {
+                self.scroll = 0;
+                None
+            }
+            MessageLogMsg::Bottom => {
+                self.scroll = self.max_scroll();
+                None
+            }
+            MessageLogMsg::Close => Some(map(MessageLogOutput::Closed)),
+        }
+    }
+    pub fn draw(&self, f: &mut Frame, area: Rect, _focused: bool) {
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .title(" Message log ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(self.theme.dialog)
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(area);
+        self.visible_rows.set(inner.height as usize);
+
+        let lines: Vec<Line> = if self.entries.is_empty() {
+            vec![Line::styled("No messages yet", self.theme.hint)]
+        } else {
+            self.entries
+                .iter()
+                .map(|entry| {
+                    let style = entry.level.style(&self.theme);
+                    Line::from(vec![
+                        Span::styled(format!("{} ", entry.timestamp()), self.theme.hint),
+                        Span::styled(format!("{:<6}", entry.level.label()), style),
+                        Span::raw(entry.message.as_str()),
+                    ])
+                })
+                .collect()
+        };
+        let scroll = self.scroll.min(self.max_scroll());
+        let paragraph = Paragraph::new(lines).block(block).scroll((scroll as u16, 0));
+        f.render_widget(paragraph, area);
+
+        if self.entries.len() > inner.height as usize {
+            let mut state = ScrollbarState::new(self.max_scroll()).position(scroll);
+            let scrollbar = Scrollbar::default()
+                .orientation(ScrollbarOrientation::VerticalRight)
+                .thumb_style(self.theme.scrollbar);
+            f.render_stateful_widget(scrollbar, area.inner(Margin::new(0, 1)), &mut state);
+        }
+    }
+}
+
+impl Component for MessageLog {
+    type Msg = MessageLogMsg;
+    type Output = MessageLogOutput;
+
+    fn update<ParentMsg>(
+        &mut self,
+        msg: Self::Msg,
+        map: impl Fn(Self::Output) -> ParentMsg,
+    ) -> Option<ParentMsg> {
+        self.update(msg, map)
+    }
+    fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
+        self.draw(f, area, focused)
+    }
+    fn handle_key(&self, _event: KeyEvent) -> Option<Self::Msg> {
+        None
+    }
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
+    }
+    fn handle_mouse(&self, event: MouseEvent) -> Option<Self::Msg> {
+        self.handle_mouse(event)
+    }
+}
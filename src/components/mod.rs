@@ -3,9 +3,14 @@ pub mod browse;
 pub mod component;
 pub mod contact_list;
 pub mod delete_confirmation;
+pub mod duplicates;
 pub mod error_dialog;
 pub mod form;
+pub mod help;
 pub mod input;
+pub mod message_log;
+pub mod notifications;
+pub mod palette;
 //pub mod search;
 pub mod status_bar;
 
@@ -0,0 +1,195 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::KeyEvent;
+use ratatui::{prelude::*, widgets::*};
+
+use crate::{components::Component, theme::Theme};
+
+/// Severity of a notification, which sets its colour and how long its toast stays up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn label(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Success => "ok",
+            Level::Warning => "warn",
+            Level::Error => "error",
+        }
+    }
+
+    pub fn style(self, theme: &Theme) -> Style {
+        match self {
+            Level::Info => theme.info,
+            Level::Success => theme.success,
+            Level::Warning => theme.warning,
+            Level::Error => theme.error,
+        }
+    }
+
+    fn duration(self) -> Duration {
+        match self {
+            Level::Info | Level::Success => Duration::from_secs(3),
+            Level::Warning => Duration::from_secs(5),
+            Level::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+/// A message recorded in the session log.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub level: Level,
+    pub message: String,
+    /// Time since the session started.
+    pub elapsed: Duration,
+}
+
+impl Notification {
+    /// Session-relative timestamp, e.g. `03:25`.
+    pub fn timestamp(&self) -> String {
+        let secs = self.elapsed.as_secs();
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum NotificationsMsg {
+    Push(Level, String),
+    /// Expire toasts whose time is up.
+    Tick(Instant),
+    /// Give every visible toast its full time again, counted from the given
+    /// instant. Used when the terminal regains focus so toasts raised while
+    /// the user was away are not missed.
+    Restart(Instant),
+}
+
+const MAX_TOASTS: usize = 4;
+const TOAST_WIDTH: u16 = 48;
+
+/// Non-blocking toasts drawn over the top-right corner, plus the full log
+/// of every notification raised this session.
+#[derive(Debug)]
+pub struct Notifications {
+    started: Instant,
+    log: Vec<Notification>,
+    /// Index into `log` and expiry time of each visible toast, oldest first.
+    toasts: VecDeque<(usize, Instant)>,
+    theme: Theme,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            log: Vec::new(),
+            toasts: VecDeque::new(),
+            theme: Theme::default(),
+        }
+    }
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+    }
+    pub fn log(&self) -> &[Notification] {
+        &self.log
+    }
+    /// Notifications currently shown as toasts, oldest first.
+    pub fn toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.toasts.iter().map(|(i, _)| &self.log[*i])
+    }
+
+    pub fn update(&mut self, msg: NotificationsMsg) {
+        match msg {
+            NotificationsMsg::Push(level, message) => {
+                let now = Instant::now();
+                self.log.push(Notification {
+                    level,
+                    message,
+                    elapsed: now.duration_since(self.started),
+                });
+                self.toasts
+                    .push_back((self.log.len() - 1, now + level.duration()));
+                if self.toasts.len() > MAX_TOASTS {
+                    self.toasts.pop_front();
+                }
+            }
+            NotificationsMsg::Tick(now) => {
+                self.toasts.retain(|(_, expires)| *expires > now);
+            }
+            NotificationsMsg::Restart(now) => {
+                for (index, expires) in &mut self.toasts {
+                    *expires = now + self.log[*index].level.duration();
+                }
+            }
+        }
+    }
+
+    pub fn draw(&self, f: &mut Frame, area: Rect, _focused: bool) {
+        let width = TOAST_WIDTH.min(area.width);
+        let mut y = area.y;
+        // Newest toast on top
+        for notification in self.toasts().collect::<Vec<_>>().into_iter().rev() {
+            let style = notification.level.style(&self.theme);
+            let text_width = width.saturating_sub(2).max(1) as usize;
+            let lines = notification
+                .message
+                .chars()
+                .count()
+                .div_ceil(text_width)
+                .clamp(1, 3) as u16;
+            let height = lines + 2;
+            if y + height > area.bottom() {
+                break;
+            }
+
+            let toast_area = Rect::new(area.right() - width, y, width, height);
+            let block = Block::default()
+                .title(format!(" {} ", notification.level.label()))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(style)
+                .style(self.theme.dialog);
+            let paragraph = Paragraph::new(notification.message.as_str())
+                .wrap(Wrap { trim: true })
+                .block(block);
+            f.render_widget(Clear, toast_area);
+            f.render_widget(paragraph, toast_area);
+            y += height;
+        }
+    }
+}
+
+impl Component for Notifications {
+    type Msg = NotificationsMsg;
+    type Output = NotificationsMsg;
+
+    fn update<ParentMsg>(
+        &mut self,
+        msg: Self::Msg,
+        _map: impl Fn(Self::Output) -> ParentMsg,
+    ) -> Option<ParentMsg> {
+        self.update(msg);
+        None
+    }
+    fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
+        self.draw(f, area, focused)
+    }
+    fn handle_key(&self, _event: KeyEvent) -> Option<Self::Msg> {
+        None
+    }
+}
@@ -0,0 +1,215 @@
+use std::cell::Cell;
+
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+use ratatui::{prelude::*, widgets::*};
+
+use crate::{
+    components::{
+        Component,
+        input::{Input, InputMode, InputMsg, InputOutput},
+    },
+    keymap::Action,
+    theme::Theme,
+};
+
+#[derive(Debug, Clone)]
+pub enum PaletteMsg {
+    Input(InputMsg),
+    Move(isize),
+    Run,
+    /// Run the command drawn at a row of the list, e.g. when it is clicked.
+    RunAt(usize),
+    Cancel,
+}
+
+pub enum PaletteOutput {
+    Run(Action),
+    Cancelled,
+}
+
+/// An entry in the palette: the action it dispatches and the keys bound to it.
+#[derive(Debug, Clone)]
+pub struct PaletteCommand {
+    pub action: Action,
+    pub keys: String,
+}
+
+/// Fuzzy-searchable list of commands, opened over any mode.
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub query: Input,
+    commands: Vec<PaletteCommand>,
+    /// Indices into `commands` matching the query, best match first.
+    pub matches: Vec<usize>,
+    pub selected: usize,
+    rows_area: Cell<Rect>,
+    theme: Theme,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self {
+            query: Input::new(" Command ", "", 0, InputMode::Regular, 40),
+            ..Default::default()
+        }
+    }
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.query.set_theme(theme);
+        self.theme = theme.clone();
+    }
+    /// Reset the palette with a fresh command list and an empty query.
+    pub fn open(&mut self, commands: Vec<PaletteCommand>) {
+        self.commands = commands;
+        self.query.update(InputMsg::Clear, |_| ());
+        self.filter();
+    }
+    pub fn selected_command(&self) -> Option<&PaletteCommand> {
+        self.matches.get(self.selected).map(|&i| &self.commands[i])
+    }
+
+    fn filter(&mut self) {
+        let query = self.query.value.trim();
+        if query.is_empty() {
+            self.matches = (0..self.commands.len()).collect();
+        } else {
+            let matcher = SkimMatcherV2::default();
+            let mut scored: Vec<(i64, usize)> = self
+                .commands
+                .iter()
+                .enumerate()
+                .filter_map(|(i, command)| {
+                    let haystack =
+                        format!("{} {}", command.action.label(), command.action.description());
+                    matcher.fuzzy_match(&haystack, query).map(|score| (score, i))
+                })
+                .collect();
+            scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+            self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        }
+        self.selected = 0;
+    }
+
+    pub fn handle_key(&self, event: KeyEvent) -> Option<PaletteMsg> {
+        self.query.handle_key(event).map(PaletteMsg::Input)
+    }
+    pub fn handle_action(&self, action: Action) -> Option<PaletteMsg> {
+        match action {
+            Action::Up => Some(PaletteMsg::Move(-1)),
+            Action::Down => Some(PaletteMsg::Move(1)),
+            Action::Select => Some(PaletteMsg::Run),
+            Action::Cancel => Some(PaletteMsg::Cancel),
+            _ => self.query.handle_action(action).map(PaletteMsg::Input),
+        }
+    }
+    pub fn handle_mouse(&self, event: MouseEvent) -> Option<PaletteMsg> {
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let area = self.rows_area.get();
+                if !area.contains(Position::new(event.column, event.row)) {
+                    return None;
+                }
+                let index = (event.row - area.y) as usize;
+                (index < self.matches.len()).then_some(PaletteMsg::RunAt(index))
+            }
+            MouseEventKind::ScrollDown => Some(PaletteMsg::Move(1)),
+            MouseEventKind::ScrollUp => Some(PaletteMsg::Move(-1)),
+            _ => None,
+        }
+    }
+    pub fn update<ParentMsg>(
+        &mut self,
+        msg: PaletteMsg,
+        map: impl Fn(PaletteOutput) -> ParentMsg,
+    ) -> Option<ParentMsg> {
+        match msg {
+            PaletteMsg::Input(input_msg) => {
+                if let Some(InputOutput::Changed(_)) = self.query.update(input_msg, |out| out) {
+                    self.filter();
+                }
+                None
+            }
+            PaletteMsg::Move(delta) => {
+                if !self.matches.is_empty() {
+                    self.selected = self
+                        .selected
+                        .saturating_add_signed(delta)
+                        .min(self.matches.len() - 1);
+                }
+                None
+            }
+            PaletteMsg::Run => self
+                .selected_command()
+                .map(|command| map(PaletteOutput::Run(command.action))),
+            PaletteMsg::RunAt(index) => {
+                self.selected = index;
+                self.update(PaletteMsg::Run, map)
+            }
+            PaletteMsg::Cancel => Some(map(PaletteOutput::Cancelled)),
+        }
+    }
+    pub fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
+        f.render_widget(Clear, area);
+
+        let block = Block::default()
+            .title(" Commands ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(self.theme.dialog);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let [query_area, rows_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(inner);
+        self.query.draw(f, query_area, focused);
+        self.rows_area.set(rows_area);
+
+        let width = rows_area.width as usize;
+        let items: Vec<ListItem> = self
+            .matches
+            .iter()
+            .map(|&i| {
+                let command = &self.commands[i];
+                let description = command.action.description();
+                let padding = width
+                    .saturating_sub(description.chars().count() + command.keys.chars().count() + 2);
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!(" {description}{}", " ".repeat(padding))),
+                    Span::styled(command.keys.as_str(), self.theme.hint),
+                ]))
+            })
+            .collect();
+
+        let mut state = ListState::default();
+        if !self.matches.is_empty() {
+            state.select(Some(self.selected));
+        }
+        let list = List::new(items).highlight_style(self.theme.highlight);
+        f.render_stateful_widget(list, rows_area, &mut state);
+    }
+}
+
+impl Component for Palette {
+    type Msg = PaletteMsg;
+    type Output = PaletteOutput;
+
+    fn update<ParentMsg>(
+        &mut self,
+        msg: Self::Msg,
+        map: impl Fn(Self::Output) -> ParentMsg,
+    ) -> Option<ParentMsg> {
+        self.update(msg, map)
+    }
+    fn draw(&self, f: &mut Frame, area: Rect, focused: bool) {
+        self.draw(f, area, focused)
+    }
+    fn handle_key(&self, event: KeyEvent) -> Option<Self::Msg> {
+        self.handle_key(event)
+    }
+    fn handle_action(&self, action: Action) -> Option<Self::Msg> {
+        self.handle_action(action)
+    }
+    fn handle_mouse(&self, event: MouseEvent) -> Option<Self::Msg> {
+        self.handle_mouse(event)
+    }
+}
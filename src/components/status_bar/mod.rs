@@ -1,9 +1,9 @@
 use crate::components::Component;
 use crate::mode::AppMode;
+use crate::theme::Theme;
 use crossterm::event::KeyEvent;
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
     text::{Line, Span},
     widgets::Paragraph,
     Frame,
@@ -13,13 +13,29 @@ use ratatui::{
 pub enum StatusBarMsg {
     UpdateCounts { total: usize, filtered: usize },
     UpdateMode(AppMode),
+    UpdateHelp(String),
+    UpdateVi(Option<String>),
+    /// What the database worker is busy with, if anything.
+    UpdateActivity(Option<&'static str>),
+    /// Result of the last action hook, shown in place of the key hints.
+    UpdateMessage(Option<String>),
+    /// Advance the busy spinner.
+    Tick,
 }
 
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
 #[derive(Debug, Clone)]
 pub struct StatusBar {
     total_contacts: usize,
     filtered_contacts: usize,
     current_mode: AppMode,
+    help_text: String,
+    vi_status: Option<String>,
+    activity: Option<&'static str>,
+    message: Option<String>,
+    spinner_frame: usize,
+    theme: Theme,
 }
 
 impl Default for StatusBar {
@@ -28,6 +44,12 @@ impl Default for StatusBar {
             total_contacts: 0,
             filtered_contacts: 0,
             current_mode: AppMode::Browse,
+            help_text: String::new(),
+            vi_status: None,
+            activity: None,
+            message: None,
+            spinner_frame: 0,
+            theme: Theme::default(),
         }
     }
 }
@@ -37,6 +59,10 @@ impl StatusBar {
         Self::default()
     }
 
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+    }
+
     pub fn update(&mut self, msg: StatusBarMsg) {
         match msg {
             StatusBarMsg::UpdateCounts { total, filtered } => {
@@ -46,16 +72,32 @@ impl StatusBar {
             StatusBarMsg::UpdateMode(mode) => {
                 self.current_mode = mode;
             }
+            StatusBarMsg::UpdateHelp(help_text) => {
+                self.help_text = help_text;
+            }
+            StatusBarMsg::UpdateVi(vi_status) => {
+                self.vi_status = vi_status;
+            }
+            StatusBarMsg::UpdateActivity(activity) => {
+                self.activity = activity;
+            }
+            StatusBarMsg::UpdateMessage(message) => {
+                self.message = message;
+            }
+            StatusBarMsg::Tick => {
+                self.spinner_frame = (self.spinner_frame + 1) % SPINNER.len();
+            }
         }
     }
 
-    fn get_help_text(&self) -> &'static str {
-        match self.current_mode {
-            AppMode::Browse => "↑↓: Navigate | Enter: Select | Ctrl+A: Add | Ctrl+E: Edit | Ctrl+D: Delete | Ctrl+Q: Quit",
-            AppMode::ContactForm => "Tab: Next Field | Enter: Save | Esc: Cancel",
-            AppMode::Delete => "Y: Confirm | N/Esc: Cancel",
-            AppMode::Error(_) => "Esc: Dismiss",
-        }
+    fn get_help_text(&self) -> &str {
+        self.message.as_deref().unwrap_or(&self.help_text)
+    }
+
+    /// Busy indicator, e.g. `⠙ Saving…`, while the database worker is running.
+    pub fn get_activity_text(&self) -> Option<String> {
+        self.activity
+            .map(|activity| format!("{} {activity}…", SPINNER[self.spinner_frame]))
     }
 
     fn get_counts_text(&self) -> String {
@@ -78,26 +120,38 @@ impl Component for StatusBar {
 
     fn draw(&self, frame: &mut Frame, area: Rect, _focused: bool) {
         let help_text = self.get_help_text();
-        let counts_text = self.get_counts_text();
+        let counts_text = match self.get_activity_text() {
+            Some(activity) => format!("{activity}  {}", self.get_counts_text()),
+            None => self.get_counts_text(),
+        };
 
         // Create layout with help text on left and counts on right
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Min(1), Constraint::Length(counts_text.len() as u16 + 2)])
+            .constraints([
+                Constraint::Min(1),
+                Constraint::Length(counts_text.chars().count() as u16 + 2),
+            ])
             .split(area);
 
-        // Help text paragraph (left side)
-        let help_paragraph = Paragraph::new(Line::from(vec![
-            Span::styled(help_text, Style::default().fg(Color::Gray))
-        ]))
-        .style(Style::default().bg(Color::DarkGray))
+        // Help text paragraph (left side), prefixed by the vi sub-mode when enabled
+        let mut spans = Vec::new();
+        if let Some(vi_status) = &self.vi_status {
+            spans.push(Span::styled(
+                format!("{vi_status} "),
+                self.theme.status_mode,
+            ));
+        }
+        spans.push(Span::styled(help_text, self.theme.status_help));
+        let help_paragraph = Paragraph::new(Line::from(spans))
+        .style(self.theme.status_bar)
         .alignment(Alignment::Left);
 
         // Counts paragraph (right side)
         let counts_paragraph = Paragraph::new(Line::from(vec![
-            Span::styled(counts_text, Style::default().fg(Color::White))
+            Span::styled(counts_text, self.theme.status_counts)
         ]))
-        .style(Style::default().bg(Color::DarkGray))
+        .style(self.theme.status_bar)
         .alignment(Alignment::Right);
 
         frame.render_widget(help_paragraph, chunks[0]);
@@ -1,11 +1,72 @@
-use crate::error::{AppError, AppResult as Result};
+use crate::{
+    clipboard::ClipboardConfig,
+    error::{AppError, AppResult as Result},
+    hooks::{ActionHooks, LifecycleHooks},
+    keymap::KeyConfig,
+    store::StoreKind,
+    sync::SyncConfig,
+    theme::ThemeConfig,
+    tui::Height,
+};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
-use std::{fs, io, path::PathBuf};
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
     pub database_path: PathBuf,
+
+    /// Where contacts are kept: `sqlite` at `database_path`, a hand-editable
+    /// `file` or a passphrase-sealed `encrypted` file at `contacts_file`, or
+    /// `memory` for a session that leaves nothing behind.
+    #[serde(default, skip_serializing_if = "StoreKind::is_default")]
+    pub store: StoreKind,
+
+    /// TOML file, or JSON if the name ends in `.json`, for `store = "file"`,
+    /// or the sealed file for `store = "encrypted"`. Defaults to
+    /// `contacts.toml` or `contacts.enc` next to the database.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contacts_file: Option<PathBuf>,
+
+    /// Key binding overrides, one `[keys.<scope>]` table per mode.
+    #[serde(default, skip_serializing_if = "KeyConfig::is_empty")]
+    pub keys: KeyConfig,
+
+    /// Start in vi-style normal mode with j/k, gg/G, counts and `/` to search.
+    #[serde(default)]
+    pub vi_mode: bool,
+
+    /// Name of a built-in theme (dark, light, high-contrast, monochrome) or a
+    /// custom `[themes.<name>]` table. Ignored when `NO_COLOR` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub themes: BTreeMap<String, ThemeConfig>,
+
+    /// Draw inline below the prompt instead of taking over the screen, using
+    /// this many rows (`20`) or this share of the terminal (`"40%"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<Height>,
+
+    /// Shell commands for the call, mail and open actions, with `{name}`,
+    /// `{email}`, `{phone}`, `{company}` and `{id}` placeholders.
+    #[serde(default, skip_serializing_if = "ActionHooks::is_empty")]
+    pub actions: ActionHooks,
+
+    /// How copied fields reach the clipboard: OSC 52 through the terminal,
+    /// with a command such as `wl-copy` as the fallback.
+    #[serde(default, skip_serializing_if = "ClipboardConfig::is_default")]
+    pub clipboard: ClipboardConfig,
+
+    /// Commands run after contacts are added, updated, deleted or imported,
+    /// with the contacts as JSON on stdin.
+    #[serde(default, skip_serializing_if = "LifecycleHooks::is_default")]
+    pub hooks: LifecycleHooks,
+
+    /// The CardDAV collection `rolodex sync` keeps in step with.
+    #[serde(default, skip_serializing_if = "SyncConfig::is_default")]
+    pub sync: SyncConfig,
 }
 
 impl Config {
@@ -24,6 +85,18 @@ impl Config {
 
         Ok(config)
     }
+
+    /// The file `store = "file"` or `store = "encrypted"` reads and writes.
+    pub fn contacts_file(&self) -> PathBuf {
+        let name = match self.store {
+            StoreKind::Encrypted => "contacts.enc",
+            _ => "contacts.toml",
+        };
+        match &self.contacts_file {
+            Some(path) => path.clone(),
+            None => self.database_path.with_file_name(name),
+        }
+    }
 }
 
 fn config_path() -> Result<PathBuf> {
@@ -43,6 +116,17 @@ fn create_default_config(path: &PathBuf) -> Result<()> {
     let default = Config {
         database_path: default_data_path()
             .ok_or_else(|| AppError::Config("could not determine data dir".into()))?,
+        store: StoreKind::default(),
+        contacts_file: None,
+        keys: KeyConfig::new(),
+        vi_mode: false,
+        theme: None,
+        themes: BTreeMap::new(),
+        height: None,
+        actions: ActionHooks::default(),
+        clipboard: ClipboardConfig::default(),
+        hooks: LifecycleHooks::default(),
+        sync: SyncConfig::default(),
     };
 
     let contents = toml::to_string_pretty(&default)
@@ -58,3 +142,28 @@ fn default_data_path() -> Option<PathBuf> {
     ProjectDirs::from("com", "popplestones", "rolodex")
         .map(|dirs| dirs.data_dir().join("rolodex.db"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contacts_file_defaults_next_to_the_database() {
+        let config = |extra: &str| {
+            let toml = format!("database_path = \"/srv/rolodex/rolodex.db\"\n{extra}");
+            toml::from_str::<Config>(&toml).unwrap()
+        };
+        assert_eq!(
+            config("store = \"file\"").contacts_file(),
+            PathBuf::from("/srv/rolodex/contacts.toml")
+        );
+        assert_eq!(
+            config("store = \"encrypted\"").contacts_file(),
+            PathBuf::from("/srv/rolodex/contacts.enc")
+        );
+        assert_eq!(
+            config("contacts_file = \"/home/me/contacts.json\"").contacts_file(),
+            PathBuf::from("/home/me/contacts.json")
+        );
+    }
+}
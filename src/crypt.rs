@@ -0,0 +1,262 @@
+//! Passphrase-sealed files, for `store = "encrypted"` and its backups.
+//!
+//! A sealed file is a header followed by the contents encrypted with
+//! ChaCha20-Poly1305, keyed by Argon2id from the passphrase:
+//!
+//! | Bytes | Field |
+//! |---|---|
+//! | 8 | `RLDXSEAL` |
+//! | 12 | Argon2id memory (KiB), passes and lanes, `u32` little-endian each |
+//! | 16 | Salt |
+//! | 12 | Nonce |
+//! | rest | Ciphertext and tag |
+//!
+//! The header is authenticated along with the contents, so a wrong
+//! passphrase and a tampered file fail the same way.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    ChaCha20Poly1305, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use rand::RngCore;
+
+use crate::error::{AppError, AppResult as Result};
+
+const MAGIC: &[u8; 8] = b"RLDXSEAL";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const HEADER_LEN: usize = MAGIC.len() + 12 + SALT_LEN + NONCE_LEN;
+
+/// Memory cost above which a header is refused rather than trusted, so a
+/// hostile file can't ask for all of RAM. 1 GiB.
+const MAX_MEMORY_KIB: u32 = 1 << 20;
+/// Likewise for passes, so it can't make opening take hours, and lanes.
+const MAX_PASSES: u32 = 16;
+const MAX_LANES: u32 = 16;
+
+/// Scripts and cron jobs set this instead of answering the prompt.
+pub const PASSPHRASE_VAR: &str = "ROLODEX_PASSPHRASE";
+/// And this for the passphrase `rolodex rekey` changes to.
+pub const NEW_PASSPHRASE_VAR: &str = "ROLODEX_NEW_PASSPHRASE";
+
+/// Argon2id cost, kept in each file so it can be raised without breaking
+/// older files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Kdf {
+    memory_kib: u32,
+    passes: u32,
+    lanes: u32,
+}
+
+impl Default for Kdf {
+    /// The parameters recommended for Argon2id by OWASP.
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            passes: Params::DEFAULT_T_COST,
+            lanes: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// A key derived from a passphrase, with the salt and cost it was derived
+/// with. Deriving is slow on purpose, so a store derives once and seals
+/// every save with the same key and a fresh nonce.
+#[derive(Clone)]
+pub struct Key {
+    key: [u8; 32],
+    salt: [u8; SALT_LEN],
+    kdf: Kdf,
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Key").field("kdf", &self.kdf).finish_non_exhaustive()
+    }
+}
+
+impl Key {
+    /// A key for `passphrase` with a new salt.
+    pub fn new(passphrase: &str) -> Result<Self> {
+        let mut salt = [0; SALT_LEN];
+        rand::rng().fill_bytes(&mut salt);
+        Self::derive(passphrase, salt, Kdf::default())
+    }
+
+    fn derive(passphrase: &str, salt: [u8; SALT_LEN], kdf: Kdf) -> Result<Self> {
+        if kdf.memory_kib > MAX_MEMORY_KIB {
+            return Err(AppError::Crypto(format!("key needs {} KiB of memory", kdf.memory_kib)));
+        }
+        if kdf.passes > MAX_PASSES {
+            return Err(AppError::Crypto(format!("key needs {} passes", kdf.passes)));
+        }
+        if kdf.lanes > MAX_LANES {
+            return Err(AppError::Crypto(format!("key needs {} lanes", kdf.lanes)));
+        }
+        let params = Params::new(kdf.memory_kib, kdf.passes, kdf.lanes, Some(32))
+            .map_err(|e| AppError::Crypto(format!("key parameters: {e}")))?;
+        let mut key = [0; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| AppError::Crypto(format!("derive key: {e}")))?;
+        Ok(Self { key, salt, kdf })
+    }
+
+    /// Decrypt a sealed file with `passphrase`, returning the contents and
+    /// the key, which seals the file again without another derivation.
+    pub fn open(passphrase: &str, sealed: &[u8]) -> Result<(Self, Vec<u8>)> {
+        let (salt, kdf) = header(sealed)?;
+        let key = Self::derive(passphrase, salt, kdf)?;
+        let contents = key.decrypt(sealed)?;
+        Ok((key, contents))
+    }
+
+    /// Decrypt a file sealed with this key, or `None` if it was sealed
+    /// with another salt and needs its own passphrase.
+    pub fn unseal(&self, sealed: &[u8]) -> Result<Option<Vec<u8>>> {
+        let (salt, kdf) = header(sealed)?;
+        if (salt, kdf) != (self.salt, self.kdf) {
+            return Ok(None);
+        }
+        self.decrypt(sealed).map(Some)
+    }
+
+    pub fn seal(&self, contents: &[u8]) -> Result<Vec<u8>> {
+        let mut sealed = Vec::with_capacity(HEADER_LEN + contents.len() + 16);
+        sealed.extend_from_slice(MAGIC);
+        for value in [self.kdf.memory_kib, self.kdf.passes, self.kdf.lanes] {
+            sealed.extend_from_slice(&value.to_le_bytes());
+        }
+        sealed.extend_from_slice(&self.salt);
+        let mut nonce = [0; NONCE_LEN];
+        rand::rng().fill_bytes(&mut nonce);
+        sealed.extend_from_slice(&nonce);
+
+        let payload = Payload {
+            msg: contents,
+            aad: &sealed,
+        };
+        let ciphertext = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|e| AppError::Crypto(format!("encrypt: {e}")))?;
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        let (header, ciphertext) = sealed.split_at(HEADER_LEN);
+        let nonce = &header[HEADER_LEN - NONCE_LEN..];
+        let payload = Payload {
+            msg: ciphertext,
+            aad: header,
+        };
+        self.cipher()
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| AppError::Crypto("wrong passphrase, or the file is damaged".into()))
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(&self.key.into())
+    }
+}
+
+/// Whether `data` starts like a sealed file.
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn header(sealed: &[u8]) -> Result<([u8; SALT_LEN], Kdf)> {
+    if !is_sealed(sealed) || sealed.len() < HEADER_LEN {
+        return Err(AppError::Crypto("not a sealed rolodex file".into()));
+    }
+    let word = |i: usize| {
+        let start = MAGIC.len() + 4 * i;
+        u32::from_le_bytes(sealed[start..start + 4].try_into().expect("4 bytes"))
+    };
+    let kdf = Kdf {
+        memory_kib: word(0),
+        passes: word(1),
+        lanes: word(2),
+    };
+    let start = MAGIC.len() + 12;
+    let salt = sealed[start..start + SALT_LEN].try_into().expect("salt length");
+    Ok((salt, kdf))
+}
+
+/// The passphrase from `ROLODEX_PASSPHRASE`, or asked for on the terminal.
+pub fn passphrase(prompt: &str) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_VAR) {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt).map_err(|e| {
+        AppError::Crypto(format!("read passphrase: {e} (set {PASSPHRASE_VAR} to skip the prompt)"))
+    })
+}
+
+/// A passphrase for a new key, from `ROLODEX_NEW_PASSPHRASE` or
+/// `ROLODEX_PASSPHRASE`, or typed twice.
+pub fn new_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(NEW_PASSPHRASE_VAR).or(std::env::var(PASSPHRASE_VAR)) {
+        return Ok(passphrase);
+    }
+    let passphrase = passphrase("New passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(AppError::Input("the passphrase can't be empty".into()));
+    }
+    if passphrase != self::passphrase("Repeat passphrase: ")? {
+        return Err(AppError::Input("the passphrases don't match".into()));
+    }
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_round_trip() {
+        let key = Key::new("correct horse").unwrap();
+        let sealed = key.seal(b"[]").unwrap();
+        assert!(is_sealed(&sealed));
+        assert_eq!(sealed.len(), HEADER_LEN + 2 + 16);
+        // Every save gets a fresh nonce
+        assert_ne!(key.seal(b"[]").unwrap(), sealed);
+
+        let (opened, contents) = Key::open("correct horse", &sealed).unwrap();
+        assert_eq!(contents, b"[]");
+        assert_eq!(opened.unseal(&sealed).unwrap().unwrap(), b"[]");
+
+        let other = Key::new("correct horse").unwrap();
+        assert_eq!(other.unseal(&sealed).unwrap(), None);
+    }
+
+    #[test]
+    fn test_wrong_passphrase_and_tampering_fail() {
+        let key = Key::new("correct horse").unwrap();
+        let sealed = key.seal(b"secret").unwrap();
+        assert!(matches!(Key::open("battery staple", &sealed), Err(AppError::Crypto(_))));
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(key.unseal(&tampered).is_err());
+
+        // Lowering the cost in the header is caught too
+        let mut cheaper = sealed.clone();
+        cheaper[MAGIC.len() + 4] = 1;
+        assert!(Key::open("correct horse", &cheaper).is_err());
+
+        // So is raising it past what a genuine file would ask for
+        for (offset, what) in [(0, "KiB of memory"), (4, "passes"), (8, "lanes")] {
+            let mut costlier = sealed.clone();
+            let at = MAGIC.len() + offset;
+            costlier[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            let err = Key::open("correct horse", &costlier).unwrap_err().to_string();
+            assert!(err.ends_with(&format!("key needs {} {what}", u32::MAX)), "{err}");
+        }
+
+        assert!(Key::open("correct horse", b"[{\"name\": \"Ada\"}]").is_err());
+        assert!(Key::open("correct horse", &sealed[..HEADER_LEN - 1]).is_err());
+    }
+}
@@ -1,15 +1,63 @@
-use std::path::Path;
+use std::{cell::Cell, path::Path, time::Duration};
 
 use crate::{
     error::{AppError, AppResult as Result},
-    model::Contact,
+    hooks::LifecycleHooks,
+    model::{Contact, Sort},
+    store::{self, ContactStore},
+    sync::SyncState,
 };
-use rand::Rng;
-use rusqlite::{Connection, OpenFlags};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
 use tracing::info;
 
+const CONTACT_COLUMNS: &str = "id, name, email, phone, company, created_at, updated_at, \
+     last_used_at, use_count, favorite, uid, version";
+
+/// How long a write waits for another process to finish its own before
+/// giving up with "database is locked".
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SORT_SETTING: &str = "sort";
+
+/// Schema changes applied in order on top of the original `contacts` table.
+/// The index of each entry + 1 is stored in `PRAGMA user_version` once applied.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    ALTER TABLE contacts ADD COLUMN updated_at TEXT;
+    ALTER TABLE contacts ADD COLUMN last_used_at TEXT;
+    UPDATE contacts SET updated_at = created_at;
+    CREATE TABLE settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+"#,
+    r#"
+    ALTER TABLE contacts ADD COLUMN use_count INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE contacts ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0;
+"#,
+    r#"
+    ALTER TABLE contacts ADD COLUMN uid TEXT;
+    CREATE UNIQUE INDEX contacts_uid ON contacts (uid);
+"#,
+    r#"
+    CREATE TABLE sync_state (
+        contact_id INTEGER PRIMARY KEY,
+        href TEXT NOT NULL UNIQUE,
+        etag TEXT NOT NULL,
+        card TEXT NOT NULL
+    );
+"#,
+    r#"
+    ALTER TABLE contacts ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+"#,
+];
+
 pub struct Db {
     conn: Connection,
+    hooks: LifecycleHooks,
+    /// `PRAGMA data_version` when last checked, which moves on whenever
+    /// another connection commits.
+    data_version: Cell<i64>,
 }
 
 impl Db {
@@ -29,17 +77,41 @@ impl Db {
         )
         .map_err(|e| AppError::Database(format!("open: {e}")))?;
 
+        // Readers don't block the writer in WAL mode, and another process
+        // holding the write lock is waited out rather than failing at once
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| AppError::Database(format!("set busy timeout: {e}")))?;
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| AppError::Database(format!("enable WAL: {e}")))?;
+
         if is_new {
             Self::init_schema(&conn)?;
         }
+        Self::migrate(&conn)?;
 
-        Ok(Self { conn })
+        let db = Self {
+            conn,
+            hooks: LifecycleHooks::default(),
+            data_version: Cell::new(0),
+        };
+        db.data_version.set(db.read_data_version()?);
+        Ok(db)
     }
 
     pub fn conn(&self) -> &Connection {
         &self.conn
     }
 
+    /// Commands to run after writes. The database only carries them to
+    /// whoever does the writing; see [`LifecycleHooks::fire`].
+    pub fn set_hooks(&mut self, hooks: LifecycleHooks) {
+        self.hooks = hooks;
+    }
+
+    pub fn hooks(&self) -> &LifecycleHooks {
+        &self.hooks
+    }
+
     fn init_schema(conn: &Connection) -> Result<()> {
         conn.execute_batch(
             r#"
@@ -57,25 +129,33 @@ impl Db {
 
         Ok(())
     }
+
+    fn migrate(conn: &Connection) -> Result<()> {
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| AppError::Database(format!("read schema version: {e}")))?;
+
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            info!("Applying schema migration {}", i + 1);
+            conn.execute_batch(&format!(
+                "BEGIN; {migration} PRAGMA user_version = {}; COMMIT;",
+                i + 1
+            ))
+            .map_err(|e| AppError::Database(format!("migrate to v{}: {e}", i + 1)))?;
+        }
+
+        Ok(())
+    }
     pub fn seed(&mut self, count: u32) -> Result<()> {
         let tx = self
             .conn
             .transaction()
             .map_err(|e| AppError::Database(format!("begin tx: {e}")))?;
 
-        let mut rng = rand::rng();
-        let mut name_gen = names::Generator::default();
-        let mut company_gen = names::Generator::with_naming(names::Name::Numbered);
-
-        for _ in 0..count {
-            let name = name_gen.next().unwrap_or("John Doe".into());
-            let email = format!("{}@example.com", name.replace(' ', "").to_lowercase());
-            let phone = format!("04{:08}", rng.random_range(0..=99999999));
-            let company = format!("{} Pty Ltd", company_gen.next().unwrap_or("Acme".into()));
-
+        for contact in store::fake_contacts(count) {
             tx.execute(
-                "INSERT INTO contacts (name, email, phone, company) VALUES (?, ?, ?, ?)",
-                (&name, &email, &phone, &company),
+                "INSERT INTO contacts (name, email, phone, company, updated_at) VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP)",
+                (&contact.name, &contact.email, &contact.phone, &contact.company),
             )
             .map_err(|e| AppError::Database(format!("insert fake: {e}")))?;
         }
@@ -85,26 +165,36 @@ impl Db {
         Ok(())
     }
 
-    pub fn add_contact(&self, contact: Contact) -> Result<()> {
+    /// Insert the contact and return its new id.
+    pub fn add_contact(&self, contact: Contact) -> Result<i64> {
         info!("Save contact: {:?}", contact);
         self.conn
             .execute(
-                "INSERT INTO contacts (name, email, phone, company) VALUES (?, ?, ?, ?)",
+                "INSERT INTO contacts (name, email, phone, company, favorite, uid, updated_at) VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
                 (
                     &contact.name,
                     &contact.email,
                     &contact.phone,
                     &contact.company,
+                    contact.favorite,
+                    &contact.uid,
                 ),
             )
             .map_err(|e| AppError::Database(format!("insert: {e}")))?;
-        Ok(())
+        Ok(self.conn.last_insert_rowid())
     }
 
     pub fn load_customers(&self) -> Result<Vec<Contact>> {
+        self.load_customers_sorted(Sort::default())
+    }
+
+    pub fn load_customers_sorted(&self, sort: Sort) -> Result<Vec<Contact>> {
         let mut stmt = self
             .conn
-            .prepare("SELECT id, name, email, phone, company from contacts order by name asc")
+            .prepare(&format!(
+                "SELECT {CONTACT_COLUMNS} FROM contacts ORDER BY {}",
+                sort.order_by()
+            ))
             .map_err(|e| AppError::Database(format!("prepare load: {e}")))?;
 
         let rows = stmt
@@ -116,20 +206,45 @@ impl Db {
         Ok(rows)
     }
 
+    pub fn get_contact(&self, id: i64) -> Result<Contact> {
+        self.conn
+            .query_row(
+                &format!("SELECT {CONTACT_COLUMNS} FROM contacts WHERE id = ?"),
+                [id],
+                Contact::from_row,
+            )
+            .optional()
+            .map_err(|e| AppError::Database(format!("get contact: {e}")))?
+            .ok_or_else(|| AppError::NotFound(format!("contact {id}")))
+    }
+
+    /// The contact a CardDAV client stored under `uid`, if any.
+    pub fn find_by_uid(&self, uid: &str) -> Result<Option<Contact>> {
+        self.conn
+            .query_row(
+                &format!("SELECT {CONTACT_COLUMNS} FROM contacts WHERE uid = ?"),
+                [uid],
+                Contact::from_row,
+            )
+            .optional()
+            .map_err(|e| AppError::Database(format!("find by uid: {e}")))
+    }
+
     pub fn search_contacts(&self, query: &str) -> Result<Vec<Contact>> {
         let search_term = format!("%{}%", query.to_lowercase());
         
         let mut stmt = self
             .conn
-            .prepare(
-                "SELECT id, name, email, phone, company 
-                 FROM contacts 
-                 WHERE LOWER(name) LIKE ?1 
-                    OR LOWER(COALESCE(company, '')) LIKE ?1 
-                    OR LOWER(COALESCE(email, '')) LIKE ?1 
+            .prepare(&format!(
+                "SELECT {CONTACT_COLUMNS}
+                 FROM contacts
+                 WHERE LOWER(name) LIKE ?1
+                    OR LOWER(COALESCE(company, '')) LIKE ?1
+                    OR LOWER(COALESCE(email, '')) LIKE ?1
                     OR LOWER(COALESCE(phone, '')) LIKE ?1
-                 ORDER BY name ASC"
-            )
+                 ORDER BY {}",
+                Sort::default().order_by()
+            ))
             .map_err(|e| AppError::Database(format!("prepare search: {e}")))?;
 
         let rows = stmt
@@ -144,17 +259,21 @@ impl Db {
     pub fn search_by_field(&self, field: &str, query: &str) -> Result<Vec<Contact>> {
         let search_term = format!("%{}%", query.to_lowercase());
         
-        let sql = match field {
-            "name" => "SELECT id, name, email, phone, company FROM contacts WHERE LOWER(name) LIKE ?1 ORDER BY name ASC",
-            "company" => "SELECT id, name, email, phone, company FROM contacts WHERE LOWER(COALESCE(company, '')) LIKE ?1 ORDER BY name ASC",
-            "email" => "SELECT id, name, email, phone, company FROM contacts WHERE LOWER(COALESCE(email, '')) LIKE ?1 ORDER BY name ASC",
-            "phone" => "SELECT id, name, email, phone, company FROM contacts WHERE LOWER(COALESCE(phone, '')) LIKE ?1 ORDER BY name ASC",
+        let column = match field {
+            "name" => "LOWER(name)",
+            "company" => "LOWER(COALESCE(company, ''))",
+            "email" => "LOWER(COALESCE(email, ''))",
+            "phone" => "LOWER(COALESCE(phone, ''))",
             _ => return Err(AppError::Database(format!("invalid search field: {}", field))),
         };
+        let sql = format!(
+            "SELECT {CONTACT_COLUMNS} FROM contacts WHERE {column} LIKE ?1 ORDER BY {}",
+            Sort::default().order_by()
+        );
 
         let mut stmt = self
             .conn
-            .prepare(sql)
+            .prepare(&sql)
             .map_err(|e| AppError::Database(format!("prepare field search: {e}")))?;
 
         let rows = stmt
@@ -167,71 +286,205 @@ impl Db {
     }
 
     pub fn delete_contact(&self, id: i64) -> Result<()> {
-        self.conn
+        let deleted = self
+            .conn
             .execute("DELETE FROM contacts WHERE id = ?", [id])
             .map_err(|e| AppError::Database(format!("delete: {e}")))?;
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!("contact {id}")));
+        }
         info!("Deleted contact with id: {}", id);
         Ok(())
     }
 
+    /// Save an edit of contact `id` made to `contact.version`. If the
+    /// contact was saved since, by another process or another client, the
+    /// edit is refused with [`AppError::Conflict`] rather than overwriting.
     pub fn update_contact(&self, id: i64, contact: Contact) -> Result<()> {
-        self.conn
+        let updated = self
+            .conn
             .execute(
-                "UPDATE contacts SET name = ?, email = ?, phone = ?, company = ? WHERE id = ?",
+                "UPDATE contacts SET name = ?, email = ?, phone = ?, company = ?, updated_at = CURRENT_TIMESTAMP, version = version + 1 WHERE id = ? AND version = ?",
                 (
                     &contact.name,
                     &contact.email,
                     &contact.phone,
                     &contact.company,
                     id,
+                    contact.version,
                 ),
             )
             .map_err(|e| AppError::Database(format!("update: {e}")))?;
+        if updated == 0 {
+            // Missing, or saved since the edit started
+            self.get_contact(id)?;
+            return Err(AppError::Conflict(format!("contact {id}")));
+        }
         info!("Updated contact with id: {}", id);
         Ok(())
     }
 
+    /// Whether another connection, such as another rolodex process, has
+    /// committed a change since the last call (or since opening).
+    pub fn changed_elsewhere(&self) -> Result<bool> {
+        let version = self.read_data_version()?;
+        Ok(self.data_version.replace(version) != version)
+    }
+
+    fn read_data_version(&self) -> Result<i64> {
+        self.conn
+            .pragma_query_value(None, "data_version", |row| row.get(0))
+            .map_err(|e| AppError::Database(format!("read data version: {e}")))
+    }
+
+    /// Record that a contact was just picked from the list.
+    pub fn touch_contact(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE contacts SET last_used_at = CURRENT_TIMESTAMP, use_count = use_count + 1 WHERE id = ?",
+                [id],
+            )
+            .map_err(|e| AppError::Database(format!("touch: {e}")))?;
+        Ok(())
+    }
+
+    /// Star or unstar a contact.
+    pub fn set_favorite(&self, id: i64, favorite: bool) -> Result<()> {
+        self.conn
+            .execute("UPDATE contacts SET favorite = ? WHERE id = ?", (favorite, id))
+            .map_err(|e| AppError::Database(format!("set favorite: {e}")))?;
+        Ok(())
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        self.conn
+            .query_row("SELECT value FROM settings WHERE key = ?", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(|e| AppError::Database(format!("read setting {key}: {e}")))
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                (key, value),
+            )
+            .map_err(|e| AppError::Database(format!("write setting {key}: {e}")))?;
+        Ok(())
+    }
+
+    /// The sort order chosen in the last session, or the default if none was saved.
+    pub fn load_sort(&self) -> Result<Sort> {
+        Ok(self
+            .get_setting(SORT_SETTING)?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default())
+    }
+
+    pub fn save_sort(&self, sort: Sort) -> Result<()> {
+        self.set_setting(SORT_SETTING, &sort.to_string())
+    }
+
+    /// What each synced contact looked like on both sides after the last
+    /// sync. Rows outlive their contacts so local deletes can be pushed.
+    pub fn sync_states(&self) -> Result<Vec<SyncState>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT contact_id, href, etag, card FROM sync_state ORDER BY contact_id")
+            .map_err(|e| AppError::Database(format!("prepare sync state: {e}")))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(SyncState {
+                    contact_id: row.get(0)?,
+                    href: row.get(1)?,
+                    etag: row.get(2)?,
+                    card: row.get(3)?,
+                })
+            })
+            .map_err(|e| AppError::Database(format!("query sync state: {e}")))?;
+        rows.collect::<std::result::Result<_, _>>()
+            .map_err(|e| AppError::Database(format!("collect sync state: {e}")))
+    }
+
+    pub fn save_sync_state(&self, state: &SyncState) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO sync_state (contact_id, href, etag, card)
+                 VALUES (?, ?, ?, ?)",
+                (state.contact_id, &state.href, &state.etag, &state.card),
+            )
+            .map_err(|e| AppError::Database(format!("save sync state: {e}")))?;
+        Ok(())
+    }
+
+    pub fn delete_sync_state(&self, contact_id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM sync_state WHERE contact_id = ?", [contact_id])
+            .map_err(|e| AppError::Database(format!("delete sync state: {e}")))?;
+        Ok(())
+    }
+
+    pub fn clear_sync_state(&self) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM sync_state", [])
+            .map_err(|e| AppError::Database(format!("clear sync state: {e}")))?;
+        Ok(())
+    }
+
     pub fn export_contacts_json(&self) -> Result<String> {
         let contacts = self.load_customers()?;
         serde_json::to_string_pretty(&contacts)
             .map_err(|e| AppError::Database(format!("export json: {e}")))
     }
 
-    pub fn import_contacts_json(&mut self, json_data: &str) -> Result<usize> {
+    /// Insert every valid contact from a JSON array and return them with
+    /// their new ids. Invalid contacts are skipped.
+    pub fn import_contacts_json(&mut self, json_data: &str) -> Result<Vec<Contact>> {
         let contacts: Vec<Contact> = serde_json::from_str(json_data)
             .map_err(|e| AppError::Database(format!("parse json: {e}")))?;
+        let imported = self.import_contacts(contacts)?;
+        info!("Imported {} contacts", imported.len());
+        Ok(imported)
+    }
 
+    /// Insert every valid contact in one transaction and return them with
+    /// their new ids. Invalid contacts are skipped.
+    pub fn import_contacts(&mut self, contacts: Vec<Contact>) -> Result<Vec<Contact>> {
         let tx = self
             .conn
             .transaction()
             .map_err(|e| AppError::Database(format!("begin import tx: {e}")))?;
 
-        let mut imported_count = 0;
-        for contact in contacts {
+        let mut imported = Vec::new();
+        for mut contact in contacts {
             if let Err(validation_err) = contact.validate() {
                 info!("Skipping invalid contact: {}", validation_err);
                 continue;
             }
 
             tx.execute(
-                "INSERT INTO contacts (name, email, phone, company) VALUES (?, ?, ?, ?)",
+                "INSERT INTO contacts (name, email, phone, company, favorite, updated_at) VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
                 (
                     &contact.name,
                     &contact.email,
                     &contact.phone,
                     &contact.company,
+                    contact.favorite,
                 ),
             )
             .map_err(|e| AppError::Database(format!("import contact: {e}")))?;
             
-            imported_count += 1;
+            contact.id = tx.last_insert_rowid();
+            imported.push(contact);
         }
 
         tx.commit()
             .map_err(|e| AppError::Database(format!("commit import tx: {e}")))?;
 
-        info!("Imported {} contacts", imported_count);
-        Ok(imported_count)
+        Ok(imported)
     }
 
     pub fn backup_to_file<P: AsRef<Path>>(&self, backup_path: P) -> Result<()> {
@@ -241,14 +494,19 @@ impl Db {
         Ok(())
     }
 
-    pub fn restore_from_file<P: AsRef<Path>>(&mut self, backup_path: P) -> Result<usize> {
+    pub fn restore_from_file<P: AsRef<Path>>(&mut self, backup_path: P) -> Result<Vec<Contact>> {
         let json_data = std::fs::read_to_string(backup_path)
             .map_err(|e| AppError::Database(format!("read backup: {e}")))?;
         self.import_contacts_json(&json_data)
     }
 
     pub fn find_potential_duplicates(&self) -> Result<Vec<Vec<Contact>>> {
-        let all_contacts = self.load_customers()?;
+        Ok(Self::group_duplicates(&self.load_customers()?))
+    }
+
+    /// Group contacts that share an email or phone number, or have nearly
+    /// the same name. Contacts in no group are left out.
+    pub(crate) fn group_duplicates(all_contacts: &[Contact]) -> Vec<Vec<Contact>> {
         let mut duplicates = Vec::new();
         let mut processed = std::collections::HashSet::new();
 
@@ -276,7 +534,7 @@ impl Db {
             }
         }
 
-        Ok(duplicates)
+        duplicates
     }
 
     fn are_potential_duplicates(contact1: &Contact, contact2: &Contact) -> bool {
@@ -321,9 +579,77 @@ impl Db {
     }
 }
 
+/// The inherent methods under the names the UI and the CLI call them by.
+impl ContactStore for Db {
+    fn load_customers_sorted(&self, sort: Sort) -> Result<Vec<Contact>> {
+        Db::load_customers_sorted(self, sort)
+    }
+
+    fn get_contact(&self, id: i64) -> Result<Contact> {
+        Db::get_contact(self, id)
+    }
+
+    fn add_contact(&self, contact: Contact) -> Result<i64> {
+        Db::add_contact(self, contact)
+    }
+
+    fn update_contact(&self, id: i64, contact: Contact) -> Result<()> {
+        Db::update_contact(self, id, contact)
+    }
+
+    fn delete_contact(&self, id: i64) -> Result<()> {
+        Db::delete_contact(self, id)
+    }
+
+    fn touch_contact(&self, id: i64) -> Result<()> {
+        Db::touch_contact(self, id)
+    }
+
+    fn set_favorite(&self, id: i64, favorite: bool) -> Result<()> {
+        Db::set_favorite(self, id, favorite)
+    }
+
+    fn load_sort(&self) -> Result<Sort> {
+        Db::load_sort(self)
+    }
+
+    fn save_sort(&self, sort: Sort) -> Result<()> {
+        Db::save_sort(self, sort)
+    }
+
+    fn import_contacts(&mut self, contacts: Vec<Contact>) -> Result<Vec<Contact>> {
+        Db::import_contacts(self, contacts)
+    }
+
+    fn hooks(&self) -> &LifecycleHooks {
+        Db::hooks(self)
+    }
+
+    fn set_hooks(&mut self, hooks: LifecycleHooks) {
+        Db::set_hooks(self, hooks)
+    }
+
+    fn search_contacts(&self, query: &str) -> Result<Vec<Contact>> {
+        Db::search_contacts(self, query)
+    }
+
+    fn search_by_field(&self, field: &str, query: &str) -> Result<Vec<Contact>> {
+        Db::search_by_field(self, field, query)
+    }
+
+    fn seed(&mut self, count: u32) -> Result<()> {
+        Db::seed(self, count)
+    }
+
+    fn changed_elsewhere(&self) -> Result<bool> {
+        Db::changed_elsewhere(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::{SortKey, SortOrder};
 
     use tempfile::tempdir;
 
@@ -354,6 +680,7 @@ mod tests {
             email: Some("test@example.com".to_string()),
             phone: Some("0412345678".to_string()),
             company: Some("Test Corp".to_string()),
+            ..Default::default()
         };
 
         let result = db.add_contact(contact);
@@ -370,6 +697,7 @@ mod tests {
             email: Some("alice@example.com".to_string()),
             phone: Some("0487654321".to_string()),
             company: Some("Alice Corp".to_string()),
+            ..Default::default()
         };
 
         db.add_contact(contact).unwrap();
@@ -390,6 +718,7 @@ mod tests {
             email: Some("original@example.com".to_string()),
             phone: Some("0411111111".to_string()),
             company: Some("Original Corp".to_string()),
+            ..Default::default()
         };
 
         db.add_contact(contact).unwrap();
@@ -402,6 +731,7 @@ mod tests {
             email: Some("updated@example.com".to_string()),
             phone: Some("0422222222".to_string()),
             company: Some("Updated Corp".to_string()),
+            ..Default::default()
         };
 
         let result = db.update_contact(contact_id, updated_contact);
@@ -423,6 +753,7 @@ mod tests {
             email: Some("delete@example.com".to_string()),
             phone: Some("0433333333".to_string()),
             company: Some("Delete Corp".to_string()),
+            ..Default::default()
         };
 
         db.add_contact(contact).unwrap();
@@ -435,6 +766,12 @@ mod tests {
 
         let contacts = db.load_customers().unwrap();
         assert_eq!(contacts.len(), 0);
+
+        // Gone contacts can't be fetched, updated or deleted again
+        assert!(matches!(db.get_contact(contact_id), Err(AppError::NotFound(_))));
+        let update = db.update_contact(contact_id, Contact::default());
+        assert!(matches!(update, Err(AppError::NotFound(_))));
+        assert!(matches!(db.delete_contact(contact_id), Err(AppError::NotFound(_))));
     }
 
     #[test]
@@ -466,6 +803,7 @@ mod tests {
                 email: Some("zoe@example.com".to_string()),
                 phone: Some("0411111111".to_string()),
                 company: Some("Z Corp".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -473,6 +811,7 @@ mod tests {
                 email: Some("alice@example.com".to_string()),
                 phone: Some("0422222222".to_string()),
                 company: Some("A Corp".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -480,6 +819,7 @@ mod tests {
                 email: Some("bob@example.com".to_string()),
                 phone: Some("0433333333".to_string()),
                 company: Some("B Corp".to_string()),
+                ..Default::default()
             },
         ];
 
@@ -504,6 +844,7 @@ mod tests {
             email: Some("export@test.com".to_string()),
             phone: Some("0444444444".to_string()),
             company: Some("Export Corp".to_string()),
+            ..Default::default()
         };
 
         db.add_contact(contact).unwrap();
@@ -535,8 +876,9 @@ mod tests {
             }
         ]"#;
 
-        let imported_count = db.import_contacts_json(json_data).unwrap();
-        assert_eq!(imported_count, 2);
+        let imported = db.import_contacts_json(json_data).unwrap();
+        assert_eq!(imported.len(), 2);
+        assert!(imported.iter().all(|contact| contact.id > 0));
 
         let contacts = db.load_customers().unwrap();
         assert_eq!(contacts.len(), 2);
@@ -554,6 +896,7 @@ mod tests {
             email: Some("backup@test.com".to_string()),
             phone: Some("0477777777".to_string()),
             company: Some("Backup Corp".to_string()),
+            ..Default::default()
         };
 
         db.add_contact(contact).unwrap();
@@ -563,8 +906,8 @@ mod tests {
         assert!(backup_path.exists());
 
         let (mut new_db, _new_temp_dir) = create_test_db();
-        let restored_count = new_db.restore_from_file(&backup_path).unwrap();
-        assert_eq!(restored_count, 1);
+        let restored = new_db.restore_from_file(&backup_path).unwrap();
+        assert_eq!(restored.len(), 1);
 
         let contacts = new_db.load_customers().unwrap();
         assert_eq!(contacts.len(), 1);
@@ -592,8 +935,8 @@ mod tests {
             }
         ]"#;
 
-        let imported_count = db.import_contacts_json(json_data).unwrap();
-        assert_eq!(imported_count, 1);
+        let imported = db.import_contacts_json(json_data).unwrap();
+        assert_eq!(imported.len(), 1);
 
         let contacts = db.load_customers().unwrap();
         assert_eq!(contacts.len(), 1);
@@ -611,6 +954,7 @@ mod tests {
                 email: Some("alice@acme.com".to_string()),
                 phone: Some("0411111111".to_string()),
                 company: Some("Acme Corp".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -618,6 +962,7 @@ mod tests {
                 email: Some("bob@tech.com".to_string()),
                 phone: Some("0422222222".to_string()),
                 company: Some("Tech Inc".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -625,6 +970,7 @@ mod tests {
                 email: Some("charlie@acme.com".to_string()),
                 phone: Some("0433333333".to_string()),
                 company: Some("Acme Corp".to_string()),
+                ..Default::default()
             },
         ];
 
@@ -656,6 +1002,7 @@ mod tests {
             email: Some("john@example.com".to_string()),
             phone: Some("0444444444".to_string()),
             company: Some("Example Corp".to_string()),
+            ..Default::default()
         };
 
         db.add_contact(contact).unwrap();
@@ -695,6 +1042,7 @@ mod tests {
                 email: Some("john@example.com".to_string()),
                 phone: Some("0411111111".to_string()),
                 company: Some("Acme Corp".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -702,6 +1050,7 @@ mod tests {
                 email: Some("john.smith@example.com".to_string()),
                 phone: Some("0411111111".to_string()),
                 company: Some("Acme Corp".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -709,6 +1058,7 @@ mod tests {
                 email: Some("jane@example.com".to_string()),
                 phone: Some("0422222222".to_string()),
                 company: Some("Tech Inc".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -716,6 +1066,7 @@ mod tests {
                 email: Some("bob@example.com".to_string()),
                 phone: Some("0433333333".to_string()),
                 company: Some("Other Corp".to_string()),
+                ..Default::default()
             },
         ];
 
@@ -741,6 +1092,7 @@ mod tests {
                 email: Some("john@example.com".to_string()),
                 phone: Some("0411111111".to_string()),
                 company: Some("Acme Corp".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -748,6 +1100,7 @@ mod tests {
                 email: Some("john@example.com".to_string()),
                 phone: Some("0422222222".to_string()),
                 company: Some("Different Corp".to_string()),
+                ..Default::default()
             },
         ];
 
@@ -771,6 +1124,7 @@ mod tests {
                 email: Some("john1@example.com".to_string()),
                 phone: Some("0411111111".to_string()),
                 company: Some("Acme Corp".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -778,6 +1132,7 @@ mod tests {
                 email: Some("different@example.com".to_string()),
                 phone: Some("(04) 1111-1111".to_string()),
                 company: Some("Different Corp".to_string()),
+                ..Default::default()
             },
         ];
 
@@ -801,6 +1156,7 @@ mod tests {
                 email: Some("john@example.com".to_string()),
                 phone: Some("0411111111".to_string()),
                 company: Some("Acme Corp".to_string()),
+                ..Default::default()
             },
             Contact {
                 id: 0,
@@ -808,6 +1164,7 @@ mod tests {
                 email: Some("jane@example.com".to_string()),
                 phone: Some("0422222222".to_string()),
                 company: Some("Tech Inc".to_string()),
+                ..Default::default()
             },
         ];
 
@@ -818,4 +1175,170 @@ mod tests {
         let duplicates = db.find_potential_duplicates().unwrap();
         assert_eq!(duplicates.len(), 0);
     }
+
+    #[test]
+    fn test_load_customers_sorted() {
+        let (db, _temp_dir) = create_test_db();
+
+        let rows = [("Alice", Some("Zed Corp")), ("Bob", None), ("Carol", Some("Acme"))];
+        for (name, company) in rows {
+            db.add_contact(Contact {
+                name: name.to_string(),
+                company: company.map(str::to_string),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        let by_company = db
+            .load_customers_sorted(Sort::new(SortKey::Company, SortOrder::Asc))
+            .unwrap();
+        let names: Vec<_> = by_company.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Carol", "Alice", "Bob"]);
+
+        let by_name_desc = db
+            .load_customers_sorted(Sort::new(SortKey::Name, SortOrder::Desc))
+            .unwrap();
+        let names: Vec<_> = by_name_desc.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Carol", "Bob", "Alice"]);
+    }
+
+    #[test]
+    fn test_sql_and_list_sort_agree() {
+        let (db, _temp_dir) = create_test_db();
+        for name in ["Äb", "äa", "bob", "Zoë", "alice", "Émile", "émile"] {
+            db.add_contact(Contact {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        for order in [SortOrder::Asc, SortOrder::Desc] {
+            let sort = Sort::new(SortKey::Name, order);
+            let from_sql = db.load_customers_sorted(sort).unwrap();
+            let mut in_list = from_sql.clone();
+            in_list.sort_by(|a, b| sort.compare(a, b));
+            assert_eq!(from_sql, in_list);
+        }
+    }
+
+    #[test]
+    fn test_timestamps_recorded() {
+        let (db, _temp_dir) = create_test_db();
+
+        db.add_contact(Contact {
+            name: "Stamped".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let contact = &db.load_customers().unwrap()[0];
+        assert!(contact.created_at.is_some());
+        assert!(contact.updated_at.is_some());
+        assert!(contact.last_used_at.is_none());
+
+        db.touch_contact(contact.id).unwrap();
+        db.touch_contact(contact.id).unwrap();
+        let contact = &db.load_customers().unwrap()[0];
+        assert!(contact.last_used_at.is_some());
+        assert_eq!(contact.use_count, 2);
+    }
+
+    #[test]
+    fn test_set_favorite() {
+        let temp_dir = tempdir().unwrap();
+        let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+        db.add_contact(Contact {
+            name: "Ada".into(),
+            ..Default::default()
+        })
+        .unwrap();
+        let id = db.load_customers().unwrap()[0].id;
+        assert!(!db.load_customers().unwrap()[0].favorite);
+
+        db.set_favorite(id, true).unwrap();
+        assert!(db.load_customers().unwrap()[0].favorite);
+        db.set_favorite(id, false).unwrap();
+        assert!(!db.load_customers().unwrap()[0].favorite);
+    }
+
+    #[test]
+    fn test_sort_setting_persists() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+
+        let db = Db::open(&db_path).unwrap();
+        assert_eq!(db.load_sort().unwrap(), Sort::default());
+        db.save_sort(Sort::new(SortKey::Modified, SortOrder::Desc)).unwrap();
+        drop(db);
+
+        let db = Db::open(&db_path).unwrap();
+        assert_eq!(
+            db.load_sort().unwrap(),
+            Sort::new(SortKey::Modified, SortOrder::Desc)
+        );
+    }
+
+    #[test]
+    fn test_concurrent_edits() {
+        let (db, temp_dir) = create_test_db();
+        let other = Db::open(temp_dir.path().join("test.db")).unwrap();
+        let mode: String = db
+            .conn()
+            .pragma_query_value(None, "journal_mode", |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+
+        let id = db
+            .add_contact(Contact {
+                name: "Ada Lovelace".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        // Our own writes aren't news, the other connection's are
+        assert!(!db.changed_elsewhere().unwrap());
+        assert!(other.changed_elsewhere().unwrap());
+        assert!(!other.changed_elsewhere().unwrap());
+
+        let opened = db.get_contact(id).unwrap();
+        let theirs = Contact {
+            email: Some("ada@example.com".to_string()),
+            ..other.get_contact(id).unwrap()
+        };
+        other.update_contact(id, theirs).unwrap();
+        assert!(db.changed_elsewhere().unwrap());
+
+        let ours = Contact {
+            name: "Ada King".to_string(),
+            ..opened
+        };
+        let stale = db.update_contact(id, ours.clone());
+        assert!(matches!(stale, Err(AppError::Conflict(_))));
+        let stored = db.get_contact(id).unwrap();
+        assert_eq!((stored.name.as_str(), stored.version), ("Ada Lovelace", 1));
+
+        db.update_contact(id, Contact { version: stored.version, ..ours }).unwrap();
+        assert_eq!(db.get_contact(id).unwrap().version, 2);
+    }
+
+    #[test]
+    fn test_migrates_legacy_schema() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("legacy.db");
+
+        let conn = Connection::open(&db_path).unwrap();
+        Db::init_schema(&conn).unwrap();
+        conn.execute("INSERT INTO contacts (name) VALUES ('Old Timer')", [])
+            .unwrap();
+        drop(conn);
+
+        let db = Db::open(&db_path).unwrap();
+        let contacts = db.load_customers().unwrap();
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].updated_at, contacts[0].created_at);
+        assert_eq!(contacts[0].use_count, 0);
+        assert!(!contacts[0].favorite);
+        db.save_sort(Sort::default()).unwrap();
+    }
 }
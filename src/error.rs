@@ -20,6 +20,25 @@ pub enum AppError {
     #[error("Row Parse: {0}")]
     RowParse(String),
 
+    #[error("Command error: {0}")]
+    Command(String),
+
+    #[error("Sync error: {0}")]
+    Sync(String),
+
+    #[error("Encryption error: {0}")]
+    Crypto(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Saved elsewhere since it was read.
+    #[error("Modified elsewhere: {0}")]
+    Conflict(String),
+
+    #[error("Request cancelled")]
+    Cancelled,
+
     #[error("JSON Error: {0}")]
     Json(#[from] serde_json::Error),
 }
@@ -0,0 +1,584 @@
+//! Shell commands from `config.toml`: the `[actions]` table (`call`,
+//! `mail`, `open`), run for the selected contact with `{field}`
+//! placeholders filled in, and the `[hooks]` table, run after the contacts
+//! change.
+
+use std::{
+    fs,
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    error::{AppError, AppResult as Result},
+    keymap::Action,
+    model::Contact,
+};
+
+/// Placeholders a command template may use.
+pub const PLACEHOLDERS: [&str; 5] = ["id", "name", "company", "email", "phone"];
+
+/// Command templates from the `[actions]` table, e.g.
+/// `call = "linphonecsh dial {phone}"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionHooks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub call: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mail: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open: Option<String>,
+}
+
+impl ActionHooks {
+    pub fn is_empty(&self) -> bool {
+        self.call.is_none() && self.mail.is_none() && self.open.is_none()
+    }
+
+    /// The template configured for `action`, if it is one of the hook actions.
+    pub fn get(&self, action: Action) -> Option<&str> {
+        match action {
+            Action::Call => self.call.as_deref(),
+            Action::Mail => self.mail.as_deref(),
+            Action::Open => self.open.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Reject templates with unknown placeholders or unbalanced braces, so
+    /// mistakes show up at startup rather than on the first key press.
+    pub fn check(&self) -> Result<()> {
+        for (name, template) in [("call", &self.call), ("mail", &self.mail), ("open", &self.open)] {
+            if let Some(template) = template {
+                expand(template, |_| Some(String::new()))
+                    .map_err(|e| AppError::Config(format!("actions.{name}: {e}")))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fill in `template` from `contact`. Values are shell-quoted, so
+/// placeholders should not be quoted in the template. Fails when the
+/// contact has no value for a placeholder the template uses.
+pub fn render(template: &str, contact: &Contact) -> Result<String> {
+    let mut missing = None;
+    let command = expand(template, |field| {
+        let value = match field {
+            "id" => Some(contact.id.to_string()),
+            "name" => Some(contact.name.clone()),
+            "company" => contact.company.clone(),
+            "email" => contact.email.clone(),
+            "phone" => contact.phone.clone(),
+            _ => None,
+        }
+        .filter(|value| !value.trim().is_empty());
+        if value.is_none() && missing.is_none() {
+            missing = Some(field.to_string());
+        }
+        value.map(|value| shell_quote(value.trim()))
+    })
+    .map_err(AppError::Command)?;
+    match missing {
+        Some(field) => Err(AppError::Command(format!("{} has no {field}", contact.name))),
+        None => Ok(command),
+    }
+}
+
+/// Replace each `{field}` using `value`. `{{` and `}}` stand for literal
+/// braces.
+fn expand(
+    template: &str,
+    mut value: impl FnMut(&str) -> Option<String>,
+) -> std::result::Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => {
+                            return Err(format!(
+                                "unclosed placeholder {{{field}, write '{{{{' for a literal brace"
+                            ));
+                        }
+                    }
+                }
+                if !PLACEHOLDERS.contains(&field.as_str()) {
+                    return Err(format!(
+                        "unknown placeholder {{{field}}}, expected one of {}",
+                        PLACEHOLDERS.map(|p| format!("{{{p}}}")).join(", ")
+                    ));
+                }
+                if let Some(value) = value(&field) {
+                    out.push_str(&value);
+                }
+            }
+            '}' => return Err("unmatched '}', write '}}' for a literal brace".to_string()),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// Quote `value` as a single POSIX shell word.
+pub fn shell_quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./-_".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// How a finished command went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// Exit code, or `None` when the command was killed by a signal.
+    pub code: Option<i32>,
+    /// Combined stdout and stderr, trimmed.
+    pub output: String,
+}
+
+impl Outcome {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// One-line report for the status bar, e.g. `call: exit 1: no such number`.
+    pub fn summary(&self, action: Action) -> String {
+        let status = match self.code {
+            Some(0) => "done".to_string(),
+            Some(code) => format!("exit {code}"),
+            None => "killed".to_string(),
+        };
+        match self.output.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) => format!("{}: {status}: {}", action.name(), line.trim()),
+            None => format!("{}: {status}", action.name()),
+        }
+    }
+}
+
+/// Run `command` with `sh -c`. Output goes to a temporary file rather than
+/// a pipe so that programs which leave a background process behind, such
+/// as `xdg-open` starting a browser, don't keep us waiting.
+pub fn run(command: &str) -> Result<Outcome> {
+    execute(command, &[], None, None)
+}
+
+/// Run `command` like [`run`], with extra environment variables, `input`
+/// on stdin, and killed once `timeout` has passed.
+fn execute(
+    command: &str,
+    env: &[(&str, &str)],
+    input: Option<Vec<u8>>,
+    timeout: Option<Duration>,
+) -> Result<Outcome> {
+    // The temporary directory is shared, so the name is unguessable and the
+    // file must be new: a planted file or symlink makes this fail instead of
+    // being written through
+    let suffix: u64 = rand::rng().random();
+    let path = std::env::temp_dir().join(format!("rolodex-hook-{suffix:016x}.log"));
+    let log = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)
+        .map_err(|e| AppError::Command(format!("create output file: {e}")))?;
+    let stderr = log
+        .try_clone()
+        .map_err(|e| AppError::Command(format!("create output file: {e}")))?;
+
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().copied())
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(log)
+        .stderr(stderr)
+        .spawn()
+        .map_err(|e| AppError::Command(format!("run sh: {e}")))
+        .and_then(|mut child| {
+            if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+                // On its own thread so a command that never reads its input
+                // can't block us past the timeout
+                thread::spawn(move || {
+                    let _ = stdin.write_all(&input);
+                });
+            }
+            wait(&mut child, timeout)
+        });
+    let output = fs::read(&path).unwrap_or_default();
+    let _ = fs::remove_file(&path);
+    let status = status?;
+
+    Ok(Outcome {
+        code: status.code(),
+        output: String::from_utf8_lossy(&output).trim().to_string(),
+    })
+}
+
+fn wait(
+    child: &mut std::process::Child,
+    timeout: Option<Duration>,
+) -> Result<std::process::ExitStatus> {
+    let Some(timeout) = timeout else {
+        return child
+            .wait()
+            .map_err(|e| AppError::Command(format!("wait: {e}")));
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(status),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(AppError::Command(format!(
+                    "timed out after {}s",
+                    timeout.as_secs_f32()
+                )));
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(10)),
+            Err(e) => return Err(AppError::Command(format!("wait: {e}"))),
+        }
+    }
+}
+
+/// A change to the contacts that can trigger a lifecycle hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Add,
+    Update,
+    Delete,
+    Import,
+}
+
+impl Event {
+    /// Name passed to hooks in `ROLODEX_EVENT`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::Add => "add",
+            Event::Update => "update",
+            Event::Delete => "delete",
+            Event::Import => "import",
+        }
+    }
+}
+
+/// Commands from the `[hooks]` table, run after a write has been saved.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LifecycleHooks {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_add: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_update: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_delete: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_import: Option<String>,
+    /// Seconds a hook may run before it is killed.
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+impl Default for LifecycleHooks {
+    fn default() -> Self {
+        Self {
+            on_add: None,
+            on_update: None,
+            on_delete: None,
+            on_import: None,
+            timeout: default_timeout(),
+        }
+    }
+}
+
+impl LifecycleHooks {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn get(&self, event: Event) -> Option<&str> {
+        match event {
+            Event::Add => self.on_add.as_deref(),
+            Event::Update => self.on_update.as_deref(),
+            Event::Delete => self.on_delete.as_deref(),
+            Event::Import => self.on_import.as_deref(),
+        }
+    }
+
+    /// Run the hook for `event`, if one is set, with `contacts` as a JSON
+    /// array on stdin and the event name in `ROLODEX_EVENT`. The write has
+    /// already happened; an error here only means the hook failed.
+    pub fn fire(&self, event: Event, contacts: &[Contact]) -> Result<()> {
+        let Some(command) = self.get(event) else {
+            return Ok(());
+        };
+        debug!("Running on_{} hook: {command}", event.name());
+        let input = serde_json::to_vec(contacts)?;
+        let timeout = Duration::from_secs(self.timeout);
+        let env = [("ROLODEX_EVENT", event.name())];
+        let outcome = match execute(command, &env, Some(input), Some(timeout)) {
+            Ok(outcome) => outcome,
+            Err(AppError::Command(reason)) => {
+                return Err(AppError::Command(format!("on_{} hook {reason}", event.name())));
+            }
+            Err(err) => return Err(err),
+        };
+        if outcome.success() {
+            return Ok(());
+        }
+        warn!("on_{} hook failed: {outcome:?}", event.name());
+        let mut message = match outcome.code {
+            Some(code) => format!("on_{} hook failed with exit {code}", event.name()),
+            None => format!("on_{} hook was killed", event.name()),
+        };
+        if let Some(line) = outcome.output.lines().last() {
+            message.push_str(": ");
+            message.push_str(line);
+        }
+        Err(AppError::Command(message))
+    }
+}
+
+/// A command that finished on its own thread.
+#[derive(Debug)]
+pub struct HookReply {
+    pub action: Action,
+    pub contact: Contact,
+    pub result: Result<Outcome>,
+}
+
+/// Runs action hooks off the UI thread, one thread per command.
+#[derive(Debug)]
+pub struct HookRunner {
+    hooks: ActionHooks,
+    sender: Sender<HookReply>,
+    replies: Receiver<HookReply>,
+    pending: usize,
+}
+
+impl Default for HookRunner {
+    fn default() -> Self {
+        Self::new(ActionHooks::default())
+    }
+}
+
+impl HookRunner {
+    pub fn new(hooks: ActionHooks) -> Self {
+        let (sender, replies) = mpsc::channel();
+        Self {
+            hooks,
+            sender,
+            replies,
+            pending: 0,
+        }
+    }
+
+    pub fn hooks(&self) -> &ActionHooks {
+        &self.hooks
+    }
+
+    /// Start the command for `action` on `contact`. Fails straight away when
+    /// no command is configured or the contact lacks a field it needs.
+    pub fn spawn(&mut self, action: Action, contact: Contact) -> Result<()> {
+        let template = self.hooks.get(action).ok_or_else(|| {
+            AppError::Command(format!("no {} command set in config.toml", action.name()))
+        })?;
+        let command = render(template, &contact)?;
+        debug!("Running {} hook: {command}", action.name());
+
+        let sender = self.sender.clone();
+        thread::Builder::new()
+            .name(format!("rolodex-{}", action.name()))
+            .spawn(move || {
+                let result = run(&command);
+                let _ = sender.send(HookReply {
+                    action,
+                    contact,
+                    result,
+                });
+            })
+            .map_err(|e| AppError::Command(format!("spawn thread: {e}")))?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.pending > 0
+    }
+
+    /// Progress text shown in the status bar while a command is running.
+    pub fn activity(&self) -> Option<&'static str> {
+        self.is_busy().then_some("Running")
+    }
+
+    pub fn try_recv(&mut self) -> Option<HookReply> {
+        let reply = self.replies.try_recv().ok()?;
+        self.pending -= 1;
+        Some(reply)
+    }
+
+    /// Wait up to `timeout` for the next command to finish.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<HookReply> {
+        match self.replies.recv_timeout(timeout) {
+            Ok(reply) => {
+                self.pending -= 1;
+                Some(reply)
+            }
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ada() -> Contact {
+        Contact {
+            id: 7,
+            name: "Ada O'Brien".to_string(),
+            email: Some("ada@example.com".to_string()),
+            phone: Some("+44 20 7946 0000".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_quotes_values() {
+        assert_eq!(
+            render("linphonecsh dial {phone}", &ada()).unwrap(),
+            "linphonecsh dial '+44 20 7946 0000'"
+        );
+        assert_eq!(
+            render("xdg-open mailto:{email}", &ada()).unwrap(),
+            "xdg-open mailto:ada@example.com"
+        );
+        assert_eq!(
+            render("notify-send {name} {{{id}}}", &ada()).unwrap(),
+            r"notify-send 'Ada O'\''Brien' {7}"
+        );
+    }
+
+    #[test]
+    fn test_render_missing_field() {
+        let err = render("open {company}", &ada()).unwrap_err();
+        assert_eq!(err.to_string(), "Command error: Ada O'Brien has no company");
+    }
+
+    #[test]
+    fn test_check_rejects_bad_templates() {
+        let hooks = |call: &str| ActionHooks {
+            call: Some(call.to_string()),
+            ..Default::default()
+        };
+        assert!(hooks("dial {phone}").check().is_ok());
+        assert!(hooks("dial {fax}").check().unwrap_err().to_string().contains("{fax}"));
+        assert!(hooks("dial }").check().is_err());
+        let err = hooks("dial {phone").check().unwrap_err().to_string();
+        assert!(err.contains("unclosed placeholder {phone"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_actions_table() {
+        let hooks: ActionHooks = toml::from_str("mail = \"xdg-open mailto:{email}\"").unwrap();
+        assert_eq!(hooks.get(Action::Mail), Some("xdg-open mailto:{email}"));
+        assert_eq!(hooks.get(Action::Call), None);
+        assert!(toml::from_str::<ActionHooks>("fax = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_run_reports_output_and_status() {
+        let outcome = run("echo hello; echo oops >&2; exit 3").unwrap();
+        assert_eq!(outcome.code, Some(3));
+        assert_eq!(outcome.output, "hello\noops");
+        assert_eq!(outcome.summary(Action::Call), "call: exit 3: oops");
+        assert_eq!(run("true").unwrap().summary(Action::Open), "open: done");
+    }
+
+    #[test]
+    fn test_runner_replies_in_background() {
+        let mut runner = HookRunner::new(ActionHooks {
+            mail: Some("echo {email}".to_string()),
+            ..Default::default()
+        });
+        assert!(runner.spawn(Action::Call, ada()).is_err());
+        runner.spawn(Action::Mail, ada()).unwrap();
+        assert_eq!(runner.activity(), Some("Running"));
+
+        let reply = runner.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(reply.action, Action::Mail);
+        assert_eq!(reply.result.unwrap().output, "ada@example.com");
+        assert!(!runner.is_busy());
+    }
+
+    #[test]
+    fn test_lifecycle_hook_gets_contacts_on_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out.json");
+        let hooks = LifecycleHooks {
+            on_add: Some(format!("echo $ROLODEX_EVENT > {0}; cat >> {0}", out.display())),
+            ..Default::default()
+        };
+        hooks.fire(Event::Add, &[ada()]).unwrap();
+        hooks.fire(Event::Delete, &[ada()]).unwrap();
+
+        let written = fs::read_to_string(&out).unwrap();
+        let (event, json) = written.split_once('\n').unwrap();
+        assert_eq!(event, "add");
+        let contacts: Vec<Contact> = serde_json::from_str(json).unwrap();
+        assert_eq!(contacts[0].name, "Ada O'Brien");
+    }
+
+    #[test]
+    fn test_lifecycle_hook_failures() {
+        let hooks = LifecycleHooks {
+            on_update: Some("echo disk full >&2; exit 4".to_string()),
+            ..Default::default()
+        };
+        let err = hooks.fire(Event::Update, &[ada()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Command error: on_update hook failed with exit 4: disk full"
+        );
+
+        let hooks = LifecycleHooks {
+            on_delete: Some("sleep 5".to_string()),
+            timeout: 1,
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let err = hooks.fire(Event::Delete, &[ada()]).unwrap_err();
+        assert!(err.to_string().contains("on_delete hook timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+}
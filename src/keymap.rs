@@ -0,0 +1,936 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{AppError, AppResult as Result},
+    mode::AppMode,
+};
+
+/// Group of bindings that are active together. `Global` bindings apply in every
+/// mode and take precedence over the mode's own scope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyScope {
+    Global,
+    Browse,
+    Form,
+    Delete,
+    Error,
+    Help,
+    Palette,
+    Log,
+    Duplicates,
+}
+
+/// Named action a key chord can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Select,
+    AddContact,
+    EditContact,
+    DeleteContact,
+    CycleSort,
+    ReverseSort,
+    ToggleFavorite,
+    Export,
+    ShowDuplicates,
+    Call,
+    Mail,
+    Open,
+    CopyEmail,
+    CopyPhone,
+    CopyNameEmail,
+    CopyVcard,
+    ShowHelp,
+    ShowLog,
+    CommandPalette,
+    Quit,
+    Up,
+    Down,
+    First,
+    Last,
+    PageUp,
+    PageDown,
+    ClearInput,
+    NextField,
+    PrevField,
+    Submit,
+    Confirm,
+    Cancel,
+    Dismiss,
+}
+
+/// One or more chords bound to an action in `config.toml`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The `[keys.<scope>]` tables from `config.toml`, mapping action names to chords.
+pub type KeyConfig = BTreeMap<KeyScope, BTreeMap<Action, KeyBinding>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyScope, Vec<(KeyChord, Action)>>,
+}
+
+const DEFAULT_BINDINGS: &[(KeyScope, Action, &[&str])] = &[
+    (KeyScope::Global, Action::Quit, &["ctrl+q"]),
+    (KeyScope::Global, Action::AddContact, &["ctrl+a"]),
+    (KeyScope::Global, Action::EditContact, &["ctrl+e"]),
+    (KeyScope::Global, Action::DeleteContact, &["ctrl+d"]),
+    (KeyScope::Global, Action::ShowHelp, &["f1"]),
+    (KeyScope::Global, Action::ShowLog, &["ctrl+o"]),
+    (KeyScope::Global, Action::CommandPalette, &["ctrl+p"]),
+    (KeyScope::Browse, Action::ShowHelp, &["?"]),
+    (KeyScope::Browse, Action::Select, &["enter"]),
+    (KeyScope::Browse, Action::Up, &["up"]),
+    (KeyScope::Browse, Action::Down, &["down"]),
+    (KeyScope::Browse, Action::First, &["home"]),
+    (KeyScope::Browse, Action::Last, &["end"]),
+    (KeyScope::Browse, Action::PageUp, &["pageup"]),
+    (KeyScope::Browse, Action::PageDown, &["pagedown"]),
+    (KeyScope::Browse, Action::CycleSort, &["ctrl+s"]),
+    (KeyScope::Browse, Action::ReverseSort, &["ctrl+r"]),
+    (KeyScope::Browse, Action::ToggleFavorite, &["ctrl+f"]),
+    (KeyScope::Browse, Action::Call, &["alt+c"]),
+    (KeyScope::Browse, Action::Mail, &["alt+m"]),
+    (KeyScope::Browse, Action::Open, &["alt+o"]),
+    (KeyScope::Browse, Action::CopyEmail, &["alt+e"]),
+    (KeyScope::Browse, Action::CopyPhone, &["alt+p"]),
+    (KeyScope::Browse, Action::CopyNameEmail, &["alt+n"]),
+    (KeyScope::Browse, Action::CopyVcard, &["alt+v"]),
+    (KeyScope::Browse, Action::ClearInput, &["ctrl+l"]),
+    (KeyScope::Form, Action::NextField, &["tab"]),
+    (KeyScope::Form, Action::PrevField, &["shift+tab"]),
+    (KeyScope::Form, Action::Submit, &["enter"]),
+    (KeyScope::Form, Action::Cancel, &["esc"]),
+    (KeyScope::Form, Action::ClearInput, &["ctrl+l"]),
+    (KeyScope::Delete, Action::Confirm, &["y"]),
+    (KeyScope::Delete, Action::Cancel, &["n", "esc"]),
+    (KeyScope::Error, Action::Dismiss, &["esc"]),
+    (KeyScope::Help, Action::Dismiss, &["esc", "q", "?"]),
+    (KeyScope::Help, Action::Up, &["up", "k"]),
+    (KeyScope::Help, Action::Down, &["down", "j"]),
+    (KeyScope::Help, Action::First, &["home"]),
+    (KeyScope::Help, Action::Last, &["end"]),
+    (KeyScope::Help, Action::PageUp, &["pageup"]),
+    (KeyScope::Help, Action::PageDown, &["pagedown"]),
+    (KeyScope::Log, Action::Dismiss, &["esc", "q"]),
+    (KeyScope::Log, Action::Up, &["up", "k"]),
+    (KeyScope::Log, Action::Down, &["down", "j"]),
+    (KeyScope::Log, Action::First, &["home"]),
+    (KeyScope::Log, Action::Last, &["end"]),
+    (KeyScope::Log, Action::PageUp, &["pageup"]),
+    (KeyScope::Log, Action::PageDown, &["pagedown"]),
+    (KeyScope::Duplicates, Action::Select, &["enter"]),
+    (KeyScope::Duplicates, Action::Dismiss, &["esc", "q"]),
+    (KeyScope::Duplicates, Action::Up, &["up", "k"]),
+    (KeyScope::Duplicates, Action::Down, &["down", "j"]),
+    (KeyScope::Duplicates, Action::First, &["home"]),
+    (KeyScope::Duplicates, Action::Last, &["end"]),
+    (KeyScope::Duplicates, Action::PageUp, &["pageup"]),
+    (KeyScope::Duplicates, Action::PageDown, &["pagedown"]),
+    (KeyScope::Palette, Action::Select, &["enter"]),
+    (KeyScope::Palette, Action::Up, &["up"]),
+    (KeyScope::Palette, Action::Down, &["down"]),
+    (KeyScope::Palette, Action::Cancel, &["esc"]),
+    (KeyScope::Palette, Action::ClearInput, &["ctrl+l"]),
+];
+
+impl KeyScope {
+    pub fn for_mode(mode: &AppMode) -> Self {
+        match mode {
+            AppMode::Browse => KeyScope::Browse,
+            AppMode::ContactForm => KeyScope::Form,
+            AppMode::Delete => KeyScope::Delete,
+            AppMode::Error(_) => KeyScope::Error,
+            AppMode::Help => KeyScope::Help,
+            AppMode::Palette => KeyScope::Palette,
+            AppMode::Log => KeyScope::Log,
+            AppMode::Duplicates => KeyScope::Duplicates,
+        }
+    }
+
+    /// Heading used for the scope in the help overlay.
+    pub fn title(self) -> &'static str {
+        match self {
+            KeyScope::Global => "Everywhere",
+            KeyScope::Browse => "Contact list",
+            KeyScope::Form => "Contact form",
+            KeyScope::Delete => "Delete dialog",
+            KeyScope::Error => "Error dialog",
+            KeyScope::Help => "Help",
+            KeyScope::Palette => "Command palette",
+            KeyScope::Log => "Message log",
+            KeyScope::Duplicates => "Possible duplicates",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyScope::Global => "global",
+            KeyScope::Browse => "browse",
+            KeyScope::Form => "form",
+            KeyScope::Delete => "delete",
+            KeyScope::Error => "error",
+            KeyScope::Help => "help",
+            KeyScope::Palette => "palette",
+            KeyScope::Log => "log",
+            KeyScope::Duplicates => "duplicates",
+        }
+    }
+}
+
+impl Action {
+    /// Actions offered in the command palette, in display order. There is
+    /// no "switch profile" command because there are no profiles yet: one
+    /// config file and one store per user.
+    pub const COMMANDS: [Action; 18] = [
+        Action::AddContact,
+        Action::EditContact,
+        Action::DeleteContact,
+        Action::Export,
+        Action::ShowDuplicates,
+        Action::ToggleFavorite,
+        Action::Call,
+        Action::Mail,
+        Action::Open,
+        Action::CopyEmail,
+        Action::CopyPhone,
+        Action::CopyNameEmail,
+        Action::CopyVcard,
+        Action::CycleSort,
+        Action::ReverseSort,
+        Action::ShowHelp,
+        Action::ShowLog,
+        Action::Quit,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::AddContact => "add_contact",
+            Action::EditContact => "edit_contact",
+            Action::DeleteContact => "delete_contact",
+            Action::CycleSort => "cycle_sort",
+            Action::ReverseSort => "reverse_sort",
+            Action::ToggleFavorite => "toggle_favorite",
+            Action::Export => "export",
+            Action::ShowDuplicates => "show_duplicates",
+            Action::Call => "call",
+            Action::Mail => "mail",
+            Action::Open => "open",
+            Action::CopyEmail => "copy_email",
+            Action::CopyPhone => "copy_phone",
+            Action::CopyNameEmail => "copy_name_email",
+            Action::CopyVcard => "copy_vcard",
+            Action::ShowHelp => "help",
+            Action::ShowLog => "show_log",
+            Action::CommandPalette => "command_palette",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::First => "first",
+            Action::Last => "last",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::Select => "select",
+            Action::ClearInput => "clear_input",
+            Action::NextField => "next_field",
+            Action::PrevField => "prev_field",
+            Action::Submit => "submit",
+            Action::Cancel => "cancel",
+            Action::Confirm => "confirm",
+            Action::Dismiss => "dismiss",
+        }
+    }
+
+    /// Short description shown in the status bar.
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::AddContact => "Add",
+            Action::EditContact => "Edit",
+            Action::DeleteContact => "Delete",
+            Action::CycleSort => "Sort",
+            Action::ReverseSort => "Reverse",
+            Action::ToggleFavorite => "Star",
+            Action::Export => "Export",
+            Action::ShowDuplicates => "Duplicates",
+            Action::Call => "Call",
+            Action::Mail => "Mail",
+            Action::Open => "Open",
+            Action::CopyEmail => "Copy Email",
+            Action::CopyPhone => "Copy Phone",
+            Action::CopyNameEmail => "Copy Address",
+            Action::CopyVcard => "Copy vCard",
+            Action::ShowHelp => "Help",
+            Action::ShowLog => "Log",
+            Action::CommandPalette => "Commands",
+            Action::Up => "Up",
+            Action::Down => "Down",
+            Action::First => "First",
+            Action::Last => "Last",
+            Action::PageUp => "Page Up",
+            Action::PageDown => "Page Down",
+            Action::Select => "Select",
+            Action::ClearInput => "Clear",
+            Action::NextField => "Next Field",
+            Action::PrevField => "Previous Field",
+            Action::Submit => "Save",
+            Action::Cancel => "Cancel",
+            Action::Confirm => "Confirm",
+            Action::Dismiss => "Dismiss",
+        }
+    }
+
+    /// Longer description shown in the help overlay and command palette.
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Quit => "Quit without selecting a contact",
+            Action::AddContact => "Add a new contact",
+            Action::EditContact => "Edit the selected contact",
+            Action::DeleteContact => "Delete the selected contact",
+            Action::CycleSort => "Sort by the next column",
+            Action::ReverseSort => "Reverse the sort order",
+            Action::ToggleFavorite => "Star or unstar the selected contact",
+            Action::Export => "Export every contact to a JSON file",
+            Action::ShowDuplicates => "List contacts that look like the same person",
+            Action::Call => "Run the call command for the selected contact",
+            Action::Mail => "Run the mail command for the selected contact",
+            Action::Open => "Run the open command for the selected contact",
+            Action::CopyEmail => "Copy the email address",
+            Action::CopyPhone => "Copy the phone number",
+            Action::CopyNameEmail => "Copy the name and email, e.g. Ada <ada@example.com>",
+            Action::CopyVcard => "Copy the contact as a vCard",
+            Action::ShowHelp => "Show all key bindings",
+            Action::ShowLog => "Show the message log",
+            Action::CommandPalette => "Open the command palette",
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::First => "Jump to the first entry",
+            Action::Last => "Jump to the last entry",
+            Action::PageUp => "Move up a page",
+            Action::PageDown => "Move down a page",
+            Action::Select => "Select the highlighted entry",
+            Action::ClearInput => "Clear the text field",
+            Action::NextField => "Next field",
+            Action::PrevField => "Previous field",
+            Action::Submit => "Save the contact",
+            Action::Cancel => "Cancel",
+            Action::Confirm => "Confirm",
+            Action::Dismiss => "Close",
+        }
+    }
+
+    /// Whether the action is important enough to list in the one-line status bar.
+    fn is_hint(self) -> bool {
+        !matches!(
+            self,
+            Action::Up
+                | Action::Down
+                | Action::First
+                | Action::Last
+                | Action::PageUp
+                | Action::PageDown
+                | Action::ClearInput
+                | Action::PrevField
+                | Action::Call
+                | Action::Mail
+                | Action::Open
+                | Action::CopyEmail
+                | Action::CopyPhone
+                | Action::CopyNameEmail
+                | Action::CopyVcard
+        )
+    }
+
+    fn allowed_in(self, scope: KeyScope) -> bool {
+        match self {
+            Action::Quit
+            | Action::AddContact
+            | Action::EditContact
+            | Action::DeleteContact
+            | Action::CycleSort
+            | Action::ReverseSort
+            | Action::ToggleFavorite
+            | Action::Export
+            | Action::ShowDuplicates
+            | Action::Call
+            | Action::Mail
+            | Action::Open
+            | Action::CopyEmail
+            | Action::CopyPhone
+            | Action::CopyNameEmail
+            | Action::CopyVcard
+            | Action::ShowHelp
+            | Action::ShowLog
+            | Action::CommandPalette => matches!(scope, KeyScope::Global | KeyScope::Browse),
+            Action::Up
+            | Action::Down
+            | Action::First
+            | Action::Last
+            | Action::PageUp
+            | Action::PageDown => {
+                matches!(
+                    scope,
+                    KeyScope::Browse
+                        | KeyScope::Help
+                        | KeyScope::Log
+                        | KeyScope::Palette
+                        | KeyScope::Duplicates
+                )
+            }
+            Action::Select => {
+                matches!(scope, KeyScope::Browse | KeyScope::Palette | KeyScope::Duplicates)
+            }
+            Action::ClearInput => {
+                matches!(scope, KeyScope::Browse | KeyScope::Form | KeyScope::Palette)
+            }
+            Action::NextField | Action::PrevField | Action::Submit => scope == KeyScope::Form,
+            Action::Cancel => {
+                matches!(scope, KeyScope::Form | KeyScope::Delete | KeyScope::Palette)
+            }
+            Action::Confirm => scope == KeyScope::Delete,
+            Action::Dismiss => matches!(
+                scope,
+                KeyScope::Error | KeyScope::Help | KeyScope::Log | KeyScope::Duplicates
+            ),
+        }
+    }
+}
+
+impl KeyBinding {
+    fn chords(&self) -> Vec<&str> {
+        match self {
+            KeyBinding::One(chord) => vec![chord.as_str()],
+            KeyBinding::Many(chords) => chords.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    /// Normalise a terminal key event so it compares equal to a parsed chord.
+    pub fn from_event(event: KeyEvent) -> Self {
+        let mut modifiers =
+            event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match event.code {
+            // Shift is already reflected in the character itself
+            KeyCode::Char(c) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                if modifiers.is_empty() {
+                    KeyCode::Char(c)
+                } else {
+                    KeyCode::Char(c.to_ascii_lowercase())
+                }
+            }
+            KeyCode::BackTab => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = s.split('+').collect();
+        // A trailing "+" means the plus key itself, e.g. "ctrl++"
+        let key = if s.ends_with("++") || s == "+" {
+            parts.truncate(parts.len().saturating_sub(2));
+            "+"
+        } else {
+            parts.pop().unwrap_or_default()
+        };
+
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+                "alt" | "meta" => modifiers |= KeyModifiers::ALT,
+                "shift" => modifiers |= KeyModifiers::SHIFT,
+                other => return Err(format!("unknown modifier `{other}` in `{s}`")),
+            }
+        }
+
+        let code = match key.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdown" | "pgdn" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            f if f.len() > 1 && f.starts_with('f') && f[1..].parse::<u8>().is_ok() => {
+                KeyCode::F(f[1..].parse().unwrap_or_default())
+            }
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    // Shift is folded into the character, matching `from_event`
+                    (Some(c), None) => {
+                        let shifted = modifiers.contains(KeyModifiers::SHIFT);
+                        modifiers.remove(KeyModifiers::SHIFT);
+                        if !modifiers.is_empty() {
+                            KeyCode::Char(c.to_ascii_lowercase())
+                        } else if shifted {
+                            KeyCode::Char(c.to_ascii_uppercase())
+                        } else {
+                            KeyCode::Char(c)
+                        }
+                    }
+                    _ => return Err(format!("unknown key `{key}` in `{s}`")),
+                }
+            }
+        };
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if !self.modifiers.is_empty() => {
+                write!(f, "{}", c.to_ascii_uppercase())
+            }
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "Shift+Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_config(&KeyConfig::new()).expect("default keymap is valid")
+    }
+}
+
+impl Keymap {
+    /// Build the keymap from the defaults overlaid with the `[keys]` config.
+    /// An action listed in a scope replaces all of its default chords there.
+    pub fn from_config(config: &KeyConfig) -> Result<Self> {
+        let mut actions: BTreeMap<(KeyScope, Action), Vec<KeyChord>> = BTreeMap::new();
+
+        for (scope, action, chords) in DEFAULT_BINDINGS {
+            let parsed = chords
+                .iter()
+                .map(|c| c.parse().expect("default chords parse"))
+                .collect();
+            actions.insert((*scope, *action), parsed);
+        }
+
+        for (scope, bindings) in config {
+            for (action, binding) in bindings {
+                if !action.allowed_in(*scope) {
+                    return Err(AppError::Config(format!(
+                        "action `{}` cannot be bound in [keys.{}]",
+                        action.name(),
+                        scope.name()
+                    )));
+                }
+                let chords = binding
+                    .chords()
+                    .into_iter()
+                    .map(|c| {
+                        c.parse().map_err(|e| {
+                            AppError::Config(format!(
+                                "[keys.{}] {}: {e}",
+                                scope.name(),
+                                action.name()
+                            ))
+                        })
+                    })
+                    .collect::<Result<Vec<KeyChord>>>()?;
+                actions.insert((*scope, *action), chords);
+            }
+        }
+
+        let mut bindings: HashMap<KeyScope, Vec<(KeyChord, Action)>> = HashMap::new();
+        for ((scope, action), chords) in actions {
+            for chord in chords {
+                bindings.entry(scope).or_default().push((chord, action));
+            }
+        }
+
+        let keymap = Self { bindings };
+        keymap.check_conflicts()?;
+        Ok(keymap)
+    }
+
+    fn check_conflicts(&self) -> Result<()> {
+        let global = self.scope(KeyScope::Global);
+        for (scope, bindings) in &self.bindings {
+            for (i, (chord, action)) in bindings.iter().enumerate() {
+                let duplicate = bindings[i + 1..]
+                    .iter()
+                    .find(|(other, other_action)| other == chord && other_action != action);
+                if let Some((_, other)) = duplicate {
+                    return Err(AppError::Config(format!(
+                        "key conflict in [keys.{}]: `{chord}` is bound to both `{}` and `{}`",
+                        scope.name(),
+                        action.name(),
+                        other.name()
+                    )));
+                }
+                if *scope == KeyScope::Global {
+                    continue;
+                }
+                if let Some((_, other)) = global.iter().find(|(other, _)| other == chord) {
+                    return Err(AppError::Config(format!(
+                        "key conflict: `{chord}` is bound to `{}` in [keys.{}] and `{}` in [keys.global]",
+                        action.name(),
+                        scope.name(),
+                        other.name()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn scope(&self, scope: KeyScope) -> &[(KeyChord, Action)] {
+        self.bindings
+            .get(&scope)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Resolve a key event to the action bound in `scope`, if any.
+    pub fn action(&self, scope: KeyScope, event: KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from_event(event);
+        self.scope(scope)
+            .iter()
+            .find(|(bound, _)| *bound == chord)
+            .map(|(_, action)| *action)
+    }
+
+    /// All chords bound to `action` in `scope`.
+    pub fn chords(&self, scope: KeyScope, action: Action) -> Vec<KeyChord> {
+        self.scope(scope)
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(chord, _)| *chord)
+            .collect()
+    }
+
+    /// Every action bound in `scope` with its chords, in `Action` order.
+    pub fn bindings(&self, scope: KeyScope) -> Vec<(Action, Vec<KeyChord>)> {
+        let mut entries: Vec<(Action, Vec<KeyChord>)> = Vec::new();
+        for (chord, action) in self.scope(scope) {
+            match entries.iter_mut().find(|(a, _)| a == action) {
+                Some((_, chords)) => chords.push(*chord),
+                None => entries.push((*action, vec![*chord])),
+            }
+        }
+        entries.sort_by_key(|(action, _)| *action);
+        entries
+    }
+
+    /// Inline hint for specific actions, e.g. `y: Confirm | n/Esc: Cancel`.
+    /// Unbound actions are left out.
+    pub fn describe(&self, scope: KeyScope, actions: &[Action]) -> String {
+        actions
+            .iter()
+            .filter_map(|action| {
+                let chords = self.chords(scope, *action);
+                if chords.is_empty() {
+                    return None;
+                }
+                let chords: Vec<String> = chords.iter().map(ToString::to_string).collect();
+                Some(format!("{}: {}", chords.join("/"), action.label()))
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// Status bar hint for a mode, e.g. `Enter: Select | Ctrl+Q: Quit`.
+    pub fn help_text(&self, mode: &AppMode) -> String {
+        let scope = KeyScope::for_mode(mode);
+        let mut scopes = vec![scope];
+        if scope == KeyScope::Browse {
+            scopes.push(KeyScope::Global);
+        }
+
+        let mut entries: Vec<(Action, Vec<String>)> = Vec::new();
+        for scope in scopes {
+            for (chord, action) in self.scope(scope) {
+                if !action.is_hint() {
+                    continue;
+                }
+                match entries.iter_mut().find(|(a, _)| a == action) {
+                    Some((_, chords)) => chords.push(chord.to_string()),
+                    None => entries.push((*action, vec![chord.to_string()])),
+                }
+            }
+        }
+
+        entries.sort_by_key(|(action, _)| *action);
+        entries
+            .iter()
+            .map(|(action, chords)| format!("{}: {}", chords.join("/"), action.label()))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn config(scope: KeyScope, action: Action, binding: KeyBinding) -> KeyConfig {
+        KeyConfig::from([(scope, BTreeMap::from([(action, binding)]))])
+    }
+
+    #[test]
+    fn test_parse_chords() {
+        let chord: KeyChord = "ctrl+a".parse().unwrap();
+        assert_eq!(
+            chord,
+            KeyChord::new(KeyCode::Char('a'), KeyModifiers::CONTROL)
+        );
+
+        let chord: KeyChord = "Shift+Tab".parse().unwrap();
+        assert_eq!(chord, KeyChord::new(KeyCode::BackTab, KeyModifiers::NONE));
+
+        let chord: KeyChord = "f5".parse().unwrap();
+        assert_eq!(chord, KeyChord::new(KeyCode::F(5), KeyModifiers::NONE));
+
+        assert!("hyper+a".parse::<KeyChord>().is_err());
+        assert!("banana".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn test_default_bindings_resolve() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(
+                KeyScope::Global,
+                key(KeyCode::Char('q'), KeyModifiers::CONTROL)
+            ),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(
+                KeyScope::Delete,
+                key(KeyCode::Char('y'), KeyModifiers::NONE)
+            ),
+            Some(Action::Confirm)
+        );
+        assert_eq!(
+            keymap.action(KeyScope::Form, key(KeyCode::BackTab, KeyModifiers::SHIFT)),
+            Some(Action::PrevField)
+        );
+        assert_eq!(
+            keymap.action(
+                KeyScope::Browse,
+                key(KeyCode::Char('y'), KeyModifiers::NONE)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_config_overrides_default() {
+        let keymap = Keymap::from_config(&config(
+            KeyScope::Global,
+            Action::Quit,
+            KeyBinding::One("ctrl+x".into()),
+        ))
+        .unwrap();
+
+        assert_eq!(
+            keymap.action(
+                KeyScope::Global,
+                key(KeyCode::Char('x'), KeyModifiers::CONTROL)
+            ),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            keymap.action(
+                KeyScope::Global,
+                key(KeyCode::Char('q'), KeyModifiers::CONTROL)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_conflict_within_scope() {
+        let err = Keymap::from_config(&config(
+            KeyScope::Delete,
+            Action::Cancel,
+            KeyBinding::Many(vec!["y".into(), "esc".into()]),
+        ))
+        .unwrap_err();
+
+        assert!(
+            matches!(err, AppError::Config(ref msg) if msg.contains("[keys.delete]") && msg.contains("`y`"))
+        );
+    }
+
+    #[test]
+    fn test_conflict_with_global() {
+        let err = Keymap::from_config(&config(
+            KeyScope::Browse,
+            Action::CycleSort,
+            KeyBinding::One("ctrl+q".into()),
+        ))
+        .unwrap_err();
+
+        assert!(matches!(err, AppError::Config(ref msg) if msg.contains("[keys.global]")));
+    }
+
+    #[test]
+    fn test_action_not_allowed_in_scope() {
+        let err = Keymap::from_config(&config(
+            KeyScope::Browse,
+            Action::Confirm,
+            KeyBinding::One("y".into()),
+        ))
+        .unwrap_err();
+
+        assert!(matches!(err, AppError::Config(ref msg) if msg.contains("confirm")));
+    }
+
+    #[test]
+    fn test_help_text_follows_bindings() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.help_text(&AppMode::Delete),
+            "y: Confirm | n/Esc: Cancel"
+        );
+
+        let keymap = Keymap::from_config(&config(
+            KeyScope::Delete,
+            Action::Confirm,
+            KeyBinding::One("enter".into()),
+        ))
+        .unwrap();
+        assert_eq!(
+            keymap.help_text(&AppMode::Delete),
+            "Enter: Confirm | n/Esc: Cancel"
+        );
+    }
+
+    #[test]
+    fn test_describe_skips_unbound_actions() {
+        let keymap = Keymap::from_config(&config(
+            KeyScope::Error,
+            Action::Dismiss,
+            KeyBinding::Many(vec![]),
+        ))
+        .unwrap();
+        assert_eq!(keymap.describe(KeyScope::Error, &[Action::Dismiss]), "");
+        assert_eq!(
+            keymap.describe(KeyScope::Form, &[Action::Submit, Action::Cancel]),
+            "Enter: Save | Esc: Cancel"
+        );
+    }
+
+    #[test]
+    fn test_parse_key_config_from_toml() {
+        let parsed: BTreeMap<String, KeyConfig> = toml::from_str(
+            r#"
+            [keys.browse]
+            select = ["enter", "ctrl+j"]
+            cycle_sort = "f2"
+            "#,
+        )
+        .unwrap();
+
+        let keymap = Keymap::from_config(&parsed["keys"]).unwrap();
+        assert_eq!(
+            keymap.action(
+                KeyScope::Browse,
+                key(KeyCode::Char('j'), KeyModifiers::CONTROL)
+            ),
+            Some(Action::Select)
+        );
+        assert_eq!(keymap.chords(KeyScope::Browse, Action::CycleSort).len(), 1);
+    }
+
+    #[test]
+    fn test_bindings_group_chords_by_action() {
+        let keymap = Keymap::default();
+        let help = keymap.bindings(KeyScope::Help);
+        let (action, chords) = &help[help.len() - 1];
+        assert_eq!(*action, Action::Dismiss);
+        let chords: Vec<String> = chords.iter().map(ToString::to_string).collect();
+        assert_eq!(chords, ["Esc", "q", "?"]);
+
+        assert_eq!(
+            keymap.action(
+                KeyScope::Browse,
+                key(KeyCode::Char('?'), KeyModifiers::SHIFT)
+            ),
+            Some(Action::ShowHelp)
+        );
+    }
+}
@@ -1,11 +1,24 @@
 pub mod cli;
+pub mod clipboard;
 pub mod components;
 pub mod config;
+pub mod crypt;
 pub mod db;
 pub mod error;
+pub mod hooks;
+pub mod keymap;
 pub mod mode;
 pub mod model;
+pub mod server;
+pub mod store;
+pub mod sync;
+pub mod theme;
 pub mod trace;
 pub mod tui;
+pub mod vi;
+pub mod vcard;
+pub mod worker;
+pub mod xml;
 pub use db::Db;
 pub mod layout;
+pub mod mail;
@@ -0,0 +1,321 @@
+//! Address book glue for mail clients: the `query_command` output format
+//! used by mutt, neomutt and aerc, and reading the sender of a message.
+
+use crate::model::Contact;
+
+/// Output for a mail client's `query_command`: a status line the client
+/// shows as a message, then `email<TAB>name<TAB>info` for each contact with
+/// an email address.
+pub fn query_output(query: &str, contacts: &[Contact]) -> String {
+    let lines: Vec<String> = contacts
+        .iter()
+        .filter_map(|contact| {
+            let email = contact.email.as_deref().map(str::trim)?;
+            if email.is_empty() {
+                return None;
+            }
+            Some(format!(
+                "{}\t{}\t{}",
+                clean(email),
+                clean(&contact.name),
+                clean(contact.company.as_deref().unwrap_or(""))
+            ))
+        })
+        .collect();
+
+    let mut output = match lines.len() {
+        0 => format!("rolodex: no contacts match {query:?}\n"),
+        1 => "rolodex: 1 contact\n".to_string(),
+        n => format!("rolodex: {n} contacts\n"),
+    };
+    for line in lines {
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output
+}
+
+/// Tabs and line breaks would break the column format.
+fn clean(field: &str) -> String {
+    field.replace(['\t', '\r', '\n'], " ")
+}
+
+/// `Name <email>`, quoting the name when it contains characters that have
+/// a meaning in address headers.
+pub fn mailbox(name: &str, email: &str) -> String {
+    let name = name.trim();
+    if name.is_empty() {
+        return email.to_string();
+    }
+    let special = |c: char| "()<>[]:;@\\,.\"".contains(c);
+    if name.contains(special) {
+        let quoted = name.replace('\\', "\\\\").replace('"', "\\\"");
+        format!("\"{quoted}\" <{email}>")
+    } else {
+        format!("{name} <{email}>")
+    }
+}
+
+/// The sender of an RFC 5322 message as a new contact, taken from the
+/// `From:` header. Falls back to the local part of the address when the
+/// header has no display name.
+pub fn sender(message: &str) -> Option<Contact> {
+    let from = header(message, "From")?;
+    let (name, email) = parse_mailbox(first_mailbox(&from))?;
+    let name = match name {
+        Some(name) if !name.is_empty() => name,
+        _ => email.split('@').next().unwrap_or(&email).to_string(),
+    };
+    Some(Contact {
+        name,
+        email: Some(email),
+        ..Default::default()
+    })
+}
+
+/// Value of the first header called `name`, with folded lines joined.
+fn header(message: &str, name: &str) -> Option<String> {
+    let mut value: Option<String> = None;
+    for line in message.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some(value) = &mut value {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if value.is_some() {
+            break;
+        }
+        if let Some((field, rest)) = line.split_once(':')
+            && field.trim().eq_ignore_ascii_case(name)
+        {
+            value = Some(rest.trim().to_string());
+        }
+    }
+    value
+}
+
+/// The first mailbox of an address list, ignoring commas inside quotes,
+/// comments and angle brackets.
+fn first_mailbox(list: &str) -> &str {
+    let (mut quoted, mut depth) = (false, 0i32);
+    let mut escaped = false;
+    for (i, c) in list.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            '(' | '<' if !quoted => depth += 1,
+            ')' | '>' if !quoted => depth -= 1,
+            ',' if !quoted && depth <= 0 => return &list[..i],
+            _ => {}
+        }
+    }
+    list
+}
+
+/// Split `Name <addr>`, `addr (Name)` or a bare `addr` into display name
+/// and address.
+fn parse_mailbox(mailbox: &str) -> Option<(Option<String>, String)> {
+    let mailbox = mailbox.trim();
+    let (name, email) = match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => (
+            Some(unquote(mailbox[..start].trim())),
+            mailbox[start + 1..end].trim().to_string(),
+        ),
+        _ => match (mailbox.find('('), mailbox.rfind(')')) {
+            (Some(start), Some(end)) if start < end => (
+                Some(mailbox[start + 1..end].trim().to_string()),
+                mailbox[..start].trim().to_string(),
+            ),
+            _ => (None, mailbox.to_string()),
+        },
+    };
+    if !email.contains('@') || email.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((name.map(|name| decode_words(&name)), email))
+}
+
+fn unquote(name: &str) -> String {
+    match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => name.to_string(),
+    }
+}
+
+/// Decode RFC 2047 encoded words such as `=?UTF-8?Q?Ren=C3=A9e?=`.
+/// UTF-8, US-ASCII and ISO-8859-1 are understood; other words are left as
+/// they are. Whitespace between adjacent encoded words is dropped.
+fn decode_words(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    let mut after_word = false;
+    while let Some(start) = rest.find("=?") {
+        let Some(decoded) = rest[start..]
+            .split_once("?=")
+            // "=?=" overlaps its own markers and leaves no word to slice
+            .and_then(|(word, _)| decode_word(word.get(2..)?).map(|text| (word.len() + 2, text)))
+        else {
+            out.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            after_word = false;
+            continue;
+        };
+        let between = &rest[..start];
+        if !(after_word && between.trim().is_empty()) {
+            out.push_str(between);
+        }
+        out.push_str(&decoded.1);
+        rest = &rest[start + decoded.0..];
+        after_word = true;
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Decode the inside of one encoded word: `charset?encoding?text`.
+fn decode_word(word: &str) -> Option<String> {
+    let mut parts = word.splitn(3, '?');
+    let (charset, encoding, text) = (parts.next()?, parts.next()?, parts.next()?);
+    let bytes = match encoding {
+        "Q" | "q" => decode_q(text)?,
+        "B" | "b" => decode_base64(text)?,
+        _ => return None,
+    };
+    let charset = charset.split('*').next().unwrap_or(charset);
+    if charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("us-ascii") {
+        String::from_utf8(bytes).ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") || charset.eq_ignore_ascii_case("latin1")
+    {
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        None
+    }
+}
+
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut input = text.bytes();
+    while let Some(b) = input.next() {
+        match b {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                let hex = [input.next()?, input.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            b => bytes.push(b),
+        }
+    }
+    Some(bytes)
+}
+
+pub(crate) fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes().filter(|&c| c != b'=') {
+        buffer = (buffer << 6) | value(c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(name: &str, email: Option<&str>, company: Option<&str>) -> Contact {
+        Contact {
+            name: name.to_string(),
+            email: email.map(str::to_string),
+            company: company.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_query_output_format() {
+        let contacts = vec![
+            contact("Ada Lovelace", Some("ada@example.com"), Some("Analytical\tEngines")),
+            contact("No Email", None, None),
+            contact("Alan Turing", Some("alan@example.com"), None),
+        ];
+        assert_eq!(
+            query_output("a", &contacts),
+            "rolodex: 2 contacts\n\
+             ada@example.com\tAda Lovelace\tAnalytical Engines\n\
+             alan@example.com\tAlan Turing\t\n"
+        );
+        assert_eq!(query_output("zz", &[]), "rolodex: no contacts match \"zz\"\n");
+    }
+
+    #[test]
+    fn test_mailbox_quotes_specials() {
+        assert_eq!(mailbox("Ada Lovelace", "ada@example.com"), "Ada Lovelace <ada@example.com>");
+        assert_eq!(mailbox("Turing, Alan", "a@example.com"), "\"Turing, Alan\" <a@example.com>");
+        assert_eq!(mailbox("", "a@example.com"), "a@example.com");
+
+        // What we write, we can read back
+        let message = format!("From: {}\n\n", mailbox("J. \"Doc\" Brown", "doc@example.com"));
+        assert_eq!(sender(&message).unwrap().name, "J. \"Doc\" Brown");
+    }
+
+    #[test]
+    fn test_sender_forms() {
+        let sender_of = |from: &str| {
+            let message = format!("Subject: hi\r\nFrom: {from}\r\nTo: me@example.com\r\n\r\nBody");
+            sender(&message).map(|c| (c.name, c.email.unwrap()))
+        };
+        let pair = |name: &str, email: &str| Some((name.to_string(), email.to_string()));
+
+        assert_eq!(
+            sender_of("Ada Lovelace <ada@example.com>"),
+            pair("Ada Lovelace", "ada@example.com")
+        );
+        assert_eq!(
+            sender_of("\"Turing, Alan\" <alan@example.com>, bob@example.com"),
+            pair("Turing, Alan", "alan@example.com")
+        );
+        assert_eq!(
+            sender_of("grace@example.com (Grace Hopper)"),
+            pair("Grace Hopper", "grace@example.com")
+        );
+        assert_eq!(sender_of("<edsger@example.com>"), pair("edsger", "edsger@example.com"));
+        assert_eq!(sender_of("barbara@example.com"), pair("barbara", "barbara@example.com"));
+        assert_eq!(
+            sender_of("\"Bad =?= Name\" <bad@example.com>"),
+            pair("Bad =?= Name", "bad@example.com")
+        );
+        assert_eq!(sender_of("undisclosed-recipients:;"), None);
+    }
+
+    #[test]
+    fn test_sender_header_folding_and_encoding() {
+        let message = "From: =?UTF-8?Q?Ren=C3=A9e?=\r\n =?ISO-8859-1?B?TfxsbGVy?=\r\n \
+                       <renee@example.com>\r\n\r\nFrom: not@header.com\r\n";
+        let contact = sender(message).unwrap();
+        assert_eq!(contact.name, "RenéeMüller");
+        assert_eq!(contact.email.as_deref(), Some("renee@example.com"));
+
+        // Only headers count, not the body
+        assert!(sender("Subject: x\n\nFrom: a@b.com\n").is_none());
+    }
+}
@@ -1,9 +1,20 @@
 use clap::Parser;
-use rolodex_tui::cli::Cli;
-use rolodex_tui::components::app::App;
+use rolodex_tui::cli::{Cli, Command};
+use rolodex_tui::components::app::{App, Preselect};
 use rolodex_tui::config::Config;
-use rolodex_tui::error::AppResult as Result;
-use rolodex_tui::{Db, trace, tui};
+use rolodex_tui::error::{AppError, AppResult as Result};
+use rolodex_tui::hooks::Event;
+use rolodex_tui::keymap::Keymap;
+use rolodex_tui::server::{CardDav, Ldap, Rpc, Server};
+use rolodex_tui::store::{self, StoreKind};
+use rolodex_tui::sync::{self, Change};
+use rolodex_tui::theme::Theme;
+use rolodex_tui::{Db, crypt, mail, trace, tui};
+use std::fs;
+use std::io::Read;
+use std::net::TcpListener;
+use std::os::unix::{fs::FileTypeExt, net::UnixListener};
+use std::sync::Arc;
 use tracing::{debug, info};
 
 fn main() -> Result<()> {
@@ -17,8 +28,158 @@ fn main() -> Result<()> {
     debug!("Loading config");
     let config = Config::load()?;
 
-    debug!("Opening database");
-    let mut db = Db::open(&config.database_path)?;
+    if matches!(args.command, Some(Command::Sync { .. } | Command::Carddav { .. })) {
+        // Both keep per-contact state that only the SQLite store has room for
+        if config.store != StoreKind::Sqlite {
+            return Err(AppError::Config(format!(
+                "sync and carddav need store = \"sqlite\", not \"{}\"",
+                config.store
+            )));
+        }
+        debug!("Opening database");
+        let mut db = Db::open(&config.database_path)?;
+        db.set_hooks(config.hooks.clone());
+
+        if let Some(Command::Sync { conflict, reset }) = args.command {
+            let remote = config.sync.remote()?;
+            if reset {
+                sync::reset(&db)?;
+            }
+            let changes = sync::sync(&db, &remote, conflict.unwrap_or(config.sync.conflict))?;
+            for change in &changes {
+                println!("{change}");
+            }
+            println!("{}", sync::summary(&changes));
+            if changes.iter().any(|change| matches!(change, Change::Failed(..))) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+
+        if let Some(Command::Carddav { listen, password }) = args.command {
+            let password = password
+                .or_else(|| std::env::var("ROLODEX_TOKEN").ok())
+                .filter(|password| !password.is_empty());
+            let open = password.is_none();
+            let server = Arc::new(CardDav::new(db, password));
+            let listener = TcpListener::bind(&listen)?;
+            let addr = listener.local_addr()?;
+            if open && !addr.ip().is_loopback() {
+                eprintln!(
+                    "Warning: serving on {addr} without --password, anyone on the network can edit"
+                );
+            }
+            eprintln!("Serving the address book over CardDAV on http://{addr}/");
+            return server.serve_tcp(listener);
+        }
+    }
+
+    // Say why a new passphrase is about to be asked for
+    if config.store == StoreKind::Encrypted {
+        let path = config.contacts_file();
+        if !path.exists() {
+            eprintln!("Creating an encrypted address book at {}", path.display());
+        }
+    }
+
+    if let Some(Command::Rekey) = args.command {
+        if config.store != StoreKind::Encrypted {
+            return Err(AppError::Config(format!(
+                "rekey needs store = \"encrypted\", not \"{}\"",
+                config.store
+            )));
+        }
+        let mut store = store::open_encrypted(&config)?;
+        store.rekey(&crypt::new_passphrase()?)?;
+        eprintln!("Changed the passphrase of {}", store.path().display());
+        return Ok(());
+    }
+
+    debug!("Opening {} store", config.store);
+    let mut db = store::open(&config)?;
+
+    if let Some(Command::List { sort, order }) = args.command {
+        let mut active = db.load_sort()?;
+        if let Some(key) = sort {
+            active.key = key;
+        }
+        if let Some(order) = order {
+            active.order = order;
+        }
+        debug!("Listing contacts sorted by {active}");
+        let contacts = db.load_customers_sorted(active)?;
+        println!("{}", serde_json::to_string_pretty(&contacts)?);
+        return Ok(());
+    }
+
+    if let Some(Command::Query { query }) = &args.command {
+        debug!("Querying contacts for {query:?}");
+        let contacts = db.search_contacts(query)?;
+        print!("{}", mail::query_output(query, &contacts));
+        return Ok(());
+    }
+
+    if let Some(Command::Serve { listen, socket, token }) = args.command {
+        let token = token
+            .or_else(|| std::env::var("ROLODEX_TOKEN").ok())
+            .filter(|token| !token.is_empty());
+        let open = token.is_none();
+        let server = Arc::new(Server::new(db, token));
+        if let Some(path) = socket {
+            // A socket left behind by an earlier run would make bind fail
+            if fs::metadata(&path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                fs::remove_file(&path)?;
+            }
+            let listener = UnixListener::bind(&path)?;
+            eprintln!("Serving contacts on {}", path.display());
+            return server.serve_unix(listener);
+        }
+        let listener = TcpListener::bind(&listen)?;
+        let addr = listener.local_addr()?;
+        if open && !addr.ip().is_loopback() {
+            eprintln!("Warning: serving on {addr} without --token, anyone on the network can edit");
+        }
+        eprintln!("Serving contacts on http://{addr}");
+        return server.serve_tcp(listener);
+    }
+
+    if let Some(Command::Ldap { listen, base }) = args.command {
+        let listener = TcpListener::bind(&listen)?;
+        let addr = listener.local_addr()?;
+        if !addr.ip().is_loopback() {
+            eprintln!("Warning: serving on {addr}, anyone on the network can read every contact");
+        }
+        eprintln!("Serving the address book over LDAP on ldap://{addr}/{base}");
+        return Arc::new(Ldap::new(db, base)).serve_tcp(listener);
+    }
+
+    if let Some(Command::Rpc) = args.command {
+        return Rpc::new(db).serve(std::io::stdin().lock(), std::io::stdout().lock());
+    }
+
+    let mut sender = None;
+    if args.add_from_mail {
+        let mut message = String::new();
+        std::io::stdin().read_to_string(&mut message)?;
+        let Some(contact) = mail::sender(&message) else {
+            eprintln!("No sender address found in the message");
+            std::process::exit(1);
+        };
+        let email = contact.email.clone().unwrap_or_default();
+        let known = db
+            .search_by_field("email", &email)?
+            .into_iter()
+            .find(|c| {
+                c.email
+                    .as_deref()
+                    .is_some_and(|e| e.trim().eq_ignore_ascii_case(&email))
+            });
+        if let Some(known) = known {
+            println!("{} <{email}> is already in the address book", known.name);
+            return Ok(());
+        }
+        sender = Some(contact);
+    }
 
     if let Some(count) = args.seed {
         debug!("Seeding database with {count} contacts");
@@ -35,15 +196,59 @@ fn main() -> Result<()> {
 
     if let Some(restore_path) = args.restore {
         debug!("Restoring contacts from {}", restore_path.display());
-        let count = db.restore_from_file(&restore_path)?;
-        println!("Restored {} contacts from {}", count, restore_path.display());
+        let restored = db.restore_from_file(&restore_path)?;
+        println!("Restored {} contacts from {}", restored.len(), restore_path.display());
+        // The restore stands even if the hook fails
+        if let Err(err) = db.hooks().fire(Event::Import, &restored) {
+            eprintln!("{err}");
+        }
+        return Ok(());
+    }
+
+    debug!("Loading keymap");
+    let keymap = Keymap::from_config(&config.keys)?;
+    let theme = Theme::from_config(config.theme.as_deref(), &config.themes)?;
+    config.actions.check()?;
+    let mut app = App::new(db)?;
+    app.set_keymap(keymap);
+    app.set_vi_mode(config.vi_mode);
+    app.set_theme(&theme);
+    app.set_hooks(config.actions.clone());
+    app.set_clipboard(config.clipboard.clone());
+    if let Some(contact) = sender {
+        debug!("Offering to add {:?}", contact.email);
+        let height = args.height.or(config.height);
+        app.add_only(contact);
+        let mut terminal = tui::init_terminal(height)?;
+        let added = App::run(&mut terminal, app);
+        tui::restore_terminal(&mut terminal, height.is_some())?;
+        if let Some(contact) = added? {
+            println!("Added {} <{}>", contact.name, contact.email.unwrap_or_default());
+        }
         return Ok(());
     }
+    if let Some(query) = &args.query {
+        debug!("Starting with query {query:?}");
+        app.set_query(query);
+    }
 
-    let mut terminal = tui::init_terminal()?;
-    debug!("Running app");
-    let selected = App::run(&mut terminal, db)?;
-    tui::restore_terminal()?;
+    let selected = match app.preselect(args.select_one, args.exit_0) {
+        Some(Preselect::NoMatches) => {
+            debug!("No matches, exiting");
+            // Let the worker finish before exiting without unwinding
+            drop(app);
+            std::process::exit(1);
+        }
+        Some(Preselect::Selected(contact)) => Some(*contact),
+        None => {
+            let height = args.height.or(config.height);
+            let mut terminal = tui::init_terminal(height)?;
+            debug!("Running app");
+            let selected = App::run(&mut terminal, app);
+            tui::restore_terminal(&mut terminal, height.is_some())?;
+            selected?
+        }
+    };
 
     if let Some(contact) = selected {
         let json = serde_json::to_string_pretty(&contact)?;
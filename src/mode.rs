@@ -5,4 +5,18 @@ pub enum AppMode {
     Delete,
     ContactForm,
     Error(String),
+    Help,
+    Palette,
+    Log,
+    Duplicates,
+}
+
+impl AppMode {
+    /// Whether the mode is drawn over another one and returns to it on close.
+    pub fn is_overlay(&self) -> bool {
+        matches!(
+            self,
+            AppMode::Help | AppMode::Palette | AppMode::Log | AppMode::Duplicates
+        )
+    }
 }
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 #[derive(Debug)]
 pub enum ValidationError {
@@ -22,13 +25,40 @@ impl std::error::Error for ValidationError {}
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
 pub struct Contact {
+    #[serde(default)]
     pub id: i64,
     pub name: String,
     pub company: Option<String>,
     pub phone: Option<String>,
     pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<String>,
+    /// Number of times the contact has been picked from the list.
+    #[serde(default)]
+    pub use_count: i64,
+    /// Starred contacts are pinned to the top of the list.
+    #[serde(default)]
+    pub favorite: bool,
+    /// Resource name given by a CardDAV client, kept so the client finds
+    /// the card where it put it. Contacts added elsewhere have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<String>,
+    /// Bumped by every edit, so an edit made to an older version can be
+    /// refused instead of silently overwriting a newer one.
+    #[serde(default)]
+    pub version: i64,
 }
 
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Weight given to each use by how many days ago the contact was last used.
+const RECENCY_WEIGHTS: [(i64, f64); 4] = [(4, 100.0), (14, 70.0), (31, 50.0), (90, 30.0)];
+const OLD_USE_WEIGHT: f64 = 10.0;
+
 impl Contact {
     pub fn from_row(row: &rusqlite::Row) -> Result<Self, rusqlite::Error> {
         Ok(Self {
@@ -37,24 +67,51 @@ impl Contact {
             email: row.get(2)?,
             phone: row.get(3)?,
             company: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+            last_used_at: row.get(7)?,
+            use_count: row.get(8)?,
+            favorite: row.get(9)?,
+            uid: row.get(10)?,
+            version: row.get(11)?,
         })
     }
 
+    /// How often and how recently the contact has been used, as of `now`.
+    /// Recent uses count for more, so a contact picked a lot last year ranks
+    /// below one picked a few times this week. Never-used contacts score 0.
+    pub fn frecency(&self, now: SystemTime) -> f64 {
+        let Some(last_used) = self.last_used_at.as_deref().and_then(parse_timestamp) else {
+            return 0.0;
+        };
+        let now = now
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+        let age_days = (now - last_used).max(0) / DAY_SECS;
+        let weight = RECENCY_WEIGHTS
+            .iter()
+            .find(|(days, _)| age_days <= *days)
+            .map_or(OLD_USE_WEIGHT, |(_, weight)| *weight);
+        self.use_count.max(1) as f64 * weight
+    }
+
     pub fn validate(&self) -> Result<(), ValidationError> {
         if self.name.trim().is_empty() {
             return Err(ValidationError::EmptyName);
         }
 
-        if let Some(email) = &self.email {
-            if !email.trim().is_empty() && !Self::is_valid_email(email) {
-                return Err(ValidationError::InvalidEmail(email.clone()));
-            }
+        if let Some(email) = &self.email
+            && !email.trim().is_empty()
+            && !Self::is_valid_email(email)
+        {
+            return Err(ValidationError::InvalidEmail(email.clone()));
         }
 
-        if let Some(phone) = &self.phone {
-            if !phone.trim().is_empty() && !Self::is_valid_phone(phone) {
-                return Err(ValidationError::InvalidPhone(phone.clone()));
-            }
+        if let Some(phone) = &self.phone
+            && !phone.trim().is_empty()
+            && !Self::is_valid_phone(phone)
+        {
+            return Err(ValidationError::InvalidPhone(phone.clone()));
         }
 
         Ok(())
@@ -83,9 +140,60 @@ impl Contact {
     }
 }
 
+/// Seconds since the Unix epoch for an SQLite `CURRENT_TIMESTAMP` value
+/// (`YYYY-MM-DD HH:MM:SS`, UTC).
+fn parse_timestamp(value: &str) -> Option<i64> {
+    let (date, time) = value.split_once([' ', 'T']).unwrap_or((value, "00:00:00"));
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.splitn(3, ':').map(|part| part.get(..2).unwrap_or(part).parse::<i64>());
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    // Days from civil date, after Howard Hinnant's algorithm
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Some(days * DAY_SECS + hour * 3600 + minute * 60 + second)
+}
+
+/// `time` in the format SQLite's `CURRENT_TIMESTAMP` uses, for stores that
+/// keep timestamps themselves.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs() as i64);
+    let (days, secs) = (secs.div_euclid(DAY_SECS), secs.rem_euclid(DAY_SECS));
+
+    // Civil date from days, after Howard Hinnant's algorithm
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     #[test]
     fn test_contact_default() {
@@ -105,6 +213,7 @@ mod tests {
             company: Some("Acme Corp".to_string()),
             phone: Some("0412345678".to_string()),
             email: Some("john@acme.com".to_string()),
+            ..Default::default()
         };
 
         assert_eq!(contact.id, 1);
@@ -122,6 +231,7 @@ mod tests {
             company: Some("Tech Inc".to_string()),
             phone: Some("0487654321".to_string()),
             email: Some("jane@tech.com".to_string()),
+            ..Default::default()
         };
 
         let contact2 = contact1.clone();
@@ -136,6 +246,7 @@ mod tests {
             company: Some("StartupXYZ".to_string()),
             phone: Some("0411111111".to_string()),
             email: Some("alice@startup.com".to_string()),
+            ..Default::default()
         };
 
         let json = serde_json::to_string(&contact).unwrap();
@@ -153,6 +264,7 @@ mod tests {
             company: Some("Acme Corp".to_string()),
             phone: Some("0412345678".to_string()),
             email: Some("john@acme.com".to_string()),
+            ..Default::default()
         };
 
         assert!(contact.validate().is_ok());
@@ -166,6 +278,7 @@ mod tests {
             company: Some("Acme Corp".to_string()),
             phone: Some("0412345678".to_string()),
             email: Some("john@acme.com".to_string()),
+            ..Default::default()
         };
 
         assert!(matches!(contact.validate(), Err(ValidationError::EmptyName)));
@@ -179,6 +292,7 @@ mod tests {
             company: Some("Acme Corp".to_string()),
             phone: Some("0412345678".to_string()),
             email: Some("invalid-email".to_string()),
+            ..Default::default()
         };
 
         assert!(matches!(contact.validate(), Err(ValidationError::InvalidEmail(_))));
@@ -192,6 +306,7 @@ mod tests {
             company: Some("Acme Corp".to_string()),
             phone: Some("123".to_string()),
             email: Some("john@acme.com".to_string()),
+            ..Default::default()
         };
 
         assert!(matches!(contact.validate(), Err(ValidationError::InvalidPhone(_))));
@@ -205,6 +320,7 @@ mod tests {
             company: None,
             phone: None,
             email: None,
+            ..Default::default()
         };
 
         assert!(contact.validate().is_ok());
@@ -220,6 +336,37 @@ mod tests {
         assert!(!Contact::is_valid_email(""));
     }
 
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01 00:00:00"), Some(0));
+        assert_eq!(parse_timestamp("2000-03-01 12:30:15"), Some(951_913_815));
+        assert_eq!(parse_timestamp("2024-02-29 23:59:59"), Some(1_709_251_199));
+        assert_eq!(parse_timestamp("yesterday"), None);
+        for secs in [0, 951_913_815, 1_709_251_199, 4_102_444_800] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(parse_timestamp(&format_timestamp(time)), Some(secs as i64));
+        }
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_251_199);
+        assert_eq!(format_timestamp(time), "2024-02-29 23:59:59");
+    }
+
+    #[test]
+    fn test_frecency_favours_recent_use() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_709_251_199);
+        let used = |count, at: &str| Contact {
+            use_count: count,
+            last_used_at: Some(at.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(Contact::default().frecency(now), 0.0);
+        assert_eq!(used(3, "2024-02-28 10:00:00").frecency(now), 300.0);
+        assert_eq!(used(3, "2024-01-20 10:00:00").frecency(now), 90.0);
+        assert_eq!(used(3, "2022-01-20 10:00:00").frecency(now), 30.0);
+        // A few recent uses outrank many old ones
+        assert!(used(2, "2024-02-27 10:00:00").frecency(now) > used(15, "2023-01-01 10:00:00").frecency(now));
+    }
+
     #[test]
     fn test_phone_validation() {
         assert!(Contact::is_valid_phone("0412345678"));
@@ -0,0 +1,264 @@
+use std::{cmp::Ordering, time::SystemTime};
+
+use fuzzy_matcher::{FuzzyMatcher, skim::SkimMatcherV2};
+
+use crate::model::{Contact, Sort, SortKey};
+
+/// Queries up to this many characters get a frecency boost.
+const SHORT_QUERY: usize = 2;
+/// Score added per unit of `ln(1 + frecency)`; roughly 70 points for a
+/// contact used ten times this week.
+const FRECENCY_BOOST: f64 = 10.0;
+
+/// Fuzzy search over a contact list that works on indices rather than
+/// copies of contacts.
+///
+/// The text searched for each contact and the sorted order of the whole list
+/// are computed once per list or sort change. When a query extends the
+/// previous one, only the contacts that matched before are scored again,
+/// since a fuzzy match for the longer query is always a match for the shorter.
+///
+/// Favorites are always listed first. When sorting by relevance, frecency
+/// decides the order of an empty query and boosts the match score of short
+/// ones, where fuzzy scores alone say little.
+pub struct ContactFilter {
+    matcher: SkimMatcherV2,
+    haystacks: Vec<String>,
+    favorite: Vec<bool>,
+    frecency: Vec<f64>,
+    sort: Sort,
+    /// Every index, ordered by `sort`.
+    sorted: Vec<usize>,
+    /// Position of each index within `sorted`.
+    rank: Vec<usize>,
+    /// The last non-empty query and the indices and scores it matched.
+    query: String,
+    matched: Vec<(usize, i64)>,
+}
+
+impl ContactFilter {
+    pub fn new(contacts: &[Contact], sort: Sort) -> Self {
+        let mut filter = Self {
+            matcher: SkimMatcherV2::default(),
+            haystacks: Vec::new(),
+            favorite: Vec::new(),
+            frecency: Vec::new(),
+            sort,
+            sorted: Vec::new(),
+            rank: Vec::new(),
+            query: String::new(),
+            matched: Vec::new(),
+        };
+        filter.set_contacts(contacts);
+        filter
+    }
+
+    pub fn sort(&self) -> Sort {
+        self.sort
+    }
+
+    /// Rebuild the search text and sort order for a new contact list.
+    pub fn set_contacts(&mut self, contacts: &[Contact]) {
+        self.haystacks = contacts
+            .iter()
+            .map(|c| {
+                format!(
+                    "{} {} {} {}",
+                    c.name,
+                    c.company.as_deref().unwrap_or(""),
+                    c.email.as_deref().unwrap_or(""),
+                    c.phone.as_deref().unwrap_or("")
+                )
+            })
+            .collect();
+        let now = SystemTime::now();
+        self.favorite = contacts.iter().map(|c| c.favorite).collect();
+        self.frecency = contacts.iter().map(|c| c.frecency(now)).collect();
+        self.query.clear();
+        self.matched.clear();
+        self.set_sort(contacts, self.sort);
+    }
+
+    pub fn set_sort(&mut self, contacts: &[Contact], sort: Sort) {
+        self.sort = sort;
+        let mut sorted: Vec<usize> = (0..contacts.len()).collect();
+        // Stable, so ties keep the order the contacts were loaded in
+        sorted.sort_by(|&a, &b| {
+            let by_key = if sort.key == SortKey::Relevance {
+                sort.order
+                    .apply(self.frecency[b].total_cmp(&self.frecency[a]))
+            } else {
+                Ordering::Equal
+            };
+            self.pinned(a, b)
+                .then(by_key)
+                .then_with(|| sort.compare(&contacts[a], &contacts[b]))
+        });
+        self.sorted = sorted;
+        self.rank = vec![0; contacts.len()];
+        for (position, &index) in self.sorted.iter().enumerate() {
+            self.rank[index] = position;
+        }
+    }
+
+    /// Indices of the contacts matching `query`, in display order.
+    pub fn apply(&mut self, query: &str) -> Vec<usize> {
+        let query = query.trim();
+        if query.is_empty() {
+            self.query.clear();
+            self.matched.clear();
+            return self.sorted.clone();
+        }
+
+        let matched: Vec<(usize, i64)> = if !self.query.is_empty() && query.starts_with(&self.query)
+        {
+            self.matched
+                .iter()
+                .filter_map(|&(index, _)| self.score(index, query))
+                .collect()
+        } else {
+            (0..self.haystacks.len())
+                .filter_map(|index| self.score(index, query))
+                .collect()
+        };
+        self.query = query.to_string();
+        self.matched = matched;
+
+        let mut ordered = self.matched.clone();
+        if self.sort.key == SortKey::Relevance {
+            if query.chars().count() <= SHORT_QUERY {
+                for (index, score) in &mut ordered {
+                    *score += (self.frecency[*index].ln_1p() * FRECENCY_BOOST) as i64;
+                }
+            }
+            // Best match first for ascending, worst first for descending
+            let order = self.sort.order;
+            ordered.sort_by(|a, b| {
+                self.pinned(a.0, b.0)
+                    .then(order.apply(b.1.cmp(&a.1)))
+                    .then(a.0.cmp(&b.0))
+            });
+        } else {
+            ordered.sort_unstable_by_key(|&(index, _)| self.rank[index]);
+        }
+        ordered.into_iter().map(|(index, _)| index).collect()
+    }
+
+    /// Favorites before everything else.
+    fn pinned(&self, a: usize, b: usize) -> Ordering {
+        self.favorite[b].cmp(&self.favorite[a])
+    }
+
+    fn score(&self, index: usize, query: &str) -> Option<(usize, i64)> {
+        self.matcher
+            .fuzzy_match(&self.haystacks[index], query)
+            .map(|score| (index, score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::SortOrder;
+
+    fn contact(name: &str, company: &str) -> Contact {
+        Contact {
+            name: name.to_string(),
+            company: Some(company.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn names(contacts: &[Contact], indices: &[usize]) -> Vec<String> {
+        indices.iter().map(|&i| contacts[i].name.clone()).collect()
+    }
+
+    #[test]
+    fn test_empty_query_uses_sort_order() {
+        let contacts = vec![
+            contact("Carol", "Acme"),
+            contact("alice", "Zeta"),
+            contact("Bob", "Beta"),
+        ];
+        let mut filter = ContactFilter::new(&contacts, Sort::default());
+        assert_eq!(names(&contacts, &filter.apply("")), ["alice", "Bob", "Carol"]);
+
+        filter.set_sort(&contacts, Sort::new(SortKey::Company, SortOrder::Desc));
+        assert_eq!(names(&contacts, &filter.apply("  ")), ["alice", "Bob", "Carol"]);
+    }
+
+    #[test]
+    fn test_incremental_query_matches_full_search() {
+        let contacts: Vec<Contact> = (0..200)
+            .map(|i| contact(&format!("Person {i}"), &format!("Company {}", i % 7)))
+            .collect();
+        let mut incremental = ContactFilter::new(&contacts, Sort::default());
+
+        let query = "person 1 company 3";
+        for end in 1..=query.len() {
+            let typed = &query[..end];
+            let mut fresh = ContactFilter::new(&contacts, Sort::default());
+            assert_eq!(incremental.apply(typed), fresh.apply(typed), "query {typed:?}");
+        }
+
+        // Deleting characters falls back to a full search
+        let mut fresh = ContactFilter::new(&contacts, Sort::default());
+        assert_eq!(incremental.apply("person 2"), fresh.apply("person 2"));
+    }
+
+    #[test]
+    fn test_favorites_are_pinned_first() {
+        let mut contacts = vec![
+            contact("Alice", "Acme"),
+            contact("Bob", "Acme"),
+            contact("Carol", "Acme"),
+        ];
+        contacts[2].favorite = true;
+
+        let mut filter = ContactFilter::new(&contacts, Sort::default());
+        assert_eq!(names(&contacts, &filter.apply("")), ["Carol", "Alice", "Bob"]);
+        assert_eq!(names(&contacts, &filter.apply("acme")), ["Carol", "Alice", "Bob"]);
+
+        filter.set_sort(&contacts, Sort::new(SortKey::Name, SortOrder::Desc));
+        assert_eq!(names(&contacts, &filter.apply("")), ["Carol", "Bob", "Alice"]);
+    }
+
+    #[test]
+    fn test_frecency_ranks_empty_and_short_queries() {
+        let mut contacts = vec![
+            contact("Alice", "Acme"),
+            contact("Bob", "Acme"),
+            contact("Albert", "Acme"),
+        ];
+        // A future timestamp counts as used just now
+        let recent = "2999-01-01 00:00:00".to_string();
+        contacts[2].use_count = 20;
+        contacts[2].last_used_at = Some(recent.clone());
+        contacts[1].use_count = 1;
+        contacts[1].last_used_at = Some(recent);
+
+        let mut filter = ContactFilter::new(&contacts, Sort::default());
+        assert_eq!(names(&contacts, &filter.apply("")), ["Albert", "Bob", "Alice"]);
+        // A short query lets frecency outweigh a slightly better match
+        assert_eq!(names(&contacts, &filter.apply("al")), ["Albert", "Alice"]);
+
+        // Other sort keys ignore frecency
+        filter.set_sort(&contacts, Sort::new(SortKey::Name, SortOrder::Asc));
+        assert_eq!(names(&contacts, &filter.apply("")), ["Albert", "Alice", "Bob"]);
+    }
+
+    #[test]
+    fn test_query_with_sort_key_keeps_sorted_order() {
+        let contacts = vec![
+            contact("Anna Smith", "Zeta"),
+            contact("Smithers", "Acme"),
+            contact("Bob", "Beta"),
+        ];
+        let sort = Sort::new(SortKey::Company, SortOrder::Asc);
+        let mut filter = ContactFilter::new(&contacts, sort);
+        assert_eq!(
+            names(&contacts, &filter.apply("smith")),
+            ["Smithers", "Anna Smith"]
+        );
+    }
+}
@@ -1,2 +1,6 @@
 pub mod contact;
+pub mod filter;
+pub mod sort;
 pub use contact::Contact;
+pub use filter::ContactFilter;
+pub use sort::{Sort, SortKey, SortOrder};
@@ -0,0 +1,216 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use clap::ValueEnum;
+
+use crate::model::Contact;
+
+/// Column the contact list is ordered by.
+///
+/// `Relevance` keeps fuzzy-match ranking while searching, boosted by
+/// frecency for short queries, and orders by frecency then name when the
+/// query is empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SortKey {
+    #[default]
+    Relevance,
+    Name,
+    Company,
+    Email,
+    Created,
+    Modified,
+    LastUsed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sort {
+    pub key: SortKey,
+    pub order: SortOrder,
+}
+
+const KEY_CYCLE: [SortKey; 7] = [
+    SortKey::Relevance,
+    SortKey::Name,
+    SortKey::Company,
+    SortKey::Email,
+    SortKey::Created,
+    SortKey::Modified,
+    SortKey::LastUsed,
+];
+
+impl SortKey {
+    pub fn next(self) -> Self {
+        let pos = KEY_CYCLE.iter().position(|k| *k == self).unwrap_or(0);
+        KEY_CYCLE[(pos + 1) % KEY_CYCLE.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Relevance => "relevance",
+            SortKey::Name => "name",
+            SortKey::Company => "company",
+            SortKey::Email => "email",
+            SortKey::Created => "created",
+            SortKey::Modified => "modified",
+            SortKey::LastUsed => "last used",
+        }
+    }
+
+    /// SQL expression used by `ORDER BY` for this key.
+    pub fn column(self) -> &'static str {
+        match self {
+            SortKey::Relevance | SortKey::Name => "name COLLATE NOCASE",
+            SortKey::Company => "company COLLATE NOCASE",
+            SortKey::Email => "email COLLATE NOCASE",
+            SortKey::Created => "created_at",
+            SortKey::Modified => "updated_at",
+            SortKey::LastUsed => "last_used_at",
+        }
+    }
+
+    /// The value compared for this key, case-folded the way `COLLATE NOCASE`
+    /// folds it (ASCII only), so the list and `ORDER BY` agree.
+    fn value(self, contact: &Contact) -> Option<String> {
+        match self {
+            SortKey::Relevance | SortKey::Name => Some(contact.name.to_ascii_lowercase()),
+            SortKey::Company => contact.company.as_ref().map(|c| c.to_ascii_lowercase()),
+            SortKey::Email => contact.email.as_ref().map(|e| e.to_ascii_lowercase()),
+            SortKey::Created => contact.created_at.clone(),
+            SortKey::Modified => contact.updated_at.clone(),
+            SortKey::LastUsed => contact.last_used_at.clone(),
+        }
+    }
+}
+
+impl SortOrder {
+    pub fn toggle(self) -> Self {
+        match self {
+            SortOrder::Asc => SortOrder::Desc,
+            SortOrder::Desc => SortOrder::Asc,
+        }
+    }
+
+    pub fn arrow(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "▲",
+            SortOrder::Desc => "▼",
+        }
+    }
+
+    pub fn sql(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+
+    /// Apply this order to an ascending comparison.
+    pub fn apply(self, ordering: Ordering) -> Ordering {
+        match self {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+}
+
+impl Sort {
+    pub fn new(key: SortKey, order: SortOrder) -> Self {
+        Self { key, order }
+    }
+
+    /// `ORDER BY` clause matching [`Sort::compare`]: missing values last, then name.
+    pub fn order_by(&self) -> String {
+        let column = self.key.column();
+        format!(
+            "{column} IS NULL, {column} {}, name COLLATE NOCASE ASC, id ASC",
+            self.order.sql()
+        )
+    }
+
+    /// Compare two contacts by the sort key. Contacts without a value for the
+    /// key always sort last; ties fall back to name ascending.
+    pub fn compare(&self, a: &Contact, b: &Contact) -> Ordering {
+        let by_key = match (self.key.value(a), self.key.value(b)) {
+            (Some(x), Some(y)) => self.order.apply(x.cmp(&y)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        by_key
+            .then_with(|| a.name.to_ascii_lowercase().cmp(&b.name.to_ascii_lowercase()))
+            .then_with(|| a.id.cmp(&b.id))
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let key = self.key.to_possible_value().expect("no skipped variants");
+        let order = self.order.to_possible_value().expect("no skipped variants");
+        write!(f, "{}:{}", key.get_name(), order.get_name())
+    }
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, order) = s.split_once(':').unwrap_or((s, "asc"));
+        Ok(Self {
+            key: SortKey::from_str(key, true)?,
+            order: SortOrder::from_str(order, true)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact(name: &str, company: Option<&str>) -> Contact {
+        Contact {
+            name: name.to_string(),
+            company: company.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sort_round_trip() {
+        let sort = Sort::new(SortKey::LastUsed, SortOrder::Desc);
+        assert_eq!(sort.to_string(), "last-used:desc");
+        assert_eq!("last-used:desc".parse::<Sort>().unwrap(), sort);
+        assert_eq!(
+            "company".parse::<Sort>().unwrap(),
+            Sort::new(SortKey::Company, SortOrder::Asc)
+        );
+        assert!("bogus:asc".parse::<Sort>().is_err());
+    }
+
+    #[test]
+    fn test_key_cycle_wraps() {
+        assert_eq!(SortKey::Relevance.next(), SortKey::Name);
+        assert_eq!(SortKey::LastUsed.next(), SortKey::Relevance);
+    }
+
+    #[test]
+    fn test_compare_puts_missing_values_last() {
+        let a = contact("Alice", None);
+        let b = contact("Bob", Some("Zed Corp"));
+        let c = contact("Carol", Some("Acme"));
+
+        let mut contacts = vec![a.clone(), b.clone(), c.clone()];
+        let asc = Sort::new(SortKey::Company, SortOrder::Asc);
+        contacts.sort_by(|x, y| asc.compare(x, y));
+        assert_eq!(contacts, vec![c.clone(), b.clone(), a.clone()]);
+
+        let desc = Sort::new(SortKey::Company, SortOrder::Desc);
+        contacts.sort_by(|x, y| desc.compare(x, y));
+        assert_eq!(contacts, vec![b, c, a]);
+    }
+}
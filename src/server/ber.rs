@@ -0,0 +1,173 @@
+//! The subset of ASN.1 BER that LDAP messages use: definite lengths,
+//! single-byte tags, integers, strings and constructed values.
+
+use std::io::{self, Read};
+
+/// Messages with a longer value than this are refused.
+pub const MAX_LENGTH: usize = 64 * 1024;
+
+pub const BOOLEAN: u8 = 0x01;
+pub const INTEGER: u8 = 0x02;
+pub const OCTET_STRING: u8 = 0x04;
+pub const ENUMERATED: u8 = 0x0a;
+pub const SEQUENCE: u8 = 0x30;
+pub const SET: u8 = 0x31;
+
+/// Set on tags whose value holds further elements.
+const CONSTRUCTED: u8 = 0x20;
+
+/// One tag-length-value element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlv {
+    pub tag: u8,
+    pub value: Vec<u8>,
+}
+
+impl Tlv {
+    pub fn new(tag: u8, value: impl Into<Vec<u8>>) -> Self {
+        Self {
+            tag,
+            value: value.into(),
+        }
+    }
+
+    pub fn integer(tag: u8, n: i64) -> Self {
+        let bytes = n.to_be_bytes();
+        // Drop leading bytes that only repeat the sign
+        let mut start = 0;
+        while start < 7
+            && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+                || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+        {
+            start += 1;
+        }
+        Self::new(tag, &bytes[start..])
+    }
+
+    pub fn boolean(b: bool) -> Self {
+        Self::new(BOOLEAN, [if b { 0xff } else { 0x00 }])
+    }
+
+    pub fn string(tag: u8, s: &str) -> Self {
+        Self::new(tag, s.as_bytes())
+    }
+
+    pub fn constructed(tag: u8, children: impl IntoIterator<Item = Tlv>) -> Self {
+        let value = children.into_iter().flat_map(|child| child.encode()).collect::<Vec<_>>();
+        Self::new(tag | CONSTRUCTED, value)
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let len = self.value.len();
+        let mut out = vec![self.tag];
+        if len < 0x80 {
+            out.push(len as u8);
+        } else {
+            let bytes = (len as u32).to_be_bytes();
+            let skip = bytes.iter().take_while(|b| **b == 0).count();
+            out.push(0x80 | (4 - skip) as u8);
+            out.extend_from_slice(&bytes[skip..]);
+        }
+        out.extend_from_slice(&self.value);
+        out
+    }
+
+    /// The elements inside a constructed value.
+    pub fn children(&self) -> io::Result<Vec<Tlv>> {
+        let mut rest = self.value.as_slice();
+        let mut children = Vec::new();
+        while !rest.is_empty() {
+            let child = read(&mut rest)?.ok_or_else(|| invalid("truncated element"))?;
+            children.push(child);
+        }
+        Ok(children)
+    }
+
+    pub fn as_integer(&self) -> io::Result<i64> {
+        if self.value.is_empty() || self.value.len() > 8 {
+            return Err(invalid("integer of the wrong size"));
+        }
+        let sign = if self.value[0] & 0x80 != 0 { -1 } else { 0 };
+        Ok(self.value.iter().fold(sign, |n, b| (n << 8) | i64::from(*b)))
+    }
+
+    pub fn as_bool(&self) -> bool {
+        self.value.iter().any(|b| *b != 0)
+    }
+
+    pub fn as_str(&self) -> io::Result<&str> {
+        std::str::from_utf8(&self.value).map_err(|_| invalid("string is not UTF-8"))
+    }
+}
+
+/// Read one element, or `None` at the end of the input.
+pub fn read(mut input: impl Read) -> io::Result<Option<Tlv>> {
+    let mut tag = [0];
+    if input.read(&mut tag)? == 0 {
+        return Ok(None);
+    }
+    if tag[0] & 0x1f == 0x1f {
+        return Err(invalid("multi-byte tags are not supported"));
+    }
+    let mut first = [0];
+    input.read_exact(&mut first)?;
+    let len = match first[0] {
+        short @ 0..0x80 => usize::from(short),
+        0x80 => return Err(invalid("indefinite lengths are not supported")),
+        long => {
+            let count = usize::from(long & 0x7f);
+            if count > 4 {
+                return Err(invalid("length too long"));
+            }
+            let mut bytes = [0; 4];
+            input.read_exact(&mut bytes[4 - count..])?;
+            u32::from_be_bytes(bytes) as usize
+        }
+    };
+    if len > MAX_LENGTH {
+        return Err(invalid("message too large"));
+    }
+    let mut value = vec![0; len];
+    input.read_exact(&mut value)?;
+    Ok(Some(Tlv::new(tag[0], value)))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for n in [0, 1, 127, 128, 255, 256, -1, -128, -129, i64::MAX, i64::MIN] {
+            let tlv = Tlv::integer(INTEGER, n);
+            let read = read(tlv.encode().as_slice()).unwrap().unwrap();
+            assert_eq!(read.as_integer().unwrap(), n, "{n}");
+        }
+        assert_eq!(Tlv::integer(INTEGER, 128).encode(), [0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(Tlv::integer(INTEGER, -128).encode(), [0x02, 0x01, 0x80]);
+
+        let long = "x".repeat(300);
+        let message = Tlv::constructed(
+            SEQUENCE,
+            [Tlv::string(OCTET_STRING, &long), Tlv::boolean(true)],
+        );
+        let encoded = message.encode();
+        assert_eq!(&encoded[..4], [0x30, 0x82, 0x01, 0x33]);
+        let read = read(encoded.as_slice()).unwrap().unwrap();
+        let children = read.children().unwrap();
+        assert_eq!(children[0].as_str().unwrap(), long);
+        assert!(children[1].as_bool());
+    }
+
+    #[test]
+    fn test_refuses_bad_input() {
+        assert!(read([0x30, 0x80].as_slice()).is_err());
+        assert!(read([0x30, 0x84, 0xff, 0xff, 0xff, 0xff].as_slice()).is_err());
+        assert!(read([0x30, 0x05, 0x01].as_slice()).is_err());
+        assert!(read([].as_slice()).unwrap().is_none());
+    }
+}
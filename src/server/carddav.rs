@@ -0,0 +1,639 @@
+//! `rolodex carddav`: the address book over the part of WebDAV (RFC 4918)
+//! and CardDAV (RFC 6352) that phones and mail clients use to sync.
+//!
+//! | Path | |
+//! |---|---|
+//! | `/.well-known/carddav` | Redirects to `/` |
+//! | `/` | Points clients at the principal |
+//! | `/principals/rolodex/` | The only user, with the address book home |
+//! | `/addressbooks/` | The home, holding one address book |
+//! | `/addressbooks/default/` | The address book: `PROPFIND` and `REPORT` |
+//! | `/addressbooks/default/{uid}.vcf` | One card: `GET`, `PUT`, `DELETE` |
+//!
+//! Cards hold only what a contact has, so other vCard properties a client
+//! sends are dropped. A card's ETag changes with its `updated_at` column and
+//! its contents.
+
+use std::{
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+
+use super::{
+    PendingHook,
+    http::{Handler, Request, Response, percent_decode, percent_encode, reason},
+    same_secret, status,
+};
+use crate::{
+    Db,
+    error::{AppError, AppResult as Result},
+    hooks::Event,
+    mail::decode_base64,
+    model::Contact,
+    vcard::{from_vcard, to_vcard},
+    xml::{self, Element, escape},
+};
+
+const DAV: &str = "DAV:";
+const CARDDAV: &str = "urn:ietf:params:xml:ns:carddav";
+/// Apple's namespace, for the `getctag` most clients poll before syncing.
+const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+const PRINCIPAL: &str = "/principals/rolodex/";
+const HOME: &str = "/addressbooks/";
+const BOOK: &str = "/addressbooks/default/";
+
+const VCARD_TYPE: &str = "text/vcard; charset=utf-8";
+const TEXT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Returned for `allprop` and for requests without a body.
+const ALL_PROPS: [(&str, &str); 5] = [
+    (DAV, "resourcetype"),
+    (DAV, "displayname"),
+    (DAV, "getetag"),
+    (DAV, "getcontenttype"),
+    (CALENDARSERVER, "getctag"),
+];
+
+pub struct CardDav {
+    db: Mutex<Db>,
+    password: Option<String>,
+}
+
+impl CardDav {
+    /// With a `password`, every request needs HTTP Basic auth with that
+    /// password. Any user name is accepted.
+    pub fn new(db: Db, password: Option<String>) -> Self {
+        Self {
+            db: Mutex::new(db),
+            password,
+        }
+    }
+
+    /// Accept connections until the listener fails, one thread each.
+    pub fn serve_tcp(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        Ok(super::http::serve_tcp(self, listener)?)
+    }
+
+    /// Answer `request`, leaving any lifecycle hook it triggers in `hook` to
+    /// run once the database lock is released.
+    fn route(&self, request: &Request, hook: &mut Option<PendingHook>) -> Result<Response> {
+        let segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+        let db = self
+            .db
+            .lock()
+            .map_err(|_| AppError::Database("database lock poisoned".to_string()))?;
+
+        match (request.method.as_str(), segments.as_slice()) {
+            (_, [".well-known", "carddav"]) => Ok(Response {
+                status: 301,
+                ..Response::no_content()
+            }
+            .with_header("Location", "/")),
+            ("PROPFIND", path) => {
+                let resource = find(&db, path)?;
+                let root = match body(request) {
+                    Ok(root) => root,
+                    Err(response) => return Ok(response),
+                };
+                let props = Props::requested(root.as_ref());
+                let mut resources = vec![resource];
+                // Depth infinity is answered as 1, which covers the whole tree
+                // below the address book
+                if request.header("depth") != Some("0") {
+                    resources.extend(children(&db, &resources[0])?);
+                }
+                Ok(multistatus(resources.iter().map(|r| r.response(&props))))
+            }
+            ("REPORT", ["addressbooks", "default"]) => report(&db, request),
+            ("GET", ["addressbooks", "default", file]) => {
+                let card = lookup(&db, file)?.ok_or_else(|| missing(file))?;
+                Ok(Response::new(200, VCARD_TYPE, card.vcard).with_header("ETag", card.etag))
+            }
+            ("PUT", ["addressbooks", "default", file]) => put(&db, request, file, hook),
+            ("DELETE", ["addressbooks", "default", file]) => {
+                let card = lookup(&db, file)?.ok_or_else(|| missing(file))?;
+                if !preconditions_hold(request, Some(&card)) {
+                    return Ok(plain(412, "the card has changed"));
+                }
+                db.delete_contact(card.contact.id)?;
+                *hook = PendingHook::new(&*db, Event::Delete, &card.contact);
+                Ok(Response::no_content())
+            }
+            (_, ["addressbooks", "default", _]) => {
+                Ok(not_allowed("OPTIONS, GET, PUT, DELETE, PROPFIND"))
+            }
+            (_, ["addressbooks", "default"]) => Ok(not_allowed("OPTIONS, PROPFIND, REPORT")),
+            (_, [] | ["principals", "rolodex"] | ["addressbooks"]) => {
+                Ok(not_allowed("OPTIONS, PROPFIND"))
+            }
+            _ => Err(AppError::NotFound(format!("path {}", request.path))),
+        }
+    }
+}
+
+impl Handler for CardDav {
+    fn handle(&self, request: &Request) -> Response {
+        if request.method == "OPTIONS" {
+            return Response {
+                status: 200,
+                ..Response::no_content()
+            }
+            .with_header("DAV", "1, 3, addressbook")
+            .with_header("Allow", "OPTIONS, GET, PUT, DELETE, PROPFIND, REPORT");
+        }
+        if let Some(password) = &self.password
+            && !authorized(request, password)
+        {
+            return plain(401, "missing or wrong password")
+                .with_header("WWW-Authenticate", "Basic realm=\"rolodex\"");
+        }
+        let mut hook = None;
+        let response = self.route(request, &mut hook).unwrap_or_else(|err| {
+            let message = match &err {
+                AppError::NotFound(what) => format!("{what} not found"),
+                AppError::Input(reason) => reason.clone(),
+                err => err.to_string(),
+            };
+            plain(status(&err), &message)
+        });
+        if let Some(hook) = hook {
+            hook.fire();
+        }
+        response
+    }
+}
+
+enum Resource {
+    Root,
+    Principal,
+    Home,
+    Book { ctag: String },
+    Card(Box<Card>),
+}
+
+impl Resource {
+    fn href(&self) -> String {
+        match self {
+            Resource::Root => "/".to_string(),
+            Resource::Principal => PRINCIPAL.to_string(),
+            Resource::Home => HOME.to_string(),
+            Resource::Book { .. } => BOOK.to_string(),
+            Resource::Card(card) => card.href(),
+        }
+    }
+
+    /// The XML inside a property element, or `None` if the resource doesn't
+    /// have it.
+    fn prop(&self, ns: &str, name: &str) -> Option<String> {
+        let href = |path: &str| format!("<d:href>{path}</d:href>");
+        let value = match (ns, name, self) {
+            (DAV, "resourcetype", Resource::Card(_)) => String::new(),
+            (DAV, "resourcetype", Resource::Principal) => "<d:principal/>".to_string(),
+            (DAV, "resourcetype", Resource::Book { .. }) => {
+                "<d:collection/><card:addressbook/>".to_string()
+            }
+            (DAV, "resourcetype", _) => "<d:collection/>".to_string(),
+            (DAV, "displayname", Resource::Principal) => "rolodex".to_string(),
+            (DAV, "displayname", Resource::Book { .. }) => "Rolodex".to_string(),
+            (DAV, "current-user-principal", _) => href(PRINCIPAL),
+            (DAV, "principal-URL", Resource::Principal) => href(PRINCIPAL),
+            (CARDDAV, "addressbook-home-set", Resource::Root | Resource::Principal) => href(HOME),
+            (DAV, "supported-report-set", Resource::Book { .. }) => {
+                ["addressbook-query", "addressbook-multiget"]
+                    .map(|report| {
+                        format!("<d:supported-report><d:report><card:{report}/></d:report></d:supported-report>")
+                    })
+                    .concat()
+            }
+            (CARDDAV, "supported-address-data", Resource::Book { .. }) => {
+                r#"<card:address-data-type content-type="text/vcard" version="3.0"/>"#.to_string()
+            }
+            (CALENDARSERVER, "getctag", Resource::Book { ctag }) => escape(ctag),
+            (DAV, "getetag", Resource::Card(card)) => escape(&card.etag),
+            (DAV, "getcontenttype", Resource::Card(_)) => VCARD_TYPE.to_string(),
+            (CARDDAV, "address-data", Resource::Card(card)) => escape(&card.vcard),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// One `<d:response>` with the requested properties, those the resource
+    /// lacks listed as not found.
+    fn response(&self, props: &Props) -> String {
+        let (mut found, mut missing) = (String::new(), String::new());
+        for (ns, name) in &props.names {
+            match self.prop(ns, name) {
+                Some(value) => found.push_str(&prop_element(ns, name, &value)),
+                None if !props.all => missing.push_str(&prop_element(ns, name, "")),
+                None => {}
+            }
+        }
+        let mut out = format!("<d:response><d:href>{}</d:href>", escape(&self.href()));
+        if !found.is_empty() || missing.is_empty() {
+            out.push_str(&propstat(&found, 200));
+        }
+        if !missing.is_empty() {
+            out.push_str(&propstat(&missing, 404));
+        }
+        out + "</d:response>"
+    }
+}
+
+/// A contact as a stored card.
+struct Card {
+    contact: Contact,
+    /// Resource name without `.vcf`: the UID a client gave it, or one made
+    /// up from the id.
+    name: String,
+    vcard: String,
+    etag: String,
+}
+
+impl Card {
+    fn new(mut contact: Contact) -> Self {
+        let name = contact
+            .uid
+            .clone()
+            .unwrap_or_else(|| format!("rolodex-{}", contact.id));
+        contact.uid = Some(name.clone());
+        let vcard = to_vcard(&contact);
+        let updated_at = contact.updated_at.as_deref().unwrap_or_default();
+        let etag = format!("\"{:016x}\"", fnv1a(&[updated_at, &vcard]));
+        Self {
+            contact,
+            name,
+            vcard,
+            etag,
+        }
+    }
+
+    fn href(&self) -> String {
+        format!("{BOOK}{}.vcf", percent_encode(&self.name))
+    }
+}
+
+/// Properties asked for by a PROPFIND or REPORT.
+struct Props {
+    names: Vec<(String, String)>,
+    /// `allprop`: leave out the ones a resource doesn't have.
+    all: bool,
+}
+
+impl Props {
+    fn requested(root: Option<&Element>) -> Self {
+        match root.and_then(|root| root.child(DAV, "prop")) {
+            Some(prop) => Self {
+                names: prop
+                    .children
+                    .iter()
+                    .map(|child| (child.ns.clone(), child.name.clone()))
+                    .collect(),
+                all: false,
+            },
+            None => Self {
+                names: ALL_PROPS
+                    .iter()
+                    .map(|(ns, name)| (ns.to_string(), name.to_string()))
+                    .collect(),
+                all: true,
+            },
+        }
+    }
+}
+
+fn find(db: &Db, segments: &[&str]) -> Result<Resource> {
+    match segments {
+        [] => Ok(Resource::Root),
+        ["principals", "rolodex"] => Ok(Resource::Principal),
+        ["addressbooks"] => Ok(Resource::Home),
+        ["addressbooks", "default"] => Ok(Resource::Book {
+            ctag: ctag(&cards(db)?),
+        }),
+        ["addressbooks", "default", file] => {
+            Ok(Resource::Card(Box::new(lookup(db, file)?.ok_or_else(|| missing(file))?)))
+        }
+        _ => Err(AppError::NotFound(format!("path /{}", segments.join("/")))),
+    }
+}
+
+fn children(db: &Db, resource: &Resource) -> Result<Vec<Resource>> {
+    Ok(match resource {
+        Resource::Root => vec![Resource::Principal, Resource::Home],
+        Resource::Home => vec![find(db, &["addressbooks", "default"])?],
+        Resource::Book { .. } => cards(db)?.into_iter().map(|card| Resource::Card(Box::new(card))).collect(),
+        Resource::Principal | Resource::Card(_) => Vec::new(),
+    })
+}
+
+fn cards(db: &Db) -> Result<Vec<Card>> {
+    Ok(db.load_customers()?.into_iter().map(Card::new).collect())
+}
+
+/// Changes whenever any card is added, changed or deleted.
+fn ctag(cards: &[Card]) -> String {
+    let etags: Vec<&str> = cards.iter().map(|card| card.etag.as_str()).collect();
+    format!("{:016x}", fnv1a(&etags))
+}
+
+/// The card stored as `file`: a contact a client saved under that UID, or
+/// `rolodex-{id}.vcf` for one added elsewhere.
+fn lookup(db: &Db, file: &str) -> Result<Option<Card>> {
+    let Some(name) = file.strip_suffix(".vcf") else {
+        return Ok(None);
+    };
+    if let Some(contact) = db.find_by_uid(name)? {
+        return Ok(Some(Card::new(contact)));
+    }
+    let Some(id) = name.strip_prefix("rolodex-").and_then(|id| id.parse().ok()) else {
+        return Ok(None);
+    };
+    match db.get_contact(id) {
+        Ok(contact) if contact.uid.is_none() => Ok(Some(Card::new(contact))),
+        Ok(_) | Err(AppError::NotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn missing(file: &str) -> AppError {
+    AppError::NotFound(format!("card {file}"))
+}
+
+fn put(
+    db: &Db,
+    request: &Request,
+    file: &str,
+    hook: &mut Option<PendingHook>,
+) -> Result<Response> {
+    let Some(name) = file.strip_suffix(".vcf") else {
+        return Ok(plain(403, "card names must end in .vcf"));
+    };
+    let existing = lookup(db, file)?;
+    if !preconditions_hold(request, existing.as_ref()) {
+        return Ok(plain(412, "the card has changed"));
+    }
+    let parsed = std::str::from_utf8(&request.body)
+        .map_err(|_| AppError::Input("vCard is not UTF-8".to_string()))
+        .and_then(from_vcard)
+        .and_then(|contact| {
+            contact
+                .validate()
+                .map_err(|e| AppError::Input(e.to_string()))?;
+            Ok(contact)
+        });
+    let mut contact = match parsed {
+        Ok(contact) => contact,
+        Err(AppError::Input(reason)) => return Ok(invalid_card(&reason)),
+        Err(err) => return Err(err),
+    };
+
+    let (status, event, id) = match existing {
+        Some(card) => {
+            contact.version = card.contact.version;
+            db.update_contact(card.contact.id, contact)?;
+            (204, Event::Update, card.contact.id)
+        }
+        None => {
+            contact.uid = Some(name.to_string());
+            (201, Event::Add, db.add_contact(contact)?)
+        }
+    };
+    let card = Card::new(db.get_contact(id)?);
+    *hook = PendingHook::new(db, event, &card.contact);
+    Ok(Response {
+        status,
+        ..Response::no_content()
+    }
+    .with_header("ETag", card.etag))
+}
+
+fn report(db: &Db, request: &Request) -> Result<Response> {
+    let root = match body(request) {
+        Ok(Some(root)) => root,
+        Ok(None) => return Ok(plain(400, "REPORT needs a body")),
+        Err(response) => return Ok(response),
+    };
+    let props = Props::requested(Some(&root));
+    let mut responses = Vec::new();
+
+    if root.is(CARDDAV, "addressbook-multiget") {
+        for href in root.children_named(DAV, "href") {
+            let href = href.text.trim();
+            let path = percent_decode(href, false);
+            // Some clients send full URLs
+            let path = match path.split_once("://") {
+                Some((_, rest)) => rest.find('/').map_or("/", |at| &rest[at..]),
+                None => &path,
+            };
+            let card = match path.strip_prefix(BOOK) {
+                Some(file) => lookup(db, file)?,
+                None => None,
+            };
+            responses.push(match card {
+                Some(card) => Resource::Card(Box::new(card)).response(&props),
+                None => format!(
+                    "<d:response><d:href>{}</d:href><d:status>{}</d:status></d:response>",
+                    escape(href),
+                    status_line(404)
+                ),
+            });
+        }
+    } else if root.is(CARDDAV, "addressbook-query") {
+        let filter = root.child(CARDDAV, "filter");
+        let limit = root
+            .child(CARDDAV, "limit")
+            .and_then(|limit| limit.child(CARDDAV, "nresults"))
+            .and_then(|n| n.text.trim().parse().ok())
+            .unwrap_or(usize::MAX);
+        for card in cards(db)?
+            .into_iter()
+            .filter(|card| filter.is_none_or(|filter| filter_matches(filter, card)))
+            .take(limit)
+        {
+            responses.push(Resource::Card(Box::new(card)).response(&props));
+        }
+    } else {
+        return Ok(Response::new(403, "application/xml; charset=utf-8", dav_error("d:supported-report")));
+    }
+    Ok(multistatus(responses))
+}
+
+/// Whether a card passes an `addressbook-query` filter. Property filters
+/// work on the properties a contact has, with `text-match` and
+/// `is-not-defined`.
+fn filter_matches(filter: &Element, card: &Card) -> bool {
+    let mut results = filter
+        .children_named(CARDDAV, "prop-filter")
+        .map(|prop_filter| prop_filter_matches(prop_filter, card))
+        .peekable();
+    if results.peek().is_none() {
+        return true;
+    }
+    match filter.attribute("test") {
+        Some("allof") => results.all(|matched| matched),
+        _ => results.any(|matched| matched),
+    }
+}
+
+fn prop_filter_matches(prop_filter: &Element, card: &Card) -> bool {
+    let contact = &card.contact;
+    let name = prop_filter.attribute("name").unwrap_or_default();
+    let value = match name.to_ascii_uppercase().as_str() {
+        "FN" | "N" => Some(contact.name.as_str()),
+        "EMAIL" => contact.email.as_deref(),
+        "TEL" => contact.phone.as_deref(),
+        "ORG" => contact.company.as_deref(),
+        "UID" => Some(card.name.as_str()),
+        _ => None,
+    };
+    let value = value.filter(|value| !value.trim().is_empty());
+    if prop_filter.child(CARDDAV, "is-not-defined").is_some() {
+        return value.is_none();
+    }
+    let Some(value) = value else {
+        return false;
+    };
+    let mut results = prop_filter
+        .children_named(CARDDAV, "text-match")
+        .map(|text_match| text_matches(text_match, value))
+        .peekable();
+    if results.peek().is_none() {
+        return true;
+    }
+    match prop_filter.attribute("test") {
+        Some("allof") => results.all(|matched| matched),
+        _ => results.any(|matched| matched),
+    }
+}
+
+fn text_matches(text_match: &Element, value: &str) -> bool {
+    let (needle, value) = match text_match.attribute("collation") {
+        Some("i;octet") => (text_match.text.trim().to_string(), value.to_string()),
+        _ => (text_match.text.trim().to_lowercase(), value.to_lowercase()),
+    };
+    let found = match text_match.attribute("match-type") {
+        Some("equals") => value == needle,
+        Some("starts-with") => value.starts_with(&needle),
+        Some("ends-with") => value.ends_with(&needle),
+        _ => value.contains(&needle),
+    };
+    found != (text_match.attribute("negate-condition") == Some("yes"))
+}
+
+/// `If-Match` and `If-None-Match` against the ETag of the card as stored,
+/// if there is one.
+fn preconditions_hold(request: &Request, current: Option<&Card>) -> bool {
+    let etag = current.map(|card| card.etag.as_str());
+    let listed = |header: &str, etag: &str| {
+        header
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag == etag)
+    };
+    if let Some(header) = request.header("if-match")
+        && !etag.is_some_and(|etag| listed(header, etag))
+    {
+        return false;
+    }
+    if let Some(header) = request.header("if-none-match")
+        && etag.is_some_and(|etag| listed(header, etag))
+    {
+        return false;
+    }
+    true
+}
+
+/// The parsed XML body, `None` when there is none. Errors are responses to
+/// send back.
+fn body(request: &Request) -> std::result::Result<Option<Element>, Response> {
+    if request.body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    let text = std::str::from_utf8(&request.body).map_err(|_| plain(400, "body is not UTF-8"))?;
+    xml::parse(text)
+        .map(Some)
+        .map_err(|e| plain(400, &format!("invalid XML: {e}")))
+}
+
+fn authorized(request: &Request, password: &str) -> bool {
+    let credentials = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| decode_base64(encoded.trim()))
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .unwrap_or_default();
+    let given = credentials.split_once(':').map(|(_, given)| given).unwrap_or_default();
+    same_secret(given, password)
+}
+
+fn prop_element(ns: &str, name: &str, value: &str) -> String {
+    let (tag, declaration) = match ns {
+        DAV => (format!("d:{name}"), String::new()),
+        CARDDAV => (format!("card:{name}"), String::new()),
+        CALENDARSERVER => (format!("cs:{name}"), String::new()),
+        "" => (name.to_string(), r#" xmlns="""#.to_string()),
+        ns => (format!("x:{name}"), format!(r#" xmlns:x="{}""#, escape(ns))),
+    };
+    if value.is_empty() {
+        format!("<{tag}{declaration}/>")
+    } else {
+        format!("<{tag}{declaration}>{value}</{tag}>")
+    }
+}
+
+fn propstat(props: &str, status: u16) -> String {
+    format!(
+        "<d:propstat><d:prop>{props}</d:prop><d:status>{}</d:status></d:propstat>",
+        status_line(status)
+    )
+}
+
+fn status_line(status: u16) -> String {
+    format!("HTTP/1.1 {status} {}", reason(status))
+}
+
+fn multistatus(responses: impl IntoIterator<Item = String>) -> Response {
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <d:multistatus xmlns:d=\"DAV:\" xmlns:card=\"{CARDDAV}\" xmlns:cs=\"{CALENDARSERVER}\">\
+         {}</d:multistatus>\n",
+        responses.into_iter().collect::<String>()
+    );
+    Response::new(207, "application/xml; charset=utf-8", body)
+}
+
+/// A `<d:error>` body naming the precondition that failed.
+fn dav_error(condition: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <d:error xmlns:d=\"DAV:\" xmlns:card=\"{CARDDAV}\"><{condition}/></d:error>\n"
+    )
+}
+
+/// RFC 6352 asks for a 403 naming `valid-address-data` when a card is
+/// refused. The reason goes in a header for people reading logs.
+fn invalid_card(reason: &str) -> Response {
+    Response::new(403, "application/xml; charset=utf-8", dav_error("card:valid-address-data"))
+        .with_header("X-Rolodex-Error", reason.replace(['\r', '\n'], " "))
+}
+
+fn plain(status: u16, message: &str) -> Response {
+    Response::new(status, TEXT_TYPE, format!("{message}\n"))
+}
+
+fn not_allowed(allow: &str) -> Response {
+    plain(405, "method not allowed").with_header("Allow", allow)
+}
+
+/// 64-bit FNV-1a over `parts`, stable across runs and Rust versions so
+/// ETags survive restarts.
+fn fnv1a(parts: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.bytes().chain([0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
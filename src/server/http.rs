@@ -0,0 +1,342 @@
+//! Just enough HTTP/1.1 for the local JSON and CardDAV servers: one request
+//! per connection, bodies sized by `Content-Length`, and every response
+//! closes the connection.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    os::unix::net::UnixListener,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use serde::Serialize;
+use tracing::{debug, info};
+
+/// Requests with a longer head than this are refused.
+const MAX_HEAD: usize = 16 * 1024;
+/// Requests with a longer body than this are refused.
+pub const MAX_BODY: usize = 1024 * 1024;
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Answers requests, shared by the threads serving each connection.
+pub trait Handler: Send + Sync + 'static {
+    fn handle(&self, request: &Request) -> Response;
+}
+
+/// Accept connections until the listener fails, one thread each.
+pub fn serve_tcp(handler: Arc<impl Handler>, listener: TcpListener) -> std::io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let handler = Arc::clone(&handler);
+        thread::spawn(move || handle_stream(&*handler, stream));
+    }
+    Ok(())
+}
+
+pub fn serve_unix(handler: Arc<impl Handler>, listener: UnixListener) -> std::io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let handler = Arc::clone(&handler);
+        thread::spawn(move || handle_stream(&*handler, stream));
+    }
+    Ok(())
+}
+
+fn handle_stream(handler: &impl Handler, mut stream: impl Read + Write) {
+    let response = match Request::read_from(&mut stream) {
+        Ok(request) => {
+            let response = handler.handle(&request);
+            info!("{} {} -> {}", request.method, request.path, response.status);
+            response
+        }
+        Err(response) => response,
+    };
+    if let Err(e) = response.write_to(&mut stream) {
+        debug!("Client went away: {e}");
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// Path without the query string, e.g. `/contacts/7`.
+    pub path: String,
+    /// Decoded query parameters in the order given.
+    pub query: Vec<(String, String)>,
+    /// Header names are lower-cased.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(method: &str, target: &str) -> Self {
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        Self {
+            method: method.to_string(),
+            path: percent_decode(path, false),
+            query: parse_query(query),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Read one request from `stream`. Errors are responses to send back.
+    pub fn read_from(stream: impl Read) -> Result<Self, Response> {
+        let mut reader = BufReader::new(stream.take((MAX_HEAD + MAX_BODY) as u64));
+        let mut head_len = 0;
+        let mut line = String::new();
+
+        let mut read_line = |line: &mut String| -> Result<(), Response> {
+            line.clear();
+            let read = reader
+                .read_line(line)
+                .map_err(|_| Response::error(400, "malformed request"))?;
+            head_len += read;
+            if read == 0 || head_len > MAX_HEAD {
+                return Err(Response::error(400, "incomplete or oversized request head"));
+            }
+            Ok(())
+        };
+
+        read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(Response::error(400, "malformed request line"));
+        };
+        if !version.starts_with("HTTP/1.") {
+            return Err(Response::error(505, "only HTTP/1.x is supported"));
+        }
+        let mut request = Request::new(method, target);
+
+        loop {
+            read_line(&mut line)?;
+            let header = line.trim_end_matches(['\r', '\n']);
+            if header.is_empty() {
+                break;
+            }
+            let Some((name, value)) = header.split_once(':') else {
+                return Err(Response::error(400, "malformed header"));
+            };
+            request
+                .headers
+                .push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+
+        let length = match request.header("content-length") {
+            Some(length) => length
+                .parse::<usize>()
+                .map_err(|_| Response::error(400, "invalid Content-Length"))?,
+            None => 0,
+        };
+        if length > MAX_BODY {
+            return Err(Response::error(413, "request body too large"));
+        }
+        request.body = vec![0; length];
+        reader
+            .read_exact(&mut request.body)
+            .map_err(|_| Response::error(400, "request body shorter than Content-Length"))?;
+        Ok(request)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type", content_type.to_string())],
+            body: body.into(),
+        }
+    }
+
+    pub fn json(status: u16, value: &impl Serialize) -> Self {
+        match serde_json::to_vec_pretty(value) {
+            Ok(body) => Self::new(status, "application/json", body),
+            Err(e) => Self::error(500, &format!("serialize response: {e}")),
+        }
+    }
+
+    /// `{"error": message}` with the given status.
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    pub fn no_content() -> Self {
+        Self {
+            status: 204,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    pub fn write_to(&self, mut stream: impl Write) -> std::io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            self.body.len()
+        ));
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&self.body)?;
+        stream.flush()
+    }
+}
+
+pub fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        207 => "Multi-Status",
+        301 => "Moved Permanently",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        412 => "Precondition Failed",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        505 => "HTTP Version Not Supported",
+        _ => "",
+    }
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key, true), percent_decode(value, true))
+        })
+        .collect()
+}
+
+/// Escape everything but unreserved characters, for one path segment.
+pub fn percent_encode(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            byte => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+/// Decode `%XX` escapes, and `+` as a space in query strings. Invalid
+/// escapes are kept as they are.
+pub fn percent_decode(text: &str, plus_as_space: bool) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b'+' if plus_as_space => out.push(b' '),
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /contacts/7?q=ada+l%C3%B6&x HTTP/1.1\r\n\
+                   Host: localhost\r\n\
+                   Authorization: Bearer abc\r\n\
+                   Content-Length: 4\r\n\r\n\
+                   {}\r\nextra";
+        let request = Request::read_from(raw.as_bytes()).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/contacts/7");
+        assert_eq!(request.param("q"), Some("ada lö"));
+        assert_eq!(request.param("x"), Some(""));
+        assert_eq!(request.header("authorization"), Some("Bearer abc"));
+        assert_eq!(request.body, b"{}\r\n");
+    }
+
+    #[test]
+    fn test_bad_requests() {
+        let status = |raw: &str| Request::read_from(raw.as_bytes()).unwrap_err().status;
+        assert_eq!(status(""), 400);
+        assert_eq!(status("GET /\r\n\r\n"), 400);
+        assert_eq!(status("GET / HTTP/2\r\n\r\n"), 505);
+        assert_eq!(status("GET / HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort"), 400);
+        assert_eq!(status("GET / HTTP/1.1\r\nContent-Length: 99999999\r\n\r\n"), 413);
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut out = Vec::new();
+        Response::no_content().write_to(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b+c", false), "a b+c");
+        assert_eq!(percent_decode("a%2", true), "a%2");
+        assert_eq!(percent_decode("100%", true), "100%");
+        assert_eq!(percent_decode("%zz", true), "%zz");
+        assert_eq!(percent_encode("a b/é~"), "a%20b%2F%C3%A9~");
+        assert_eq!(percent_decode(&percent_encode("a b/é~"), false), "a b/é~");
+    }
+}
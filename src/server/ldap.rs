@@ -0,0 +1,592 @@
+//! `rolodex ldap`: a read-only LDAPv3 directory of the contacts, for desk
+//! phones and mail clients whose address book lookups only speak LDAP.
+//!
+//! Each contact is an `inetOrgPerson` entry `uid=<id>,<base>` with `cn`,
+//! `sn`, `givenName`, `mail`, `telephoneNumber` and `o`. Binds must be
+//! anonymous, searches filter with equality, substrings, presence, `&`, `|`
+//! and `!`, and every write is refused.
+
+use std::{
+    io::{self, BufReader, BufWriter, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use tracing::{debug, info};
+
+use super::ber::{self, ENUMERATED, INTEGER, OCTET_STRING, SEQUENCE, SET, Tlv};
+use crate::{
+    error::{AppError, AppResult as Result},
+    model::Contact,
+    store::ContactStore,
+};
+
+/// How long a connection may sit idle before it is closed.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Protocol operations, by their application tags
+const BIND_REQUEST: u8 = 0x60;
+const BIND_RESPONSE: u8 = 0x61;
+const UNBIND_REQUEST: u8 = 0x42;
+const SEARCH_REQUEST: u8 = 0x63;
+const SEARCH_ENTRY: u8 = 0x64;
+const SEARCH_DONE: u8 = 0x65;
+const ABANDON_REQUEST: u8 = 0x50;
+const EXTENDED_REQUEST: u8 = 0x77;
+const EXTENDED_RESPONSE: u8 = 0x78;
+/// Modify, add, delete, modify DN and compare, with their responses.
+const REFUSED: [(u8, u8); 5] =
+    [(0x66, 0x67), (0x68, 0x69), (0x4a, 0x6b), (0x6c, 0x6d), (0x6e, 0x6f)];
+
+/// `simple` choice of a bind's authentication.
+const SIMPLE: u8 = 0x80;
+
+const SUCCESS: i64 = 0;
+const OPERATIONS_ERROR: i64 = 1;
+const PROTOCOL_ERROR: i64 = 2;
+const SIZE_LIMIT_EXCEEDED: i64 = 4;
+const AUTH_METHOD_NOT_SUPPORTED: i64 = 7;
+const NO_SUCH_OBJECT: i64 = 32;
+const INVALID_CREDENTIALS: i64 = 49;
+const UNWILLING_TO_PERFORM: i64 = 53;
+
+/// Filters nested deeper than this are refused, which also bounds the
+/// recursion in [`Filter::matches`].
+const MAX_FILTER_DEPTH: usize = 32;
+
+pub struct Ldap {
+    db: Mutex<Box<dyn ContactStore>>,
+    /// DN the contacts sit under, e.g. `ou=contacts,dc=rolodex`.
+    base: String,
+}
+
+impl Ldap {
+    pub fn new(db: impl ContactStore + 'static, base: String) -> Self {
+        Self {
+            db: Mutex::new(Box::new(db)),
+            base,
+        }
+    }
+
+    /// Accept connections until the listener fails, one thread each.
+    pub fn serve_tcp(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+            let ldap = Arc::clone(&self);
+            thread::spawn(move || {
+                if let Err(e) = ldap.serve_connection(stream) {
+                    debug!("LDAP client went away: {e}");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Answer messages until the client unbinds or hangs up. A message that
+    /// can't be decoded ends the connection.
+    fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+        while let Some(message) = ber::read(&mut reader)? {
+            let Some(replies) = self.respond(&message)? else {
+                break;
+            };
+            for reply in replies {
+                writer.write_all(&reply.encode())?;
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// The messages answering `message`, or `None` after an unbind.
+    fn respond(&self, message: &Tlv) -> io::Result<Option<Vec<Tlv>>> {
+        let parts = message.children()?;
+        let (SEQUENCE, [id, operation, ..]) = (message.tag, parts.as_slice()) else {
+            return Err(invalid("not an LDAP message"));
+        };
+        let id = id.as_integer()?;
+        let reply = |operation| Tlv::constructed(SEQUENCE, [Tlv::integer(INTEGER, id), operation]);
+
+        let replies = match operation.tag {
+            UNBIND_REQUEST => return Ok(None),
+            ABANDON_REQUEST => Vec::new(),
+            BIND_REQUEST => vec![reply(bind(operation)?)],
+            SEARCH_REQUEST => self.search(operation)?.into_iter().map(reply).collect(),
+            EXTENDED_REQUEST => {
+                let message = "extended operations are not supported";
+                vec![reply(result(EXTENDED_RESPONSE, PROTOCOL_ERROR, "", message))]
+            }
+            tag => {
+                let Some((_, response)) = REFUSED.iter().find(|(request, _)| *request == tag) else {
+                    return Err(invalid("unknown operation"));
+                };
+                let message = "the directory is read-only";
+                vec![reply(result(*response, UNWILLING_TO_PERFORM, "", message))]
+            }
+        };
+        Ok(Some(replies))
+    }
+
+    fn search(&self, request: &Tlv) -> io::Result<Vec<Tlv>> {
+        let search = Search::parse(request)?;
+        info!("LDAP search {:?} under {:?}", search.filter, search.base);
+        let candidates = match self.candidates(&search) {
+            Ok(candidates) => candidates,
+            Err((code, matched, message)) => {
+                return Ok(vec![result(SEARCH_DONE, code, &matched, &message)]);
+            }
+        };
+
+        let mut replies = Vec::new();
+        let mut code = SUCCESS;
+        for entry in candidates.iter().filter(|entry| search.filter.matches(entry) == Some(true)) {
+            if search.size_limit > 0 && replies.len() == search.size_limit {
+                code = SIZE_LIMIT_EXCEEDED;
+                break;
+            }
+            replies.push(entry.encode(&search.attributes, search.types_only));
+        }
+        replies.push(result(SEARCH_DONE, code, "", ""));
+        Ok(replies)
+    }
+
+    /// The entries within the search's base and scope, or the result code,
+    /// matched DN and message to fail with.
+    fn candidates(&self, search: &Search) -> std::result::Result<Vec<Entry>, Failure> {
+        let base = normalize_dn(&search.base);
+        let own = normalize_dn(&self.base);
+        let contacts = || {
+            let db = self
+                .db
+                .lock()
+                .map_err(|_| AppError::Database("database lock poisoned".to_string()))?;
+            db.load_customers()
+        };
+        let contacts = || contacts().map_err(|e| (OPERATIONS_ERROR, String::new(), e.to_string()));
+        let entries = |contacts: Vec<Contact>| {
+            contacts.iter().map(|contact| self.contact_entry(contact)).collect()
+        };
+
+        if base.is_empty() || base == own {
+            let top = match base.is_empty() {
+                true => self.root_dse(),
+                false => self.base_entry(),
+            };
+            return Ok(match search.scope {
+                Scope::Base => vec![top],
+                Scope::One if base.is_empty() => vec![self.base_entry()],
+                Scope::One => entries(contacts()?),
+                Scope::Subtree => {
+                    let mut all = vec![self.base_entry()];
+                    all.extend(entries(contacts()?));
+                    all
+                }
+            });
+        }
+
+        let id = base
+            .strip_suffix(&format!(",{own}"))
+            .and_then(|rdn| rdn.strip_prefix("uid="))
+            .and_then(|id| id.parse::<i64>().ok());
+        let contact = match id {
+            Some(id) => contacts()?.into_iter().find(|contact| contact.id == id),
+            None => None,
+        };
+        match (contact, search.scope) {
+            (Some(_), Scope::One) => Ok(Vec::new()),
+            (Some(contact), _) => Ok(vec![self.contact_entry(&contact)]),
+            (None, _) => {
+                let matched = match base.ends_with(&format!(",{own}")) {
+                    true => self.base.clone(),
+                    false => String::new(),
+                };
+                Err((NO_SUCH_OBJECT, matched, format!("no entry {}", search.base)))
+            }
+        }
+    }
+
+    fn contact_entry(&self, contact: &Contact) -> Entry {
+        let name = contact.name.trim();
+        let (given, family) = match name.rsplit_once(' ') {
+            Some((given, family)) => (given.trim(), family),
+            None => ("", name),
+        };
+        let mut attributes = vec![
+            ("objectClass", vec!["top", "person", "organizationalPerson", "inetOrgPerson"]),
+            ("cn", vec![name]),
+            ("sn", vec![family]),
+        ];
+        let fields = [
+            ("givenName", Some(given)),
+            ("mail", contact.email.as_deref()),
+            ("telephoneNumber", contact.phone.as_deref()),
+            ("o", contact.company.as_deref()),
+        ];
+        for (attribute, value) in fields {
+            if let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) {
+                attributes.push((attribute, vec![value]));
+            }
+        }
+        let id = contact.id.to_string();
+        attributes.push(("uid", vec![&id]));
+        Entry::new(format!("uid={id},{}", self.base), attributes)
+    }
+
+    fn base_entry(&self) -> Entry {
+        let ou = self.base.split(',').next().and_then(|rdn| rdn.split_once('='));
+        let ou = ou.map(|(_, value)| value.trim()).unwrap_or_default();
+        let attributes = vec![("objectClass", vec!["top", "organizationalUnit"]), ("ou", vec![ou])];
+        Entry::new(self.base.clone(), attributes)
+    }
+
+    /// The root entry clients read to find the directory's base.
+    fn root_dse(&self) -> Entry {
+        let attributes = vec![
+            ("objectClass", vec!["top"]),
+            ("namingContexts", vec![self.base.as_str()]),
+            ("supportedLDAPVersion", vec!["3"]),
+        ];
+        Entry::new(String::new(), attributes)
+    }
+}
+
+/// Answer to a bind: anonymous simple binds succeed, anything else fails.
+fn bind(request: &Tlv) -> io::Result<Tlv> {
+    let parts = request.children()?;
+    let [version, _name, authentication] = parts.as_slice() else {
+        return Err(invalid("malformed bind"));
+    };
+    let anonymous = "only anonymous binds are allowed";
+    let (code, message) = match (version.as_integer()?, authentication.tag) {
+        (2 | 3, SIMPLE) if authentication.value.is_empty() => (SUCCESS, ""),
+        (2 | 3, SIMPLE) => (INVALID_CREDENTIALS, anonymous),
+        (2 | 3, _) => (AUTH_METHOD_NOT_SUPPORTED, anonymous),
+        _ => (PROTOCOL_ERROR, "only LDAP versions 2 and 3 are supported"),
+    };
+    Ok(result(BIND_RESPONSE, code, "", message))
+}
+
+/// An LDAPResult under `tag`.
+fn result(tag: u8, code: i64, matched: &str, message: &str) -> Tlv {
+    Tlv::constructed(
+        tag,
+        [
+            Tlv::integer(ENUMERATED, code),
+            Tlv::string(OCTET_STRING, matched),
+            Tlv::string(OCTET_STRING, message),
+        ],
+    )
+}
+
+/// Result code, matched DN and message of a search that failed.
+type Failure = (i64, String, String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Base,
+    One,
+    Subtree,
+}
+
+struct Search {
+    base: String,
+    scope: Scope,
+    /// No limit when zero.
+    size_limit: usize,
+    types_only: bool,
+    filter: Filter,
+    attributes: Vec<String>,
+}
+
+impl Search {
+    fn parse(request: &Tlv) -> io::Result<Self> {
+        let parts = request.children()?;
+        let [base, scope, _deref, size_limit, _time_limit, types_only, filter, attributes] =
+            parts.as_slice()
+        else {
+            return Err(invalid("malformed search"));
+        };
+        let scope = match scope.as_integer()? {
+            0 => Scope::Base,
+            1 => Scope::One,
+            2 => Scope::Subtree,
+            _ => return Err(invalid("unknown search scope")),
+        };
+        let attributes = attributes
+            .children()?
+            .iter()
+            .map(|attribute| attribute.as_str().map(str::to_string))
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            base: base.as_str()?.to_string(),
+            scope,
+            size_limit: size_limit.as_integer()?.max(0) as usize,
+            types_only: types_only.as_bool(),
+            filter: Filter::parse(filter)?,
+            attributes,
+        })
+    }
+}
+
+/// A search filter. Attribute names are canonical, values as given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+    Equal(String, String),
+    Substrings {
+        attribute: String,
+        initial: Option<String>,
+        any: Vec<String>,
+        last: Option<String>,
+    },
+    Present(String),
+    /// Ordering and extensible matches, which no attribute here supports.
+    Undefined,
+}
+
+impl Filter {
+    fn parse(tlv: &Tlv) -> io::Result<Self> {
+        Filter::parse_nested(tlv, 0)
+    }
+
+    /// Parse a filter found `depth` levels inside the search's own.
+    fn parse_nested(tlv: &Tlv, depth: usize) -> io::Result<Self> {
+        if depth >= MAX_FILTER_DEPTH {
+            return Err(invalid("filter nested too deeply"));
+        }
+        let inner = |tlv: &Tlv| Filter::parse_nested(tlv, depth + 1);
+        let all = |tlv: &Tlv| tlv.children()?.iter().map(inner).collect::<io::Result<_>>();
+        let pair = |tlv: &Tlv| -> io::Result<(String, String)> {
+            match tlv.children()?.as_slice() {
+                [attribute, value] => Ok((canonical(attribute.as_str()?), value.as_str()?.into())),
+                _ => Err(invalid("malformed attribute value assertion")),
+            }
+        };
+        Ok(match tlv.tag {
+            0xa0 => Filter::And(all(tlv)?),
+            0xa1 => Filter::Or(all(tlv)?),
+            0xa2 => match tlv.children()?.as_slice() {
+                [filter] => Filter::Not(Box::new(inner(filter)?)),
+                _ => return Err(invalid("malformed not filter")),
+            },
+            // Approximate matches are treated as equality
+            0xa3 | 0xa8 => {
+                let (attribute, value) = pair(tlv)?;
+                Filter::Equal(attribute, value)
+            }
+            0xa4 => {
+                let parts = tlv.children()?;
+                let [attribute, substrings] = parts.as_slice() else {
+                    return Err(invalid("malformed substrings filter"));
+                };
+                let (mut initial, mut any, mut last) = (None, Vec::new(), None);
+                for substring in substrings.children()? {
+                    let value = substring.as_str()?.to_string();
+                    match substring.tag {
+                        0x80 => initial = Some(value),
+                        0x81 => any.push(value),
+                        0x82 => last = Some(value),
+                        _ => return Err(invalid("malformed substrings filter")),
+                    }
+                }
+                let attribute = canonical(attribute.as_str()?);
+                Filter::Substrings { attribute, initial, any, last }
+            }
+            0x87 => Filter::Present(canonical(tlv.as_str()?)),
+            0xa5 | 0xa6 | 0xa9 => Filter::Undefined,
+            _ => return Err(invalid("unknown filter")),
+        })
+    }
+
+    /// Whether `entry` matches, or `None` when that's undefined, as for
+    /// attributes the directory doesn't know.
+    fn matches(&self, entry: &Entry) -> Option<bool> {
+        let known = |attribute: &str| KNOWN.contains(&attribute);
+        match self {
+            Filter::And(filters) => {
+                let results: Vec<_> = filters.iter().map(|f| f.matches(entry)).collect();
+                match results.contains(&Some(false)) {
+                    true => Some(false),
+                    false => results.iter().all(Option::is_some).then_some(true),
+                }
+            }
+            Filter::Or(filters) => {
+                let results: Vec<_> = filters.iter().map(|f| f.matches(entry)).collect();
+                match results.contains(&Some(true)) {
+                    true => Some(true),
+                    false => results.iter().all(Option::is_some).then_some(false),
+                }
+            }
+            Filter::Not(filter) => filter.matches(entry).map(|matched| !matched),
+            Filter::Equal(attribute, value) => {
+                let value = normalize(attribute, value);
+                let values = entry.values(attribute);
+                known(attribute).then(|| values.iter().any(|v| normalize(attribute, v) == value))
+            }
+            Filter::Substrings { attribute, initial, any, last } => {
+                let matches = |value: &str| {
+                    let value = normalize(attribute, value);
+                    let mut rest = value.as_str();
+                    if let Some(initial) = initial {
+                        let Some(after) = rest.strip_prefix(&normalize(attribute, initial)) else {
+                            return false;
+                        };
+                        rest = after;
+                    }
+                    for part in any {
+                        let part = normalize(attribute, part);
+                        let Some(at) = rest.find(&part) else {
+                            return false;
+                        };
+                        rest = &rest[at + part.len()..];
+                    }
+                    last.as_ref().is_none_or(|last| rest.ends_with(&normalize(attribute, last)))
+                };
+                let values = entry.values(attribute);
+                known(attribute).then(|| values.iter().any(|v| matches(v)))
+            }
+            Filter::Present(attribute) => Some(!entry.values(attribute).is_empty()),
+            Filter::Undefined => None,
+        }
+    }
+}
+
+/// Attributes filters can test.
+const KNOWN: [&str; 8] =
+    ["objectClass", "cn", "sn", "givenName", "mail", "telephoneNumber", "o", "uid"];
+
+/// The attribute `name` refers to, spelled as entries spell it.
+fn canonical(name: &str) -> String {
+    let name = match name.to_ascii_lowercase().as_str() {
+        "objectclass" => "objectClass",
+        "cn" | "commonname" => "cn",
+        "sn" | "surname" => "sn",
+        "givenname" | "gn" => "givenName",
+        "mail" | "rfc822mailbox" => "mail",
+        "telephonenumber" => "telephoneNumber",
+        "o" | "organizationname" => "o",
+        "ou" | "organizationalunitname" => "ou",
+        "uid" | "userid" => "uid",
+        "namingcontexts" => "namingContexts",
+        "supportedldapversion" => "supportedLDAPVersion",
+        _ => return name.to_string(),
+    };
+    name.to_string()
+}
+
+/// Case and runs of spaces don't matter; phone numbers also ignore spaces
+/// and hyphens entirely.
+fn normalize(attribute: &str, value: &str) -> String {
+    let value = value.to_lowercase();
+    match attribute {
+        "telephoneNumber" => value.chars().filter(|c| !matches!(c, ' ' | '-')).collect(),
+        _ => value.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// A DN lower-cased without spaces around its separators.
+fn normalize_dn(dn: &str) -> String {
+    let rdns = dn.split(',').filter(|rdn| !rdn.trim().is_empty()).map(|rdn| {
+        let (attribute, value) = rdn.split_once('=').unwrap_or((rdn, ""));
+        format!("{}={}", attribute.trim(), value.trim()).to_lowercase()
+    });
+    rdns.collect::<Vec<_>>().join(",")
+}
+
+struct Entry {
+    dn: String,
+    attributes: Vec<(&'static str, Vec<String>)>,
+}
+
+impl Entry {
+    fn new(dn: String, attributes: Vec<(&'static str, Vec<&str>)>) -> Self {
+        let attributes = attributes
+            .into_iter()
+            .map(|(name, values)| (name, values.into_iter().map(str::to_string).collect()))
+            .collect();
+        Self { dn, attributes }
+    }
+
+    fn values(&self, attribute: &str) -> &[String] {
+        let values = self.attributes.iter().find(|(name, _)| *name == attribute);
+        values.map(|(_, values)| values.as_slice()).unwrap_or_default()
+    }
+
+    /// A SearchResultEntry with the `requested` attributes: all of them for
+    /// none or `*`, none for `1.1`.
+    fn encode(&self, requested: &[String], types_only: bool) -> Tlv {
+        let all = requested.is_empty() || requested.iter().any(|name| name == "*");
+        let wanted = |name: &str| all || requested.iter().any(|r| canonical(r) == name);
+        let attributes = self.attributes.iter().filter(|(name, _)| wanted(name));
+        let attributes = attributes.map(|(name, values)| {
+            let values = values.iter().filter(|_| !types_only);
+            Tlv::constructed(
+                SEQUENCE,
+                [
+                    Tlv::string(OCTET_STRING, name),
+                    Tlv::constructed(SET, values.map(|v| Tlv::string(OCTET_STRING, v))),
+                ],
+            )
+        });
+        Tlv::constructed(
+            SEARCH_ENTRY,
+            [Tlv::string(OCTET_STRING, &self.dn), Tlv::constructed(SEQUENCE, attributes)],
+        )
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> Entry {
+        let attributes = vec![
+            ("cn", vec!["Ada  Lovelace"]),
+            ("telephoneNumber", vec!["+44 20-7946 0000"]),
+        ];
+        Entry::new("uid=1,ou=contacts".to_string(), attributes)
+    }
+
+    #[test]
+    fn test_filter_matching() {
+        let equal = |attribute: &str, value: &str| {
+            Filter::Equal(canonical(attribute), value.to_string())
+        };
+        assert_eq!(equal("CommonName", "ada lovelace").matches(&entry()), Some(true));
+        assert_eq!(equal("telephoneNumber", "+442079460000").matches(&entry()), Some(true));
+        assert_eq!(equal("mail", "ada@example.com").matches(&entry()), Some(false));
+        assert_eq!(equal("shoeSize", "9").matches(&entry()), None);
+
+        let substrings = Filter::Substrings {
+            attribute: "cn".to_string(),
+            initial: Some("ADA".to_string()),
+            any: vec!["love".to_string()],
+            last: Some("ace".to_string()),
+        };
+        assert_eq!(substrings.matches(&entry()), Some(true));
+
+        // Undefined stays undefined under `!` and decides nothing in `|`
+        let unknown = Filter::Not(Box::new(equal("shoeSize", "9")));
+        assert_eq!(unknown.matches(&entry()), None);
+        let or = Filter::Or(vec![unknown.clone(), equal("cn", "Ada Lovelace")]);
+        assert_eq!(or.matches(&entry()), Some(true));
+        let and = Filter::And(vec![unknown, equal("cn", "Ada Lovelace")]);
+        assert_eq!(and.matches(&entry()), None);
+    }
+
+    #[test]
+    fn test_normalize_dn() {
+        assert_eq!(normalize_dn(" UID=7 , ou=Contacts,dc=rolodex"), "uid=7,ou=contacts,dc=rolodex");
+        assert_eq!(normalize_dn(""), "");
+    }
+}
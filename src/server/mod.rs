@@ -0,0 +1,273 @@
+//! `rolodex serve`: the database over a small HTTP/JSON API, for tools that
+//! want to look up contacts without linking the crate.
+//!
+//! | Method | Path | |
+//! |---|---|---|
+//! | `GET` | `/contacts` | All contacts; `?q=` searches, `?sort=name:desc` orders |
+//! | `POST` | `/contacts` | Create a contact, `201` with the stored contact |
+//! | `GET` | `/contacts/{id}` | One contact |
+//! | `PUT` | `/contacts/{id}` | Replace a contact's fields |
+//! | `DELETE` | `/contacts/{id}` | Delete a contact, `204` |
+//! | `GET` | `/export` | Every contact in the `--backup` format |
+
+pub mod ber;
+pub mod carddav;
+pub mod http;
+pub mod ldap;
+pub mod rpc;
+
+use std::{
+    net::TcpListener,
+    os::unix::net::UnixListener,
+    sync::{Arc, Mutex},
+};
+
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::{
+    error::{AppError, AppResult as Result},
+    hooks::{Event, LifecycleHooks},
+    model::{Contact, Sort},
+    store::ContactStore,
+};
+
+pub use carddav::CardDav;
+pub use http::{Handler, Request, Response};
+pub use ldap::Ldap;
+pub use rpc::Rpc;
+
+/// Body of `POST /contacts` and `PUT /contacts/{id}`. Other fields, such as
+/// those returned by `GET`, are ignored. An update that carries the
+/// `version` it was read at fails with 409 if the contact changed since;
+/// without one it overwrites whatever is there.
+#[derive(Debug, Deserialize)]
+struct ContactInput {
+    name: String,
+    #[serde(default)]
+    company: Option<String>,
+    #[serde(default)]
+    phone: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    favorite: bool,
+    #[serde(default)]
+    version: Option<i64>,
+}
+
+impl ContactInput {
+    fn into_contact(self, id: i64) -> Result<Contact> {
+        let contact = Contact {
+            version: self.version.unwrap_or_default(),
+            id,
+            name: self.name,
+            company: self.company,
+            phone: self.phone,
+            email: self.email,
+            favorite: self.favorite,
+            ..Default::default()
+        };
+        contact
+            .validate()
+            .map_err(|e| AppError::Input(e.to_string()))?;
+        Ok(contact)
+    }
+}
+
+pub struct Server {
+    db: Mutex<Box<dyn ContactStore>>,
+    token: Option<String>,
+}
+
+impl Server {
+    /// With a `token`, every request needs `Authorization: Bearer <token>`.
+    pub fn new(db: impl ContactStore + 'static, token: Option<String>) -> Self {
+        Self {
+            db: Mutex::new(Box::new(db)),
+            token,
+        }
+    }
+
+    /// Accept connections until the listener fails, one thread each.
+    pub fn serve_tcp(self: Arc<Self>, listener: TcpListener) -> Result<()> {
+        Ok(http::serve_tcp(self, listener)?)
+    }
+
+    pub fn serve_unix(self: Arc<Self>, listener: UnixListener) -> Result<()> {
+        Ok(http::serve_unix(self, listener)?)
+    }
+
+    /// Answer `request`, leaving any lifecycle hook it triggers in `hook` to
+    /// run once the database lock is released.
+    fn route(&self, request: &Request, hook: &mut Option<PendingHook>) -> Result<Response> {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        let db = self
+            .db
+            .lock()
+            .map_err(|_| AppError::Database("database lock poisoned".to_string()))?;
+
+        if matches!(request.method.as_str(), "POST" | "PUT")
+            && let Some(kind) = request.header("content-type")
+            && !kind.starts_with("application/json")
+        {
+            let message = format!("expected application/json, got {kind}");
+            return Ok(Response::error(415, &message));
+        }
+
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["contacts"]) => {
+                let contacts = match (request.param("q"), request.param("sort")) {
+                    (Some(query), _) => db.search_contacts(query)?,
+                    (None, Some(sort)) => {
+                        let sort = sort.parse::<Sort>().map_err(AppError::Input)?;
+                        db.load_customers_sorted(sort)?
+                    }
+                    (None, None) => db.load_customers_sorted(db.load_sort()?)?,
+                };
+                Ok(Response::json(200, &contacts))
+            }
+            ("POST", ["contacts"]) => {
+                let contact = parse_body(request)?.into_contact(0)?;
+                let contact = db.get_contact(db.add_contact(contact)?)?;
+                *hook = PendingHook::new(&**db, Event::Add, &contact);
+                Ok(Response::json(201, &contact)
+                    .with_header("Location", format!("/contacts/{}", contact.id)))
+            }
+            ("GET", ["contacts", id]) => Ok(Response::json(200, &db.get_contact(parse_id(id)?)?)),
+            ("PUT", ["contacts", id]) => {
+                let id = parse_id(id)?;
+                let input = parse_body(request)?;
+                let version = input.version.unwrap_or(db.get_contact(id)?.version);
+                let contact = Contact { version, ..input.into_contact(id)? };
+                db.update_contact(id, contact.clone())?;
+                db.set_favorite(id, contact.favorite)?;
+                let contact = db.get_contact(id)?;
+                *hook = PendingHook::new(&**db, Event::Update, &contact);
+                Ok(Response::json(200, &contact))
+            }
+            ("DELETE", ["contacts", id]) => {
+                let contact = db.get_contact(parse_id(id)?)?;
+                db.delete_contact(contact.id)?;
+                *hook = PendingHook::new(&**db, Event::Delete, &contact);
+                Ok(Response::no_content())
+            }
+            ("GET", ["export"]) => Ok(Response::json(200, &db.load_customers()?)),
+            (_, ["contacts"]) => Ok(not_allowed("GET, POST")),
+            (_, ["contacts", _]) => Ok(not_allowed("GET, PUT, DELETE")),
+            (_, ["export"]) => Ok(not_allowed("GET")),
+            _ => Err(AppError::NotFound(format!("path {}", request.path))),
+        }
+    }
+}
+
+impl Handler for Server {
+    /// Answer one request.
+    fn handle(&self, request: &Request) -> Response {
+        if let Some(token) = &self.token
+            && !authorized(request, token)
+        {
+            return Response::error(401, "missing or wrong bearer token")
+                .with_header("WWW-Authenticate", "Bearer");
+        }
+        let mut hook = None;
+        let response = self.route(request, &mut hook).unwrap_or_else(|err| {
+            let message = match &err {
+                AppError::NotFound(what) => format!("{what} not found"),
+                AppError::Input(reason) => reason.clone(),
+                err => err.to_string(),
+            };
+            Response::error(status(&err), &message)
+        });
+        if let Some(hook) = hook {
+            hook.fire();
+        }
+        response
+    }
+}
+
+/// HTTP status for an error from the database or the request.
+pub fn status(err: &AppError) -> u16 {
+    match err {
+        AppError::NotFound(_) => 404,
+        AppError::Conflict(_) => 409,
+        AppError::Json(_) => 400,
+        AppError::Input(_) => 422,
+        AppError::Cancelled => 503,
+        AppError::Io(_)
+        | AppError::Terminal(_)
+        | AppError::Config(_)
+        | AppError::Database(_)
+        | AppError::RowParse(_)
+        | AppError::Command(_)
+        | AppError::Sync(_)
+        | AppError::Crypto(_) => 500,
+    }
+}
+
+/// Writes from the API trigger the same lifecycle hooks as the UI. A failed
+/// hook doesn't fail the request.
+fn fire(db: &dyn ContactStore, event: Event, contact: &Contact) {
+    if let Some(hook) = PendingHook::new(db, event, contact) {
+        hook.fire();
+    }
+}
+
+/// A lifecycle hook taken out of the store so it can run after the server
+/// has let go of the database; a slow hook would otherwise hold up every
+/// other connection.
+struct PendingHook {
+    hooks: LifecycleHooks,
+    event: Event,
+    contact: Contact,
+}
+
+impl PendingHook {
+    /// The hook `db` has for `event`, or `None` when there is nothing to run.
+    fn new(db: &dyn ContactStore, event: Event, contact: &Contact) -> Option<Self> {
+        db.hooks().get(event)?;
+        Some(Self {
+            hooks: db.hooks().clone(),
+            event,
+            contact: contact.clone(),
+        })
+    }
+
+    fn fire(self) {
+        if let Err(err) = self.hooks.fire(self.event, std::slice::from_ref(&self.contact)) {
+            warn!("{err}");
+        }
+    }
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    let given = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default()
+        .trim();
+    same_secret(given, token)
+}
+
+/// Compare every byte so the time taken doesn't reveal the prefix.
+fn same_secret(given: &str, secret: &str) -> bool {
+    given.len() == secret.len()
+        && given
+            .bytes()
+            .zip(secret.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn parse_id(id: &str) -> Result<i64> {
+    id.parse()
+        .map_err(|_| AppError::NotFound(format!("contact {id}")))
+}
+
+fn parse_body(request: &Request) -> Result<ContactInput> {
+    Ok(serde_json::from_slice(&request.body)?)
+}
+
+fn not_allowed(allow: &str) -> Response {
+    Response::error(405, "method not allowed").with_header("Allow", allow)
+}
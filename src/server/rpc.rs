@@ -0,0 +1,225 @@
+//! `rolodex rpc`: JSON-RPC 2.0 over stdin and stdout, one message per line,
+//! so editor plugins can keep a single process around for completion.
+//!
+//! | Method | Params | Result |
+//! |---|---|---|
+//! | `search` | `{"query": "ada", "limit": 10}`, both optional | Matching contacts |
+//! | `get` | `{"id": 7}` | The contact |
+//! | `add` | Contact fields as for `POST /contacts` | The stored contact |
+//! | `update` | `{"id": 7}`, the contact fields and optionally `version` | The stored contact |
+//!
+//! Failures carry a code from [`code`] and `{"kind": "NotFound"}` naming the
+//! [`AppError`] variant in `data`.
+
+use std::io::{BufRead, Write};
+
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+
+use super::{ContactInput, fire};
+use crate::{
+    error::{AppError, AppResult as Result},
+    hooks::Event,
+    model::Contact,
+    store::ContactStore,
+};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// JSON-RPC error code and variant name for an error from the database or
+/// the request. Application errors use the range reserved for servers.
+pub fn code(err: &AppError) -> (i64, &'static str) {
+    match err {
+        AppError::NotFound(_) => (-32001, "NotFound"),
+        AppError::Input(_) => (-32002, "Input"),
+        AppError::Database(_) => (-32003, "Database"),
+        AppError::RowParse(_) => (-32004, "RowParse"),
+        AppError::Io(_) => (-32005, "Io"),
+        AppError::Config(_) => (-32006, "Config"),
+        AppError::Command(_) => (-32007, "Command"),
+        AppError::Sync(_) => (-32008, "Sync"),
+        AppError::Terminal(_) => (-32009, "Terminal"),
+        AppError::Cancelled => (-32010, "Cancelled"),
+        AppError::Crypto(_) => (-32011, "Crypto"),
+        AppError::Conflict(_) => (-32012, "Conflict"),
+        AppError::Json(_) => (INVALID_PARAMS, "Json"),
+    }
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<AppError> for RpcError {
+    fn from(err: AppError) -> Self {
+        let (code, kind) = code(&err);
+        let message = match &err {
+            AppError::NotFound(what) => format!("{what} not found"),
+            AppError::Conflict(what) => format!("{what} was modified elsewhere"),
+            AppError::Input(reason) => reason.clone(),
+            err => err.to_string(),
+        };
+        Self {
+            code,
+            message,
+            data: Some(json!({ "kind": kind })),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    #[serde(default)]
+    query: String,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct IdParams {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+struct UpdateParams {
+    id: i64,
+    #[serde(flatten)]
+    contact: ContactInput,
+}
+
+pub struct Rpc {
+    db: Box<dyn ContactStore>,
+}
+
+impl Rpc {
+    pub fn new(db: impl ContactStore + 'static) -> Self {
+        Self { db: Box::new(db) }
+    }
+
+    /// Answer each line of `input` on `output` until `input` ends.
+    pub fn serve(&self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(reply) = self.handle_line(&line) {
+                writeln!(output, "{reply}")?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The reply to one line, a request or a batch of them. Notifications
+    /// get none.
+    pub fn handle_line(&self, line: &str) -> Option<Value> {
+        let message = match serde_json::from_str::<Value>(line) {
+            Ok(message) => message,
+            Err(e) => {
+                let err = RpcError::new(PARSE_ERROR, format!("parse error: {e}"));
+                return Some(reply(Value::Null, Err(err)));
+            }
+        };
+        match message {
+            Value::Array(batch) if !batch.is_empty() => {
+                let replies: Vec<Value> = batch.into_iter().filter_map(|r| self.call(r)).collect();
+                (!replies.is_empty()).then_some(Value::Array(replies))
+            }
+            request => self.call(request),
+        }
+    }
+
+    fn call(&self, request: Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let valid = request.get("jsonrpc").and_then(Value::as_str) == Some("2.0")
+            && id.as_ref().is_none_or(|id| id.is_string() || id.is_number() || id.is_null());
+        let Some(method) = request.get("method").and_then(Value::as_str).filter(|_| valid) else {
+            let err = RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request");
+            return Some(reply(Value::Null, Err(err)));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let result = self.dispatch(method, params);
+        // Notifications are run but never answered
+        id.map(|id| reply(id, result))
+    }
+
+    fn dispatch(&self, method: &str, params: Value) -> std::result::Result<Value, RpcError> {
+        let db = &*self.db;
+        match method {
+            "search" => {
+                let SearchParams { query, limit } = parse_params(params)?;
+                let mut contacts = match query.trim() {
+                    "" => db.load_customers_sorted(db.load_sort()?)?,
+                    query => db.search_contacts(query)?,
+                };
+                contacts.truncate(limit.unwrap_or(usize::MAX));
+                Ok(json!(contacts))
+            }
+            "get" => {
+                let IdParams { id } = parse_params(params)?;
+                Ok(json!(db.get_contact(id)?))
+            }
+            "add" => {
+                let contact = parse_params::<ContactInput>(params)?.into_contact(0)?;
+                let contact = db.get_contact(db.add_contact(contact)?)?;
+                fire(db, Event::Add, &contact);
+                Ok(json!(contact))
+            }
+            "update" => {
+                let UpdateParams { id, contact } = parse_params(params)?;
+                let version = contact.version.unwrap_or(db.get_contact(id)?.version);
+                let contact = Contact { version, ..contact.into_contact(id)? };
+                db.update_contact(id, contact.clone())?;
+                db.set_favorite(id, contact.favorite)?;
+                let contact = db.get_contact(id)?;
+                fire(db, Event::Update, &contact);
+                Ok(json!(contact))
+            }
+            _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("no method {method}"))),
+        }
+    }
+}
+
+/// Named params; missing params count as an empty object.
+fn parse_params<T: DeserializeOwned>(params: Value) -> std::result::Result<T, RpcError> {
+    let params = match params {
+        Value::Null => json!({}),
+        params => params,
+    };
+    serde_json::from_value(params)
+        .map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid params: {e}")))
+}
+
+fn reply(id: Value, result: std::result::Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(RpcError {
+            code,
+            message,
+            data,
+        }) => {
+            let mut error = json!({ "code": code, "message": message });
+            if let Some(data) = data {
+                error["data"] = data;
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::{ContactStore, MemoryStore};
+use crate::{
+    crypt::Key,
+    error::{AppError, AppResult as Result},
+    hooks::LifecycleHooks,
+    model::{Contact, Sort},
+};
+
+/// Contacts in a TOML file, or JSON if the name ends in `.json`, written
+/// back after every change so the file can live in a dotfiles repo. The
+/// file is read once on open; edit it by hand while rolodex isn't running.
+///
+/// ```toml
+/// sort = "name:asc"
+///
+/// [[contacts]]
+/// id = 1
+/// name = "Ada Lovelace"
+/// email = "ada@example.com"
+/// ```
+///
+/// Contacts added by hand may leave out `id`; they get one on open.
+///
+/// [`FileStore::open_sealed`] keeps the JSON sealed with a passphrase
+/// instead, for `store = "encrypted"`; see [`crate::crypt`].
+#[derive(Debug)]
+pub struct FileStore {
+    path: PathBuf,
+    format: Format,
+    contacts: MemoryStore,
+}
+
+#[derive(Debug)]
+enum Format {
+    Toml,
+    Json,
+    /// JSON sealed with [`Key`].
+    Sealed(Key),
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Contents {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sort: Option<String>,
+    #[serde(default)]
+    contacts: Vec<Contact>,
+}
+
+impl FileStore {
+    /// Read the contacts in `path`, or start empty if it doesn't exist yet.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let format = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Format::Json,
+            _ => Format::Toml,
+        };
+        let contents = match read(&path)? {
+            Some(data) => parse(&data, &format)
+                .map_err(|e| AppError::Database(format!("parse {}: {e}", path.display())))?,
+            None => Contents::default(),
+        };
+        Self::with_contents(path, format, contents)
+    }
+
+    /// Like [`FileStore::open`] for a JSON file sealed with `passphrase`.
+    /// A new file is written straight away, so the passphrase sticks even
+    /// if no contact is ever added.
+    pub fn open_sealed(path: impl Into<PathBuf>, passphrase: &str) -> Result<Self> {
+        let path = path.into();
+        let Some(sealed) = read(&path)? else {
+            let format = Format::Sealed(Key::new(passphrase)?);
+            let store = Self::with_contents(path, format, Contents::default())?;
+            store.save()?;
+            return Ok(store);
+        };
+        let (key, data) = Key::open(passphrase, &sealed)?;
+        let contents = parse(&data, &Format::Json)
+            .map_err(|e| AppError::Database(format!("parse {}: {e}", path.display())))?;
+        Self::with_contents(path, Format::Sealed(key), contents)
+    }
+
+    fn with_contents(path: PathBuf, format: Format, contents: Contents) -> Result<Self> {
+        let mut contacts = contents.contacts;
+        let mut ids = std::collections::HashSet::new();
+        if let Some(id) = contacts.iter().map(|c| c.id).find(|&id| id != 0 && !ids.insert(id)) {
+            let path = path.display();
+            return Err(AppError::Database(format!("{path}: contact id {id} is used twice")));
+        }
+        let mut next_id = ids.into_iter().max().unwrap_or(0);
+        for contact in contacts.iter_mut().filter(|c| c.id == 0) {
+            next_id += 1;
+            contact.id = next_id;
+        }
+        let sort = contents.sort.and_then(|sort| sort.parse().ok());
+        info!("Loaded {} contacts from {}", contacts.len(), path.display());
+
+        Ok(Self {
+            path,
+            format,
+            contacts: MemoryStore::with_contacts(contacts, sort),
+        })
+    }
+
+    /// Seal the file with a new passphrase. Backups made before keep the
+    /// old one.
+    pub fn rekey(&mut self, passphrase: &str) -> Result<()> {
+        if !matches!(self.format, Format::Sealed(_)) {
+            let path = self.path.display();
+            return Err(AppError::Input(format!("{path} isn't encrypted")));
+        }
+        self.format = Format::Sealed(Key::new(passphrase)?);
+        self.save()?;
+        info!("Rekeyed {}", self.path.display());
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write every contact back, replacing the file in one step so a crash
+    /// never leaves it half written.
+    fn save(&self) -> Result<()> {
+        let (contacts, sort) = self.contacts.contents();
+        let contents = Contents {
+            sort: sort.map(|sort| sort.to_string()),
+            contacts,
+        };
+        let text = match &self.format {
+            Format::Toml => toml::to_string_pretty(&contents).map_err(|e| e.to_string()),
+            Format::Json | Format::Sealed(_) => serde_json::to_string_pretty(&contents)
+                .map(|json| json + "\n")
+                .map_err(|e| e.to_string()),
+        }
+        .map_err(|e| AppError::Database(format!("serialize contacts: {e}")))?;
+        let data = match &self.format {
+            Format::Sealed(key) => key.seal(text.as_bytes())?,
+            _ => text.into_bytes(),
+        };
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::Database(format!("create {}: {e}", parent.display())))?;
+        }
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        fs::write(&temp, data)
+            .map_err(|e| AppError::Database(format!("write {}: {e}", self.path.display())))?;
+        fs::rename(&temp, &self.path)
+            .map_err(|e| AppError::Database(format!("replace {}: {e}", self.path.display())))
+    }
+}
+
+/// The bytes in `path`, or `None` if there is no such file.
+fn read(path: &Path) -> Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(AppError::Database(format!("read {}: {e}", path.display()))),
+    }
+}
+
+fn parse(data: &[u8], format: &Format) -> std::result::Result<Contents, String> {
+    match format {
+        Format::Toml => {
+            let text = std::str::from_utf8(data).map_err(|e| e.to_string())?;
+            toml::from_str(text).map_err(|e| e.to_string())
+        }
+        Format::Json | Format::Sealed(_) => {
+            serde_json::from_slice(data).map_err(|e| e.to_string())
+        }
+    }
+}
+
+impl ContactStore for FileStore {
+    fn load_customers_sorted(&self, sort: Sort) -> Result<Vec<Contact>> {
+        self.contacts.load_customers_sorted(sort)
+    }
+
+    fn get_contact(&self, id: i64) -> Result<Contact> {
+        self.contacts.get_contact(id)
+    }
+
+    fn add_contact(&self, contact: Contact) -> Result<i64> {
+        let id = self.contacts.add_contact(contact)?;
+        self.save()?;
+        Ok(id)
+    }
+
+    fn update_contact(&self, id: i64, contact: Contact) -> Result<()> {
+        self.contacts.update_contact(id, contact)?;
+        self.save()
+    }
+
+    fn delete_contact(&self, id: i64) -> Result<()> {
+        self.contacts.delete_contact(id)?;
+        self.save()
+    }
+
+    fn touch_contact(&self, id: i64) -> Result<()> {
+        self.contacts.touch_contact(id)?;
+        self.save()
+    }
+
+    fn set_favorite(&self, id: i64, favorite: bool) -> Result<()> {
+        self.contacts.set_favorite(id, favorite)?;
+        self.save()
+    }
+
+    fn load_sort(&self) -> Result<Sort> {
+        self.contacts.load_sort()
+    }
+
+    fn save_sort(&self, sort: Sort) -> Result<()> {
+        self.contacts.save_sort(sort)?;
+        self.save()
+    }
+
+    fn import_contacts(&mut self, contacts: Vec<Contact>) -> Result<Vec<Contact>> {
+        let imported = self.contacts.import_contacts(contacts)?;
+        self.save()?;
+        Ok(imported)
+    }
+
+    fn hooks(&self) -> &LifecycleHooks {
+        self.contacts.hooks()
+    }
+
+    fn set_hooks(&mut self, hooks: LifecycleHooks) {
+        self.contacts.set_hooks(hooks);
+    }
+
+    fn key(&self) -> Option<&Key> {
+        match &self.format {
+            Format::Sealed(key) => Some(key),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        crypt,
+        model::{SortKey, SortOrder},
+    };
+
+    fn ada() -> Contact {
+        Contact {
+            name: "Ada Lovelace".to_string(),
+            email: Some("ada@example.com".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for name in ["contacts.toml", "contacts.json"] {
+            let path = temp_dir.path().join(name);
+            let store = FileStore::open(&path).unwrap();
+            let id = store.add_contact(ada()).unwrap();
+            store.set_favorite(id, true).unwrap();
+            store.save_sort(Sort::new(SortKey::Company, SortOrder::Desc)).unwrap();
+
+            let reopened = FileStore::open(&path).unwrap();
+            assert_eq!(reopened.get_contact(id).unwrap(), store.get_contact(id).unwrap());
+            assert!(reopened.get_contact(id).unwrap().favorite);
+            assert_eq!(reopened.load_sort().unwrap().key, SortKey::Company);
+        }
+
+        let toml = fs::read_to_string(temp_dir.path().join("contacts.toml")).unwrap();
+        assert!(toml.starts_with("sort = \"company:desc\"\n"), "{toml}");
+        assert!(toml.contains("[[contacts]]\nid = 1\nname = \"Ada Lovelace\"\n"), "{toml}");
+        let json = fs::read_to_string(temp_dir.path().join("contacts.json")).unwrap();
+        assert!(json.contains("\"name\": \"Ada Lovelace\""), "{json}");
+    }
+
+    #[test]
+    fn test_hand_edited_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("contacts.toml");
+        let text = "[[contacts]]\nid = 4\nname = \"Ada Lovelace\"\n\n\
+                    [[contacts]]\nname = \"Alan Turing\"\nphone = \"0412 345 678\"\n";
+        fs::write(&path, text).unwrap();
+
+        let store = FileStore::open(&path).unwrap();
+        let contacts = store.load_customers().unwrap();
+        let ids: Vec<i64> = contacts.iter().map(|c| c.id).collect();
+        assert_eq!(ids, [4, 5]);
+        assert_eq!(contacts[1].phone.as_deref(), Some("0412 345 678"));
+        assert_eq!(store.add_contact(ada()).unwrap(), 6);
+
+        fs::write(&path, "[[contacts]]\nid = 1\nname = \"A\"\n[[contacts]]\nid = 1\nname = \"B\"\n")
+            .unwrap();
+        assert!(FileStore::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_sealed_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("contacts.enc");
+        let mut store = FileStore::open_sealed(&path, "correct horse").unwrap();
+        let id = store.add_contact(ada()).unwrap();
+
+        let sealed = fs::read(&path).unwrap();
+        assert!(crypt::is_sealed(&sealed));
+        assert!(!String::from_utf8_lossy(&sealed).contains("Ada"));
+        assert!(FileStore::open_sealed(&path, "battery staple").is_err());
+        let reopened = FileStore::open_sealed(&path, "correct horse").unwrap();
+        assert_eq!(reopened.get_contact(id).unwrap().name, "Ada Lovelace");
+
+        // Backups are sealed with the same key and restore without asking
+        let backup = temp_dir.path().join("backup.json");
+        store.backup_to_file(&backup).unwrap();
+        assert!(crypt::is_sealed(&fs::read(&backup).unwrap()));
+        assert_eq!(store.restore_from_file(&backup).unwrap().len(), 1);
+
+        store.rekey("battery staple").unwrap();
+        assert!(FileStore::open_sealed(&path, "correct horse").is_err());
+        let reopened = FileStore::open_sealed(&path, "battery staple").unwrap();
+        assert_eq!(reopened.load_customers().unwrap().len(), 2);
+
+        let mut plain = FileStore::open(temp_dir.path().join("contacts.toml")).unwrap();
+        assert!(plain.rekey("battery staple").is_err());
+    }
+}
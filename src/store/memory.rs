@@ -0,0 +1,247 @@
+use std::{cell::RefCell, time::SystemTime};
+
+use tracing::info;
+
+use super::ContactStore;
+use crate::{
+    error::{AppError, AppResult as Result},
+    hooks::LifecycleHooks,
+    model::{Contact, Sort, contact::format_timestamp},
+};
+
+/// Contacts kept in memory for the length of the session, for tests and
+/// demos. Ids are never reused, as with SQLite.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    state: RefCell<State>,
+    hooks: LifecycleHooks,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    contacts: Vec<Contact>,
+    next_id: i64,
+    sort: Option<Sort>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store holding `contacts` as they are, ids included.
+    pub fn with_contacts(contacts: Vec<Contact>, sort: Option<Sort>) -> Self {
+        let next_id = contacts.iter().map(|c| c.id).max().unwrap_or(0);
+        Self {
+            state: RefCell::new(State {
+                contacts,
+                next_id,
+                sort,
+            }),
+            hooks: LifecycleHooks::default(),
+        }
+    }
+
+    /// Every contact in id order, with the saved sort order if any.
+    pub fn contents(&self) -> (Vec<Contact>, Option<Sort>) {
+        let state = self.state.borrow();
+        (state.contacts.clone(), state.sort)
+    }
+
+    /// Apply `change` to contact `id`, or fail with `NotFound`.
+    fn modify(&self, id: i64, change: impl FnOnce(&mut Contact)) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let contact = state
+            .contacts
+            .iter_mut()
+            .find(|c| c.id == id)
+            .ok_or_else(|| AppError::NotFound(format!("contact {id}")))?;
+        change(contact);
+        Ok(())
+    }
+}
+
+fn now() -> Option<String> {
+    Some(format_timestamp(SystemTime::now()))
+}
+
+impl ContactStore for MemoryStore {
+    fn load_customers_sorted(&self, sort: Sort) -> Result<Vec<Contact>> {
+        let mut contacts = self.state.borrow().contacts.clone();
+        contacts.sort_by(|a, b| sort.compare(a, b));
+        Ok(contacts)
+    }
+
+    fn get_contact(&self, id: i64) -> Result<Contact> {
+        let state = self.state.borrow();
+        state
+            .contacts
+            .iter()
+            .find(|c| c.id == id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("contact {id}")))
+    }
+
+    fn add_contact(&self, contact: Contact) -> Result<i64> {
+        info!("Save contact: {:?}", contact);
+        let mut state = self.state.borrow_mut();
+        if let Some(uid) = &contact.uid
+            && state.contacts.iter().any(|c| c.uid.as_ref() == Some(uid))
+        {
+            return Err(AppError::Database(format!("insert: uid {uid} is taken")));
+        }
+        state.next_id += 1;
+        let id = state.next_id;
+        let now = now();
+        state.contacts.push(Contact {
+            id,
+            created_at: now.clone(),
+            updated_at: now,
+            last_used_at: None,
+            use_count: 0,
+            version: 0,
+            ..contact
+        });
+        Ok(id)
+    }
+
+    fn update_contact(&self, id: i64, contact: Contact) -> Result<()> {
+        let mut conflict = false;
+        self.modify(id, |stored| {
+            if stored.version != contact.version {
+                conflict = true;
+                return;
+            }
+            stored.name = contact.name;
+            stored.email = contact.email;
+            stored.phone = contact.phone;
+            stored.company = contact.company;
+            stored.updated_at = now();
+            stored.version += 1;
+        })?;
+        if conflict {
+            return Err(AppError::Conflict(format!("contact {id}")));
+        }
+        info!("Updated contact with id: {}", id);
+        Ok(())
+    }
+
+    fn delete_contact(&self, id: i64) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let count = state.contacts.len();
+        state.contacts.retain(|c| c.id != id);
+        if state.contacts.len() == count {
+            return Err(AppError::NotFound(format!("contact {id}")));
+        }
+        info!("Deleted contact with id: {}", id);
+        Ok(())
+    }
+
+    fn touch_contact(&self, id: i64) -> Result<()> {
+        self.modify(id, |contact| {
+            contact.last_used_at = now();
+            contact.use_count += 1;
+        })
+    }
+
+    fn set_favorite(&self, id: i64, favorite: bool) -> Result<()> {
+        self.modify(id, |contact| contact.favorite = favorite)
+    }
+
+    fn load_sort(&self) -> Result<Sort> {
+        Ok(self.state.borrow().sort.unwrap_or_default())
+    }
+
+    fn save_sort(&self, sort: Sort) -> Result<()> {
+        self.state.borrow_mut().sort = Some(sort);
+        Ok(())
+    }
+
+    fn import_contacts(&mut self, contacts: Vec<Contact>) -> Result<Vec<Contact>> {
+        let mut imported = Vec::new();
+        for contact in contacts {
+            if let Err(validation_err) = contact.validate() {
+                info!("Skipping invalid contact: {}", validation_err);
+                continue;
+            }
+            // Imports never carry a CardDAV resource name over, as in SQLite
+            let id = self.add_contact(Contact { uid: None, ..contact })?;
+            imported.push(self.get_contact(id)?);
+        }
+        Ok(imported)
+    }
+
+    fn hooks(&self) -> &LifecycleHooks {
+        &self.hooks
+    }
+
+    fn set_hooks(&mut self, hooks: LifecycleHooks) {
+        self.hooks = hooks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{SortKey, SortOrder};
+
+    fn contact(name: &str, email: &str) -> Contact {
+        Contact {
+            name: name.to_string(),
+            email: Some(email.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_crud() {
+        let store = MemoryStore::new();
+        let id = store.add_contact(contact("Ada Lovelace", "ada@example.com")).unwrap();
+        let stored = store.get_contact(id).unwrap();
+        assert_eq!(stored.name, "Ada Lovelace");
+        assert!(stored.created_at.is_some());
+
+        let renamed = contact("Ada King", "ada@example.com");
+        store.update_contact(id, renamed.clone()).unwrap();
+        // The same edit again is made to a version that's gone
+        let stale = store.update_contact(id, renamed);
+        assert!(matches!(stale, Err(AppError::Conflict(_))));
+        store.set_favorite(id, true).unwrap();
+        store.touch_contact(id).unwrap();
+        let stored = store.get_contact(id).unwrap();
+        assert_eq!(stored.name, "Ada King");
+        assert_eq!((stored.favorite, stored.use_count), (true, 1));
+
+        store.delete_contact(id).unwrap();
+        assert!(matches!(store.get_contact(id), Err(AppError::NotFound(_))));
+        assert!(matches!(store.delete_contact(id), Err(AppError::NotFound(_))));
+        // Ids aren't reused after a delete
+        assert_eq!(store.add_contact(contact("Alan Turing", "alan@example.com")).unwrap(), id + 1);
+    }
+
+    #[test]
+    fn test_search_and_sort() {
+        let mut store = MemoryStore::new();
+        let contacts = vec![
+            contact("Grace Hopper", "grace@navy.example"),
+            contact("Ada Lovelace", "ada@example.com"),
+            contact("", "nobody@example.com"),
+        ];
+        assert_eq!(store.import_contacts(contacts).unwrap().len(), 2);
+
+        let names = |contacts: Vec<Contact>| -> Vec<String> {
+            contacts.into_iter().map(|c| c.name).collect()
+        };
+        assert_eq!(names(store.load_customers().unwrap()), ["Ada Lovelace", "Grace Hopper"]);
+        assert_eq!(names(store.search_contacts("NAVY").unwrap()), ["Grace Hopper"]);
+        let by_email = store.search_by_field("email", "example.com").unwrap();
+        assert_eq!(names(by_email), ["Ada Lovelace"]);
+        assert!(store.search_by_field("notes", "x").is_err());
+
+        let sort = Sort::new(SortKey::Name, SortOrder::Desc);
+        store.save_sort(sort).unwrap();
+        assert_eq!(store.load_sort().unwrap(), sort);
+        let sorted = store.load_customers_sorted(sort).unwrap();
+        assert_eq!(names(sorted), ["Grace Hopper", "Ada Lovelace"]);
+    }
+}
@@ -0,0 +1,325 @@
+//! Where contacts are kept. [`ContactStore`] covers what the UI and the CLI
+//! need; [`Db`] keeps them in SQLite, [`MemoryStore`] for the length of a
+//! session and [`FileStore`] in a TOML or JSON file meant to be read and
+//! edited by hand, or sealed with a passphrase.
+
+pub mod file;
+pub mod memory;
+
+use std::{fmt, path::Path};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+pub use file::FileStore;
+pub use memory::MemoryStore;
+
+use crate::{
+    Db,
+    config::Config,
+    crypt::{self, Key},
+    error::{AppError, AppResult as Result},
+    hooks::LifecycleHooks,
+    model::{Contact, Sort},
+};
+
+/// Which [`ContactStore`] `config.toml` asks for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreKind {
+    /// SQLite at `database_path`.
+    #[default]
+    Sqlite,
+    /// A TOML or JSON file at `contacts_file`.
+    File,
+    /// Nothing survives the session, for demos.
+    Memory,
+    /// A JSON file at `contacts_file` sealed with a passphrase.
+    Encrypted,
+}
+
+impl StoreKind {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for StoreKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreKind::Sqlite => write!(f, "sqlite"),
+            StoreKind::File => write!(f, "file"),
+            StoreKind::Memory => write!(f, "memory"),
+            StoreKind::Encrypted => write!(f, "encrypted"),
+        }
+    }
+}
+
+/// Open the store `config` asks for, with its lifecycle hooks.
+pub fn open(config: &Config) -> Result<Box<dyn ContactStore>> {
+    let mut store: Box<dyn ContactStore> = match config.store {
+        StoreKind::Sqlite => Box::new(Db::open(&config.database_path)?),
+        StoreKind::File => Box::new(FileStore::open(config.contacts_file())?),
+        StoreKind::Memory => Box::new(MemoryStore::new()),
+        StoreKind::Encrypted => Box::new(open_encrypted(config)?),
+    };
+    store.set_hooks(config.hooks.clone());
+    Ok(store)
+}
+
+/// The `store = "encrypted"` file, asking for its passphrase, or for a new
+/// one if the file doesn't exist yet.
+pub fn open_encrypted(config: &Config) -> Result<FileStore> {
+    let path = config.contacts_file();
+    let passphrase = if path.exists() {
+        crypt::passphrase(&format!("Passphrase for {}: ", path.display()))?
+    } else {
+        info!("Creating an encrypted address book at {}", path.display());
+        crypt::new_passphrase()?
+    };
+    FileStore::open_sealed(path, &passphrase)
+}
+
+/// The contact operations the UI and the CLI use. Writes take `&self` so a
+/// store can be shared behind a lock; implementations that keep contacts in
+/// memory use interior mutability.
+pub trait ContactStore: Send {
+    fn load_customers_sorted(&self, sort: Sort) -> Result<Vec<Contact>>;
+
+    fn get_contact(&self, id: i64) -> Result<Contact>;
+
+    /// Insert the contact and return its new id.
+    fn add_contact(&self, contact: Contact) -> Result<i64>;
+
+    /// Replace the name, email, phone and company of contact `id` and bump
+    /// its version. Fails with [`AppError::Conflict`] if `contact.version`
+    /// is no longer the stored one.
+    fn update_contact(&self, id: i64, contact: Contact) -> Result<()>;
+
+    fn delete_contact(&self, id: i64) -> Result<()>;
+
+    /// Record that a contact was just picked from the list.
+    fn touch_contact(&self, id: i64) -> Result<()>;
+
+    /// Star or unstar a contact.
+    fn set_favorite(&self, id: i64, favorite: bool) -> Result<()>;
+
+    /// The sort order chosen in the last session, or the default if none
+    /// was saved.
+    fn load_sort(&self) -> Result<Sort>;
+
+    fn save_sort(&self, sort: Sort) -> Result<()>;
+
+    /// Insert every valid contact and return them with their new ids.
+    /// Invalid contacts are skipped.
+    fn import_contacts(&mut self, contacts: Vec<Contact>) -> Result<Vec<Contact>>;
+
+    /// Commands to run after writes. The store only carries them to whoever
+    /// does the writing; see [`LifecycleHooks::fire`].
+    fn hooks(&self) -> &LifecycleHooks;
+
+    fn set_hooks(&mut self, hooks: LifecycleHooks);
+
+    /// The key an encrypted store is sealed with. Backups are sealed with
+    /// it too.
+    fn key(&self) -> Option<&Key> {
+        None
+    }
+
+    /// Whether someone else has changed the contacts since the last call,
+    /// so the list should be reloaded. Stores only this process writes to
+    /// never are.
+    fn changed_elsewhere(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn load_customers(&self) -> Result<Vec<Contact>> {
+        self.load_customers_sorted(Sort::default())
+    }
+
+    /// Contacts with `query` in their name, company, email or phone,
+    /// ignoring case.
+    fn search_contacts(&self, query: &str) -> Result<Vec<Contact>> {
+        let query = query.to_lowercase();
+        let mut contacts = self.load_customers()?;
+        contacts.retain(|contact| {
+            let fields = [Some(&contact.name), contact.company.as_ref(), contact.email.as_ref()];
+            fields
+                .into_iter()
+                .chain([contact.phone.as_ref()])
+                .flatten()
+                .any(|field| field.to_lowercase().contains(&query))
+        });
+        Ok(contacts)
+    }
+
+    /// Contacts with `query` in `field` (name, company, email or phone),
+    /// ignoring case.
+    fn search_by_field(&self, field: &str, query: &str) -> Result<Vec<Contact>> {
+        let value = match field {
+            "name" => |c: &Contact| Some(c.name.clone()),
+            "company" => |c: &Contact| c.company.clone(),
+            "email" => |c: &Contact| c.email.clone(),
+            "phone" => |c: &Contact| c.phone.clone(),
+            _ => return Err(AppError::Database(format!("invalid search field: {field}"))),
+        };
+        let query = query.to_lowercase();
+        let mut contacts = self.load_customers()?;
+        contacts.retain(|contact| {
+            value(contact).is_some_and(|value| value.to_lowercase().contains(&query))
+        });
+        Ok(contacts)
+    }
+
+    /// Groups of contacts that look like the same person.
+    fn find_potential_duplicates(&self) -> Result<Vec<Vec<Contact>>> {
+        Ok(Db::group_duplicates(&self.load_customers()?))
+    }
+
+    /// Add `count` made-up contacts.
+    fn seed(&mut self, count: u32) -> Result<()> {
+        self.import_contacts(fake_contacts(count)).map(|_| ())
+    }
+
+    fn export_contacts_json(&self) -> Result<String> {
+        let contacts = self.load_customers()?;
+        serde_json::to_string_pretty(&contacts)
+            .map_err(|e| AppError::Database(format!("export json: {e}")))
+    }
+
+    /// Insert every valid contact from a JSON array and return them with
+    /// their new ids. Invalid contacts are skipped.
+    fn import_contacts_json(&mut self, json_data: &str) -> Result<Vec<Contact>> {
+        let contacts: Vec<Contact> = serde_json::from_str(json_data)
+            .map_err(|e| AppError::Database(format!("parse json: {e}")))?;
+        let imported = self.import_contacts(contacts)?;
+        info!("Imported {} contacts", imported.len());
+        Ok(imported)
+    }
+
+    /// Write every contact to `backup_path` as JSON, sealed if the store
+    /// is encrypted.
+    fn backup_to_file(&self, backup_path: &Path) -> Result<()> {
+        let json_data = self.export_contacts_json()?;
+        let data = match self.key() {
+            Some(key) => key.seal(json_data.as_bytes())?,
+            None => json_data.into_bytes(),
+        };
+        std::fs::write(backup_path, data)
+            .map_err(|e| AppError::Database(format!("write backup: {e}")))
+    }
+
+    /// Import a JSON backup. A sealed one opens with the store's own key if
+    /// it was made with it, or else asks for its passphrase.
+    fn restore_from_file(&mut self, backup_path: &Path) -> Result<Vec<Contact>> {
+        let mut data = std::fs::read(backup_path)
+            .map_err(|e| AppError::Database(format!("read backup: {e}")))?;
+        if crypt::is_sealed(&data) {
+            data = match self.key().map(|key| key.unseal(&data)).transpose()?.flatten() {
+                Some(data) => data,
+                None => {
+                    let prompt = format!("Passphrase for {}: ", backup_path.display());
+                    Key::open(&crypt::passphrase(&prompt)?, &data)?.1
+                }
+            };
+        }
+        let json_data = String::from_utf8(data)
+            .map_err(|e| AppError::Database(format!("read backup: {e}")))?;
+        self.import_contacts_json(&json_data)
+    }
+}
+
+/// Lets [`open`]'s result go wherever a concrete store would.
+impl<S: ContactStore + ?Sized> ContactStore for Box<S> {
+    fn load_customers_sorted(&self, sort: Sort) -> Result<Vec<Contact>> {
+        (**self).load_customers_sorted(sort)
+    }
+
+    fn get_contact(&self, id: i64) -> Result<Contact> {
+        (**self).get_contact(id)
+    }
+
+    fn add_contact(&self, contact: Contact) -> Result<i64> {
+        (**self).add_contact(contact)
+    }
+
+    fn update_contact(&self, id: i64, contact: Contact) -> Result<()> {
+        (**self).update_contact(id, contact)
+    }
+
+    fn delete_contact(&self, id: i64) -> Result<()> {
+        (**self).delete_contact(id)
+    }
+
+    fn touch_contact(&self, id: i64) -> Result<()> {
+        (**self).touch_contact(id)
+    }
+
+    fn set_favorite(&self, id: i64, favorite: bool) -> Result<()> {
+        (**self).set_favorite(id, favorite)
+    }
+
+    fn load_sort(&self) -> Result<Sort> {
+        (**self).load_sort()
+    }
+
+    fn save_sort(&self, sort: Sort) -> Result<()> {
+        (**self).save_sort(sort)
+    }
+
+    fn import_contacts(&mut self, contacts: Vec<Contact>) -> Result<Vec<Contact>> {
+        (**self).import_contacts(contacts)
+    }
+
+    fn hooks(&self) -> &LifecycleHooks {
+        (**self).hooks()
+    }
+
+    fn set_hooks(&mut self, hooks: LifecycleHooks) {
+        (**self).set_hooks(hooks)
+    }
+
+    fn key(&self) -> Option<&Key> {
+        (**self).key()
+    }
+
+    fn search_contacts(&self, query: &str) -> Result<Vec<Contact>> {
+        (**self).search_contacts(query)
+    }
+
+    fn search_by_field(&self, field: &str, query: &str) -> Result<Vec<Contact>> {
+        (**self).search_by_field(field, query)
+    }
+
+    fn seed(&mut self, count: u32) -> Result<()> {
+        (**self).seed(count)
+    }
+
+    fn changed_elsewhere(&self) -> Result<bool> {
+        (**self).changed_elsewhere()
+    }
+}
+
+/// `count` contacts with made-up names, emails, phones and companies.
+pub fn fake_contacts(count: u32) -> Vec<Contact> {
+    let mut rng = rand::rng();
+    let mut name_gen = names::Generator::default();
+    let mut company_gen = names::Generator::with_naming(names::Name::Numbered);
+
+    (0..count)
+        .map(|_| {
+            let name = name_gen.next().unwrap_or("John Doe".into());
+            let email = format!("{}@example.com", name.replace(' ', "").to_lowercase());
+            let phone = format!("04{:08}", rng.random_range(0..=99999999));
+            let company = format!("{} Pty Ltd", company_gen.next().unwrap_or("Acme".into()));
+            Contact {
+                name,
+                email: Some(email),
+                phone: Some(phone),
+                company: Some(company),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,468 @@
+//! `rolodex sync`: two-way sync with an address book on a CardDAV server.
+//!
+//! Each synced contact keeps the href and ETag of its remote card and the
+//! vCard it had locally after the last sync. A changed ETag means the card
+//! was edited remotely and a different vCard means the contact was edited
+//! locally; when both happened the [`Conflict`] policy decides.
+
+pub mod remote;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    process::Command,
+};
+
+use clap::ValueEnum;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+pub use remote::{Expect, Remote};
+
+use crate::{
+    Db,
+    error::{AppError, AppResult as Result},
+    hooks::Event,
+    model::Contact,
+    server::http::percent_decode,
+    vcard::{from_vcard, merge, to_vcard},
+};
+
+/// Setting holding the collection URL the sync state belongs to.
+const URL_SETTING: &str = "sync_url";
+
+/// The `[sync]` table from `config.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncConfig {
+    /// The CardDAV collection, e.g.
+    /// `https://dav.example.com/addressbooks/team/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// Command printing the password, such as `pass show dav`, to keep it
+    /// out of the config file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_command: Option<String>,
+    #[serde(default)]
+    pub conflict: Conflict,
+}
+
+impl SyncConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The collection to sync with, running `password_command` if set.
+    pub fn remote(&self) -> Result<Remote> {
+        let url = self
+            .url
+            .as_deref()
+            .ok_or_else(|| AppError::Config("no url in the [sync] table".to_string()))?;
+        let password = match &self.password_command {
+            Some(command) => Some(password_from(command)?),
+            None => self.password.clone(),
+        };
+        Ok(Remote::new(url, self.username.as_deref(), password.as_deref()))
+    }
+}
+
+fn password_from(command: &str) -> Result<String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| AppError::Command(format!("run {command}: {e}")))?;
+    if !output.status.success() {
+        return Err(AppError::Command(format!("{command} failed with {}", output.status)));
+    }
+    let password = String::from_utf8_lossy(&output.stdout);
+    Ok(password.lines().next().unwrap_or_default().to_string())
+}
+
+/// What to do with a contact changed on both sides since the last sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Conflict {
+    /// Leave both sides as they are and report it again next time.
+    #[default]
+    Skip,
+    /// Keep the local contact and overwrite the remote card.
+    Local,
+    /// Keep the remote card and overwrite the local contact.
+    Remote,
+}
+
+/// A contact as it was on both sides after the last sync.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncState {
+    pub contact_id: i64,
+    pub href: String,
+    pub etag: String,
+    /// The contact as a vCard, without a UID.
+    pub card: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Added,
+    Updated,
+    Deleted,
+}
+
+/// One thing a sync did, named by contact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Pulled(Kind, String),
+    Pushed(Kind, String),
+    /// Changed on both sides, settled by the policy.
+    Conflict(String, Conflict),
+    Failed(String, String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = |kind: &Kind| match kind {
+            Kind::Added => "added",
+            Kind::Updated => "updated",
+            Kind::Deleted => "deleted",
+        };
+        match self {
+            Change::Pulled(k, name) => write!(f, "pulled: {} {name}", kind(k)),
+            Change::Pushed(k, name) => write!(f, "pushed: {} {name}", kind(k)),
+            Change::Conflict(name, policy) => {
+                let outcome = match policy {
+                    Conflict::Skip => "skipped",
+                    Conflict::Local => "kept the local contact",
+                    Conflict::Remote => "kept the remote card",
+                };
+                write!(f, "conflict: {name} changed on both sides, {outcome}")
+            }
+            Change::Failed(name, reason) => write!(f, "failed: {name}: {reason}"),
+        }
+    }
+}
+
+/// One line totting up `changes`.
+pub fn summary(changes: &[Change]) -> String {
+    if changes.is_empty() {
+        return "Already in sync".to_string();
+    }
+    let count = |f: fn(&Change) -> bool| changes.iter().filter(|change| f(change)).count();
+    format!(
+        "{} pulled, {} pushed, {} conflicts, {} failed",
+        count(|c| matches!(c, Change::Pulled(..))),
+        count(|c| matches!(c, Change::Pushed(..))),
+        count(|c| matches!(c, Change::Conflict(..))),
+        count(|c| matches!(c, Change::Failed(..))),
+    )
+}
+
+/// Forget earlier syncs, so every contact on both sides counts as new.
+pub fn reset(db: &Db) -> Result<()> {
+    db.clear_sync_state()
+}
+
+/// Bring `db` and `remote` up to date with each other. Failures for single
+/// contacts are reported as changes and retried on the next sync.
+pub fn sync(db: &Db, remote: &Remote, policy: Conflict) -> Result<Vec<Change>> {
+    let states = db.sync_states()?;
+    match db.get_setting(URL_SETTING)? {
+        Some(previous) if previous != remote.url() && !states.is_empty() => {
+            return Err(AppError::Sync(format!(
+                "contacts were synced with {previous}, run with --reset to sync with {} instead",
+                remote.url()
+            )));
+        }
+        _ => db.set_setting(URL_SETTING, remote.url())?,
+    }
+
+    let listing: HashMap<String, String> = remote.list()?.into_iter().collect();
+    let mut contacts: HashMap<i64, Contact> = db
+        .load_customers()?
+        .into_iter()
+        .map(|contact| (contact.id, contact))
+        .collect();
+    let mut run = Run {
+        db,
+        remote,
+        policy,
+        changes: Vec::new(),
+    };
+
+    let mut known = HashSet::new();
+    for state in &states {
+        known.insert(state.href.as_str());
+        let local = contacts.remove(&state.contact_id);
+        let name = match &local {
+            Some(contact) => contact.name.clone(),
+            None => from_vcard(&state.card).map(|c| c.name).unwrap_or_default(),
+        };
+        let remote_etag = listing.get(&state.href).map(String::as_str);
+        let result = run.reconcile(state, local, remote_etag, &name);
+        run.record(&name, result);
+    }
+
+    let mut added: Vec<(&String, &String)> = listing
+        .iter()
+        .filter(|(href, _)| !known.contains(href.as_str()))
+        .collect();
+    added.sort();
+    for (href, etag) in added {
+        let result = run
+            .pull(href, etag, None)
+            .map(|contact| Some(Change::Pulled(Kind::Added, contact.name)));
+        run.record(href, result);
+    }
+
+    let mut created: Vec<Contact> = contacts.into_values().collect();
+    created.sort_by_key(|contact| contact.id);
+    for contact in created {
+        let href = format!("{}{}.vcf", remote.path(), new_uid());
+        let result = run
+            .push(&contact, &href, None)
+            .map(|()| Some(Change::Pushed(Kind::Added, contact.name.clone())));
+        run.record(&contact.name, result);
+    }
+
+    Ok(run.changes)
+}
+
+struct Run<'a> {
+    db: &'a Db,
+    remote: &'a Remote,
+    policy: Conflict,
+    changes: Vec<Change>,
+}
+
+impl Run<'_> {
+    fn record(&mut self, name: &str, result: Result<Option<Change>>) {
+        match result {
+            Ok(Some(change)) => {
+                info!("Sync {change}");
+                self.changes.push(change);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                warn!("Sync of {name} failed: {err}");
+                self.changes.push(Change::Failed(name.to_string(), err.to_string()));
+            }
+        }
+    }
+
+    /// Carry over whatever changed on one side of a contact synced before.
+    fn reconcile(
+        &self,
+        state: &SyncState,
+        local: Option<Contact>,
+        remote_etag: Option<&str>,
+        name: &str,
+    ) -> Result<Option<Change>> {
+        let local_changed = local.as_ref().is_none_or(|c| local_card(c) != state.card);
+        let remote_changed = remote_etag != Some(state.etag.as_str());
+        let change = |change: fn(Kind, String) -> Change, kind| Ok(Some(change(kind, name.into())));
+
+        match (local, remote_etag) {
+            (None, None) => {
+                self.db.delete_sync_state(state.contact_id)?;
+                Ok(None)
+            }
+            (Some(_), Some(_)) if !local_changed && !remote_changed => Ok(None),
+            (None, Some(etag)) if !remote_changed => {
+                self.remote.delete(&state.href, etag)?;
+                self.db.delete_sync_state(state.contact_id)?;
+                change(Change::Pushed, Kind::Deleted)
+            }
+            (Some(contact), None) if !local_changed => {
+                self.delete_local(&contact)?;
+                change(Change::Pulled, Kind::Deleted)
+            }
+            (Some(contact), Some(etag)) if !remote_changed => {
+                self.push(&contact, &state.href, Some(etag))?;
+                change(Change::Pushed, Kind::Updated)
+            }
+            (Some(contact), Some(etag)) if !local_changed => {
+                let pulled = self.pull(&state.href, etag, Some(contact.id))?;
+                // Servers may change the ETag without changing the card
+                if local_card(&pulled) == state.card {
+                    return Ok(None);
+                }
+                Ok(Some(Change::Pulled(Kind::Updated, pulled.name)))
+            }
+            (local, remote_etag) => self.conflict(state, local, remote_etag, name),
+        }
+    }
+
+    fn conflict(
+        &self,
+        state: &SyncState,
+        local: Option<Contact>,
+        remote_etag: Option<&str>,
+        name: &str,
+    ) -> Result<Option<Change>> {
+        if let (Some(contact), Some(etag)) = (&local, remote_etag) {
+            let (card, current) = self.remote.get(&state.href)?;
+            // Both sides made the same edit
+            if from_vcard(&card).is_ok_and(|remote| local_card(&remote) == local_card(contact)) {
+                self.db.save_sync_state(&SyncState {
+                    etag: current.unwrap_or_else(|| etag.to_string()),
+                    card: local_card(contact),
+                    ..state.clone()
+                })?;
+                return Ok(None);
+            }
+        }
+
+        match (self.policy, local, remote_etag) {
+            (Conflict::Skip, ..) | (_, None, None) => {}
+            (Conflict::Local, Some(contact), etag) => self.push(&contact, &state.href, etag)?,
+            (Conflict::Local, None, Some(etag)) => {
+                self.remote.delete(&state.href, etag)?;
+                self.db.delete_sync_state(state.contact_id)?;
+            }
+            (Conflict::Remote, local, Some(etag)) => {
+                self.pull(&state.href, etag, local.map(|contact| contact.id))?;
+            }
+            (Conflict::Remote, Some(contact), None) => self.delete_local(&contact)?,
+        }
+        Ok(Some(Change::Conflict(name.to_string(), self.policy)))
+    }
+
+    /// Store the remote card at `href` locally, over contact `id` if given.
+    fn pull(&self, href: &str, listed_etag: &str, id: Option<i64>) -> Result<Contact> {
+        let (card, etag) = self.remote.get(href)?;
+        let contact = Contact {
+            uid: None,
+            ..from_vcard(&card)?
+        };
+        contact
+            .validate()
+            .map_err(|e| AppError::Input(e.to_string()))?;
+
+        let (event, id) = match id {
+            Some(id) => {
+                let version = self.db.get_contact(id)?.version;
+                self.db.update_contact(id, Contact { version, ..contact })?;
+                (Event::Update, id)
+            }
+            None => (Event::Add, self.db.add_contact(contact)?),
+        };
+        let stored = self.db.get_contact(id)?;
+        self.db.save_sync_state(&SyncState {
+            contact_id: id,
+            href: href.to_string(),
+            etag: etag.unwrap_or_else(|| listed_etag.to_string()),
+            card: local_card(&stored),
+        })?;
+        self.fire(event, &stored);
+        Ok(stored)
+    }
+
+    /// Send a local contact to `href`. An existing card, last seen with
+    /// `etag`, keeps the properties a contact doesn't hold.
+    fn push(&self, contact: &Contact, href: &str, etag: Option<&str>) -> Result<()> {
+        let etag = match etag {
+            Some(listed) => {
+                let (card, current) = self.remote.get(href)?;
+                let current = current.unwrap_or_else(|| listed.to_string());
+                let card = merge(&card, contact);
+                self.remote.put(href, &card, Expect::Etag(&current))?
+            }
+            None => {
+                let name = href.rsplit('/').next().unwrap_or_default();
+                let uid = percent_decode(name.strip_suffix(".vcf").unwrap_or(name), false);
+                let card = to_vcard(&Contact {
+                    uid: Some(uid),
+                    ..contact.clone()
+                });
+                self.remote.put(href, &card, Expect::Missing)?
+            }
+        };
+        // Servers that rewrite the card send no ETag for it
+        let etag = match etag {
+            Some(etag) => etag,
+            None => self
+                .remote
+                .get(href)?
+                .1
+                .ok_or_else(|| AppError::Sync(format!("no ETag for {href}")))?,
+        };
+        self.db.save_sync_state(&SyncState {
+            contact_id: contact.id,
+            href: href.to_string(),
+            etag,
+            card: local_card(contact),
+        })
+    }
+
+    fn delete_local(&self, contact: &Contact) -> Result<()> {
+        self.db.delete_contact(contact.id)?;
+        self.db.delete_sync_state(contact.id)?;
+        self.fire(Event::Delete, contact);
+        Ok(())
+    }
+
+    /// Local writes run the lifecycle hooks like any other. A failed hook
+    /// doesn't fail the sync.
+    fn fire(&self, event: Event, contact: &Contact) {
+        if let Err(err) = self.db.hooks().fire(event, std::slice::from_ref(contact)) {
+            warn!("{err}");
+        }
+    }
+}
+
+/// The fields a contact holds as a vCard, for telling whether it changed.
+fn local_card(contact: &Contact) -> String {
+    to_vcard(&Contact {
+        uid: None,
+        ..contact.clone()
+    })
+}
+
+/// A random (version 4) UUID for cards created by a sync.
+fn new_uid() -> String {
+    let n: u128 = rand::rng().random();
+    let n = (n & !(0xf << 76)) | (0x4 << 76);
+    let n = (n & !(0x3 << 62)) | (0x2 << 62);
+    let hex = format!("{n:032x}");
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_uid_format() {
+        let uid = new_uid();
+        let parts: Vec<&str> = uid.split('-').collect();
+        assert_eq!(parts.iter().map(|p| p.len()).collect::<Vec<_>>(), [8, 4, 4, 4, 12]);
+        assert!(parts[2].starts_with('4'));
+        assert!(matches!(parts[3].chars().next(), Some('8' | '9' | 'a' | 'b')));
+        assert_ne!(new_uid(), uid);
+    }
+
+    #[test]
+    fn test_change_log() {
+        let changes = [
+            Change::Pulled(Kind::Added, "Ada".to_string()),
+            Change::Conflict("Alan".to_string(), Conflict::Remote),
+            Change::Failed("Grace".to_string(), "Sync error: 500".to_string()),
+        ];
+        let lines: Vec<String> = changes.iter().map(Change::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "pulled: added Ada",
+                "conflict: Alan changed on both sides, kept the remote card",
+                "failed: Grace: Sync error: 500",
+            ]
+        );
+        assert_eq!(summary(&changes), "1 pulled, 0 pushed, 1 conflicts, 1 failed");
+        assert_eq!(summary(&[]), "Already in sync");
+    }
+}
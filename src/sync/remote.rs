@@ -0,0 +1,200 @@
+//! The WebDAV requests a sync makes against the remote collection.
+
+use std::time::Duration;
+
+use crate::{
+    clipboard::base64,
+    error::{AppError, AppResult as Result},
+    xml,
+};
+
+const DAV: &str = "DAV:";
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+const LIST_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getetag/></d:prop></d:propfind>"#;
+
+/// What the server must hold for a write to go ahead.
+pub enum Expect<'a> {
+    /// Nothing, so an existing card isn't overwritten.
+    Missing,
+    /// The card as last seen, so changes made since aren't lost.
+    Etag(&'a str),
+}
+
+/// A CardDAV collection on a server.
+pub struct Remote {
+    agent: ureq::Agent,
+    /// Collection URL, ending in `/`.
+    url: String,
+    authorization: Option<String>,
+}
+
+impl Remote {
+    /// With a `password`, requests use HTTP Basic auth.
+    pub fn new(url: &str, username: Option<&str>, password: Option<&str>) -> Self {
+        let url = match url.ends_with('/') {
+            true => url.to_string(),
+            false => format!("{url}/"),
+        };
+        let authorization = password.map(|password| {
+            let credentials = format!("{}:{password}", username.unwrap_or_default());
+            format!("Basic {}", base64(credentials.as_bytes()))
+        });
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            url,
+            authorization,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Path of the collection, which starts the href of every card in it.
+    pub fn path(&self) -> &str {
+        &self.url[origin(&self.url).len()..]
+    }
+
+    /// Every card in the collection, as href and ETag.
+    pub fn list(&self) -> Result<Vec<(String, String)>> {
+        let request = self
+            .request("PROPFIND", &self.url)
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml; charset=utf-8");
+        let text = read(self.send(request, Some(LIST_BODY))?)?;
+        let root = xml::parse(&text)
+            .map_err(|e| AppError::Sync(format!("listing from {} is not XML: {e}", self.url)))?;
+
+        let mut cards = Vec::new();
+        for response in root.children_named(DAV, "response") {
+            let Some(href) = response.child(DAV, "href") else {
+                continue;
+            };
+            let href = href.text.trim();
+            let href = &href[origin(href).len()..];
+            let (mut etag, mut collection) = (None, false);
+            let props = response
+                .children_named(DAV, "propstat")
+                .filter(|propstat| {
+                    propstat
+                        .child(DAV, "status")
+                        .is_none_or(|status| status.text.contains(" 200 "))
+                })
+                .filter_map(|propstat| propstat.child(DAV, "prop"));
+            for prop in props {
+                if let Some(tag) = prop.child(DAV, "getetag") {
+                    etag = Some(tag.text.trim().to_string());
+                }
+                if let Some(kind) = prop.child(DAV, "resourcetype") {
+                    collection |= kind.child(DAV, "collection").is_some();
+                }
+            }
+            if let Some(etag) = etag.filter(|_| !collection && href != self.path()) {
+                cards.push((href.to_string(), etag));
+            }
+        }
+        Ok(cards)
+    }
+
+    /// The card at `href` and its ETag, if the server sent one.
+    pub fn get(&self, href: &str) -> Result<(String, Option<String>)> {
+        let response = self.send(self.request("GET", &self.url_for(href)), None)?;
+        let etag = response.header("ETag").map(str::to_string);
+        Ok((read(response)?, etag))
+    }
+
+    /// Store `card` at `href` and return its new ETag, if the server sent one.
+    pub fn put(&self, href: &str, card: &str, expect: Expect) -> Result<Option<String>> {
+        let request = self
+            .request("PUT", &self.url_for(href))
+            .set("Content-Type", "text/vcard; charset=utf-8");
+        let request = match expect {
+            Expect::Missing => request.set("If-None-Match", "*"),
+            Expect::Etag(etag) => request.set("If-Match", etag),
+        };
+        let response = self.send(request, Some(card))?;
+        Ok(response.header("ETag").map(str::to_string))
+    }
+
+    /// Delete the card at `href` unless it changed since `etag`. A card
+    /// that is already gone counts as deleted.
+    pub fn delete(&self, href: &str, etag: &str) -> Result<()> {
+        let request = self.request("DELETE", &self.url_for(href)).set("If-Match", etag);
+        match self.send(request, None) {
+            Ok(_) => Ok(()),
+            Err(AppError::NotFound(_)) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn url_for(&self, href: &str) -> String {
+        match href.contains("://") {
+            true => href.to_string(),
+            false => format!("{}{href}", origin(&self.url)),
+        }
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    fn send(&self, request: ureq::Request, body: Option<&str>) -> Result<ureq::Response> {
+        let what = format!("{} {}", request.method(), request.url());
+        let result = match body {
+            Some(body) => request.send_string(body),
+            None => request.call(),
+        };
+        result.map_err(|err| match err {
+            ureq::Error::Status(404, _) => AppError::NotFound(what),
+            ureq::Error::Status(412, _) => {
+                AppError::Sync(format!("{what}: changed on the server since it was listed"))
+            }
+            ureq::Error::Status(code, response) => {
+                AppError::Sync(format!("{what}: {code} {}", response.status_text()))
+            }
+            ureq::Error::Transport(e) => AppError::Sync(format!("{what}: {e}")),
+        })
+    }
+}
+
+fn read(response: ureq::Response) -> Result<String> {
+    response
+        .into_string()
+        .map_err(|e| AppError::Sync(format!("read response: {e}")))
+}
+
+/// `https://host:port` of a URL, or nothing for a bare path.
+fn origin(url: &str) -> &str {
+    let Some(scheme) = url.find("://") else {
+        return "";
+    };
+    match url[scheme + 3..].find('/') {
+        Some(slash) => &url[..scheme + 3 + slash],
+        None => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls() {
+        let remote = Remote::new("https://dav.example.com:8443/books/team", None, None);
+        assert_eq!(remote.url(), "https://dav.example.com:8443/books/team/");
+        assert_eq!(remote.path(), "/books/team/");
+        assert_eq!(
+            remote.url_for("/books/team/a.vcf"),
+            "https://dav.example.com:8443/books/team/a.vcf"
+        );
+        assert_eq!(remote.url_for("http://other/a.vcf"), "http://other/a.vcf");
+        assert_eq!(origin("http://host"), "http://host");
+        assert_eq!(origin("/only/a/path"), "");
+    }
+}
@@ -0,0 +1,362 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{AppError, AppResult as Result};
+
+/// Every colour used by the UI, grouped by the element it styles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Border of the focused input box
+    pub border_focused: Style,
+    /// Text typed into the focused input
+    pub input: Style,
+    /// Labels in front of inline form fields
+    pub label: Style,
+    /// Column headings in the contact list
+    pub header: Style,
+    /// The selected row in the contact list
+    pub highlight: Style,
+    pub scrollbar: Style,
+    /// Key hints inside dialogs and forms
+    pub hint: Style,
+    /// Background of modal dialogs
+    pub dialog: Style,
+    /// Prominent message inside a dialog, e.g. the delete question
+    pub dialog_message: Style,
+    /// Secondary dialog text, e.g. the contact being deleted
+    pub dialog_text: Style,
+    pub error: Style,
+    /// Toasts and message log entries by severity; errors use `error`
+    pub info: Style,
+    pub success: Style,
+    pub warning: Style,
+    pub status_bar: Style,
+    pub status_help: Style,
+    pub status_counts: Style,
+    /// The vi sub-mode indicator in the status bar
+    pub status_mode: Style,
+}
+
+/// A `[themes.<name>]` table in `config.toml`. Unset slots come from `base`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub base: Option<String>,
+    #[serde(flatten)]
+    pub styles: BTreeMap<String, StyleConfig>,
+}
+
+/// Style for one slot, e.g. `highlight = { fg = "cyan", modifiers = ["bold"] }`
+/// or just a foreground colour: `highlight = "cyan"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StyleConfig {
+    Fg(String),
+    Full {
+        #[serde(default)]
+        fg: Option<String>,
+        #[serde(default)]
+        bg: Option<String>,
+        #[serde(default)]
+        modifiers: Vec<String>,
+    },
+}
+
+pub const BUILTIN_THEMES: [&str; 4] = ["dark", "light", "high-contrast", "monochrome"];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+fn fg(color: Color) -> Style {
+    Style::default().fg(color)
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            border_focused: fg(Color::Cyan),
+            input: fg(Color::Yellow),
+            label: fg(Color::Cyan),
+            header: Style::default().add_modifier(Modifier::UNDERLINED | Modifier::BOLD),
+            highlight: fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            scrollbar: Style::default().bg(Color::Cyan),
+            hint: fg(Color::DarkGray),
+            dialog: fg(Color::White).bg(Color::Black),
+            dialog_message: fg(Color::Yellow),
+            dialog_text: fg(Color::Gray),
+            error: fg(Color::Red),
+            info: fg(Color::Cyan),
+            success: fg(Color::Green),
+            warning: fg(Color::Yellow),
+            status_bar: Style::default().bg(Color::DarkGray),
+            status_help: fg(Color::Gray),
+            status_counts: fg(Color::White),
+            status_mode: fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            border_focused: fg(Color::Blue),
+            input: fg(Color::Magenta),
+            label: fg(Color::Blue),
+            highlight: fg(Color::Blue).add_modifier(Modifier::BOLD),
+            scrollbar: Style::default().bg(Color::Blue),
+            hint: fg(Color::Gray),
+            dialog: fg(Color::Black).bg(Color::White),
+            dialog_message: fg(Color::Magenta),
+            dialog_text: fg(Color::DarkGray),
+            info: fg(Color::Blue),
+            warning: fg(Color::Magenta),
+            status_bar: Style::default().bg(Color::Gray),
+            status_help: fg(Color::Black),
+            status_counts: fg(Color::Black),
+            status_mode: fg(Color::Magenta).add_modifier(Modifier::BOLD),
+            ..Self::dark()
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            border_focused: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            input: fg(Color::White).add_modifier(Modifier::BOLD),
+            label: fg(Color::LightYellow),
+            highlight: fg(Color::Black)
+                .bg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            scrollbar: Style::default().bg(Color::White),
+            hint: fg(Color::White),
+            dialog: fg(Color::White).bg(Color::Black),
+            dialog_message: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            dialog_text: fg(Color::White),
+            error: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            info: fg(Color::White),
+            success: fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            warning: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+            status_bar: Style::default().bg(Color::Black),
+            status_help: fg(Color::White),
+            status_counts: fg(Color::LightYellow),
+            status_mode: fg(Color::Black).bg(Color::LightYellow),
+            ..Self::dark()
+        }
+    }
+
+    /// No colours at all, only text attributes. Used when `NO_COLOR` is set.
+    pub fn monochrome() -> Self {
+        Self {
+            border_focused: Style::default().add_modifier(Modifier::BOLD),
+            input: Style::default().add_modifier(Modifier::BOLD),
+            label: Style::default(),
+            header: Style::default().add_modifier(Modifier::UNDERLINED | Modifier::BOLD),
+            highlight: Style::default().add_modifier(Modifier::REVERSED),
+            scrollbar: Style::default().add_modifier(Modifier::REVERSED),
+            hint: Style::default().add_modifier(Modifier::DIM),
+            dialog: Style::default(),
+            dialog_message: Style::default().add_modifier(Modifier::BOLD),
+            dialog_text: Style::default(),
+            error: Style::default().add_modifier(Modifier::BOLD),
+            info: Style::default(),
+            success: Style::default(),
+            warning: Style::default().add_modifier(Modifier::BOLD),
+            status_bar: Style::default().add_modifier(Modifier::REVERSED),
+            status_help: Style::default(),
+            status_counts: Style::default(),
+            status_mode: Style::default().add_modifier(Modifier::BOLD),
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// Resolve the configured theme. `NO_COLOR` always wins so the UI stays
+    /// colourless even when a colourful theme is configured.
+    pub fn from_config(name: Option<&str>, themes: &BTreeMap<String, ThemeConfig>) -> Result<Self> {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Self::resolve(name, themes, no_color)
+    }
+
+    pub fn resolve(
+        name: Option<&str>,
+        themes: &BTreeMap<String, ThemeConfig>,
+        no_color: bool,
+    ) -> Result<Self> {
+        if no_color {
+            return Ok(Self::monochrome());
+        }
+        Self::named(name.unwrap_or("dark"), themes, 0)
+    }
+
+    fn named(name: &str, themes: &BTreeMap<String, ThemeConfig>, depth: usize) -> Result<Self> {
+        // Guard against themes that use each other as a base
+        if depth > themes.len() {
+            return Err(AppError::Config(format!(
+                "theme `{name}` has a circular `base`"
+            )));
+        }
+
+        let Some(custom) = themes.get(name) else {
+            return Self::builtin(name).ok_or_else(|| {
+                AppError::Config(format!(
+                    "unknown theme `{name}` (built-in themes: {})",
+                    BUILTIN_THEMES.join(", ")
+                ))
+            });
+        };
+
+        let base = match custom.base.as_deref() {
+            Some(base) if base == name => Self::builtin(base).ok_or_else(|| {
+                AppError::Config(format!("theme `{name}` cannot use itself as `base`"))
+            })?,
+            Some(base) => Self::named(base, themes, depth + 1)?,
+            None => Self::dark(),
+        };
+
+        custom
+            .styles
+            .iter()
+            .try_fold(base, |mut theme, (slot, style)| {
+                let style = style
+                    .to_style()
+                    .map_err(|e| AppError::Config(format!("[themes.{name}] {slot}: {e}")))?;
+                *theme.slot_mut(slot).ok_or_else(|| {
+                    AppError::Config(format!("[themes.{name}] unknown style `{slot}`"))
+                })? = style;
+                Ok(theme)
+            })
+    }
+
+    fn slot_mut(&mut self, slot: &str) -> Option<&mut Style> {
+        Some(match slot {
+            "border_focused" => &mut self.border_focused,
+            "input" => &mut self.input,
+            "label" => &mut self.label,
+            "header" => &mut self.header,
+            "highlight" => &mut self.highlight,
+            "scrollbar" => &mut self.scrollbar,
+            "hint" => &mut self.hint,
+            "dialog" => &mut self.dialog,
+            "dialog_message" => &mut self.dialog_message,
+            "dialog_text" => &mut self.dialog_text,
+            "error" => &mut self.error,
+            "info" => &mut self.info,
+            "success" => &mut self.success,
+            "warning" => &mut self.warning,
+            "status_bar" => &mut self.status_bar,
+            "status_help" => &mut self.status_help,
+            "status_counts" => &mut self.status_counts,
+            "status_mode" => &mut self.status_mode,
+            _ => return None,
+        })
+    }
+}
+
+impl StyleConfig {
+    fn to_style(&self) -> std::result::Result<Style, String> {
+        let parse =
+            |color: &str| Color::from_str(color).map_err(|_| format!("invalid colour `{color}`"));
+        match self {
+            StyleConfig::Fg(color) => Ok(Style::default().fg(parse(color)?)),
+            StyleConfig::Full { fg, bg, modifiers } => {
+                let mut style = Style::default();
+                if let Some(color) = fg {
+                    style = style.fg(parse(color)?);
+                }
+                if let Some(color) = bg {
+                    style = style.bg(parse(color)?);
+                }
+                for modifier in modifiers {
+                    style = style.add_modifier(parse_modifier(modifier)?);
+                }
+                Ok(style)
+            }
+        }
+    }
+}
+
+fn parse_modifier(name: &str) -> std::result::Result<Modifier, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "bold" => Ok(Modifier::BOLD),
+        "dim" => Ok(Modifier::DIM),
+        "italic" => Ok(Modifier::ITALIC),
+        "underlined" | "underline" => Ok(Modifier::UNDERLINED),
+        "reversed" | "reverse" => Ok(Modifier::REVERSED),
+        "crossed_out" => Ok(Modifier::CROSSED_OUT),
+        other => Err(format!("unknown modifier `{other}`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn themes(toml: &str) -> BTreeMap<String, ThemeConfig> {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_themes_resolve() {
+        for name in BUILTIN_THEMES {
+            assert!(Theme::resolve(Some(name), &BTreeMap::new(), false).is_ok());
+        }
+        assert_eq!(
+            Theme::resolve(None, &BTreeMap::new(), false).unwrap(),
+            Theme::dark()
+        );
+    }
+
+    #[test]
+    fn test_no_color_forces_monochrome() {
+        let theme = Theme::resolve(Some("high-contrast"), &BTreeMap::new(), true).unwrap();
+        assert_eq!(theme, Theme::monochrome());
+    }
+
+    #[test]
+    fn test_custom_theme_overrides_base() {
+        let themes = themes(
+            r##"
+            [solar]
+            base = "light"
+            highlight = { fg = "#b58900", modifiers = ["bold"] }
+            error = "magenta"
+            "##,
+        );
+        let theme = Theme::resolve(Some("solar"), &themes, false).unwrap();
+
+        assert_eq!(
+            theme.highlight,
+            Style::default()
+                .fg(Color::Rgb(0xb5, 0x89, 0x00))
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(theme.error, Style::default().fg(Color::Magenta));
+        assert_eq!(theme.dialog, Theme::light().dialog);
+    }
+
+    #[test]
+    fn test_invalid_themes_are_config_errors() {
+        let err = Theme::resolve(Some("nope"), &BTreeMap::new(), false).unwrap_err();
+        assert!(matches!(err, AppError::Config(ref msg) if msg.contains("unknown theme")));
+
+        let bad_slot = themes("[t]\nsparkle = \"red\"\n");
+        let err = Theme::resolve(Some("t"), &bad_slot, false).unwrap_err();
+        assert!(matches!(err, AppError::Config(ref msg) if msg.contains("sparkle")));
+
+        let bad_colour = themes("[t]\nerror = \"not-a-colour\"\n");
+        assert!(Theme::resolve(Some("t"), &bad_colour, false).is_err());
+
+        let circular = themes("[a]\nbase = \"b\"\n[b]\nbase = \"a\"\n");
+        assert!(Theme::resolve(Some("a"), &circular, false).is_err());
+    }
+}
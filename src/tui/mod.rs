@@ -1,33 +1,161 @@
-use std::panic;
+use std::{fmt, panic, str::FromStr};
 
 use crate::error::AppResult as Result;
 use crossterm::{
     ExecutableCommand,
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture,
+    },
+    terminal::{
+        self, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+    },
 };
 use ratatui::{
-    Terminal,
+    Terminal, TerminalOptions, Viewport,
     backend::{Backend, CrosstermBackend},
 };
+use serde::{Deserialize, Serialize};
+
+/// Fewest rows the UI can be drawn in: search box, list header, one contact
+/// and the status bar.
+pub const MIN_HEIGHT: u16 = 8;
+
+/// Height of the inline (non-alternate-screen) UI, fzf-style: a number of
+/// rows such as `20`, or a share of the terminal such as `40%`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Height {
+    Rows(u16),
+    Percent(u16),
+}
+
+impl Height {
+    /// Rows to use on a terminal `available` rows tall, at least
+    /// [`MIN_HEIGHT`] where the terminal allows it.
+    pub fn rows(self, available: u16) -> u16 {
+        let rows = match self {
+            Height::Rows(rows) => rows,
+            Height::Percent(percent) => (available as u32 * percent as u32 / 100) as u16,
+        };
+        rows.max(MIN_HEIGHT).min(available)
+    }
+}
+
+impl FromStr for Height {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("invalid height `{s}`, expected rows (20) or a percentage (40%)");
+        match s.trim().strip_suffix('%') {
+            Some(percent) => match percent.parse() {
+                Ok(percent @ 1..=100) => Ok(Height::Percent(percent)),
+                _ => Err(invalid()),
+            },
+            None => match s.trim().parse() {
+                Ok(rows @ 1..) => Ok(Height::Rows(rows)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Height {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Height::Rows(rows) => write!(f, "{rows}"),
+            Height::Percent(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+impl TryFrom<String> for Height {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Height> for String {
+    fn from(height: Height) -> Self {
+        height.to_string()
+    }
+}
 
 pub fn install_panic_hook() {
     let original_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
+        let _ = std::io::stderr().execute(DisableMouseCapture);
+        let _ = std::io::stderr().execute(DisableBracketedPaste);
+        let _ = std::io::stderr().execute(DisableFocusChange);
         let _ = std::io::stderr().execute(LeaveAlternateScreen);
         let _ = disable_raw_mode();
         original_hook(panic_info);
     }));
 }
 
-pub fn init_terminal() -> Result<Terminal<impl Backend>> {
+/// Take over the terminal: the whole alternate screen, or with `height` an
+/// inline area below the prompt that leaves the scrollback visible.
+pub fn init_terminal(height: Option<Height>) -> Result<Terminal<impl Backend>> {
     enable_raw_mode()?;
-    std::io::stderr().execute(EnterAlternateScreen)?;
-    let terminal = Terminal::new(CrosstermBackend::new(std::io::stderr()))?;
+    let viewport = match height {
+        Some(height) => {
+            let (_, rows) = terminal::size()?;
+            Viewport::Inline(height.rows(rows))
+        }
+        None => {
+            std::io::stderr().execute(EnterAlternateScreen)?;
+            Viewport::Fullscreen
+        }
+    };
+    std::io::stderr().execute(EnableMouseCapture)?;
+    std::io::stderr().execute(EnableBracketedPaste)?;
+    std::io::stderr().execute(EnableFocusChange)?;
+    let terminal = Terminal::with_options(
+        CrosstermBackend::new(std::io::stderr()),
+        TerminalOptions { viewport },
+    )?;
     Ok(terminal)
 }
 
-pub fn restore_terminal() -> Result<()> {
-    std::io::stderr().execute(LeaveAlternateScreen)?;
+/// Undo [`init_terminal`]. An inline UI is cleared so the prompt continues
+/// where it started.
+pub fn restore_terminal(terminal: &mut Terminal<impl Backend>, inline: bool) -> Result<()> {
+    if inline {
+        terminal.clear()?;
+    }
+    std::io::stderr().execute(DisableMouseCapture)?;
+    std::io::stderr().execute(DisableBracketedPaste)?;
+    std::io::stderr().execute(DisableFocusChange)?;
+    if !inline {
+        std::io::stderr().execute(LeaveAlternateScreen)?;
+    }
     disable_raw_mode()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_height_parse() {
+        assert_eq!("20".parse(), Ok(Height::Rows(20)));
+        assert_eq!(" 40% ".parse(), Ok(Height::Percent(40)));
+        assert!("0".parse::<Height>().is_err());
+        assert!("150%".parse::<Height>().is_err());
+        assert!("tall".parse::<Height>().is_err());
+        assert_eq!(Height::Percent(40).to_string(), "40%");
+    }
+
+    #[test]
+    fn test_height_rows_are_clamped() {
+        assert_eq!(Height::Rows(20).rows(50), 20);
+        assert_eq!(Height::Rows(200).rows(50), 50);
+        assert_eq!(Height::Rows(2).rows(50), MIN_HEIGHT);
+        assert_eq!(Height::Percent(40).rows(50), 20);
+        assert_eq!(Height::Percent(10).rows(50), MIN_HEIGHT);
+        assert_eq!(Height::Rows(20).rows(5), 5);
+    }
+}
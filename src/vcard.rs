@@ -0,0 +1,397 @@
+//! vCard 3.0 (RFC 2426) export of a single contact, and reading the fields
+//! a contact has back out of vCards from other programs.
+
+use crate::{
+    error::{AppError, AppResult as Result},
+    model::Contact,
+};
+
+/// The contact as a vCard, with CRLF line endings and long lines folded.
+pub fn to_vcard(contact: &Contact) -> String {
+    let name = contact.name.trim();
+    let (given, family) = match name.rsplit_once(' ') {
+        Some((given, family)) => (given.trim(), family),
+        None => (name, ""),
+    };
+
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        "VERSION:3.0".to_string(),
+        format!("FN:{}", escape(name)),
+        format!("N:{};{};;;", escape(family), escape(given)),
+    ];
+    let fields = [
+        ("ORG", &contact.company),
+        ("EMAIL;TYPE=INTERNET", &contact.email),
+        ("TEL", &contact.phone),
+    ];
+    for (property, value) in fields {
+        if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+            lines.push(format!("{property}:{}", escape(value)));
+        }
+    }
+    if let Some(uid) = &contact.uid {
+        lines.push(format!("UID:{}", escape(uid)));
+    }
+    lines.push("END:VCARD".to_string());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Read the first card in `text` (vCard 3.0 or 4.0). Only what a contact
+/// holds is kept: FN (or N when FN is empty), the first ORG component, the
+/// preferred or else first EMAIL and TEL, and the UID.
+pub fn from_vcard(text: &str) -> Result<Contact> {
+    let mut contact = Contact::default();
+    let mut structured_name = String::new();
+    let (mut email_pref, mut phone_pref) = (false, false);
+    let mut in_card = false;
+
+    for line in unfold(text) {
+        let Some((name, pref, value)) = property(&line) else {
+            continue;
+        };
+        match name.as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VCARD") => in_card = true,
+            _ if !in_card => {}
+            "END" if value.eq_ignore_ascii_case("VCARD") => {
+                if contact.name.trim().is_empty() {
+                    contact.name = structured_name;
+                }
+                return Ok(contact);
+            }
+            "FN" => contact.name = unescape(value).trim().to_string(),
+            "N" => structured_name = join_name(value),
+            "ORG" if contact.company.is_none() => {
+                contact.company = non_empty(unescape(components(value)[0]));
+            }
+            "EMAIL" => keep(&mut contact.email, &mut email_pref, unescape(value), pref),
+            "TEL" => {
+                let value = unescape(value);
+                let number = value.strip_prefix("tel:").unwrap_or(&value).to_string();
+                keep(&mut contact.phone, &mut phone_pref, number, pref);
+            }
+            "UID" => contact.uid = non_empty(unescape(value)),
+            _ => {}
+        }
+    }
+    Err(AppError::Input("no BEGIN:VCARD ... END:VCARD block".to_string()))
+}
+
+/// `card` with the fields a contact holds replaced by `contact`'s. Other
+/// properties, such as addresses, notes and further emails, are kept.
+pub fn merge(card: &str, contact: &Contact) -> String {
+    let lines = unfold(card);
+    let properties: Vec<_> = lines.iter().map(|line| property(line)).collect();
+    let fresh = Contact {
+        uid: None,
+        ..contact.clone()
+    };
+    let mut fresh: Vec<(String, String)> = unfold(&to_vcard(&fresh))
+        .into_iter()
+        .filter_map(|line| Some((property(&line)?.0, line)))
+        .filter(|(name, _)| !matches!(name.as_str(), "BEGIN" | "VERSION" | "END"))
+        .collect();
+    let mut take = |wanted: &str| {
+        let at = fresh.iter().position(|(name, _)| name == wanted)?;
+        Some(fresh.remove(at).1)
+    };
+    // The EMAIL and TEL lines that from_vcard reads are replaced in place,
+    // keeping a preferred mark so they are still the ones read back
+    let mut replaced = Vec::new();
+    for wanted in ["EMAIL", "TEL"] {
+        let mut chosen: Option<(usize, bool)> = None;
+        for (i, property) in properties.iter().enumerate() {
+            if let Some((name, pref, value)) = property
+                && name == wanted
+                && !value.trim().is_empty()
+                && chosen.is_none_or(|(_, chosen_pref)| *pref && !chosen_pref)
+            {
+                chosen = Some((i, *pref));
+            }
+        }
+        if let Some((at, pref)) = chosen {
+            let line = take(wanted).map(|line| match line.split_once(':') {
+                Some((head, value)) if pref => format!("{head};TYPE=pref:{value}"),
+                _ => line,
+            });
+            replaced.push((at, line));
+        }
+    }
+
+    let mut out = Vec::new();
+    for (i, (line, property)) in lines.iter().zip(&properties).enumerate() {
+        let name = property.as_ref().map(|(name, ..)| name.as_str());
+        if let Some((_, replacement)) = replaced.iter_mut().find(|(at, _)| *at == i) {
+            out.extend(replacement.take());
+            continue;
+        }
+        if matches!(name, Some("FN" | "N" | "ORG")) {
+            continue;
+        }
+        if name == Some("END") {
+            out.extend(fresh.drain(..).map(|(_, line)| line));
+        }
+        if !line.is_empty() {
+            out.push(line.clone());
+        }
+    }
+    out.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Upper-cased name without its group (`item1.EMAIL`), whether a parameter
+/// marks it preferred, and the raw value.
+fn property(line: &str) -> Option<(String, bool, &str)> {
+    let (head, value) = split_property(line)?;
+    let mut params = head.split(';');
+    let name = params.next().unwrap_or_default();
+    let name = name.rsplit('.').next().unwrap_or(name).to_ascii_uppercase();
+    let pref = params.any(|param| param.to_ascii_uppercase().contains("PREF"));
+    Some((name, pref, value))
+}
+
+/// Join folded lines back together.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Split `NAME;PARAM="a:b":value` at the first colon outside quotes.
+fn split_property(line: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some((&line[..i], &line[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split a structured value on unescaped semicolons. Always at least one.
+fn components(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut escaped) = (0, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ';' => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// `Family;Given;Additional;Prefix;Suffix` as it would be written out.
+fn join_name(value: &str) -> String {
+    let parts: Vec<String> = components(value).into_iter().map(unescape).collect();
+    let part = |i: usize| parts.get(i).map(String::as_str).unwrap_or_default();
+    [part(3), part(1), part(2), part(0), part(4)]
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Keep the first value, unless a later one is marked preferred.
+fn keep(slot: &mut Option<String>, slot_pref: &mut bool, value: String, pref: bool) {
+    if value.trim().is_empty() || *slot_pref || (slot.is_some() && !pref) {
+        return;
+    }
+    *slot = Some(value.trim().to_string());
+    *slot_pref = pref;
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Escape a text value: backslashes, commas, semicolons and line breaks.
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ',' => out.push_str("\\,"),
+            ';' => out.push_str("\\;"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Fold lines longer than 75 octets, continuing with a leading space and
+/// never splitting a UTF-8 character.
+fn fold(line: &str) -> String {
+    const LIMIT: usize = 75;
+    let mut out = String::with_capacity(line.len() + line.len() / LIMIT * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > LIMIT {
+            out.push_str("\r\n ");
+            // The leading space counts towards the continuation line
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_vcard() {
+        let contact = Contact {
+            name: "Ada King Lovelace".to_string(),
+            company: Some("Analytical Engines; Ltd, UK".to_string()),
+            email: Some("ada@example.com".to_string()),
+            phone: None,
+            ..Default::default()
+        };
+        assert_eq!(
+            to_vcard(&contact),
+            "BEGIN:VCARD\r\n\
+             VERSION:3.0\r\n\
+             FN:Ada King Lovelace\r\n\
+             N:Lovelace;Ada King;;;\r\n\
+             ORG:Analytical Engines\\; Ltd\\, UK\r\n\
+             EMAIL;TYPE=INTERNET:ada@example.com\r\n\
+             END:VCARD\r\n"
+        );
+    }
+
+    #[test]
+    fn test_from_vcard_round_trip() {
+        let contact = Contact {
+            name: "Ada Lovelace".to_string(),
+            company: Some("Engines; Ltd, UK".to_string()),
+            email: Some("ada@example.com".to_string()),
+            phone: Some("+44 20 7946 0000".to_string()),
+            uid: Some("a1b2".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(from_vcard(&to_vcard(&contact)).unwrap(), contact);
+    }
+
+    #[test]
+    fn test_from_vcard_forms() {
+        let card = "BEGIN:VCARD\n\
+                    VERSION:4.0\n\
+                    N:Hopper;Grace;Brewster;Rear Admiral;\n\
+                    FN:\n\
+                    ORG:Navy;Computing\n\
+                    item1.EMAIL;TYPE=work:grace@work.example\n\
+                    EMAIL;TYPE=\"home,pref\":grace@home.example\n\
+                    EMAIL:other@example.com\n\
+                    TEL;VALUE=uri:tel:+1-555-010-0000\n\
+                    NOTE:folded\n \n\
+                     note\n\
+                    END:VCARD\n";
+        let contact = from_vcard(card).unwrap();
+        assert_eq!(contact.name, "Rear Admiral Grace Brewster Hopper");
+        assert_eq!(contact.company.as_deref(), Some("Navy"));
+        assert_eq!(contact.email.as_deref(), Some("grace@home.example"));
+        assert_eq!(contact.phone.as_deref(), Some("+1-555-010-0000"));
+        assert_eq!(contact.uid, None);
+
+        assert!(from_vcard("FN:No card").is_err());
+        assert!(from_vcard("BEGIN:VCARD\r\nFN:Unfinished\r\n").is_err());
+    }
+
+    #[test]
+    fn test_merge_keeps_other_properties() {
+        let card = "BEGIN:VCARD\r\n\
+                    VERSION:3.0\r\n\
+                    UID:team-42\r\n\
+                    FN:Grace Hopper\r\n\
+                    N:Hopper;Grace;;;\r\n\
+                    EMAIL;TYPE=work:grace@work.example\r\n\
+                    EMAIL;TYPE=home,pref:grace@home.example\r\n\
+                    NOTE:Invented the\r\n  compiler\r\n\
+                    END:VCARD\r\n";
+        let contact = Contact {
+            name: "Grace B. Hopper".to_string(),
+            email: Some("grace@navy.example".to_string()),
+            phone: Some("+1 555 010 0000".to_string()),
+            ..Default::default()
+        };
+        let merged = merge(card, &contact);
+        assert_eq!(
+            merged,
+            "BEGIN:VCARD\r\n\
+             VERSION:3.0\r\n\
+             UID:team-42\r\n\
+             EMAIL;TYPE=work:grace@work.example\r\n\
+             EMAIL;TYPE=INTERNET;TYPE=pref:grace@navy.example\r\n\
+             NOTE:Invented the compiler\r\n\
+             FN:Grace B. Hopper\r\n\
+             N:Hopper;Grace B.;;;\r\n\
+             TEL:+1 555 010 0000\r\n\
+             END:VCARD\r\n"
+        );
+        let read = from_vcard(&merged).unwrap();
+        assert_eq!(read.email, contact.email);
+        assert_eq!(read.phone, contact.phone);
+
+        // Fields the contact no longer has are removed
+        let cleared = merge(&merged, &Contact::default());
+        assert!(!cleared.contains("navy") && !cleared.contains("TEL"));
+        assert!(cleared.contains("grace@work.example") && cleared.contains("NOTE"));
+    }
+
+    #[test]
+    fn test_long_lines_fold_on_char_boundaries() {
+        let contact = Contact {
+            name: "é".repeat(60),
+            ..Default::default()
+        };
+        let card = to_vcard(&contact);
+        let fn_line: Vec<&str> = card
+            .split("\r\n")
+            .skip_while(|line| !line.starts_with("FN:"))
+            .take_while(|line| !line.starts_with("N:"))
+            .collect();
+        assert_eq!(fn_line.len(), 2);
+        assert!(fn_line.iter().all(|line| line.len() <= 75));
+        assert!(fn_line[1].starts_with(' '));
+        let unfolded = format!("{}{}", fn_line[0], &fn_line[1][1..]);
+        assert_eq!(unfolded, format!("FN:{}", "é".repeat(60)));
+    }
+}
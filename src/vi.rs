@@ -0,0 +1,235 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Sub-mode of the optional vi-style navigation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ViMode {
+    #[default]
+    Normal,
+    Insert,
+}
+
+#[derive(Debug, Clone)]
+pub enum ViMsg {
+    Key(KeyEvent),
+    SetMode(ViMode),
+}
+
+/// A complete normal-mode command, with any count already applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViCommand {
+    Down(usize),
+    Up(usize),
+    HalfPageDown(usize),
+    HalfPageUp(usize),
+    Top,
+    Bottom,
+    /// Jump to a 1-based line, as in `5G` or `5gg`.
+    GoTo(usize),
+    Search,
+    Insert,
+    Normal,
+    Delete,
+    Add,
+    Edit,
+    ToggleFavorite,
+    Help,
+}
+
+/// Normal-mode keys listed in the help overlay.
+pub const HELP: &[(&str, &str)] = &[
+    ("j / k", "Move down / up (accepts a count, e.g. 5j)"),
+    ("gg / G", "Jump to the first / last contact (10G jumps to row 10)"),
+    ("Ctrl+D / Ctrl+U", "Half page down / up"),
+    ("/", "Search"),
+    ("i / a", "Insert mode"),
+    ("Esc", "Back to normal mode"),
+    ("o / e", "Add / edit contact"),
+    ("dd", "Delete contact"),
+    ("*", "Star / unstar contact"),
+    ("?", "Show all key bindings"),
+];
+
+/// Key parser for vi-style normal mode: collects counts and two-key
+/// commands (`gg`, `dd`) until a full command is typed.
+#[derive(Debug, Clone, Default)]
+pub struct Vi {
+    pub mode: ViMode,
+    count: Option<usize>,
+    operator: Option<char>,
+}
+
+impl Vi {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a key is consumed by vi handling in the current sub-mode rather
+    /// than falling through to the keymap or text input.
+    pub fn handles(&self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match self.mode {
+            ViMode::Insert => key.code == KeyCode::Esc,
+            ViMode::Normal => match key.code {
+                KeyCode::Char('d' | 'u') if ctrl => true,
+                KeyCode::Char(_) => !ctrl && !alt,
+                KeyCode::Esc => self.is_pending(),
+                _ => false,
+            },
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || self.operator.is_some()
+    }
+
+    /// Keys typed so far for an incomplete command, e.g. `5d`.
+    pub fn pending(&self) -> String {
+        let mut pending = self.count.map(|c| c.to_string()).unwrap_or_default();
+        pending.extend(self.operator);
+        pending
+    }
+
+    pub fn status(&self) -> String {
+        let mode = match self.mode {
+            ViMode::Normal => "NORMAL",
+            ViMode::Insert => "INSERT",
+        };
+        match self.pending() {
+            pending if pending.is_empty() => format!("-- {mode} --"),
+            pending => format!("-- {mode} -- {pending}"),
+        }
+    }
+
+    pub fn update(&mut self, msg: ViMsg) -> Option<ViCommand> {
+        match msg {
+            ViMsg::SetMode(mode) => {
+                self.mode = mode;
+                self.reset();
+                None
+            }
+            ViMsg::Key(key) => match self.mode {
+                ViMode::Insert => match key.code {
+                    KeyCode::Esc => {
+                        self.mode = ViMode::Normal;
+                        Some(ViCommand::Normal)
+                    }
+                    _ => None,
+                },
+                ViMode::Normal => self.normal_key(key),
+            },
+        }
+    }
+
+    fn reset(&mut self) {
+        self.count = None;
+        self.operator = None;
+    }
+
+    fn normal_key(&mut self, key: KeyEvent) -> Option<ViCommand> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let count = self.count;
+        let times = count.unwrap_or(1);
+        let operator = self.operator.take();
+
+        let command = match key.code {
+            KeyCode::Char('d') if ctrl => Some(ViCommand::HalfPageDown(times)),
+            KeyCode::Char('u') if ctrl => Some(ViCommand::HalfPageUp(times)),
+            KeyCode::Char(c @ '0'..='9') if operator.is_none() && (c != '0' || count.is_some()) => {
+                let digit = c.to_digit(10).unwrap_or_default() as usize;
+                self.count = Some(count.unwrap_or(0).saturating_mul(10).saturating_add(digit));
+                return None;
+            }
+            KeyCode::Char('g') if operator == Some('g') => {
+                Some(count.map_or(ViCommand::Top, ViCommand::GoTo))
+            }
+            KeyCode::Char('d') if operator == Some('d') => Some(ViCommand::Delete),
+            KeyCode::Char(c @ ('g' | 'd')) if operator.is_none() => {
+                self.operator = Some(c);
+                return None;
+            }
+            _ if operator.is_some() => None,
+            KeyCode::Char('j') => Some(ViCommand::Down(times)),
+            KeyCode::Char('k') => Some(ViCommand::Up(times)),
+            KeyCode::Char('G') => Some(count.map_or(ViCommand::Bottom, ViCommand::GoTo)),
+            KeyCode::Char('/') => Some(ViCommand::Search),
+            KeyCode::Char('i' | 'a') => Some(ViCommand::Insert),
+            KeyCode::Char('o') => Some(ViCommand::Add),
+            KeyCode::Char('e') => Some(ViCommand::Edit),
+            KeyCode::Char('*') => Some(ViCommand::ToggleFavorite),
+            KeyCode::Char('?') => Some(ViCommand::Help),
+            _ => None,
+        };
+
+        self.reset();
+        if matches!(command, Some(ViCommand::Search | ViCommand::Insert)) {
+            self.mode = ViMode::Insert;
+        }
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(vi: &mut Vi, keys: &str) -> Option<ViCommand> {
+        let mut last = None;
+        for c in keys.chars() {
+            last = vi.update(ViMsg::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)));
+        }
+        last
+    }
+
+    #[test]
+    fn test_counts_and_motions() {
+        let mut vi = Vi::new();
+        assert_eq!(feed(&mut vi, "j"), Some(ViCommand::Down(1)));
+        assert_eq!(feed(&mut vi, "5j"), Some(ViCommand::Down(5)));
+        assert_eq!(feed(&mut vi, "12k"), Some(ViCommand::Up(12)));
+        assert_eq!(feed(&mut vi, "G"), Some(ViCommand::Bottom));
+        assert_eq!(feed(&mut vi, "10G"), Some(ViCommand::GoTo(10)));
+        assert!(!vi.is_pending());
+    }
+
+    #[test]
+    fn test_two_key_commands() {
+        let mut vi = Vi::new();
+        assert_eq!(feed(&mut vi, "g"), None);
+        assert_eq!(vi.pending(), "g");
+        assert_eq!(feed(&mut vi, "g"), Some(ViCommand::Top));
+        assert_eq!(feed(&mut vi, "3gg"), Some(ViCommand::GoTo(3)));
+        assert_eq!(feed(&mut vi, "dd"), Some(ViCommand::Delete));
+        // An unrelated key cancels a pending operator
+        assert_eq!(feed(&mut vi, "dj"), None);
+        assert!(!vi.is_pending());
+    }
+
+    #[test]
+    fn test_ctrl_half_page() {
+        let mut vi = Vi::new();
+        feed(&mut vi, "2");
+        let cmd = vi.update(ViMsg::Key(KeyEvent::new(
+            KeyCode::Char('d'),
+            KeyModifiers::CONTROL,
+        )));
+        assert_eq!(cmd, Some(ViCommand::HalfPageDown(2)));
+    }
+
+    #[test]
+    fn test_insert_mode_round_trip() {
+        let mut vi = Vi::new();
+        assert_eq!(feed(&mut vi, "/"), Some(ViCommand::Search));
+        assert_eq!(vi.mode, ViMode::Insert);
+        assert_eq!(vi.status(), "-- INSERT --");
+
+        let x = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
+        assert!(!vi.handles(x));
+
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        assert!(vi.handles(esc));
+        assert_eq!(vi.update(ViMsg::Key(esc)), Some(ViCommand::Normal));
+        assert_eq!(vi.mode, ViMode::Normal);
+        assert!(vi.handles(x));
+    }
+}
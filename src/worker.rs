@@ -0,0 +1,449 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use tracing::{debug, info};
+
+use crate::{
+    error::{AppError, AppResult as Result},
+    hooks::{Event, LifecycleHooks},
+    model::{Contact, Sort},
+    store::ContactStore,
+};
+
+/// Work for the database thread.
+#[derive(Debug, Clone)]
+pub enum DbRequest {
+    /// Reload the contact list. A newer load cancels any older one that has
+    /// not run yet, and replies to superseded loads are dropped.
+    LoadContacts,
+    /// Reload the contact list only if another process has changed it.
+    /// Runs quietly, with no activity in the status bar.
+    Refresh,
+    AddContact(Contact),
+    UpdateContact(Contact),
+    DeleteContact(Contact),
+    TouchContact(i64),
+    /// Star (`true`) or unstar a contact.
+    SetFavorite(Contact, bool),
+    SaveSort(Sort),
+    /// Write every contact to a file in the `--backup` format.
+    Export(PathBuf),
+    FindDuplicates,
+}
+
+/// Successful result of a [`DbRequest`].
+#[derive(Debug, Clone)]
+pub enum DbResponse {
+    Contacts(Vec<Contact>),
+    Added(Contact),
+    Updated(Contact),
+    /// The update was refused because the contact was saved elsewhere since
+    /// it was read; this is the stored one.
+    Conflict(Contact),
+    /// A refresh found nothing new.
+    Unchanged,
+    Deleted(Contact),
+    Touched,
+    FavoriteSet(Contact, bool),
+    SortSaved,
+    Exported(PathBuf),
+    Duplicates(Vec<Vec<Contact>>),
+    /// The lifecycle hook for an earlier write has finished. It arrives as a
+    /// second reply to that write, with any failure in `hook_error`.
+    HookFinished,
+}
+
+/// A finished request, paired with the request it answers so failures can
+/// be reported in context.
+#[derive(Debug)]
+pub struct DbReply {
+    pub id: u64,
+    pub request: DbRequest,
+    pub result: Result<DbResponse>,
+    /// Why the lifecycle hook for a successful write failed, if it did. Only
+    /// set on [`DbResponse::HookFinished`]; the write itself stands.
+    pub hook_error: Option<AppError>,
+}
+
+impl DbRequest {
+    /// Progress text shown in the status bar while the request is running.
+    pub fn activity(&self) -> Option<&'static str> {
+        let activity = match self {
+            DbRequest::LoadContacts => "Loading",
+            DbRequest::Refresh => return None,
+            DbRequest::AddContact(_) | DbRequest::UpdateContact(_) => "Saving",
+            DbRequest::DeleteContact(_) => "Deleting",
+            DbRequest::TouchContact(_)
+            | DbRequest::SetFavorite(..)
+            | DbRequest::SaveSort(_) => "Saving",
+            DbRequest::Export(_) => "Exporting",
+            DbRequest::FindDuplicates => "Searching",
+        };
+        Some(activity)
+    }
+
+    fn run(&self, db: &dyn ContactStore) -> Result<DbResponse> {
+        match self {
+            DbRequest::LoadContacts => db.load_customers().map(DbResponse::Contacts),
+            DbRequest::Refresh if db.changed_elsewhere()? => {
+                db.load_customers().map(DbResponse::Contacts)
+            }
+            DbRequest::Refresh => Ok(DbResponse::Unchanged),
+            DbRequest::AddContact(contact) => db.add_contact(contact.clone()).map(|id| {
+                DbResponse::Added(Contact {
+                    id,
+                    ..contact.clone()
+                })
+            }),
+            DbRequest::UpdateContact(contact) => {
+                match db.update_contact(contact.id, contact.clone()) {
+                    Ok(()) => Ok(DbResponse::Updated(contact.clone())),
+                    Err(AppError::Conflict(_)) => {
+                        db.get_contact(contact.id).map(DbResponse::Conflict)
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+            DbRequest::DeleteContact(contact) => db
+                .delete_contact(contact.id)
+                .map(|_| DbResponse::Deleted(contact.clone())),
+            DbRequest::TouchContact(id) => db.touch_contact(*id).map(|_| DbResponse::Touched),
+            DbRequest::SetFavorite(contact, favorite) => db
+                .set_favorite(contact.id, *favorite)
+                .map(|_| DbResponse::FavoriteSet(contact.clone(), *favorite)),
+            DbRequest::SaveSort(sort) => db.save_sort(*sort).map(|_| DbResponse::SortSaved),
+            DbRequest::Export(path) => {
+                db.backup_to_file(path).map(|_| DbResponse::Exported(path.clone()))
+            }
+            DbRequest::FindDuplicates => db.find_potential_duplicates().map(DbResponse::Duplicates),
+        }
+    }
+}
+
+/// Whether `response` reports a write that `hooks` has a command for.
+fn runs_hook(hooks: &LifecycleHooks, response: &DbResponse) -> bool {
+    response.event().is_some_and(|(event, _)| hooks.get(event).is_some())
+}
+
+impl DbResponse {
+    /// The change this response reports, for the lifecycle hooks.
+    fn event(&self) -> Option<(Event, &Contact)> {
+        match self {
+            DbResponse::Added(contact) => Some((Event::Add, contact)),
+            DbResponse::Updated(contact) => Some((Event::Update, contact)),
+            DbResponse::Deleted(contact) => Some((Event::Delete, contact)),
+            _ => None,
+        }
+    }
+}
+
+/// Handle to the thread that owns the [`ContactStore`]. Requests are answered
+/// in order.
+/// Lifecycle hooks run on a second thread so a slow one doesn't hold up the
+/// database; a write counts as pending until its hook has finished too.
+/// Dropping the handle lets queued requests finish before the thread exits.
+#[derive(Debug)]
+pub struct DbWorker {
+    requests: Option<Sender<(u64, DbRequest)>>,
+    replies: Receiver<DbReply>,
+    /// The store's hooks, to tell which writes will be followed by a
+    /// [`DbResponse::HookFinished`].
+    hooks: LifecycleHooks,
+    /// Id of the newest `LoadContacts` request, shared with the thread so it
+    /// can skip superseded loads.
+    latest_load: Arc<AtomicU64>,
+    next_id: u64,
+    /// Requests sent but not yet answered, oldest first.
+    pending: Vec<(u64, Option<&'static str>)>,
+    thread: Option<JoinHandle<()>>,
+    hook_thread: Option<JoinHandle<()>>,
+}
+
+impl DbWorker {
+    pub fn spawn(db: impl ContactStore + 'static) -> Result<Self> {
+        let (request_tx, request_rx) = mpsc::channel::<(u64, DbRequest)>();
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let (hook_tx, hook_rx) = mpsc::channel::<DbReply>();
+        let latest_load = Arc::new(AtomicU64::new(0));
+        let hooks = db.hooks().clone();
+
+        let hook_replies = reply_tx.clone();
+        let lifecycle = hooks.clone();
+        let hook_thread = thread::Builder::new()
+            .name("rolodex-hooks".into())
+            .spawn(move || {
+                for mut reply in hook_rx {
+                    let Some((event, contact)) = reply.result.as_ref().ok().and_then(|r| r.event())
+                    else {
+                        continue;
+                    };
+                    reply.hook_error = lifecycle.fire(event, std::slice::from_ref(contact)).err();
+                    reply.result = Ok(DbResponse::HookFinished);
+                    if hook_replies.send(reply).is_err() {
+                        break;
+                    }
+                }
+            })
+            .map_err(|e| AppError::Database(format!("spawn hook thread: {e}")))?;
+
+        let latest = Arc::clone(&latest_load);
+        let thread = thread::Builder::new()
+            .name("rolodex-db".into())
+            .spawn(move || {
+                for (id, request) in request_rx {
+                    let stale = matches!(request, DbRequest::LoadContacts)
+                        && id < latest.load(Ordering::Acquire);
+                    let result = if stale {
+                        debug!("Skipping superseded request {id}");
+                        Err(AppError::Cancelled)
+                    } else {
+                        request.run(&db)
+                    };
+                    // Handed to the hook thread once the reply is on its way
+                    let follow_up = match &result {
+                        Ok(response) if runs_hook(db.hooks(), response) => Some(DbReply {
+                            id,
+                            request: request.clone(),
+                            result: Ok(response.clone()),
+                            hook_error: None,
+                        }),
+                        _ => None,
+                    };
+                    let reply = DbReply {
+                        id,
+                        request,
+                        result,
+                        hook_error: None,
+                    };
+                    if reply_tx.send(reply).is_err() {
+                        break;
+                    }
+                    if let Some(follow_up) = follow_up {
+                        let _ = hook_tx.send(follow_up);
+                    }
+                }
+                info!("Database worker stopped");
+            })
+            .map_err(|e| AppError::Database(format!("spawn worker: {e}")))?;
+
+        Ok(Self {
+            requests: Some(request_tx),
+            replies: reply_rx,
+            hooks,
+            latest_load,
+            next_id: 1,
+            pending: Vec::new(),
+            thread: Some(thread),
+            hook_thread: Some(hook_thread),
+        })
+    }
+
+    /// Queue a request and return its id.
+    pub fn send(&mut self, request: DbRequest) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        if matches!(request, DbRequest::LoadContacts) {
+            self.latest_load.store(id, Ordering::Release);
+        }
+        self.pending.push((id, request.activity()));
+        if let Some(requests) = &self.requests {
+            // The thread only exits once this sender is dropped
+            let _ = requests.send((id, request));
+        }
+        id
+    }
+
+    /// Whether a reply belongs to a load that a newer one has replaced.
+    pub fn is_stale(&self, reply: &DbReply) -> bool {
+        matches!(reply.request, DbRequest::LoadContacts)
+            && reply.id < self.latest_load.load(Ordering::Acquire)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Progress text for the oldest outstanding request that shows any.
+    pub fn activity(&self) -> Option<&'static str> {
+        self.pending.iter().find_map(|(_, activity)| *activity)
+    }
+
+    pub fn try_recv(&mut self) -> Option<DbReply> {
+        let reply = self.replies.try_recv().ok()?;
+        self.complete(&reply);
+        Some(reply)
+    }
+
+    /// Wait up to `timeout` for the next reply.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<DbReply> {
+        match self.replies.recv_timeout(timeout) {
+            Ok(reply) => {
+                self.complete(&reply);
+                Some(reply)
+            }
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => None,
+        }
+    }
+
+    fn complete(&mut self, reply: &DbReply) {
+        self.pending.retain(|(pending, _)| *pending != reply.id);
+        // Still waiting on the hook, but quietly: the write is done
+        if let Ok(response) = &reply.result
+            && runs_hook(&self.hooks, response)
+        {
+            self.pending.push((reply.id, None));
+        }
+    }
+}
+
+impl Drop for DbWorker {
+    fn drop(&mut self) {
+        // Closing the channel ends the thread's loop once the queue is empty
+        self.requests.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        // Its channel closed with the database thread
+        if let Some(thread) = self.hook_thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Db;
+    use tempfile::tempdir;
+
+    fn worker() -> (DbWorker, tempfile::TempDir) {
+        let dir = tempdir().unwrap();
+        let mut db = Db::open(dir.path().join("test.db")).unwrap();
+        db.seed(3).unwrap();
+        (DbWorker::spawn(db).unwrap(), dir)
+    }
+
+    fn wait(worker: &mut DbWorker) -> DbReply {
+        worker.recv_timeout(Duration::from_secs(5)).expect("reply")
+    }
+
+    #[test]
+    fn test_requests_round_trip() {
+        let (mut worker, _dir) = worker();
+
+        let id = worker.send(DbRequest::LoadContacts);
+        assert!(worker.is_busy());
+        assert_eq!(worker.activity(), Some("Loading"));
+
+        let reply = wait(&mut worker);
+        assert_eq!(reply.id, id);
+        assert!(matches!(reply.result, Ok(DbResponse::Contacts(ref c)) if c.len() == 3));
+        assert!(!worker.is_busy());
+
+        let contact = Contact {
+            name: "Ada".into(),
+            ..Default::default()
+        };
+        worker.send(DbRequest::AddContact(contact));
+        assert!(matches!(wait(&mut worker).result, Ok(DbResponse::Added(_))));
+    }
+
+    #[test]
+    fn test_superseded_loads_are_cancelled() {
+        let (mut worker, _dir) = worker();
+
+        let replies: Vec<DbReply> = {
+            for _ in 0..5 {
+                worker.send(DbRequest::LoadContacts);
+            }
+            (0..5).map(|_| wait(&mut worker)).collect()
+        };
+
+        let (stale, fresh): (Vec<_>, Vec<_>) =
+            replies.iter().partition(|reply| worker.is_stale(reply));
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].id, 5);
+        assert!(matches!(fresh[0].result, Ok(DbResponse::Contacts(_))));
+        assert_eq!(stale.len(), 4);
+    }
+
+    #[test]
+    fn test_refresh_and_conflicts() {
+        let (mut worker, dir) = worker();
+        worker.send(DbRequest::Refresh);
+        assert_eq!(worker.activity(), None);
+        assert!(matches!(wait(&mut worker).result, Ok(DbResponse::Unchanged)));
+
+        // Another process edits a contact we have open
+        let other = Db::open(dir.path().join("test.db")).unwrap();
+        let opened = other.load_customers().unwrap().remove(0);
+        let renamed = Contact {
+            name: "Ada".into(),
+            ..opened.clone()
+        };
+        other.update_contact(opened.id, renamed).unwrap();
+
+        worker.send(DbRequest::Refresh);
+        let reply = wait(&mut worker);
+        assert!(matches!(reply.result, Ok(DbResponse::Contacts(ref c)) if c.len() == 3));
+
+        worker.send(DbRequest::UpdateContact(opened));
+        let reply = wait(&mut worker);
+        assert!(matches!(reply.result, Ok(DbResponse::Conflict(ref c)) if c.name == "Ada"));
+    }
+
+    #[test]
+    fn test_hooks_run_after_the_reply() {
+        let dir = tempdir().unwrap();
+        let mut db = Db::open(dir.path().join("test.db")).unwrap();
+        db.set_hooks(LifecycleHooks {
+            on_add: Some("cat > /dev/null; sleep 1; echo offline; exit 1".to_string()),
+            ..Default::default()
+        });
+        let mut worker = DbWorker::spawn(db).unwrap();
+
+        let contact = Contact {
+            name: "Ada".into(),
+            ..Default::default()
+        };
+        let id = worker.send(DbRequest::AddContact(contact));
+        worker.send(DbRequest::LoadContacts);
+
+        // The database carries on while the hook runs
+        let reply = wait(&mut worker);
+        assert!(matches!(reply.result, Ok(DbResponse::Added(_))));
+        assert!(reply.hook_error.is_none());
+        let reply = wait(&mut worker);
+        assert!(matches!(reply.result, Ok(DbResponse::Contacts(ref c)) if c.len() == 1));
+        assert!(worker.is_busy());
+        assert_eq!(worker.activity(), None);
+
+        let reply = wait(&mut worker);
+        assert_eq!(reply.id, id);
+        assert!(matches!(reply.result, Ok(DbResponse::HookFinished)));
+        let err = reply.hook_error.unwrap().to_string();
+        assert!(err.ends_with("on_add hook failed with exit 1: offline"), "{err}");
+        assert!(!worker.is_busy());
+    }
+
+    #[test]
+    fn test_failures_are_reported_with_request() {
+        let (mut worker, dir) = worker();
+        // Deleting the schema out from under the worker makes every query fail
+        let db = Db::open(dir.path().join("test.db")).unwrap();
+        db.conn().execute("DROP TABLE contacts", []).unwrap();
+
+        worker.send(DbRequest::LoadContacts);
+        let reply = wait(&mut worker);
+        assert!(matches!(reply.request, DbRequest::LoadContacts));
+        assert!(matches!(reply.result, Err(AppError::Database(_))));
+    }
+}
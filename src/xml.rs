@@ -0,0 +1,315 @@
+//! The little XML that WebDAV needs: parsing bodies into a tree with
+//! namespaces resolved, and escaping text. DTDs are refused rather than
+//! expanded.
+
+/// Requests nested deeper than this are refused.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    /// Namespace URI, empty when there is none.
+    pub ns: String,
+    /// Local name, without the prefix.
+    pub name: String,
+    /// Attributes other than namespace declarations, names as written.
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    /// Text directly inside the element, unescaped and untrimmed.
+    pub text: String,
+}
+
+impl Element {
+    pub fn is(&self, ns: &str, name: &str) -> bool {
+        self.ns == ns && self.name == name
+    }
+
+    pub fn child(&self, ns: &str, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.is(ns, name))
+    }
+
+    pub fn children_named<'a>(
+        &'a self,
+        ns: &'a str,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.is(ns, name))
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub fn parse(input: &str) -> Result<Element, String> {
+    let mut parser = Parser {
+        rest: input.trim_start_matches('\u{feff}'),
+        scopes: Vec::new(),
+    };
+    parser.skip_misc()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+    if !parser.rest.is_empty() {
+        return Err("content after the root element".to_string());
+    }
+    Ok(root)
+}
+
+/// Escape text for element content or a double-quoted attribute.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+    /// Namespace prefixes declared on each open element, innermost last.
+    scopes: Vec<Vec<(&'a str, String)>>,
+}
+
+impl<'a> Parser<'a> {
+    /// Skip whitespace, comments and processing instructions between
+    /// elements.
+    fn skip_misc(&mut self) -> Result<(), String> {
+        loop {
+            self.rest = self.rest.trim_start();
+            if self.rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest.starts_with("<!") {
+                return Err("DTDs are not supported".to_string());
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<(), String> {
+        let at = self.rest.find(end).ok_or_else(|| format!("missing {end}"))?;
+        self.rest = &self.rest[at + end.len()..];
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<&'a str, String> {
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '>' | '/' | '='))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return Err("expected a name".to_string());
+        }
+        let name = &self.rest[..end];
+        self.rest = &self.rest[end..];
+        Ok(name)
+    }
+
+    fn element(&mut self) -> Result<Element, String> {
+        if self.scopes.len() >= MAX_DEPTH {
+            return Err("elements nested too deeply".to_string());
+        }
+        self.rest = self.rest.strip_prefix('<').ok_or("expected an element")?;
+        let tag = self.name()?;
+
+        let mut scope = Vec::new();
+        let mut attributes = Vec::new();
+        let empty = loop {
+            self.rest = self.rest.trim_start();
+            if let Some(rest) = self.rest.strip_prefix("/>") {
+                self.rest = rest;
+                break true;
+            }
+            if let Some(rest) = self.rest.strip_prefix('>') {
+                self.rest = rest;
+                break false;
+            }
+            let name = self.name()?;
+            self.rest = self
+                .rest
+                .trim_start()
+                .strip_prefix('=')
+                .ok_or_else(|| format!("expected = after {name}"))?
+                .trim_start();
+            let quote = self
+                .rest
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '"' | '\''))
+                .ok_or_else(|| format!("expected a quoted value for {name}"))?;
+            let end = self.rest[1..]
+                .find(quote)
+                .ok_or_else(|| format!("unclosed value for {name}"))?
+                + 1;
+            let value = unescape(&self.rest[1..end])?;
+            self.rest = &self.rest[end + 1..];
+            if name == "xmlns" {
+                scope.push(("", value));
+            } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+                scope.push((prefix, value));
+            } else {
+                attributes.push((name.to_string(), value));
+            }
+        };
+
+        self.scopes.push(scope);
+        let (ns, name) = self.resolve(tag)?;
+        let mut element = Element {
+            ns,
+            name: name.to_string(),
+            attributes,
+            children: Vec::new(),
+            text: String::new(),
+        };
+        if !empty {
+            loop {
+                let end = self.rest.find('<').ok_or_else(|| format!("unclosed <{tag}>"))?;
+                element.text.push_str(&unescape(&self.rest[..end])?);
+                self.rest = &self.rest[end..];
+                if let Some(rest) = self.rest.strip_prefix("</") {
+                    let end = rest.find('>').ok_or_else(|| format!("unclosed </{tag}"))?;
+                    if rest[..end].trim_end() != tag {
+                        return Err(format!("expected </{tag}>"));
+                    }
+                    self.rest = &rest[end + 1..];
+                    break;
+                } else if let Some(rest) = self.rest.strip_prefix("<![CDATA[") {
+                    let end = rest.find("]]>").ok_or("unclosed CDATA section")?;
+                    element.text.push_str(&rest[..end]);
+                    self.rest = &rest[end + 3..];
+                } else if self.rest.starts_with("<!--") {
+                    self.skip_past("-->")?;
+                } else if self.rest.starts_with("<?") {
+                    self.skip_past("?>")?;
+                } else {
+                    element.children.push(self.element()?);
+                }
+            }
+        }
+        self.scopes.pop();
+        Ok(element)
+    }
+
+    /// Namespace URI and local name for a `prefix:name` tag.
+    fn resolve(&self, tag: &'a str) -> Result<(String, &'a str), String> {
+        let (prefix, name) = tag.split_once(':').unwrap_or(("", tag));
+        let uri = self
+            .scopes
+            .iter()
+            .rev()
+            .flatten()
+            .find(|(declared, _)| *declared == prefix)
+            .map(|(_, uri)| uri.clone());
+        match uri {
+            Some(uri) => Ok((uri, name)),
+            None if prefix.is_empty() => Ok((String::new(), name)),
+            None => Err(format!("undeclared namespace prefix {prefix}")),
+        }
+    }
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(at) = rest.find('&') {
+        out.push_str(&rest[..at]);
+        let end = rest[at..].find(';').ok_or("unterminated entity")? + at;
+        let entity = &rest[at + 1..end];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("unknown entity &{entity};"))?,
+        };
+        out.push(c);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolves_namespaces() {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+            <!-- discovery -->
+            <d:propfind xmlns:d="DAV:" xmlns:C='urn:ietf:params:xml:ns:carddav'>
+              <d:prop>
+                <d:getetag/>
+                <C:address-data />
+                <x:color xmlns:x="http://example.com/ns" />
+                <plain/>
+              </d:prop>
+            </d:propfind>"#;
+        let root = parse(body).unwrap();
+        assert!(root.is("DAV:", "propfind"));
+        let names: Vec<(&str, &str)> = root
+            .child("DAV:", "prop")
+            .unwrap()
+            .children
+            .iter()
+            .map(|child| (child.ns.as_str(), child.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("DAV:", "getetag"),
+                ("urn:ietf:params:xml:ns:carddav", "address-data"),
+                ("http://example.com/ns", "color"),
+                ("", "plain"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_text_and_attributes() {
+        let body = r#"<multiget xmlns="DAV:"><href> /a%20b.vcf?x=1&amp;y=&#x32; </href>
+            <href><![CDATA[<raw>]]></href><match type="contains">Ada</match></multiget>"#;
+        let root = parse(body).unwrap();
+        let hrefs: Vec<&str> = root.children_named("DAV:", "href").map(|e| e.text.trim()).collect();
+        assert_eq!(hrefs, ["/a%20b.vcf?x=1&y=2", "<raw>"]);
+        assert_eq!(root.child("DAV:", "match").unwrap().attribute("type"), Some("contains"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for body in [
+            "",
+            "<a>",
+            "<a></b>",
+            "<x:a/>",
+            "<a/><b/>",
+            "<a>&bogus;</a>",
+            "<!DOCTYPE a [<!ENTITY e 'x'>]><a>&e;</a>",
+            &"<a>".repeat(MAX_DEPTH + 1),
+        ] {
+            assert!(parse(body).is_err(), "{body}");
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(r#"<a href="x">&</a>"#), "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;");
+    }
+}
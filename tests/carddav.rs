@@ -0,0 +1,236 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+};
+
+use rolodex_tui::{Db, model::Contact, server::CardDav};
+use tempfile::{TempDir, tempdir};
+
+const BOOK: &str = "/addressbooks/default/";
+
+struct Reply {
+    status: u16,
+    headers: String,
+    body: String,
+}
+
+impl Reply {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+    }
+}
+
+fn seeded_db(temp_dir: &TempDir) -> Db {
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    let contacts = [("Ada Lovelace", "ada@example.com"), ("Alan Turing", "alan@example.com")];
+    for (name, email) in contacts {
+        db.add_contact(Contact {
+            name: name.to_string(),
+            email: Some(email.to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    }
+    db
+}
+
+/// Serve `db` on a free local port for the rest of the test.
+fn start(db: Db, password: Option<&str>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Arc::new(CardDav::new(db, password.map(str::to_string)));
+    thread::spawn(move || server.serve_tcp(listener));
+    addr
+}
+
+/// Send a request with extra header lines (each ending in CRLF) and `body`.
+fn request(addr: SocketAddr, method: &str, path: &str, headers: &str, body: &str) -> Reply {
+    let raw = format!(
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\n{headers}Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(raw.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let (status_line, headers) = head.split_once("\r\n").unwrap_or((head, ""));
+    Reply {
+        status: status_line.split(' ').nth(1).unwrap().parse().unwrap(),
+        headers: headers.to_string(),
+        body: body.to_string(),
+    }
+}
+
+fn propfind(addr: SocketAddr, path: &str, depth: &str, props: &str) -> Reply {
+    let body = format!(
+        r#"<?xml version="1.0"?><d:propfind xmlns:d="DAV:"
+            xmlns:card="urn:ietf:params:xml:ns:carddav" xmlns:cs="http://calendarserver.org/ns/">
+            <d:prop>{props}</d:prop></d:propfind>"#
+    );
+    request(addr, "PROPFIND", path, &format!("Depth: {depth}\r\n"), &body)
+}
+
+fn vcard(name: &str, email: &str) -> String {
+    format!("BEGIN:VCARD\r\nVERSION:3.0\r\nFN:{name}\r\nEMAIL:{email}\r\nEND:VCARD\r\n")
+}
+
+#[test]
+fn test_discovery() {
+    let temp_dir = tempdir().unwrap();
+    let addr = start(seeded_db(&temp_dir), None);
+
+    let reply = request(addr, "GET", "/.well-known/carddav", "", "");
+    assert_eq!(reply.status, 301);
+    assert_eq!(reply.header("Location"), Some("/"));
+
+    let reply = request(addr, "OPTIONS", "/", "", "");
+    assert_eq!(reply.header("DAV"), Some("1, 3, addressbook"));
+
+    let reply = propfind(addr, "/", "0", "<d:current-user-principal/>");
+    assert_eq!(reply.status, 207);
+    assert!(reply.body.contains(
+        "<d:current-user-principal><d:href>/principals/rolodex/</d:href></d:current-user-principal>"
+    ));
+
+    let reply = propfind(addr, "/principals/rolodex/", "0", "<card:addressbook-home-set/>");
+    assert!(reply.body.contains("<d:href>/addressbooks/</d:href></card:addressbook-home-set>"));
+
+    let reply = propfind(addr, "/addressbooks/", "1", "<d:resourcetype/><x:color xmlns:x=\"X:\"/>");
+    assert!(reply.body.contains(&format!(
+        "<d:href>{BOOK}</d:href><d:propstat><d:prop><d:resourcetype>\
+         <d:collection/><card:addressbook/></d:resourcetype>"
+    )));
+    assert!(reply.body.contains(
+        "<d:prop><x:color xmlns:x=\"X:\"/></d:prop><d:status>HTTP/1.1 404 Not Found</d:status>"
+    ));
+
+    let reply = propfind(addr, BOOK, "1", "<d:getetag/><cs:getctag/>");
+    assert_eq!(reply.body.matches("<d:response>").count(), 3);
+    assert!(reply.body.contains(&format!("<d:href>{BOOK}rolodex-1.vcf</d:href>")));
+    assert!(reply.body.contains(&format!("<d:href>{BOOK}rolodex-2.vcf</d:href>")));
+    assert!(reply.body.contains("<cs:getctag>"));
+
+    assert_eq!(propfind(addr, "/nowhere/", "0", "").status, 404);
+    let reply = request(addr, "PROPFIND", BOOK, "", "<d:propfind xmlns:d='DAV:'>");
+    assert_eq!(reply.status, 400);
+}
+
+#[test]
+fn test_put_get_delete_with_etags() {
+    let temp_dir = tempdir().unwrap();
+    let addr = start(seeded_db(&temp_dir), None);
+    let ctag = || propfind(addr, BOOK, "0", "<cs:getctag/>").body;
+    let before = ctag();
+
+    let path = format!("{BOOK}5A7B-grace.vcf");
+    let card = vcard("Grace Hopper", "grace@example.com");
+    let reply = request(addr, "PUT", &path, "If-None-Match: *\r\n", &card);
+    assert_eq!(reply.status, 201);
+    let etag = reply.header("ETag").unwrap().to_string();
+    assert_ne!(ctag(), before);
+
+    let reply = request(addr, "GET", &path, "", "");
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.header("ETag"), Some(etag.as_str()));
+    assert!(reply.body.contains("FN:Grace Hopper\r\n"));
+    assert!(reply.body.contains("UID:5A7B-grace\r\n"));
+
+    // Creating it again, or editing an older version, is refused
+    let reply = request(addr, "PUT", &path, "If-None-Match: *\r\n", &card);
+    assert_eq!(reply.status, 412);
+    let card = vcard("Grace B. Hopper", "grace@navy.example");
+    let reply = request(addr, "PUT", &path, "If-Match: \"stale\"\r\n", &card);
+    assert_eq!(reply.status, 412);
+
+    let reply = request(addr, "PUT", &path, &format!("If-Match: {etag}\r\n"), &card);
+    assert_eq!(reply.status, 204);
+    let new_etag = reply.header("ETag").unwrap().to_string();
+    assert_ne!(new_etag, etag);
+
+    let reply = request(addr, "PUT", &format!("{BOOK}bad.vcf"), "", "BEGIN:VCARD\r\nFN:\r\n");
+    assert_eq!(reply.status, 403);
+    assert!(reply.body.contains("<card:valid-address-data/>"));
+
+    let reply = request(addr, "DELETE", &path, &format!("If-Match: {etag}\r\n"), "");
+    assert_eq!(reply.status, 412);
+    let reply = request(addr, "DELETE", &path, &format!("If-Match: {new_etag}\r\n"), "");
+    assert_eq!(reply.status, 204);
+    assert_eq!(request(addr, "GET", &path, "", "").status, 404);
+
+    // Contacts added elsewhere are edited under their made-up names
+    let path = format!("{BOOK}rolodex-1.vcf");
+    let reply = request(addr, "PUT", &path, "", &vcard("Ada King", "ada@example.com"));
+    assert_eq!(reply.status, 204);
+
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    let contacts = db.load_customers().unwrap();
+    let names: Vec<&str> = contacts.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["Ada King", "Alan Turing"]);
+}
+
+#[test]
+fn test_reports() {
+    let temp_dir = tempdir().unwrap();
+    let addr = start(seeded_db(&temp_dir), None);
+
+    let body = format!(
+        r#"<card:addressbook-multiget xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">
+             <d:prop><d:getetag/><card:address-data/></d:prop>
+             <d:href>{BOOK}rolodex-2.vcf</d:href>
+             <d:href>http://localhost{BOOK}missing.vcf</d:href>
+           </card:addressbook-multiget>"#
+    );
+    let reply = request(addr, "REPORT", BOOK, "Depth: 1\r\n", &body);
+    assert_eq!(reply.status, 207);
+    assert!(reply.body.contains("FN:Alan Turing\r\n"));
+    assert!(!reply.body.contains("Ada Lovelace"));
+    assert!(reply.body.contains(&format!(
+        "<d:href>http://localhost{BOOK}missing.vcf</d:href><d:status>HTTP/1.1 404 Not Found"
+    )));
+
+    let query = |filter: &str| {
+        let body = format!(
+            r#"<C:addressbook-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:carddav">
+                 <D:prop><D:getetag/></D:prop><C:filter>{filter}</C:filter>
+               </C:addressbook-query>"#
+        );
+        let reply = request(addr, "REPORT", BOOK, "", &body);
+        assert_eq!(reply.status, 207);
+        reply.body.matches("<d:response>").count()
+    };
+    assert_eq!(query(""), 2);
+    let by_email = r#"<C:prop-filter name="EMAIL"><C:text-match>ADA@</C:text-match></C:prop-filter>"#;
+    assert_eq!(query(by_email), 1);
+    let negated = r#"<C:prop-filter name="FN">
+        <C:text-match match-type="starts-with" negate-condition="yes">Ada</C:text-match>
+        </C:prop-filter>"#;
+    assert_eq!(query(negated), 1);
+    let no_phone = r#"<C:prop-filter name="TEL"><C:is-not-defined/></C:prop-filter>"#;
+    assert_eq!(query(no_phone), 2);
+
+    let body = r#"<d:sync-collection xmlns:d="DAV:"/>"#;
+    assert_eq!(request(addr, "REPORT", BOOK, "", body).status, 403);
+}
+
+#[test]
+fn test_basic_auth() {
+    let temp_dir = tempdir().unwrap();
+    let addr = start(seeded_db(&temp_dir), Some("s3cret"));
+
+    let reply = propfind(addr, BOOK, "0", "<d:getetag/>");
+    assert_eq!(reply.status, 401);
+    assert_eq!(reply.header("WWW-Authenticate"), Some("Basic realm=\"rolodex\""));
+
+    // "phone:s3cret" and "phone:wrong"
+    let get = |credentials: &str| {
+        let header = format!("Authorization: Basic {credentials}\r\n");
+        request(addr, "GET", &format!("{BOOK}rolodex-1.vcf"), &header, "").status
+    };
+    assert_eq!(get("cGhvbmU6czNjcmV0"), 200);
+    assert_eq!(get("cGhvbmU6d3Jvbmc="), 401);
+}
@@ -0,0 +1,159 @@
+//! Headless driver for the full app: feeds scripted key input through
+//! `App::handle_key`/`App::update`, renders to a `TestBackend` and compares
+//! the screen against snapshot files in `tests/snapshots/`.
+//!
+//! Run with `UPDATE_SNAPSHOTS=1` to write missing or changed snapshots
+//! instead of failing, then review the diff.
+
+use std::{fs, path::PathBuf};
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{Terminal, backend::TestBackend};
+use rolodex_tui::{
+    Db,
+    components::app::{App, AppMsg},
+    keymap::KeyChord,
+    model::Contact,
+};
+use tempfile::TempDir;
+
+pub const WIDTH: u16 = 80;
+pub const HEIGHT: u16 = 24;
+
+pub struct Harness {
+    pub app: App,
+    terminal: Terminal<TestBackend>,
+    _dir: TempDir,
+}
+
+impl Harness {
+    /// An app over a fresh database holding `contacts`, on an 80x24 screen.
+    pub fn new(contacts: &[Contact]) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Db::open(dir.path().join("test.db")).unwrap();
+        for contact in contacts {
+            db.add_contact(contact.clone()).unwrap();
+        }
+        let terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+        Self {
+            app: App::new(db).unwrap(),
+            terminal,
+            _dir: dir,
+        }
+    }
+
+    pub fn vi(mut self) -> Self {
+        self.app.set_vi_mode(true);
+        self
+    }
+
+    /// Run a message directly, for states no key reaches such as fatal errors.
+    pub fn send(&mut self, msg: AppMsg) -> &mut Self {
+        let mut msg = msg;
+        while let Some(next) = self.app.update(msg, |msg| msg) {
+            msg = next;
+        }
+        self.app.drain_db(true);
+        self
+    }
+
+    /// Send one terminal event and run every message it produces, waiting for
+    /// the database worker so the result is deterministic.
+    pub fn event(&mut self, event: Event) -> &mut Self {
+        match self.app.handle_event(event) {
+            Some(msg) => self.send(msg),
+            None => self,
+        }
+    }
+
+    /// Type a key script. Plain characters are typed as-is and `<chord>`
+    /// presses a key in the keymap's syntax, e.g. `<ctrl+a>`, `<enter>`,
+    /// `<esc>`; `<lt>` types a literal `<`.
+    pub fn keys(&mut self, script: &str) -> &mut Self {
+        let mut rest = script;
+        while let Some(c) = rest.chars().next() {
+            let event = match (c, rest.find('>')) {
+                ('<', Some(end)) => {
+                    let name = &rest[1..end];
+                    rest = &rest[end + 1..];
+                    if name == "lt" {
+                        KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE)
+                    } else {
+                        let chord: KeyChord = name.parse().unwrap();
+                        KeyEvent::new(chord.code, chord.modifiers)
+                    }
+                }
+                _ => {
+                    rest = &rest[c.len_utf8()..];
+                    KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+                }
+            };
+            self.event(Event::Key(event));
+        }
+        self
+    }
+
+    /// Draw a frame and return the screen as text, one line per row with
+    /// trailing spaces removed.
+    pub fn screen(&mut self) -> String {
+        let app = &self.app;
+        self.terminal
+            .draw(|f| app.draw(f, f.area(), false))
+            .unwrap();
+        let buffer = self.terminal.backend().buffer();
+        let mut screen = String::new();
+        for y in 0..buffer.area.height {
+            let line: String = (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            screen.push_str(line.trim_end());
+            screen.push('\n');
+        }
+        screen
+    }
+
+    /// Compare the current screen with `tests/snapshots/<name>.txt`.
+    pub fn assert_snapshot(&mut self, name: &str) {
+        let screen = self.screen();
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots")
+            .join(format!("{name}.txt"));
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+
+        match fs::read_to_string(&path) {
+            Ok(expected) if expected == screen => {}
+            _ if update => fs::write(&path, &screen).unwrap(),
+            Ok(expected) => panic!(
+                "screen differs from snapshot {}\n\
+                 rerun with UPDATE_SNAPSHOTS=1 to accept it\n\
+                 --- expected\n{expected}--- actual\n{screen}",
+                path.display()
+            ),
+            Err(_) => panic!(
+                "no snapshot at {}; rerun with UPDATE_SNAPSHOTS=1 to create it\n{screen}",
+                path.display()
+            ),
+        }
+    }
+}
+
+pub fn contact(name: &str, company: &str, email: &str, phone: &str) -> Contact {
+    Contact {
+        name: name.to_string(),
+        company: Some(company.to_string()),
+        email: Some(email.to_string()),
+        phone: Some(phone.to_string()),
+        ..Default::default()
+    }
+}
+
+/// A small fixed address book so snapshots don't depend on random seed data.
+pub fn address_book() -> Vec<Contact> {
+    vec![
+        contact("Ada Lovelace", "Analytical Engines", "ada@example.com", "0400 000 001"),
+        contact("Alan Turing", "Bletchley Park", "alan@example.com", "0400 000 002"),
+        contact("Grace Hopper", "US Navy", "grace@example.com", "0400 000 003"),
+        contact("Edsger Dijkstra", "Eindhoven", "edsger@example.com", "0400 000 004"),
+        contact("Barbara Liskov", "MIT", "barbara@example.com", "0400 000 005"),
+    ]
+}
@@ -1,7 +1,18 @@
-use rolodex::{
-    components::app::{App, AppMode, AppMsg},
-    model::Contact,
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{Terminal, backend::TestBackend, layout::Rect};
+use std::time::{Duration, Instant};
+use rolodex_tui::{
     Db,
+    clipboard::osc52,
+    components::{
+        app::{App, AppMsg, Preselect},
+        notifications::Level,
+    },
+    hooks::{ActionHooks, LifecycleHooks},
+    mode::AppMode,
+    model::{Contact, SortOrder},
+    store::{ContactStore, FileStore, MemoryStore},
+    vi::ViMode,
 };
 use tempfile::tempdir;
 
@@ -38,7 +49,7 @@ fn test_app_add_contact_mode() {
     
     let result = app.update(AppMsg::AddContact, |msg| msg);
     assert!(result.is_none());
-    assert_eq!(app.mode, AppMode::Form);
+    assert_eq!(app.mode, AppMode::ContactForm);
 }
 
 #[test]
@@ -51,13 +62,14 @@ fn test_app_edit_contact_mode() {
         email: Some("test@example.com".to_string()),
         phone: Some("0412345678".to_string()),
         company: Some("Test Corp".to_string()),
+        ..Default::default()
     };
     
     app.selected_contact = Some(contact.clone());
     
     let result = app.update(AppMsg::EditContact(contact), |msg| msg);
     assert!(result.is_none());
-    assert_eq!(app.mode, AppMode::Form);
+    assert_eq!(app.mode, AppMode::ContactForm);
 }
 
 #[test]
@@ -70,6 +82,7 @@ fn test_app_confirm_delete_mode() {
         email: Some("test@example.com".to_string()),
         phone: Some("0412345678".to_string()),
         company: Some("Test Corp".to_string()),
+        ..Default::default()
     };
     
     let result = app.update(AppMsg::ConfirmDelete(contact), |msg| msg);
@@ -84,7 +97,7 @@ fn test_app_show_error_mode() {
     let error_message = "Test error message".to_string();
     let result = app.update(AppMsg::ShowError(error_message), |msg| msg);
     assert!(result.is_none());
-    assert_eq!(app.mode, AppMode::Error);
+    assert!(matches!(app.mode, AppMode::Error(_)));
 }
 
 #[test]
@@ -96,6 +109,708 @@ fn test_app_with_seeded_data() {
     db.seed(3).unwrap();
     
     let app = App::new(db).unwrap();
-    let contacts = app.db.load_customers().unwrap();
-    assert_eq!(contacts.len(), 3);
-}
\ No newline at end of file
+    assert_eq!(app.browse.all_contacts.len(), 3);
+}
+fn press(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+    if let Some(mut msg) = app.handle_key(KeyEvent::new(code, modifiers)) {
+        while let Some(next) = app.update(msg, |msg| msg) {
+            msg = next;
+        }
+    }
+    app.drain_db(true);
+}
+
+fn type_keys(app: &mut App, keys: &str) {
+    for c in keys.chars() {
+        press(app, KeyCode::Char(c), KeyModifiers::NONE);
+    }
+}
+
+fn create_seeded_vi_app(count: u32) -> (App, tempfile::TempDir) {
+    let temp_dir = tempdir().unwrap();
+    let db_path = temp_dir.path().join("test.db");
+    let mut db = Db::open(&db_path).unwrap();
+    db.seed(count).unwrap();
+    let mut app = App::new(db).unwrap();
+    app.set_vi_mode(true);
+    (app, temp_dir)
+}
+
+#[test]
+fn test_vi_normal_mode_navigation() {
+    let (mut app, _temp_dir) = create_seeded_vi_app(20);
+
+    type_keys(&mut app, "5j");
+    assert_eq!(app.browse.contact_list.selected_index, 5);
+    type_keys(&mut app, "2k");
+    assert_eq!(app.browse.contact_list.selected_index, 3);
+    type_keys(&mut app, "G");
+    assert_eq!(app.browse.contact_list.selected_index, 19);
+    type_keys(&mut app, "gg");
+    assert_eq!(app.browse.contact_list.selected_index, 0);
+    type_keys(&mut app, "10G");
+    assert_eq!(app.browse.contact_list.selected_index, 9);
+
+    // Letters never reach the search box in normal mode
+    assert!(app.browse.search.value.is_empty());
+}
+
+#[test]
+fn test_vi_search_and_commands() {
+    let (mut app, _temp_dir) = create_seeded_vi_app(5);
+
+    type_keys(&mut app, "/ab");
+    assert_eq!(app.browse.search.value, "ab");
+    assert_eq!(app.vi.as_ref().unwrap().mode, ViMode::Insert);
+
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    assert_eq!(app.vi.as_ref().unwrap().mode, ViMode::Normal);
+    press(&mut app, KeyCode::Char('l'), KeyModifiers::CONTROL);
+    assert!(app.browse.search.value.is_empty());
+
+    type_keys(&mut app, "o");
+    assert_eq!(app.mode, AppMode::ContactForm);
+    assert_eq!(app.vi.as_ref().unwrap().mode, ViMode::Insert);
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Browse);
+
+    type_keys(&mut app, "dd");
+    assert_eq!(app.mode, AppMode::Delete);
+}
+
+#[test]
+fn test_paging_an_empty_list() {
+    let (mut app, _temp_dir) = create_seeded_vi_app(5);
+    app.set_vi_mode(false);
+    type_keys(&mut app, "zzzzqqq");
+    assert!(app.browse.contact_list.get_selected_contact().is_none());
+
+    press(&mut app, KeyCode::PageDown, KeyModifiers::NONE);
+    press(&mut app, KeyCode::PageUp, KeyModifiers::NONE);
+    assert_eq!(app.browse.contact_list.selected_index, 0);
+}
+
+#[test]
+fn test_vi_jumps_in_an_empty_list() {
+    let (mut app, _temp_dir) = create_seeded_vi_app(5);
+    type_keys(&mut app, "/zzzzqqq");
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    assert!(app.browse.contact_list.get_selected_contact().is_none());
+
+    type_keys(&mut app, "G");
+    type_keys(&mut app, "gg");
+    type_keys(&mut app, "3G");
+    assert_eq!(app.browse.contact_list.selected_index, 0);
+}
+
+fn render(app: &App) -> Terminal<TestBackend> {
+    let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+    terminal.draw(|f| app.draw(f, f.area(), false)).unwrap();
+    terminal
+}
+
+fn mouse(app: &mut App, kind: MouseEventKind, column: u16, row: u16) {
+    render(app);
+    let event = MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    };
+    if let Some(mut msg) = app.handle_mouse(event) {
+        while let Some(next) = app.update(msg, |msg| msg) {
+            msg = next;
+        }
+    }
+    app.drain_db(true);
+}
+
+/// Screen position of the first cell showing `text`.
+fn find_text(app: &App, text: &str) -> (u16, u16) {
+    let terminal = render(app);
+    let buffer = terminal.backend().buffer();
+    for y in 0..buffer.area.height {
+        let line: String = (0..buffer.area.width)
+            .map(|x| buffer[(x, y)].symbol().to_string())
+            .collect();
+        if let Some(x) = line.find(text) {
+            return (line[..x].chars().count() as u16, y);
+        }
+    }
+    panic!("{text:?} not on screen");
+}
+
+#[test]
+fn test_mouse_click_selects_and_double_click_activates() {
+    let (mut app, _temp_dir) = create_seeded_vi_app(5);
+    let target = app.browse.contact_list.contact(2).unwrap().clone();
+    let (x, y) = find_text(&app, &target.name);
+
+    mouse(&mut app, MouseEventKind::Down(MouseButton::Left), x, y);
+    assert_eq!(app.browse.contact_list.selected_index, 2);
+    assert!(!app.should_quit);
+
+    mouse(&mut app, MouseEventKind::Down(MouseButton::Left), x, y);
+    assert!(app.should_quit);
+    assert_eq!(app.selected_contact.map(|c| c.id), Some(target.id));
+}
+
+#[test]
+fn test_mouse_scroll_moves_selection() {
+    let (mut app, _temp_dir) = create_seeded_vi_app(20);
+
+    mouse(&mut app, MouseEventKind::ScrollDown, 10, 10);
+    assert_eq!(app.browse.contact_list.selected_index, 3);
+    mouse(&mut app, MouseEventKind::ScrollUp, 10, 10);
+    mouse(&mut app, MouseEventKind::ScrollUp, 10, 10);
+    assert_eq!(app.browse.contact_list.selected_index, 0);
+
+    // Clicks outside the list are ignored
+    mouse(&mut app, MouseEventKind::Down(MouseButton::Left), 0, 0);
+    assert_eq!(app.browse.contact_list.selected_index, 0);
+}
+
+#[test]
+fn test_mouse_delete_buttons() {
+    let (mut app, temp_dir) = create_seeded_vi_app(3);
+
+    type_keys(&mut app, "dd");
+    assert_eq!(app.mode, AppMode::Delete);
+    let (x, y) = find_text(&app, "[ n/Esc: Cancel ]");
+    mouse(&mut app, MouseEventKind::Down(MouseButton::Left), x + 2, y);
+    assert_eq!(app.mode, AppMode::Browse);
+
+    type_keys(&mut app, "dd");
+    let (x, y) = find_text(&app, "[ y: Confirm ]");
+    mouse(&mut app, MouseEventKind::Down(MouseButton::Left), x + 2, y);
+    assert_eq!(app.mode, AppMode::Browse);
+    assert_eq!(app.browse.all_contacts.len(), 2);
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    assert_eq!(db.load_customers().unwrap().len(), 2);
+}
+
+fn screen_contains(app: &App, text: &str) -> bool {
+    let terminal = render(app);
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height).any(|y| {
+        let line: String = (0..buffer.area.width)
+            .map(|x| buffer[(x, y)].symbol().to_string())
+            .collect();
+        line.contains(text)
+    })
+}
+
+#[test]
+fn test_help_overlay_lists_mode_bindings() {
+    let (mut app, _temp_dir) = create_test_app();
+
+    press(&mut app, KeyCode::Char('?'), KeyModifiers::SHIFT);
+    assert_eq!(app.mode, AppMode::Help);
+    let titles: Vec<&str> = app.help.sections().iter().map(|(t, _)| t.as_str()).collect();
+    assert_eq!(titles, ["Contact list", "Everywhere"]);
+    assert!(screen_contains(&app, "Reverse the sort order"));
+
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Browse);
+
+    // Help opened over a form lists the form's bindings and returns to it
+    press(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL);
+    press(&mut app, KeyCode::F(1), KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Help);
+    assert_eq!(app.help.sections()[0].0, "Contact form");
+    assert!(screen_contains(&app, "Save the contact"));
+    press(&mut app, KeyCode::F(1), KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::ContactForm);
+}
+
+#[test]
+fn test_command_palette_dispatches_actions() {
+    let (mut app, _temp_dir) = create_seeded_vi_app(3);
+
+    press(&mut app, KeyCode::Char('p'), KeyModifiers::CONTROL);
+    assert_eq!(app.mode, AppMode::Palette);
+    assert_eq!(app.palette.matches.len(), 18);
+
+    type_keys(&mut app, "reverse");
+    assert!(screen_contains(&app, "Reverse the sort order"));
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Browse);
+    assert_eq!(app.browse.sort().order, SortOrder::Desc);
+
+    press(&mut app, KeyCode::Char('p'), KeyModifiers::CONTROL);
+    type_keys(&mut app, "delete");
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Delete);
+
+    press(&mut app, KeyCode::Char('p'), KeyModifiers::CONTROL);
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Delete);
+}
+
+#[test]
+fn test_saving_contact_shows_expiring_toast() {
+    let (mut app, _temp_dir) = create_test_app();
+
+    press(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL);
+    type_keys(&mut app, "Ada Lovelace");
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Browse);
+
+    let toasts: Vec<_> = app.notifications.toasts().collect();
+    assert_eq!(toasts.len(), 1);
+    assert_eq!(toasts[0].level, Level::Success);
+    assert_eq!(toasts[0].message, "Added Ada Lovelace");
+    assert!(screen_contains(&app, "Added Ada Lovelace"));
+
+    let later = Instant::now() + Duration::from_secs(10);
+    app.update(AppMsg::Tick(later), |msg| msg);
+    assert_eq!(app.notifications.toasts().count(), 0);
+    assert_eq!(app.notifications.log().len(), 1);
+}
+
+#[test]
+fn test_message_log_keeps_session_history() {
+    let (mut app, _temp_dir) = create_test_app();
+
+    for i in 0..6 {
+        app.update(AppMsg::Notify(Level::Info, format!("message {i}")), |msg| msg);
+    }
+    app.update(AppMsg::Notify(Level::Error, "disk full".into()), |msg| msg);
+    // Only the newest toasts stay on screen
+    assert_eq!(app.notifications.toasts().count(), 4);
+    assert_eq!(app.mode, AppMode::Browse);
+
+    press(&mut app, KeyCode::Char('o'), KeyModifiers::CONTROL);
+    assert_eq!(app.mode, AppMode::Log);
+    assert_eq!(app.message_log.entries().len(), 7);
+    assert!(screen_contains(&app, "disk full"));
+    assert!(screen_contains(&app, "message 0"));
+
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Browse);
+}
+
+#[test]
+fn test_saves_run_on_worker_with_loading_indicator() {
+    let (mut app, _temp_dir) = create_test_app();
+
+    press(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL);
+    type_keys(&mut app, "Grace Hopper");
+    let msg = app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+    assert!(app.update(msg.unwrap(), |msg| msg).is_none());
+
+    // The form waits for the worker instead of blocking on the database
+    assert_eq!(app.mode, AppMode::ContactForm);
+    assert!(app.worker.is_busy());
+    let activity = app.status_bar.get_activity_text().unwrap();
+    assert!(activity.ends_with("Saving…"));
+
+    app.drain_db(true);
+    assert_eq!(app.mode, AppMode::Browse);
+    assert!(app.status_bar.get_activity_text().is_none());
+    assert_eq!(app.browse.all_contacts.len(), 1);
+    assert_eq!(app.browse.all_contacts[0].name, "Grace Hopper");
+}
+
+fn send(app: &mut App, event: Event) {
+    if let Some(mut msg) = app.handle_event(event) {
+        while let Some(next) = app.update(msg, |msg| msg) {
+            msg = next;
+        }
+    }
+    app.drain_db(true);
+}
+
+/// Draw the way `App::run` does, returning whether a frame was drawn.
+fn draw_if_needed(terminal: &mut Terminal<TestBackend>, app: &mut App) -> bool {
+    if !app.needs_redraw {
+        return false;
+    }
+    terminal.draw(|f| app.draw(f, f.area(), false)).unwrap();
+    app.needs_redraw = false;
+    true
+}
+
+#[test]
+fn test_redraws_only_after_state_changes() {
+    let (mut app, _temp_dir) = create_test_app();
+    let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+
+    assert!(draw_if_needed(&mut terminal, &mut app));
+    // Idle ticks with nothing on screen changing don't redraw
+    app.update(AppMsg::Tick(Instant::now()), |msg| msg);
+    assert!(!draw_if_needed(&mut terminal, &mut app));
+
+    send(&mut app, Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE)));
+    assert!(draw_if_needed(&mut terminal, &mut app));
+
+    app.update(AppMsg::Notify(Level::Info, "hello".into()), |msg| msg);
+    assert!(draw_if_needed(&mut terminal, &mut app));
+    app.update(AppMsg::Tick(Instant::now()), |msg| msg);
+    assert!(!draw_if_needed(&mut terminal, &mut app));
+    // Expiring the toast is a visible change
+    app.update(AppMsg::Tick(Instant::now() + Duration::from_secs(10)), |msg| msg);
+    assert!(draw_if_needed(&mut terminal, &mut app));
+}
+
+#[test]
+fn test_resize_redraws_at_new_size() {
+    let (mut app, _temp_dir) = create_seeded_vi_app(3);
+    let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+    draw_if_needed(&mut terminal, &mut app);
+
+    terminal.backend_mut().resize(120, 40);
+    send(&mut app, Event::Resize(120, 40));
+    assert!(draw_if_needed(&mut terminal, &mut app));
+
+    let buffer = terminal.backend().buffer();
+    assert_eq!(buffer.area, Rect::new(0, 0, 120, 40));
+    // The status bar moves to the new bottom row
+    let bottom: String = (0..buffer.area.width)
+        .map(|x| buffer[(x, 39)].symbol().to_string())
+        .collect();
+    assert!(bottom.contains("Contacts: 3/3"), "{bottom:?}");
+}
+
+#[test]
+fn test_paste_goes_to_focused_input_without_newlines() {
+    let (mut app, _temp_dir) = create_test_app();
+
+    send(&mut app, Event::Paste("Ada\r\n".into()));
+    assert_eq!(app.browse.search.value, "Ada");
+
+    press(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL);
+    send(&mut app, Event::Paste("Grace\nHopper".into()));
+    assert!(screen_contains(&app, "GraceHopper"));
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+
+    press(&mut app, KeyCode::Char('p'), KeyModifiers::CONTROL);
+    send(&mut app, Event::Paste("help".into()));
+    assert_eq!(app.palette.query.value, "help");
+}
+
+#[test]
+fn test_cursor_moves_by_characters_after_non_ascii_paste() {
+    let (mut app, _temp_dir) = create_test_app();
+
+    send(&mut app, Event::Paste("José".into()));
+    press(&mut app, KeyCode::Left, KeyModifiers::NONE);
+    press(&mut app, KeyCode::Backspace, KeyModifiers::NONE);
+    assert_eq!(app.browse.search.value, "Joé");
+    press(&mut app, KeyCode::Char('x'), KeyModifiers::NONE);
+    assert_eq!(app.browse.search.value, "Joxé");
+}
+
+#[test]
+fn test_toasts_wait_while_terminal_is_unfocused() {
+    let (mut app, _temp_dir) = create_test_app();
+
+    send(&mut app, Event::FocusLost);
+    assert!(!app.focused);
+    app.update(AppMsg::Notify(Level::Info, "saved".into()), |msg| msg);
+    app.update(AppMsg::Tick(Instant::now() + Duration::from_secs(10)), |msg| msg);
+    assert_eq!(app.notifications.toasts().count(), 1);
+
+    // Regaining focus restarts the toast's timer rather than expiring it at once
+    send(&mut app, Event::FocusGained);
+    assert!(app.focused);
+    app.update(AppMsg::Tick(Instant::now() + Duration::from_secs(1)), |msg| msg);
+    assert_eq!(app.notifications.toasts().count(), 1);
+    app.update(AppMsg::Tick(Instant::now() + Duration::from_secs(10)), |msg| msg);
+    assert_eq!(app.notifications.toasts().count(), 0);
+}
+
+#[test]
+fn test_starred_contacts_pin_to_top_and_use_is_counted() {
+    let (mut app, temp_dir) = create_seeded_vi_app(5);
+    let target = app.browse.contact_list.contact(3).unwrap().clone();
+
+    type_keys(&mut app, "3j*");
+    assert_eq!(app.browse.contact_list.contact(0).unwrap().id, target.id);
+    assert!(app.browse.contact_list.contact(0).unwrap().favorite);
+    // The starred contact stays selected as it moves
+    assert_eq!(app.browse.contact_list.selected_index, 0);
+    assert!(screen_contains(&app, &format!("★ {}", target.name)));
+
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert!(app.should_quit);
+    drop(app);
+
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    let used = db.load_customers().unwrap();
+    let used = used.iter().find(|c| c.id == target.id).unwrap();
+    assert_eq!(used.use_count, 1);
+    assert!(used.last_used_at.is_some());
+}
+
+fn create_named_app(names: &[&str]) -> (App, tempfile::TempDir) {
+    let temp_dir = tempdir().unwrap();
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    for name in names {
+        db.add_contact(Contact {
+            name: name.to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+    }
+    (App::new(db).unwrap(), temp_dir)
+}
+
+#[test]
+fn test_query_prefills_search() {
+    let (mut app, _temp_dir) = create_named_app(&["Ada Lovelace", "Alan Turing", "Grace Hopper"]);
+
+    app.set_query("grace");
+    assert_eq!(app.browse.search.value, "grace");
+    assert_eq!(app.browse.contact_list.len(), 1);
+    assert_eq!(app.selected_contact.as_ref().unwrap().name, "Grace Hopper");
+    assert!(screen_contains(&app, "Contacts: 1/3"));
+
+    // Without the picker flags the UI is always shown
+    assert_eq!(app.preselect(false, false), None);
+}
+
+#[test]
+fn test_select_one_and_exit_0() {
+    let (mut app, temp_dir) = create_named_app(&["Ada Lovelace", "Alan Turing", "Grace Hopper"]);
+
+    app.set_query("a");
+    assert_eq!(app.preselect(true, true), None);
+
+    app.set_query("zzz");
+    assert_eq!(app.preselect(true, false), None);
+    assert_eq!(app.preselect(false, true), Some(Preselect::NoMatches));
+
+    app.set_query("turing");
+    let Some(Preselect::Selected(contact)) = app.preselect(true, true) else {
+        panic!("expected a single match");
+    };
+    assert_eq!(contact.name, "Alan Turing");
+    assert!(app.should_quit);
+
+    // Picking the only match counts as a use
+    drop(app);
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    let turing = db.search_contacts("turing").unwrap();
+    assert_eq!(turing[0].use_count, 1);
+}
+
+#[test]
+fn test_add_only_quits_when_form_closes() {
+    let sender = || Contact {
+        name: "Ada Lovelace".to_string(),
+        email: Some("ada@example.com".to_string()),
+        ..Default::default()
+    };
+
+    let (mut app, temp_dir) = create_test_app();
+    app.add_only(sender());
+    assert_eq!(app.mode, AppMode::ContactForm);
+    assert!(screen_contains(&app, "ada@example.com"));
+    press(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+    assert!(app.should_quit);
+    assert!(app.selected_contact.is_none());
+
+    let mut app = App::new(Db::open(temp_dir.path().join("test.db")).unwrap()).unwrap();
+    app.add_only(sender());
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert!(app.should_quit);
+    assert_eq!(app.selected_contact.unwrap().email.as_deref(), Some("ada@example.com"));
+
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    assert_eq!(db.search_by_field("email", "ada@").unwrap().len(), 1);
+}
+
+#[test]
+fn test_action_hooks_report_in_status_bar() {
+    let (mut app, _temp_dir) = create_named_app(&["Ada Lovelace"]);
+    app.set_hooks(ActionHooks {
+        call: Some("echo dialling {name}".to_string()),
+        mail: Some("echo bounced >&2; exit 2".to_string()),
+        open: None,
+    });
+
+    press(&mut app, KeyCode::Char('c'), KeyModifiers::ALT);
+    app.drain_hooks(true);
+    assert!(screen_contains(&app, "call: done: dialling Ada Lovelace"));
+    assert_eq!(app.notifications.log().len(), 0);
+
+    // Typing clears the result again
+    type_keys(&mut app, "a");
+    assert!(!screen_contains(&app, "call: done"));
+
+    press(&mut app, KeyCode::Char('m'), KeyModifiers::ALT);
+    app.drain_hooks(true);
+    assert!(screen_contains(&app, "mail: exit 2: bounced"));
+    assert_eq!(app.notifications.log()[0].level, Level::Error);
+
+    // Unconfigured actions only warn
+    press(&mut app, KeyCode::Char('o'), KeyModifiers::ALT);
+    assert!(!app.hooks.is_busy());
+    let log = app.notifications.log();
+    assert_eq!(log.last().unwrap().message, "no open command set in config.toml");
+}
+
+#[test]
+fn test_copy_actions_write_osc52() {
+    let temp_dir = tempdir().unwrap();
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    db.add_contact(Contact {
+        name: "Ada Lovelace".to_string(),
+        email: Some("ada@example.com".to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    let mut app = App::new(db).unwrap();
+
+    press(&mut app, KeyCode::Char('n'), KeyModifiers::ALT);
+    let copied = "Ada Lovelace <ada@example.com>";
+    assert_eq!(app.clipboard.take_pending(), vec![osc52(copied, false)]);
+    assert_eq!(app.notifications.log().last().unwrap().message, format!("Copied {copied}"));
+
+    press(&mut app, KeyCode::Char('p'), KeyModifiers::ALT);
+    assert!(app.clipboard.take_pending().is_empty());
+    assert_eq!(app.notifications.log().last().unwrap().level, Level::Warning);
+
+    press(&mut app, KeyCode::Char('v'), KeyModifiers::ALT);
+    assert_eq!(app.clipboard.take_pending().len(), 1);
+    assert_eq!(
+        app.notifications.log().last().unwrap().message,
+        "Copied vCard for Ada Lovelace"
+    );
+}
+
+#[test]
+fn test_failing_lifecycle_hook_keeps_the_write() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    db.set_hooks(LifecycleHooks {
+        on_add: Some("cat > /dev/null; echo push rejected; exit 1".to_string()),
+        ..Default::default()
+    });
+    let mut app = App::new(db).unwrap();
+
+    press(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL);
+    type_keys(&mut app, "Ada Lovelace");
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+
+    assert_eq!(app.mode, AppMode::Browse);
+    assert_eq!(app.browse.all_contacts.len(), 1);
+    let messages: Vec<_> = app.notifications.log().iter().map(|n| n.message.clone()).collect();
+    assert_eq!(
+        messages,
+        ["Added Ada Lovelace", "on_add hook failed with exit 1: push rejected"]
+    );
+}
+
+#[test]
+fn test_app_runs_on_other_stores() {
+    let mut store = MemoryStore::new();
+    store.seed(3).unwrap();
+    let app = App::new(store).unwrap();
+    assert_eq!(app.browse.all_contacts.len(), 3);
+
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().join("contacts.toml");
+    let mut app = App::new(FileStore::open(&path).unwrap()).unwrap();
+    press(&mut app, KeyCode::Char('a'), KeyModifiers::CONTROL);
+    type_keys(&mut app, "Ada Lovelace");
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.browse.all_contacts.len(), 1);
+    drop(app);
+
+    let contacts = FileStore::open(&path).unwrap().load_customers().unwrap();
+    assert_eq!(contacts[0].name, "Ada Lovelace");
+}
+
+#[test]
+fn test_stale_edit_asks_before_overwriting() {
+    let (mut app, temp_dir) = create_named_app(&["Ada Lovelace"]);
+    let contact = app.selected_contact.clone().unwrap();
+    app.update(AppMsg::EditContact(contact.clone()), |msg| msg);
+
+    // Another rolodex saves the same contact while the form is open
+    let other = Db::open(temp_dir.path().join("test.db")).unwrap();
+    let theirs = Contact {
+        name: "Ada King".to_string(),
+        ..other.get_contact(contact.id).unwrap()
+    };
+    other.update_contact(contact.id, theirs).unwrap();
+
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::ContactForm);
+    assert!(app.contact_form.has_conflict());
+    assert!(screen_contains(&app, "Modified elsewhere"));
+    let last = app.notifications.log().last().unwrap();
+    assert_eq!(last.level, Level::Warning);
+    assert_eq!(last.message, "Ada King was modified elsewhere");
+    assert_eq!(other.get_contact(contact.id).unwrap().name, "Ada King");
+
+    // Saving again overwrites on purpose
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Browse);
+    assert_eq!(other.get_contact(contact.id).unwrap().name, "Ada Lovelace");
+}
+
+#[test]
+fn test_list_refreshes_after_changes_elsewhere() {
+    let (mut app, temp_dir) = create_named_app(&["Ada Lovelace", "Alan Turing"]);
+    press(&mut app, KeyCode::Down, KeyModifiers::NONE);
+    let selected = app.selected_contact.clone().unwrap();
+
+    let other = Db::open(temp_dir.path().join("test.db")).unwrap();
+    other
+        .add_contact(Contact {
+            name: "Aaron Swartz".to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+
+    // Not due yet
+    app.update(AppMsg::Tick(Instant::now()), |msg| msg);
+    app.drain_db(true);
+    assert_eq!(app.browse.all_contacts.len(), 2);
+
+    app.update(AppMsg::Tick(Instant::now() + Duration::from_secs(3)), |msg| msg);
+    app.drain_db(true);
+    assert_eq!(app.browse.all_contacts.len(), 3);
+    assert_eq!(app.selected_contact.unwrap().id, selected.id);
+}
+
+#[test]
+fn test_palette_exports_and_lists_duplicates() {
+    let (mut app, temp_dir) =
+        create_named_app(&["Ada Lovelace", "Alan Turing", "Grace Hopper", "ada lovelace"]);
+    app.export_dir = temp_dir.path().to_path_buf();
+
+    press(&mut app, KeyCode::Char('p'), KeyModifiers::CONTROL);
+    type_keys(&mut app, "export");
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    let message = &app.notifications.log().last().unwrap().message;
+    assert!(message.starts_with("Exported contacts to "), "{message}");
+    let exported: Vec<_> = std::fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    let json = std::fs::read_to_string(&exported[0]).unwrap();
+    assert_eq!(serde_json::from_str::<Vec<Contact>>(&json).unwrap().len(), 4);
+
+    // A search hiding the duplicates is cleared to show the picked one
+    type_keys(&mut app, "grace");
+    press(&mut app, KeyCode::Char('p'), KeyModifiers::CONTROL);
+    type_keys(&mut app, "duplicates");
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Duplicates);
+    assert_eq!(app.duplicates.groups().len(), 1);
+    assert!(screen_contains(&app, "Group 1"));
+    assert!(screen_contains(&app, "ada lovelace"));
+
+    press(&mut app, KeyCode::Down, KeyModifiers::NONE);
+    let picked = app.duplicates.selected_contact().unwrap().clone();
+    press(&mut app, KeyCode::Enter, KeyModifiers::NONE);
+    assert_eq!(app.mode, AppMode::Browse);
+    assert_eq!(app.browse.search.value, "");
+    assert_eq!(app.selected_contact.unwrap().id, picked.id);
+}
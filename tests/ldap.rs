@@ -0,0 +1,308 @@
+use std::{
+    io::Write,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+};
+
+use rolodex_tui::{
+    Db,
+    model::Contact,
+    server::{
+        Ldap,
+        ber::{self, BOOLEAN, ENUMERATED, INTEGER, OCTET_STRING, SEQUENCE, SET, Tlv},
+    },
+};
+use tempfile::{TempDir, tempdir};
+
+const BASE: &str = "ou=contacts,dc=rolodex";
+
+type Attributes = Vec<(String, Vec<String>)>;
+
+/// What a search returned: entries as DN and attributes, and the result code.
+struct Found {
+    entries: Vec<(String, Attributes)>,
+    code: i64,
+}
+
+impl Found {
+    fn dns(&self) -> Vec<&str> {
+        self.entries.iter().map(|(dn, _)| dn.as_str()).collect()
+    }
+
+    fn values(&self, dn: &str, attribute: &str) -> Vec<&str> {
+        let (_, attributes) = self.entries.iter().find(|(d, _)| d == dn).unwrap();
+        let values = attributes.iter().find(|(name, _)| name == attribute);
+        values.map_or(Vec::new(), |(_, values)| values.iter().map(String::as_str).collect())
+    }
+}
+
+/// Just enough of an LDAP client to bind and search.
+struct Client {
+    stream: TcpStream,
+    next_id: i64,
+}
+
+impl Client {
+    fn connect(addr: SocketAddr) -> Self {
+        Self {
+            stream: TcpStream::connect(addr).unwrap(),
+            next_id: 1,
+        }
+    }
+
+    fn send(&mut self, operation: Tlv) {
+        let message = Tlv::constructed(SEQUENCE, [Tlv::integer(INTEGER, self.next_id), operation]);
+        self.next_id += 1;
+        self.stream.write_all(&message.encode()).unwrap();
+    }
+
+    /// The protocol operation of the next message, checking its ID.
+    fn receive(&mut self) -> Tlv {
+        let message = ber::read(&mut self.stream).unwrap().unwrap();
+        let mut parts = message.children().unwrap();
+        assert_eq!(parts[0].as_integer().unwrap(), self.next_id - 1);
+        parts.remove(1)
+    }
+
+    fn result_code(operation: &Tlv) -> i64 {
+        let parts = operation.children().unwrap();
+        assert_eq!(parts[0].tag, ENUMERATED);
+        parts[0].as_integer().unwrap()
+    }
+
+    fn bind(&mut self, name: &str, password: &str) -> i64 {
+        let request = [
+            Tlv::integer(INTEGER, 3),
+            Tlv::string(OCTET_STRING, name),
+            Tlv::string(0x80, password),
+        ];
+        self.send(Tlv::constructed(0x60, request));
+        let response = self.receive();
+        assert_eq!(response.tag, 0x61);
+        Self::result_code(&response)
+    }
+
+    fn search(&mut self, base: &str, scope: i64, filter: Tlv, attributes: &[&str]) -> Found {
+        self.search_limited(base, scope, filter, attributes, 0)
+    }
+
+    fn search_limited(
+        &mut self,
+        base: &str,
+        scope: i64,
+        filter: Tlv,
+        attributes: &[&str],
+        size_limit: i64,
+    ) -> Found {
+        let attributes = attributes.iter().map(|a| Tlv::string(OCTET_STRING, a));
+        let request = [
+            Tlv::string(OCTET_STRING, base),
+            Tlv::integer(ENUMERATED, scope),
+            Tlv::integer(ENUMERATED, 0),
+            Tlv::integer(INTEGER, size_limit),
+            Tlv::integer(INTEGER, 0),
+            Tlv::new(BOOLEAN, [0]),
+            filter,
+            Tlv::constructed(SEQUENCE, attributes),
+        ];
+        self.send(Tlv::constructed(0x63, request));
+
+        let mut entries = Vec::new();
+        loop {
+            let response = self.receive();
+            if response.tag == 0x65 {
+                return Found {
+                    entries,
+                    code: Self::result_code(&response),
+                };
+            }
+            assert_eq!(response.tag, 0x64);
+            let parts = response.children().unwrap();
+            let attributes = parts[1].children().unwrap();
+            let attributes = attributes.iter().map(|attribute| {
+                let parts = attribute.children().unwrap();
+                assert_eq!(parts[1].tag, SET);
+                let values = parts[1].children().unwrap();
+                let values = values.iter().map(|v| v.as_str().unwrap().to_string()).collect();
+                (parts[0].as_str().unwrap().to_string(), values)
+            });
+            entries.push((parts[0].as_str().unwrap().to_string(), attributes.collect()));
+        }
+    }
+}
+
+fn equal(attribute: &str, value: &str) -> Tlv {
+    let assertion = [Tlv::string(OCTET_STRING, attribute), Tlv::string(OCTET_STRING, value)];
+    Tlv::constructed(0xa3, assertion)
+}
+
+/// `attribute=*any*`, or `attribute=initial*` when `initial` is set.
+fn substring(attribute: &str, value: &str, initial: bool) -> Tlv {
+    let tag = if initial { 0x80 } else { 0x81 };
+    let substrings = Tlv::constructed(SEQUENCE, [Tlv::string(tag, value)]);
+    Tlv::constructed(0xa4, [Tlv::string(OCTET_STRING, attribute), substrings])
+}
+
+fn present(attribute: &str) -> Tlv {
+    Tlv::string(0x87, attribute)
+}
+
+fn seeded_db(temp_dir: &TempDir) -> Db {
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    let contacts = [
+        ("Ada Lovelace", "ada@example.com", "+44 20 7946 0000", "Analytical Engines"),
+        ("Alan Turing", "alan@example.com", "", "Bletchley Park"),
+        ("Grace Hopper", "grace@example.com", "+1-202-555-0100", "US Navy"),
+    ];
+    for (name, email, phone, company) in contacts {
+        db.add_contact(Contact {
+            name: name.to_string(),
+            email: Some(email.to_string()),
+            phone: Some(phone.to_string()).filter(|p| !p.is_empty()),
+            company: Some(company.to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    }
+    db
+}
+
+/// Serve `db` on a free local port for the rest of the test.
+fn start(db: Db) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let ldap = Arc::new(Ldap::new(db, BASE.to_string()));
+    thread::spawn(move || ldap.serve_tcp(listener));
+    addr
+}
+
+#[test]
+fn test_bind_and_discovery() {
+    let temp_dir = tempdir().unwrap();
+    let mut client = Client::connect(start(seeded_db(&temp_dir)));
+
+    assert_eq!(client.bind("", ""), 0);
+    assert_eq!(client.bind("cn=phone", ""), 0);
+    assert_eq!(client.bind("cn=admin", "s3cret"), 49);
+
+    let found = client.search("", 0, present("objectClass"), &["namingContexts"]);
+    assert_eq!(found.code, 0);
+    assert_eq!(found.dns(), [""]);
+    assert_eq!(found.values("", "namingContexts"), [BASE]);
+    assert!(found.values("", "objectClass").is_empty());
+
+    let found = client.search("OU=Contacts, DC=rolodex", 0, present("objectClass"), &[]);
+    assert_eq!(found.dns(), [BASE]);
+    assert_eq!(found.values(BASE, "ou"), ["contacts"]);
+
+    let found = client.search(&format!("uid=2,{BASE}"), 0, present("objectClass"), &["cn"]);
+    assert_eq!(found.values(&format!("uid=2,{BASE}"), "cn"), ["Alan Turing"]);
+
+    let found = client.search(&format!("uid=99,{BASE}"), 0, present("objectClass"), &[]);
+    assert_eq!((found.code, found.entries.len()), (32, 0));
+    assert_eq!(client.search("dc=elsewhere", 2, present("cn"), &[]).code, 32);
+}
+
+#[test]
+fn test_search_filters() {
+    let temp_dir = tempdir().unwrap();
+    let mut client = Client::connect(start(seeded_db(&temp_dir)));
+    client.bind("", "");
+    let mut dns = |filter: Tlv| {
+        let found = client.search(BASE, 1, filter, &["1.1"]);
+        assert_eq!(found.code, 0);
+        found.entries.iter().for_each(|(_, attributes)| assert!(attributes.is_empty()));
+        found.dns().iter().map(|dn| dn.to_string()).collect::<Vec<_>>()
+    };
+    let uid = |id: i64| format!("uid={id},{BASE}");
+
+    assert_eq!(dns(present("objectClass")), [uid(1), uid(2), uid(3)]);
+    assert_eq!(dns(equal("mail", "GRACE@example.com")), [uid(3)]);
+    assert_eq!(dns(equal("commonName", "alan turing")), [uid(2)]);
+    assert_eq!(dns(equal("telephoneNumber", "+12025550100")), [uid(3)]);
+    assert_eq!(dns(substring("cn", "a", true)), [uid(1), uid(2)]);
+    assert_eq!(dns(substring("o", "park", false)), [uid(2)]);
+    assert_eq!(dns(substring("telephoneNumber", "7946", false)), [uid(1)]);
+    assert_eq!(dns(present("telephoneNumber")), [uid(1), uid(3)]);
+
+    // The filter a mail client sends while completing "gr"
+    let completion = Tlv::constructed(
+        0xa0,
+        [
+            equal("objectClass", "inetOrgPerson"),
+            Tlv::constructed(0xa1, [substring("cn", "gr", true), substring("mail", "gr", true)]),
+        ],
+    );
+    assert_eq!(dns(completion), [uid(3)]);
+    let not = Tlv::constructed(0xa2, [substring("cn", "a", true)]);
+    assert_eq!(dns(not), [uid(3)]);
+    assert!(dns(equal("shoeSize", "9")).is_empty());
+}
+
+#[test]
+fn test_entries_and_limits() {
+    let temp_dir = tempdir().unwrap();
+    let mut client = Client::connect(start(seeded_db(&temp_dir)));
+
+    let found = client.search(BASE, 2, equal("cn", "Ada Lovelace"), &[]);
+    let ada = format!("uid=1,{BASE}");
+    assert_eq!(found.dns(), [ada.as_str()]);
+    assert_eq!(found.values(&ada, "sn"), ["Lovelace"]);
+    assert_eq!(found.values(&ada, "givenName"), ["Ada"]);
+    assert_eq!(found.values(&ada, "mail"), ["ada@example.com"]);
+    assert_eq!(found.values(&ada, "telephoneNumber"), ["+44 20 7946 0000"]);
+    assert_eq!(found.values(&ada, "o"), ["Analytical Engines"]);
+    assert!(found.values(&ada, "objectClass").contains(&"inetOrgPerson"));
+
+    let found = client.search(BASE, 2, present("objectClass"), &["MAIL"]);
+    assert_eq!(found.entries.len(), 4);
+    assert_eq!(found.values(&ada, "mail"), ["ada@example.com"]);
+    assert!(found.values(&ada, "cn").is_empty());
+
+    let found = client.search_limited(BASE, 1, present("cn"), &["cn"], 2);
+    assert_eq!((found.code, found.entries.len()), (4, 2));
+
+    // Writes are refused, and the connection carries on
+    let delete = Tlv::string(0x4a, &ada);
+    client.send(delete);
+    let response = client.receive();
+    assert_eq!((response.tag, Client::result_code(&response)), (0x6b, 53));
+    assert_eq!(client.search(BASE, 1, present("cn"), &[]).entries.len(), 3);
+
+    client.send(Tlv::new(0x42, []));
+    assert!(ber::read(&mut client.stream).unwrap().is_none());
+}
+
+#[test]
+fn test_deeply_nested_filter_is_refused() {
+    let temp_dir = tempdir().unwrap();
+    let addr = start(seeded_db(&temp_dir));
+
+    // Thousands of nested NOTs, well past the limit but within a message's
+    // size, used to overflow the stack and take the server down
+    let mut filter = present("cn");
+    for _ in 0..3_000 {
+        filter = Tlv::constructed(0xa2, [filter]);
+    }
+    let mut client = Client::connect(addr);
+    client.send(Tlv::constructed(
+        0x63,
+        [
+            Tlv::string(OCTET_STRING, BASE),
+            Tlv::integer(ENUMERATED, 1),
+            Tlv::integer(ENUMERATED, 0),
+            Tlv::integer(INTEGER, 0),
+            Tlv::integer(INTEGER, 0),
+            Tlv::new(BOOLEAN, [0]),
+            filter,
+            Tlv::constructed(SEQUENCE, []),
+        ],
+    ));
+    // The connection is dropped
+    assert!(ber::read(&mut client.stream).map_or(true, |message| message.is_none()));
+
+    let mut client = Client::connect(addr);
+    let not = Tlv::constructed(0xa2, [Tlv::constructed(0xa2, [present("cn")])]);
+    assert_eq!(client.search(BASE, 1, not, &[]).entries.len(), 3);
+}
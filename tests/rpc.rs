@@ -0,0 +1,135 @@
+use rolodex_tui::{Db, model::Contact, server::Rpc};
+use serde_json::{Value, json};
+use tempfile::{TempDir, tempdir};
+
+fn seeded_db(temp_dir: &TempDir) -> Db {
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    let contacts = [("Ada Lovelace", "ada@example.com"), ("Alan Turing", "alan@example.com")];
+    for (name, email) in contacts {
+        db.add_contact(Contact {
+            name: name.to_string(),
+            email: Some(email.to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    }
+    db
+}
+
+/// Feed `lines` to a session and collect the replies, one per line.
+fn session(rpc: &Rpc, lines: &[Value]) -> Vec<Value> {
+    let input: String = lines.iter().map(|line| format!("{line}\n")).collect();
+    let mut output = Vec::new();
+    rpc.serve(input.as_bytes(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    output.lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+}
+
+fn call(rpc: &Rpc, method: &str, params: Value) -> Value {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    rpc.handle_line(&request.to_string()).unwrap()
+}
+
+#[test]
+fn test_methods() {
+    let temp_dir = tempdir().unwrap();
+    let rpc = Rpc::new(seeded_db(&temp_dir));
+    let names = |reply: &Value| -> Vec<String> {
+        let contacts = reply["result"].as_array().unwrap();
+        contacts.iter().map(|c| c["name"].as_str().unwrap().to_string()).collect()
+    };
+
+    let replies = session(
+        &rpc,
+        &[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "search", "params": { "query": "ADA" } }),
+            json!({ "jsonrpc": "2.0", "id": "two", "method": "search" }),
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "search", "params": { "limit": 1 } }),
+        ],
+    );
+    assert_eq!(replies.len(), 3);
+    assert_eq!(replies[0]["id"], 1);
+    assert_eq!(names(&replies[0]), ["Ada Lovelace"]);
+    assert_eq!(replies[1]["id"], "two");
+    assert_eq!(names(&replies[1]), ["Ada Lovelace", "Alan Turing"]);
+    assert_eq!(names(&replies[2]), ["Ada Lovelace"]);
+
+    let reply = call(&rpc, "get", json!({ "id": 2 }));
+    assert_eq!(reply["jsonrpc"], "2.0");
+    assert_eq!(reply["result"]["email"], "alan@example.com");
+
+    let reply = call(&rpc, "add", json!({ "name": "Grace Hopper", "company": "US Navy" }));
+    let id = reply["result"]["id"].as_i64().unwrap();
+    assert_eq!(reply["result"]["company"], "US Navy");
+
+    let params = json!({ "id": id, "name": "Grace Hopper", "email": "grace@navy.example" });
+    let reply = call(&rpc, "update", params);
+    assert_eq!(reply["result"]["email"], "grace@navy.example");
+    assert_eq!(reply["result"]["company"], Value::Null);
+    assert_eq!(call(&rpc, "get", json!({ "id": id }))["result"], reply["result"]);
+}
+
+#[test]
+fn test_errors_carry_codes() {
+    let temp_dir = tempdir().unwrap();
+    let rpc = Rpc::new(seeded_db(&temp_dir));
+    let error = |reply: Value| (reply["error"]["code"].as_i64().unwrap(), reply["error"].clone());
+
+    let (code, error_object) = error(call(&rpc, "get", json!({ "id": 99 })));
+    assert_eq!(code, -32001);
+    assert_eq!(error_object["message"], "contact 99 not found");
+    assert_eq!(error_object["data"], json!({ "kind": "NotFound" }));
+
+    let (code, error_object) = error(call(&rpc, "add", json!({ "name": " " })));
+    assert_eq!(code, -32002);
+    assert_eq!(error_object["data"]["kind"], "Input");
+    assert_eq!(error(call(&rpc, "update", json!({ "id": 99, "name": "X" }))).0, -32001);
+    let stale = json!({ "id": 1, "name": "Ada King", "version": 3 });
+    let (code, error_object) = error(call(&rpc, "update", stale));
+    assert_eq!(code, -32012);
+    assert_eq!(error_object["message"], "contact 1 was modified elsewhere");
+
+    assert_eq!(error(call(&rpc, "get", json!({ "id": "one" }))).0, -32602);
+    assert_eq!(error(call(&rpc, "delete", json!({ "id": 1 }))).0, -32601);
+
+    let reply = rpc.handle_line("{not json").unwrap();
+    assert_eq!((reply["id"].clone(), error(reply).0), (Value::Null, -32700));
+    let reply = rpc.handle_line(r#"{"id": 4, "method": "get"}"#).unwrap();
+    assert_eq!(error(reply).0, -32600);
+    let reply = rpc.handle_line("[]").unwrap();
+    assert_eq!(error(reply).0, -32600);
+
+    // A failed request doesn't end the session
+    let replies = session(
+        &rpc,
+        &[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "nope" }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "get", "params": { "id": 1 } }),
+        ],
+    );
+    assert_eq!(replies[1]["result"]["name"], "Ada Lovelace");
+}
+
+#[test]
+fn test_batches_and_notifications() {
+    let temp_dir = tempdir().unwrap();
+    let rpc = Rpc::new(seeded_db(&temp_dir));
+
+    // Notifications run but get no reply
+    let notification = json!({ "jsonrpc": "2.0", "method": "add", "params": { "name": "Grace" } });
+    assert_eq!(rpc.handle_line(&notification.to_string()), None);
+
+    let batch = json!([
+        { "jsonrpc": "2.0", "id": 1, "method": "search", "params": { "query": "grace" } },
+        { "jsonrpc": "2.0", "method": "search" },
+        { "jsonrpc": "2.0", "id": 2, "method": "get", "params": { "id": 42 } },
+    ]);
+    let replies = rpc.handle_line(&batch.to_string()).unwrap();
+    let replies = replies.as_array().unwrap();
+    assert_eq!(replies.len(), 2);
+    assert_eq!(replies[0]["result"][0]["name"], "Grace");
+    assert_eq!(replies[1]["error"]["code"], -32001);
+
+    let only_notifications = json!([{ "jsonrpc": "2.0", "method": "search" }]);
+    assert_eq!(rpc.handle_line(&only_notifications.to_string()), None);
+}
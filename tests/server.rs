@@ -0,0 +1,231 @@
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    os::unix::net::{UnixListener, UnixStream},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use rolodex_tui::{
+    Db,
+    hooks::LifecycleHooks,
+    model::Contact,
+    server::Server,
+};
+use serde_json::{Value, json};
+use tempfile::{TempDir, tempdir};
+
+struct Reply {
+    status: u16,
+    headers: String,
+    body: Value,
+}
+
+fn seeded_db(temp_dir: &TempDir) -> Db {
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    let contacts = [("Ada Lovelace", "ada@example.com"), ("Alan Turing", "alan@example.com")];
+    for (name, email) in contacts {
+        db.add_contact(Contact {
+            name: name.to_string(),
+            email: Some(email.to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    }
+    db
+}
+
+/// Serve `db` on a free local port for the rest of the test.
+fn start(db: Db, token: Option<&str>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = Arc::new(Server::new(db, token.map(str::to_string)));
+    thread::spawn(move || server.serve_tcp(listener));
+    addr
+}
+
+fn exchange(mut stream: impl Read + Write, raw: &str) -> Reply {
+    stream.write_all(raw.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let (status_line, headers) = head.split_once("\r\n").unwrap_or((head, ""));
+    Reply {
+        status: status_line.split(' ').nth(1).unwrap().parse().unwrap(),
+        headers: headers.to_string(),
+        body: if body.is_empty() { Value::Null } else { serde_json::from_str(body).unwrap() },
+    }
+}
+
+fn request(addr: SocketAddr, method: &str, path: &str, body: Option<Value>) -> Reply {
+    let body = body.map(|body| body.to_string()).unwrap_or_default();
+    let raw = format!(
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    exchange(TcpStream::connect(addr).unwrap(), &raw)
+}
+
+fn names(body: &Value) -> Vec<&str> {
+    body.as_array()
+        .unwrap()
+        .iter()
+        .map(|contact| contact["name"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_list_search_and_get() {
+    let temp_dir = tempdir().unwrap();
+    let addr = start(seeded_db(&temp_dir), None);
+
+    let reply = request(addr, "GET", "/contacts", None);
+    assert_eq!(reply.status, 200);
+    assert!(reply.headers.contains("Content-Type: application/json"));
+    assert_eq!(names(&reply.body), ["Ada Lovelace", "Alan Turing"]);
+
+    let reply = request(addr, "GET", "/contacts?sort=name:desc", None);
+    assert_eq!(names(&reply.body), ["Alan Turing", "Ada Lovelace"]);
+
+    let reply = request(addr, "GET", "/contacts?q=tur", None);
+    assert_eq!(names(&reply.body), ["Alan Turing"]);
+
+    let id = reply.body[0]["id"].as_i64().unwrap();
+    let reply = request(addr, "GET", &format!("/contacts/{id}"), None);
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body["email"], "alan@example.com");
+
+    let reply = request(addr, "GET", "/export", None);
+    assert_eq!(names(&reply.body).len(), 2);
+}
+
+#[test]
+fn test_create_update_delete() {
+    let temp_dir = tempdir().unwrap();
+    let addr = start(seeded_db(&temp_dir), None);
+
+    let reply = request(addr, "POST", "/contacts", Some(json!({ "name": "Grace Hopper" })));
+    assert_eq!(reply.status, 201);
+    let id = reply.body["id"].as_i64().unwrap();
+    assert!(reply.headers.contains(&format!("Location: /contacts/{id}")));
+
+    let path = format!("/contacts/{id}");
+    let update = json!({ "name": "Grace Hopper", "company": "US Navy", "favorite": true });
+    let reply = request(addr, "PUT", &path, Some(update));
+    assert_eq!(reply.status, 200);
+    assert_eq!(reply.body["company"], "US Navy");
+    assert_eq!(reply.body["favorite"], true);
+    assert_eq!(reply.body["version"], 1);
+
+    // An edit made to the version before is refused
+    let stale = json!({ "name": "Grace Brewster Hopper", "version": 0 });
+    assert_eq!(request(addr, "PUT", &path, Some(stale)).status, 409);
+    let current = json!({ "name": "Grace Brewster Hopper", "version": 1 });
+    assert_eq!(request(addr, "PUT", &path, Some(current)).status, 200);
+
+    let reply = request(addr, "DELETE", &path, None);
+    assert_eq!(reply.status, 204);
+    assert_eq!(reply.body, Value::Null);
+    assert_eq!(request(addr, "GET", &path, None).status, 404);
+
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    assert_eq!(db.load_customers().unwrap().len(), 2);
+}
+
+#[test]
+fn test_error_statuses() {
+    let temp_dir = tempdir().unwrap();
+    let addr = start(seeded_db(&temp_dir), None);
+
+    let reply = request(addr, "GET", "/contacts/9999", None);
+    assert_eq!(reply.status, 404);
+    assert_eq!(reply.body["error"], "contact 9999 not found");
+    assert_eq!(request(addr, "GET", "/contacts/abc", None).status, 404);
+    assert_eq!(request(addr, "GET", "/nowhere", None).status, 404);
+    assert_eq!(request(addr, "PUT", "/contacts/9999", Some(json!({ "name": "X" }))).status, 404);
+    assert_eq!(request(addr, "DELETE", "/contacts/9999", None).status, 404);
+
+    // Malformed JSON and failed validation
+    assert_eq!(request(addr, "POST", "/contacts", None).status, 400);
+    let reply = request(addr, "POST", "/contacts", Some(json!({ "name": "" })));
+    assert_eq!(reply.status, 422);
+    let invalid = json!({ "name": "X", "email": "not-an-email" });
+    assert_eq!(request(addr, "POST", "/contacts", Some(invalid)).status, 422);
+    assert_eq!(request(addr, "GET", "/contacts?sort=shoe-size", None).status, 422);
+
+    let reply = request(addr, "PATCH", "/contacts", None);
+    assert_eq!(reply.status, 405);
+    assert!(reply.headers.contains("Allow: GET, POST"));
+
+    let raw = "POST /contacts HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\nhi";
+    assert_eq!(exchange(TcpStream::connect(addr).unwrap(), raw).status, 415);
+    let raw = "GARBAGE\r\n\r\n";
+    assert_eq!(exchange(TcpStream::connect(addr).unwrap(), raw).status, 400);
+}
+
+#[test]
+fn test_token_auth() {
+    let temp_dir = tempdir().unwrap();
+    let addr = start(seeded_db(&temp_dir), Some("s3cret"));
+
+    let reply = request(addr, "GET", "/contacts", None);
+    assert_eq!(reply.status, 401);
+    assert!(reply.headers.contains("WWW-Authenticate: Bearer"));
+
+    let get = |token: &str| {
+        let raw = format!("GET /contacts HTTP/1.1\r\nAuthorization: Bearer {token}\r\n\r\n");
+        exchange(TcpStream::connect(addr).unwrap(), &raw).status
+    };
+    assert_eq!(get("wrong"), 401);
+    assert_eq!(get("s3cret"), 200);
+}
+
+#[test]
+fn test_unix_socket_and_hooks() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = seeded_db(&temp_dir);
+    let log = temp_dir.path().join("hook.log");
+    db.set_hooks(LifecycleHooks {
+        on_add: Some(format!("cat > {}", log.display())),
+        ..Default::default()
+    });
+
+    let path = temp_dir.path().join("rolodex.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    let server = Arc::new(Server::new(db, None));
+    thread::spawn(move || server.serve_unix(listener));
+
+    let body = json!({ "name": "Edsger Dijkstra" }).to_string();
+    let raw = format!("POST /contacts HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}", body.len());
+    let reply = exchange(UnixStream::connect(&path).unwrap(), &raw);
+    assert_eq!(reply.status, 201);
+
+    let hooked: Vec<Contact> =
+        serde_json::from_str(&std::fs::read_to_string(&log).unwrap()).unwrap();
+    assert_eq!(hooked[0].name, "Edsger Dijkstra");
+    assert_eq!(hooked[0].id, reply.body["id"].as_i64().unwrap());
+}
+
+#[test]
+fn test_slow_hook_does_not_block_other_requests() {
+    let temp_dir = tempdir().unwrap();
+    let mut db = seeded_db(&temp_dir);
+    db.set_hooks(LifecycleHooks {
+        on_add: Some("cat > /dev/null; sleep 2".to_string()),
+        ..Default::default()
+    });
+    let addr = start(db, None);
+
+    let body = json!({ "name": "Edsger Dijkstra" });
+    let adding = thread::spawn(move || request(addr, "POST", "/contacts", Some(body)));
+    thread::sleep(Duration::from_millis(500));
+
+    // The database lock is free again while the hook runs
+    let started = Instant::now();
+    let reply = request(addr, "GET", "/contacts", None);
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(names(&reply.body).len(), 3);
+    assert_eq!(adding.join().unwrap().status, 201);
+}
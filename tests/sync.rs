@@ -0,0 +1,253 @@
+use std::{
+    collections::BTreeMap,
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use rolodex_tui::{
+    Db,
+    model::Contact,
+    server::http::{self, Handler, Request, Response},
+    sync::{self, Change, Conflict, Kind, Remote},
+};
+use tempfile::{TempDir, tempdir};
+
+const BOOK: &str = "/dav/book/";
+
+/// A CardDAV collection in memory: cards by href, each with a version
+/// that makes up its ETag.
+#[derive(Default)]
+struct MockDav {
+    cards: Mutex<BTreeMap<String, (String, u32)>>,
+}
+
+impl MockDav {
+    fn card(&self, href: &str) -> Option<String> {
+        let cards = self.cards.lock().unwrap();
+        cards.get(href).map(|(card, _)| card.clone())
+    }
+
+    fn find(&self, needle: &str) -> Option<String> {
+        let cards = self.cards.lock().unwrap();
+        cards
+            .iter()
+            .find(|(_, (card, _))| card.contains(needle))
+            .map(|(href, _)| href.clone())
+    }
+
+    fn put(&self, href: &str, card: &str) {
+        let mut cards = self.cards.lock().unwrap();
+        let version = cards.get(href).map_or(1, |(_, version)| version + 1);
+        cards.insert(href.to_string(), (card.to_string(), version));
+    }
+
+    fn delete(&self, href: &str) {
+        self.cards.lock().unwrap().remove(href);
+    }
+}
+
+impl Handler for MockDav {
+    fn handle(&self, request: &Request) -> Response {
+        let etag = |version: &u32| format!("\"v{version}\"");
+        let mut cards = self.cards.lock().unwrap();
+        let current = cards.get(&request.path).map(|(_, version)| etag(version));
+        let body = String::from_utf8_lossy(&request.body).to_string();
+
+        match request.method.as_str() {
+            "PROPFIND" if request.path == BOOK => {
+                let mut xml = format!(
+                    "<d:multistatus xmlns:d=\"DAV:\"><d:response><d:href>{BOOK}</d:href>\
+                     <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype>\
+                     </d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>"
+                );
+                for (href, (_, version)) in cards.iter() {
+                    xml += &format!(
+                        "<d:response><d:href>http://localhost{href}</d:href><d:propstat>\
+                         <d:prop><d:resourcetype/><d:getetag>{}</d:getetag></d:prop>\
+                         <d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+                        etag(version)
+                    );
+                }
+                Response::new(207, "application/xml", xml + "</d:multistatus>")
+            }
+            "GET" => match cards.get(&request.path) {
+                Some((card, version)) => Response::new(200, "text/vcard", card.clone())
+                    .with_header("ETag", etag(version)),
+                None => Response::error(404, "no such card"),
+            },
+            "PUT" | "DELETE" => {
+                let fresh = request.header("If-None-Match") == Some("*") && current.is_none();
+                let matched = request.header("If-Match") == current.as_deref() && current.is_some();
+                if !fresh && !matched {
+                    return Response::error(412, "precondition failed");
+                }
+                if request.method == "DELETE" {
+                    cards.remove(&request.path);
+                    return Response::no_content();
+                }
+                let version = cards.get(&request.path).map_or(1, |(_, version)| version + 1);
+                cards.insert(request.path.clone(), (body, version));
+                Response::new(201, "text/plain", "").with_header("ETag", etag(&version))
+            }
+            _ => Response::error(405, "not supported"),
+        }
+    }
+}
+
+/// Serve a mock collection on a free local port for the rest of the test.
+fn start() -> (Arc<MockDav>, Remote) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let dav = Arc::new(MockDav::default());
+    let handler = Arc::clone(&dav);
+    thread::spawn(move || http::serve_tcp(handler, listener));
+    (dav, Remote::new(&format!("http://{addr}{BOOK}"), Some("me"), Some("s3cret")))
+}
+
+fn seeded_db(temp_dir: &TempDir) -> Db {
+    let db = Db::open(temp_dir.path().join("test.db")).unwrap();
+    let contacts = [("Ada Lovelace", "ada@example.com"), ("Alan Turing", "alan@example.com")];
+    for (name, email) in contacts {
+        db.add_contact(Contact {
+            name: name.to_string(),
+            email: Some(email.to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    }
+    db
+}
+
+fn vcard(name: &str, email: &str) -> String {
+    format!(
+        "BEGIN:VCARD\r\nVERSION:3.0\r\nUID:{email}\r\nFN:{name}\r\nEMAIL:{email}\r\n\
+         NOTE:Met at the conference\r\nEND:VCARD\r\n"
+    )
+}
+
+fn names(db: &Db) -> Vec<String> {
+    db.load_customers().unwrap().into_iter().map(|c| c.name).collect()
+}
+
+fn by_name(db: &Db, name: &str) -> Contact {
+    db.load_customers().unwrap().into_iter().find(|c| c.name == name).unwrap()
+}
+
+#[test]
+fn test_first_sync_goes_both_ways() {
+    let temp_dir = tempdir().unwrap();
+    let db = seeded_db(&temp_dir);
+    let (dav, remote) = start();
+    dav.put(&format!("{BOOK}grace.vcf"), &vcard("Grace Hopper", "grace@example.com"));
+
+    let changes = sync::sync(&db, &remote, Conflict::Skip).unwrap();
+    assert_eq!(
+        changes,
+        [
+            Change::Pulled(Kind::Added, "Grace Hopper".to_string()),
+            Change::Pushed(Kind::Added, "Ada Lovelace".to_string()),
+            Change::Pushed(Kind::Added, "Alan Turing".to_string()),
+        ]
+    );
+    assert_eq!(names(&db), ["Ada Lovelace", "Alan Turing", "Grace Hopper"]);
+    let href = dav.find("FN:Ada Lovelace").unwrap();
+    let uid = href.strip_prefix(BOOK).unwrap().strip_suffix(".vcf").unwrap();
+    assert!(dav.card(&href).unwrap().contains(&format!("UID:{uid}\r\n")));
+
+    assert_eq!(sync::sync(&db, &remote, Conflict::Skip).unwrap(), []);
+
+    // The state belongs to one collection
+    let other = Remote::new(&remote.url().replace(BOOK, "/dav/other/"), None, None);
+    assert!(sync::sync(&db, &other, Conflict::Skip).is_err());
+}
+
+#[test]
+fn test_edits_and_deletes_both_ways() {
+    let temp_dir = tempdir().unwrap();
+    let db = seeded_db(&temp_dir);
+    let (dav, remote) = start();
+    let grace = format!("{BOOK}grace.vcf");
+    dav.put(&grace, &vcard("Grace Hopper", "grace@example.com"));
+    sync::sync(&db, &remote, Conflict::Skip).unwrap();
+
+    let ada = by_name(&db, "Ada Lovelace");
+    db.update_contact(ada.id, Contact { email: Some("ada@king.example".to_string()), ..ada })
+        .unwrap();
+    dav.put(&grace, &vcard("Grace B. Hopper", "grace@example.com"));
+    db.delete_contact(by_name(&db, "Alan Turing").id).unwrap();
+
+    let changes = sync::sync(&db, &remote, Conflict::Skip).unwrap();
+    assert_eq!(
+        changes,
+        [
+            Change::Pushed(Kind::Updated, "Ada Lovelace".to_string()),
+            Change::Pushed(Kind::Deleted, "Alan Turing".to_string()),
+            Change::Pulled(Kind::Updated, "Grace B. Hopper".to_string()),
+        ]
+    );
+    assert!(dav.find(":ada@king.example\r\n").is_some());
+    assert!(dav.find("Alan Turing").is_none());
+    assert_eq!(names(&db), ["Ada Lovelace", "Grace B. Hopper"]);
+
+    // Local edits leave the properties rolodex doesn't know about alone
+    let grace_contact = by_name(&db, "Grace B. Hopper");
+    db.update_contact(
+        grace_contact.id,
+        Contact { company: Some("US Navy".to_string()), ..grace_contact },
+    )
+    .unwrap();
+    dav.delete(&dav.find("Ada Lovelace").unwrap());
+
+    let changes = sync::sync(&db, &remote, Conflict::Skip).unwrap();
+    assert_eq!(
+        changes,
+        [
+            Change::Pulled(Kind::Deleted, "Ada Lovelace".to_string()),
+            Change::Pushed(Kind::Updated, "Grace B. Hopper".to_string()),
+        ]
+    );
+    let card = dav.card(&grace).unwrap();
+    assert!(card.contains("ORG:US Navy\r\n"));
+    assert!(card.contains("NOTE:Met at the conference\r\n"));
+    assert!(card.contains("UID:grace@example.com\r\n"));
+    assert_eq!(names(&db), ["Grace B. Hopper"]);
+    assert_eq!(sync::sync(&db, &remote, Conflict::Skip).unwrap(), []);
+}
+
+#[test]
+fn test_conflict_policies() {
+    let temp_dir = tempdir().unwrap();
+    let db = seeded_db(&temp_dir);
+    let (dav, remote) = start();
+    sync::sync(&db, &remote, Conflict::Skip).unwrap();
+    let href = dav.find("Ada Lovelace").unwrap();
+
+    let edit_both = |local: &str, remote: &str| {
+        let ada = db.get_contact(by_name(&db, "Ada Lovelace").id).unwrap();
+        db.update_contact(ada.id, Contact { email: Some(local.to_string()), ..ada })
+            .unwrap();
+        let card = dav.card(&href).unwrap();
+        let email = card.lines().find(|line| line.starts_with("EMAIL")).unwrap().to_string();
+        dav.put(&href, &card.replace(&email, &format!("EMAIL:{remote}")));
+    };
+    let conflict = |policy| vec![Change::Conflict("Ada Lovelace".to_string(), policy)];
+    let email = || by_name(&db, "Ada Lovelace").email.unwrap();
+
+    edit_both("ada@local.example", "ada@remote.example");
+    assert_eq!(sync::sync(&db, &remote, Conflict::Skip).unwrap(), conflict(Conflict::Skip));
+    assert_eq!(sync::sync(&db, &remote, Conflict::Skip).unwrap(), conflict(Conflict::Skip));
+    assert_eq!(email(), "ada@local.example");
+
+    assert_eq!(sync::sync(&db, &remote, Conflict::Remote).unwrap(), conflict(Conflict::Remote));
+    assert_eq!(email(), "ada@remote.example");
+
+    edit_both("ada@mine.example", "ada@theirs.example");
+    assert_eq!(sync::sync(&db, &remote, Conflict::Local).unwrap(), conflict(Conflict::Local));
+    assert!(dav.card(&href).unwrap().contains(":ada@mine.example\r\n"));
+    assert_eq!(sync::sync(&db, &remote, Conflict::Skip).unwrap(), []);
+
+    // The same edit on both sides isn't a conflict
+    edit_both("ada@same.example", "ada@same.example");
+    assert_eq!(sync::sync(&db, &remote, Conflict::Skip).unwrap(), []);
+}
@@ -0,0 +1,105 @@
+//! Screen snapshots of every component, driven through the key handling the
+//! real terminal uses. See `harness` for how to update them.
+
+mod harness;
+
+use harness::{Harness, address_book};
+use rolodex_tui::components::{app::AppMsg, notifications::Level};
+
+#[test]
+fn snapshot_browse_list() {
+    Harness::new(&address_book()).assert_snapshot("browse_list");
+}
+
+#[test]
+fn snapshot_browse_empty() {
+    Harness::new(&[]).assert_snapshot("browse_empty");
+}
+
+#[test]
+fn snapshot_browse_search() {
+    Harness::new(&address_book())
+        .keys("ada<down>")
+        .assert_snapshot("browse_search");
+}
+
+#[test]
+fn snapshot_browse_sorted_by_company() {
+    Harness::new(&address_book())
+        .keys("<ctrl+s><ctrl+s><ctrl+r>")
+        .assert_snapshot("browse_sorted_by_company");
+}
+
+#[test]
+fn snapshot_add_form() {
+    Harness::new(&address_book())
+        .keys("<ctrl+a>Katherine Johnson<tab>NASA")
+        .assert_snapshot("add_form");
+}
+
+#[test]
+fn snapshot_edit_form() {
+    Harness::new(&address_book())
+        .keys("<down><ctrl+e>")
+        .assert_snapshot("edit_form");
+}
+
+#[test]
+fn snapshot_delete_dialog() {
+    Harness::new(&address_book())
+        .keys("<end><ctrl+d>")
+        .assert_snapshot("delete_dialog");
+}
+
+#[test]
+fn snapshot_error_dialog() {
+    Harness::new(&address_book())
+        .send(AppMsg::ShowError("Database is locked".into()))
+        .assert_snapshot("error_dialog");
+}
+
+#[test]
+fn snapshot_help_overlay() {
+    Harness::new(&address_book())
+        .keys("?")
+        .assert_snapshot("help_overlay");
+}
+
+#[test]
+fn snapshot_command_palette() {
+    Harness::new(&address_book())
+        .keys("<ctrl+p>sort")
+        .assert_snapshot("command_palette");
+}
+
+#[test]
+fn snapshot_toasts_after_save() {
+    Harness::new(&address_book())
+        .keys("<ctrl+a>Katherine Johnson<enter>")
+        .send(AppMsg::Notify(Level::Warning, "Sort order not saved".into()))
+        .assert_snapshot("toasts_after_save");
+}
+
+#[test]
+fn snapshot_message_log() {
+    Harness::new(&address_book())
+        .send(AppMsg::Notify(Level::Info, "Loaded 5 contacts".into()))
+        .send(AppMsg::Notify(Level::Error, "Could not save".into()))
+        .keys("<ctrl+o>")
+        .assert_snapshot("message_log");
+}
+
+#[test]
+fn snapshot_vi_normal_mode() {
+    Harness::new(&address_book())
+        .vi()
+        .keys("jj")
+        .assert_snapshot("vi_normal_mode");
+}
+
+#[test]
+fn snapshot_favorite_pinned() {
+    Harness::new(&address_book())
+        .keys("<end><ctrl+f>")
+        .assert_snapshot("favorite_pinned");
+}